@@ -555,6 +555,16 @@ extern "C" {
 extern "C" {
     pub fn JS_IsError(ctx: *mut JSContext, val: JSValue) -> ::std::os::raw::c_int;
 }
+extern "C" {
+    pub fn JS_IsUncatchableError(ctx: *mut JSContext, val: JSValue) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn JS_SetUncatchableError(
+        ctx: *mut JSContext,
+        val: JSValue,
+        flag: ::std::os::raw::c_int,
+    );
+}
 extern "C" {
     pub fn JS_ResetUncatchableError(ctx: *mut JSContext);
 }
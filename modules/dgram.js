@@ -0,0 +1,300 @@
+// UDP sockets over `WasiUdpSocket`. Sockets can't be connected, so every
+// `send` names its destination.
+
+import * as net from 'wasi_net';
+import { Buffer } from 'buffer';
+import { EventEmitter } from 'events';
+import process from 'process';
+import {
+  ERR_FEATURE_UNAVAILABLE_ON_PLATFORM,
+  ERR_INVALID_ARG_TYPE,
+  ERR_OUT_OF_RANGE,
+  ERR_SOCKET_ALREADY_BOUND,
+  ERR_SOCKET_BAD_TYPE,
+  ERR_SOCKET_DGRAM_NOT_RUNNING,
+} from './internal/errors';
+import {
+  validateAbortSignal,
+  validateBoolean,
+  validatePort,
+  validateString,
+} from './internal/validators';
+
+const kUnbound = 0;
+const kBound = 1;
+const kClosed = 2;
+
+function toBuffer(data) {
+  if (typeof data === 'string') {
+    return Buffer.from(data);
+  }
+  if (!ArrayBuffer.isView(data)) {
+    throw new ERR_INVALID_ARG_TYPE(
+      'buffer', ['Buffer', 'TypedArray', 'DataView', 'string'], data);
+  }
+  return Buffer.from(data.buffer, data.byteOffset, data.byteLength);
+}
+
+function sliceBuffer(data, offset, length) {
+  const buffer = toBuffer(data);
+  offset = offset >>> 0;
+  length = length >>> 0;
+  if (offset > buffer.length) {
+    throw new ERR_OUT_OF_RANGE('offset', `<= ${buffer.length}`, offset);
+  }
+  if (offset + length > buffer.length) {
+    throw new ERR_OUT_OF_RANGE('length', `<= ${buffer.length - offset}`, length);
+  }
+  return buffer.subarray(offset, offset + length);
+}
+
+export class Socket extends EventEmitter {
+  #handle;
+  #state = kUnbound;
+  #refed = true;
+  #reuseAddr;
+  #signal;
+  #onAbort;
+
+  constructor(type, listener) {
+    super();
+    let options = {};
+    if (type !== null && typeof type === 'object') {
+      options = type;
+      type = options.type;
+    }
+    if (type !== 'udp4' && type !== 'udp6') {
+      throw new ERR_SOCKET_BAD_TYPE();
+    }
+    this.type = type;
+    if (options.reuseAddr !== undefined) {
+      validateBoolean(options.reuseAddr, 'options.reuseAddr');
+    }
+    this.#reuseAddr = !!options.reuseAddr;
+    if (options.ipv6Only !== undefined) {
+      validateBoolean(options.ipv6Only, 'options.ipv6Only');
+    }
+    if (options.ipv6Only) {
+      // WasmEdge can't set IPV6_V6ONLY
+      throw new ERR_FEATURE_UNAVAILABLE_ON_PLATFORM('ipv6Only');
+    }
+
+    if (typeof listener === 'function') {
+      this.on('message', listener);
+    }
+
+    if (options.signal !== undefined) {
+      validateAbortSignal(options.signal, 'options.signal');
+      if (options.signal.aborted) {
+        process.nextTick(() => this.close());
+      } else {
+        this.#signal = options.signal;
+        this.#onAbort = () => this.close();
+        this.#signal.addEventListener('abort', this.#onAbort, { once: true });
+      }
+    }
+  }
+
+  #healthCheck() {
+    if (this.#state === kClosed) {
+      throw new ERR_SOCKET_DGRAM_NOT_RUNNING();
+    }
+  }
+
+  #boundHandle() {
+    this.#healthCheck();
+    if (this.#state !== kBound) {
+      throw new ERR_SOCKET_DGRAM_NOT_RUNNING();
+    }
+    return this.#handle;
+  }
+
+  bind(port, address, callback) {
+    this.#healthCheck();
+    if (this.#state !== kUnbound) {
+      throw new ERR_SOCKET_ALREADY_BOUND();
+    }
+
+    if (port !== null && typeof port === 'object') {
+      callback = address;
+      address = port.address;
+      port = port.port;
+    } else if (typeof port === 'function') {
+      callback = port;
+      port = undefined;
+      address = undefined;
+    }
+    if (typeof address === 'function') {
+      callback = address;
+      address = undefined;
+    }
+    port = port === undefined ? 0 : +validatePort(port, 'Port', true);
+    if (address != null) {
+      validateString(address, 'address');
+    }
+    if (typeof callback === 'function') {
+      this.once('listening', callback);
+    }
+
+    try {
+      this.#handle = new net.WasiUdpSocket({
+        type: this.type,
+        host: address ?? undefined,
+        port,
+        reuseAddr: this.#reuseAddr,
+      });
+    } catch (e) {
+      process.nextTick(() => this.emit('error', e));
+      return this;
+    }
+    if (!this.#refed) {
+      this.#handle.unref();
+    }
+    this.#state = kBound;
+    process.nextTick(() => this.emit('listening'));
+    this.#receive(this.#handle);
+    return this;
+  }
+
+  async #receive(handle) {
+    while (this.#handle === handle) {
+      let msg;
+      try {
+        msg = await handle.recvFrom();
+      } catch (e) {
+        // closing the socket rejects the pending receive; any other error
+        // would likely repeat, so stop receiving. Emitted from a tick of its
+        // own, an unhandled 'error' is uncaught instead of a lost rejection.
+        if (this.#handle === handle) {
+          process.nextTick(() => this.emit('error', e));
+        }
+        return;
+      }
+      const rinfo = {
+        address: msg.address,
+        family: msg.family,
+        port: msg.port,
+        size: msg.size,
+      };
+      this.emit('message', Buffer.from(msg.data), rinfo);
+    }
+  }
+
+  send(buffer, offset, length, port, address, callback) {
+    if (address || (port && typeof port !== 'function')) {
+      buffer = sliceBuffer(buffer, offset, length);
+    } else {
+      callback = port;
+      port = offset;
+      address = length;
+    }
+
+    const list = Array.isArray(buffer) ? buffer.map(toBuffer) : [toBuffer(buffer)];
+    port = +validatePort(port, 'Port', false);
+    if (typeof address === 'function') {
+      callback = address;
+      address = undefined;
+    } else if (address != null) {
+      validateString(address, 'address');
+    }
+    this.#healthCheck();
+
+    if (this.#state === kUnbound) {
+      this.bind(0);
+      if (this.#state !== kBound) {
+        // the bind error is emitted
+        return;
+      }
+    }
+    if (!address) {
+      address = this.type === 'udp4' ? '127.0.0.1' : '::1';
+    }
+
+    const data = Buffer.concat(list);
+    const arrayBuffer = data.buffer.slice(data.byteOffset, data.byteOffset + data.length);
+    this.#handle.sendTo(arrayBuffer, address, port).then(
+      (sent) => {
+        if (typeof callback === 'function') {
+          callback(null, sent);
+        }
+      },
+      (e) => {
+        if (typeof callback === 'function') {
+          callback(e);
+        } else {
+          process.nextTick(() => this.emit('error', e));
+        }
+      });
+  }
+
+  close(callback) {
+    this.#healthCheck();
+    if (typeof callback === 'function') {
+      this.once('close', callback);
+    }
+    const handle = this.#handle;
+    this.#handle = undefined;
+    this.#state = kClosed;
+    handle?.close();
+    if (this.#signal) {
+      this.#signal.removeEventListener('abort', this.#onAbort);
+      this.#signal = undefined;
+    }
+    process.nextTick(() => this.emit('close'));
+    return this;
+  }
+
+  address() {
+    return this.#boundHandle().localAddress();
+  }
+
+  setBroadcast(flag) {
+    this.#boundHandle().setBroadcast(!!flag);
+  }
+
+  setTTL(ttl) {
+    this.#boundHandle().setTTL(ttl);
+    return ttl;
+  }
+
+  setMulticastTTL(ttl) {
+    this.#boundHandle().setMulticastTTL(ttl);
+    return ttl;
+  }
+
+  setMulticastLoopback(flag) {
+    this.#boundHandle().setMulticastLoopback(!!flag);
+    return flag;
+  }
+
+  addMembership(multicastAddress, interfaceAddress) {
+    validateString(multicastAddress, 'multicastAddress');
+    this.#boundHandle().addMembership(multicastAddress, interfaceAddress);
+  }
+
+  dropMembership(multicastAddress, interfaceAddress) {
+    validateString(multicastAddress, 'multicastAddress');
+    this.#boundHandle().dropMembership(multicastAddress, interfaceAddress);
+  }
+
+  ref() {
+    this.#refed = true;
+    this.#handle?.ref();
+    return this;
+  }
+
+  unref() {
+    this.#refed = false;
+    this.#handle?.unref();
+    return this;
+  }
+}
+
+export function createSocket(type, listener) {
+  return new Socket(type, listener);
+}
+
+export default {
+  Socket,
+  createSocket,
+};
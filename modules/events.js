@@ -1,5 +1,9 @@
 'use strict';
 
+import { AbortError, ERR_INVALID_ARG_TYPE } from './internal/errors';
+import { kEmptyObject } from './internal/util';
+import { validateAbortSignal } from './internal/validators';
+
 var domain;
 
 // This constructor is used to store event handlers. Instantiating this is
@@ -22,6 +26,9 @@ EventEmitter.captureRejectionSymbol = kRejection;
 export default EventEmitter;
 export { EventEmitter, captureRejectionSymbol };
 
+EventEmitter.once = once;
+EventEmitter.on = on;
+
 // nodejs oddity
 // require('events') === require('events').EventEmitter
 EventEmitter.EventEmitter = EventEmitter
@@ -528,4 +535,140 @@ export async function once(emitter, name, options = kEmptyObject) {
         signal, 'abort', abortListener, { once: true });
     }
   });
-}
\ No newline at end of file
+}
+
+/**
+ * Returns an `AsyncIterator` that iterates `event` events.
+ * @param {EventEmitter} emitter
+ * @param {string} event
+ * @param {{ signal: AbortSignal; }} [options]
+ * @returns {AsyncIterator}
+ */
+export function on(emitter, event, options = kEmptyObject) {
+  const signal = options?.signal;
+  validateAbortSignal(signal, 'options.signal');
+  if (signal?.aborted)
+    throw new AbortError(undefined, { cause: signal?.reason });
+
+  const unconsumedEvents = [];
+  const unconsumedPromises = [];
+  let error = null;
+  let finished = false;
+
+  const iterator = {
+    next() {
+      // First, we consume all unread events
+      if (unconsumedEvents.length > 0) {
+        return Promise.resolve({ value: unconsumedEvents.shift(), done: false });
+      }
+
+      // Then we error, if an error happened
+      if (error) {
+        const p = Promise.reject(error);
+        // Only the first element errors
+        error = null;
+        return p;
+      }
+
+      // If the iterator is finished, resolve to done
+      if (finished) {
+        return Promise.resolve({ value: undefined, done: true });
+      }
+
+      // Wait until an event happens
+      return new Promise(function (resolve, reject) {
+        unconsumedPromises.push({ resolve, reject });
+      });
+    },
+
+    return() {
+      closeHandler();
+      return Promise.resolve({ value: undefined, done: true });
+    },
+
+    throw(err) {
+      if (!err || !(err instanceof Error)) {
+        throw new ERR_INVALID_ARG_TYPE('EventEmitter.AsyncIterator', 'Error', err);
+      }
+      errorHandler(err);
+    },
+
+    [Symbol.asyncIterator]() {
+      return this;
+    },
+  };
+
+  eventTargetAgnosticAddListener(emitter, event, eventHandler);
+  if (event !== 'error' && typeof emitter.on === 'function') {
+    emitter.on('error', errorHandler);
+  }
+  if (signal) {
+    eventTargetAgnosticAddListener(signal, 'abort', abortListener, { once: true });
+  }
+
+  return iterator;
+
+  function abortListener() {
+    errorHandler(new AbortError(undefined, { cause: signal?.reason }));
+  }
+
+  function eventHandler(...args) {
+    const promise = unconsumedPromises.shift();
+    if (promise) {
+      promise.resolve({ value: args, done: false });
+    } else {
+      unconsumedEvents.push(args);
+    }
+  }
+
+  function errorHandler(err) {
+    const promise = unconsumedPromises.shift();
+    if (promise) {
+      promise.reject(err);
+    } else {
+      // The next time we call next()
+      error = err;
+    }
+    closeHandler();
+  }
+
+  function closeHandler() {
+    eventTargetAgnosticRemoveListener(emitter, event, eventHandler);
+    if (typeof emitter.removeListener === 'function') {
+      emitter.removeListener('error', errorHandler);
+    }
+    if (signal) {
+      eventTargetAgnosticRemoveListener(signal, 'abort', abortListener);
+    }
+    finished = true;
+    for (const promise of unconsumedPromises.splice(0)) {
+      promise.resolve({ value: undefined, done: true });
+    }
+  }
+}
+
+// EventEmitters are listened to with on/once, anything else is taken to be an
+// EventTarget, whose listeners receive the event object as their only argument.
+function eventTargetAgnosticAddListener(emitter, name, listener, flags) {
+  if (typeof emitter.on === 'function') {
+    if (flags?.once) {
+      emitter.once(name, listener);
+    } else {
+      emitter.on(name, listener);
+    }
+  } else if (typeof emitter.addEventListener === 'function') {
+    emitter.addEventListener(name, listener, flags);
+  } else {
+    throw new ERR_INVALID_ARG_TYPE('emitter', 'EventEmitter', emitter);
+  }
+}
+
+function eventTargetAgnosticRemoveListener(emitter, name, listener, flags) {
+  if (typeof emitter.removeListener === 'function') {
+    emitter.removeListener(name, listener);
+  } else if (typeof emitter.removeEventListener === 'function') {
+    emitter.removeEventListener(name, listener, flags);
+  } else {
+    throw new ERR_INVALID_ARG_TYPE('emitter', 'EventEmitter', emitter);
+  }
+}
@@ -5,6 +5,7 @@ import { Buffer } from 'buffer'
 import { EventEmitter } from 'events'
 import process from 'process'
 import { validatePort } from 'internal/validators'
+import { addAbortSignal } from 'internal/streams/add-abort-signal'
 import { Readable, Writable } from "stream";
 import { isTypedArray } from 'util/types'
 
@@ -52,12 +53,16 @@ export class Response {
     #chunked = false;
     #chunkBuff = null;
     #bodyUsed = false
+    #signal = undefined
+    #onDone = undefined
 
     constructor(resp, buffer, reader, option = {}) {
         this.response = resp
         this.buffer = buffer
         this.reader = reader
         this.url = option.url
+        this.#signal = option.signal
+        this.#onDone = option.onDone
 
         this.headers = resp.headers
         this.statusText = resp.statusText
@@ -91,7 +96,7 @@ export class Response {
             let chunk = this.#chunkBuff.parseChunk();
 
             if (chunk === undefined) {
-                let data = await this.reader.read()
+                let data = await this.reader.read(undefined, this.#signal)
                 if (data === undefined) {
                     throw new Error('socket is shutdown')
                 }
@@ -117,7 +122,7 @@ export class Response {
                 return buf;
             }
 
-            let data = await this.reader.read()
+            let data = await this.reader.read(undefined, this.#signal)
             if (data === undefined) {
                 let buf = this.buffer.buffer;
                 this.buffer.clear();
@@ -130,6 +135,16 @@ export class Response {
 
     async arrayBuffer() {
         this.#bodyUsed = true;
+        try {
+            return await this.#readAll()
+        } finally {
+            // the body is done with, successfully or not
+            this.#onDone?.()
+            this.#onDone = undefined
+        }
+    }
+
+    async #readAll() {
         if (this.#chunked) {
             while (true) {
                 let chunk = await this.#readChunk();
@@ -167,18 +182,18 @@ export class Response {
     }
 }
 
-async function wait_response(reader, url) {
+async function wait_response(reader, url, signal, onDone) {
     let buf = new httpx.Buffer()
     let resp = undefined
     while (true) {
-        let buff = await reader.read()
+        let buff = await reader.read(undefined, signal)
         if (buff == undefined && resp == undefined) {
             throw new TypeError('Illegal response')
         }
         buf.append(buff)
         resp = buf.parseResponse()
         if (resp instanceof httpx.WasiResponse) {
-            return new Response(resp, buf, reader, { url })
+            return new Response(resp, buf, reader, { url, signal, onDone })
         }
     }
 }
@@ -189,6 +204,9 @@ export async function fetch(input, init = {}) {
         throw new TypeError(`${input} is an url with embedded credentails.`)
     }
 
+    let signal = init.signal
+    signal?.throwIfAborted()
+
     let method = init.method || 'GET'
     method = method.toUpperCase();
 
@@ -202,10 +220,14 @@ export async function fetch(input, init = {}) {
 
     var s;
     if (url.scheme == 'https' && net.WasiTlsConn) {
-        s = await net.WasiTlsConn.connect(url.host, url.port);
+        s = await net.WasiTlsConn.connect(url.host, url.port, undefined, signal);
     } else {
-        s = await net.WasiTcpConn.connect(url.host, url.port);
+        s = await net.WasiTcpConn.connect(url.host, url.port, undefined, signal);
     }
+    // the pending read is cancelled by the signal itself; this drops the connection
+    const onAbort = () => s.close()
+    signal?.addEventListener('abort', onAbort, { once: true })
+    const onDone = () => signal?.removeEventListener('abort', onAbort)
 
     let req = new httpx.WasiRequest()
     req.version = init.version || 'HTTP/1.1'
@@ -221,8 +243,13 @@ export async function fetch(input, init = {}) {
 
     req.method = method
     req.body = init.body || ''
-    s.write(req.encode())
-    return await wait_response(s, url)
+    try {
+        s.write(req.encode())
+        return await wait_response(s, url, signal, onDone)
+    } catch (e) {
+        onDone()
+        throw e
+    }
 }
 
 const STATUS_CODES = {
@@ -320,6 +347,9 @@ class ClientRequest extends Writable {
         this.opts = opts;
         this.cb = cb
         this.body = new httpx.Buffer()
+        if (opts.signal !== undefined) {
+            addAbortSignal(opts.signal, this);
+        }
     }
 
     // deno-lint-ignore no-explicit-any
@@ -330,13 +360,21 @@ class ClientRequest extends Writable {
 
     async _final() {
         try {
-            const opts = { body: this.body, method: this.opts.method, headers: this.opts.headers };
+            const opts = {
+                body: this.body,
+                method: this.opts.method,
+                headers: this.opts.headers,
+                signal: this.opts.signal,
+            };
             const mayResponse = await fetch(this._createUrlStrFromOptions(this.opts), opts)
             const res = new IncomingMessageForClient(mayResponse);
             this.emit("response", res);
             this.cb?.(res);
         } catch (e) {
-            this.emit('error', e)
+            // an aborted request has already been destroyed with an AbortError
+            if (!this.destroyed) {
+                this.emit('error', e)
+            }
         }
     }
 
@@ -711,11 +749,15 @@ function _normalizeArgs(args) {
         // (options[...][, cb])
         options = arg0;
     } else {
-        // ([port][, host][...][, cb])
+        // ([port][, host][, backlog][, cb])
         options.port = arg0;
 
-        if (args.length > 1 && typeof args[1] === "string") {
-            options.host = args[1];
+        let i = 1;
+        if (args.length > i && typeof args[i] === "string") {
+            options.host = args[i++];
+        }
+        if (args.length > i && typeof args[i] === "number") {
+            options.backlog = args[i];
         }
     }
 
@@ -761,11 +803,14 @@ class ServerImpl extends EventEmitter {
             port = options.port | 0;
         }
 
-        // TODO(bnoordhuis) Node prefers [::] when host is omitted,
-        // we on the other hand default to 0.0.0.0.
-        // const hostname = options.host ?? "";
-
-        this.#listener = new net.WasiTcpServer(port);
+        // Node prefers [::] when host is omitted; we default to 0.0.0.0,
+        // as dual-stack sockets depend on the WasmEdge host.
+        this.#listener = new net.WasiTcpServer({
+            port,
+            host: options.host,
+            ipv6Only: options.ipv6Only,
+            backlog: options.backlog,
+        });
         this.#listening = true;
         this.#listenLoop();
 
@@ -817,7 +862,10 @@ class ServerImpl extends EventEmitter {
                     }
                 }
             } catch (e) {
-                this.emit('error', e)
+                // accept() rejects once close() marks the listener closed.
+                if (this.#listening) {
+                    this.emit('error', e)
+                }
             } finally {
                 this.listening = false
             }
@@ -867,11 +915,10 @@ class ServerImpl extends EventEmitter {
     }
 
     address() {
-        const addr = this.#listener.addr;
-        return {
-            port: addr.port,
-            address: addr.hostname,
-        };
+        if (this.#listener === undefined) {
+            return null;
+        }
+        return this.#listener.localAddress();
     }
 }
 
@@ -362,6 +362,34 @@ export class ERR_SOCKET_BAD_PORT extends RangeError {
     }
 }
 
+export class ERR_FEATURE_UNAVAILABLE_ON_PLATFORM extends TypeError {
+    constructor(feature) {
+        super(`The feature ${feature} is unavailable on the current platform, which is being used to run Node.js`);
+        this.code = "ERR_FEATURE_UNAVAILABLE_ON_PLATFORM";
+    }
+}
+
+export class ERR_SOCKET_BAD_TYPE extends TypeError {
+    constructor() {
+        super("Bad socket type specified. Valid types are: udp4, udp6");
+        this.code = "ERR_SOCKET_BAD_TYPE";
+    }
+}
+
+export class ERR_SOCKET_ALREADY_BOUND extends Error {
+    constructor() {
+        super("Socket is already bound");
+        this.code = "ERR_SOCKET_ALREADY_BOUND";
+    }
+}
+
+export class ERR_SOCKET_DGRAM_NOT_RUNNING extends Error {
+    constructor() {
+        super("Not running");
+        this.code = "ERR_SOCKET_DGRAM_NOT_RUNNING";
+    }
+}
+
 export class ERR_STREAM_PREMATURE_CLOSE extends Error {
     constructor() {
         super(`Premature close`);
@@ -370,8 +398,8 @@ export class ERR_STREAM_PREMATURE_CLOSE extends Error {
 }
 
 export class AbortError extends Error {
-    constructor() {
-        super("The operation was aborted");
+    constructor(message = "The operation was aborted", options = undefined) {
+        super(message, options);
         this.code = "ABORT_ERR";
         this.name = "AbortError";
     }
@@ -936,3 +964,17 @@ export class ERR_CRYPTO_UNKNOWN_CIPHER extends Error {
         this.code = "ERR_CRYPTO_UNKNOWN_CIPHER";
     }
 }
+
+export class ERR_VM_MODULE_NOT_MODULE extends Error {
+    constructor() {
+        super(`Provided module is not an instance of Module`);
+        this.code = "ERR_VM_MODULE_NOT_MODULE";
+    }
+}
+
+export class ERR_VM_MODULE_DIFFERENT_CONTEXT extends Error {
+    constructor() {
+        super(`Linked modules must use the same context`);
+        this.code = "ERR_VM_MODULE_DIFFERENT_CONTEXT";
+    }
+}
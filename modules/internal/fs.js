@@ -1,4 +1,4 @@
-import { validateFunction, validateInteger, validateBoolean } from "../internal/validators"
+import { validateAbortSignal, validateFunction, validateInteger, validateBoolean } from "../internal/validators"
 import { getValidatedPath, getValidMode, Stats, validateBufferArray, validateEncoding, stringToFlags } from "../internal/fs/utils"
 import * as binding from "_node:fs"
 import * as errors from "../internal/errors"
@@ -1643,6 +1643,14 @@ function open(path, flag = "r", mode = 0o666, callback) {
     }, 0);
 }
 
+function abortError(signal) {
+    return new errors.AbortError(undefined, { cause: signal.reason });
+}
+
+// readFile and writeFile go through the file in chunks of this size, so that
+// an aborted signal stops them part way.
+const kIoChunkSize = 512 * 1024;
+
 function readFile(path, option, callback) {
     if (typeof (option) === "function") {
         callback = option;
@@ -1662,6 +1670,12 @@ function readFile(path, option, callback) {
     })
     validateEncoding(option.encoding, "encoding");
     validateFunction(callback, "callback");
+    const signal = option.signal;
+    validateAbortSignal(signal, "options.signal");
+    if (signal?.aborted) {
+        setTimeout(() => callback(abortError(signal)), 0);
+        return;
+    }
 
     let fd;
     if (typeof (path) === "number") {
@@ -1684,19 +1698,38 @@ function readFile(path, option, callback) {
     }
 
     let buf = Buffer.alloc(Math.max(len, 1));
+    let total = 0;
 
-    read(fd, buf, (err, rlen, obuf) => {
+    const done = (err, data) => {
         if (typeof (path) !== "number") {
             closeSync(fd);
         }
-        if (err) {
-            callback(err);
-        } else if (option.encoding !== "") {
-            callback(err, obuf.slice(0, rlen).toString(option.encoding));
-        } else {
-            callback(err, obuf.slice(0, rlen));
+        callback(err, data);
+    };
+    const readChunk = () => {
+        if (signal?.aborted) {
+            done(abortError(signal));
+            return;
         }
-    })
+        const length = Math.min(kIoChunkSize, buf.byteLength - total);
+        read(fd, buf, total, length, -1, (err, rlen) => {
+            if (err) {
+                done(err);
+                return;
+            }
+            total += rlen;
+            if (rlen > 0 && total < buf.byteLength) {
+                readChunk();
+            } else if (signal?.aborted) {
+                done(abortError(signal));
+            } else if (option.encoding !== "") {
+                done(null, buf.slice(0, total).toString(option.encoding));
+            } else {
+                done(null, buf.slice(0, total));
+            }
+        });
+    };
+    readChunk();
 }
 
 
@@ -2028,6 +2061,12 @@ function writeFile(file, data, options, callback) {
         signal: null
     });
     validateFunction(callback, "callback");
+    const signal = options.signal ?? undefined;
+    validateAbortSignal(signal, "options.signal");
+    if (signal?.aborted) {
+        setTimeout(() => callback(abortError(signal)), 0);
+        return;
+    }
     if (typeof (file) === "string") {
         file = getValidatedPath(file);
     }
@@ -2044,16 +2083,33 @@ function writeFile(file, data, options, callback) {
         } else {
             fd = openSync(file, options.flag, options.mode);
         }
-        write(fd, buffer, (err) => {
-            if (err) {
-                callback(err);
-            } else {
-                callback(null);
-            }
+        let written = 0;
+        const done = (err) => {
             if (typeof (file) !== "number") {
                 closeSync(fd);
             }
-        })
+            callback(err);
+        };
+        const writeChunk = () => {
+            if (signal?.aborted) {
+                done(abortError(signal));
+                return;
+            }
+            const length = Math.min(kIoChunkSize, buffer.byteLength - written);
+            write(fd, buffer, written, length, -1, (err, wlen) => {
+                if (err) {
+                    done(err);
+                    return;
+                }
+                written += wlen;
+                if (wlen > 0 && written < buffer.byteLength) {
+                    writeChunk();
+                } else {
+                    done(null);
+                }
+            });
+        };
+        writeChunk();
     } catch (err) {
         callback(err);
     }
@@ -0,0 +1,287 @@
+// `performance` and the entry and observer classes are the runtime's globals,
+// implemented natively. The histograms are kept here.
+
+import { ERR_INVALID_ARG_TYPE, ERR_OUT_OF_RANGE } from './internal/errors';
+import { validateObject } from './internal/validators';
+
+const performance = globalThis.performance;
+const PerformanceEntry = globalThis.PerformanceEntry;
+const PerformanceMark = globalThis.PerformanceMark;
+const PerformanceMeasure = globalThis.PerformanceMeasure;
+const PerformanceObserver = globalThis.PerformanceObserver;
+const PerformanceObserverEntryList = globalThis.PerformanceObserverEntryList;
+
+const constants = {
+  NODE_PERFORMANCE_GC_MAJOR: 4,
+  NODE_PERFORMANCE_GC_MINOR: 1,
+  NODE_PERFORMANCE_GC_INCREMENTAL: 8,
+  NODE_PERFORMANCE_GC_WEAKCB: 16,
+  NODE_PERFORMANCE_GC_FLAGS_NO: 0,
+  NODE_PERFORMANCE_GC_FLAGS_CONSTRUCT_RETAINED: 2,
+  NODE_PERFORMANCE_GC_FLAGS_FORCED: 4,
+  NODE_PERFORMANCE_GC_FLAGS_SYNCHRONOUS_PHANTOM_PROCESSING: 8,
+  NODE_PERFORMANCE_GC_FLAGS_ALL_AVAILABLE_GARBAGE: 16,
+  NODE_PERFORMANCE_GC_FLAGS_ALL_EXTERNAL_MEMORY: 32,
+  NODE_PERFORMANCE_GC_FLAGS_SCHEDULE_IDLE: 64,
+};
+
+// what Node.js reports as the minimum of an empty histogram
+const kEmptyMin = 9223372036854776000;
+
+// Values are counted in buckets of about a thousandth of their magnitude, like
+// an HDR histogram with 3 significant figures, so memory stays bounded.
+function bucketOf(value) {
+  if (value < 2048) {
+    return value;
+  }
+  const unit = 2 ** (Math.floor(Math.log2(value)) - 10);
+  return Math.floor(value / unit) * unit;
+}
+
+class Histogram {
+  #counts = new Map();
+  #count = 0;
+  #min = kEmptyMin;
+  #max = 0;
+  #sum = 0;
+  #sumOfSquares = 0;
+  #exceeds = 0;
+
+  get count() { return this.#count; }
+  get countBigInt() { return BigInt(this.#count); }
+  get min() { return this.#min; }
+  get minBigInt() { return BigInt(this.#min); }
+  get max() { return this.#max; }
+  get maxBigInt() { return BigInt(this.#max); }
+  get exceeds() { return this.#exceeds; }
+  get exceedsBigInt() { return BigInt(this.#exceeds); }
+
+  get mean() {
+    return this.#count === 0 ? NaN : this.#sum / this.#count;
+  }
+
+  get stddev() {
+    if (this.#count === 0) {
+      return NaN;
+    }
+    const mean = this.mean;
+    return Math.sqrt(Math.max(this.#sumOfSquares / this.#count - mean * mean, 0));
+  }
+
+  percentile(percentile) {
+    if (typeof percentile !== 'number') {
+      throw new ERR_INVALID_ARG_TYPE('percentile', 'number', percentile);
+    }
+    if (!(percentile > 0 && percentile <= 100)) {
+      throw new ERR_OUT_OF_RANGE('percentile', '> 0 && <= 100', percentile);
+    }
+    if (this.#count === 0) {
+      return 0;
+    }
+    const rank = Math.ceil((percentile / 100) * this.#count);
+    let seen = 0;
+    for (const [value, count] of this.#sortedBuckets()) {
+      seen += count;
+      if (seen >= rank) {
+        return value;
+      }
+    }
+    return this.#max;
+  }
+
+  percentileBigInt(percentile) {
+    return BigInt(this.percentile(percentile));
+  }
+
+  get percentiles() {
+    const percentiles = new Map();
+    if (this.#count === 0) {
+      percentiles.set(100, 0);
+      return percentiles;
+    }
+    percentiles.set(0, this.#min);
+    let seen = 0;
+    for (const [value, count] of this.#sortedBuckets()) {
+      seen += count;
+      percentiles.set((seen / this.#count) * 100, value);
+    }
+    return percentiles;
+  }
+
+  get percentilesBigInt() {
+    const percentiles = new Map();
+    for (const [percentile, value] of this.percentiles) {
+      percentiles.set(percentile, BigInt(value));
+    }
+    return percentiles;
+  }
+
+  reset() {
+    this.#counts.clear();
+    this.#count = 0;
+    this.#min = kEmptyMin;
+    this.#max = 0;
+    this.#sum = 0;
+    this.#sumOfSquares = 0;
+    this.#exceeds = 0;
+  }
+
+  toJSON() {
+    return {
+      count: this.count,
+      min: this.min,
+      max: this.max,
+      mean: this.mean,
+      exceeds: this.exceeds,
+      stddev: this.stddev,
+      percentiles: Object.fromEntries(this.percentiles),
+    };
+  }
+
+  #sortedBuckets() {
+    return [...this.#counts].sort((a, b) => a[0] - b[0]);
+  }
+
+  // shared by RecordableHistogram and the event loop delay monitor
+  _record(value) {
+    if (value < 1) {
+      this.#exceeds++;
+      return;
+    }
+    const bucket = bucketOf(value);
+    this.#counts.set(bucket, (this.#counts.get(bucket) || 0) + 1);
+    this.#count++;
+    this.#sum += value;
+    this.#sumOfSquares += value * value;
+    if (value < this.#min) {
+      this.#min = value;
+    }
+    if (value > this.#max) {
+      this.#max = value;
+    }
+  }
+
+  _add(other) {
+    for (const [value, count] of other.#counts) {
+      this.#counts.set(value, (this.#counts.get(value) || 0) + count);
+    }
+    this.#count += other.#count;
+    this.#sum += other.#sum;
+    this.#sumOfSquares += other.#sumOfSquares;
+    this.#exceeds += other.#exceeds;
+    this.#min = Math.min(this.#min, other.#min);
+    this.#max = Math.max(this.#max, other.#max);
+  }
+}
+
+class RecordableHistogram extends Histogram {
+  #lastDelta;
+
+  record(value) {
+    if (typeof value === 'bigint') {
+      value = Number(value);
+    }
+    if (typeof value !== 'number') {
+      throw new ERR_INVALID_ARG_TYPE('val', ['number', 'bigint'], value);
+    }
+    if (value < 1 || value > Number.MAX_SAFE_INTEGER) {
+      throw new ERR_OUT_OF_RANGE('val', `>= 1 && <= ${Number.MAX_SAFE_INTEGER}`, value);
+    }
+    this._record(Math.round(value));
+  }
+
+  // records the nanoseconds since the previous call
+  recordDelta() {
+    const now = performance.now();
+    if (this.#lastDelta !== undefined) {
+      this._record(Math.round((now - this.#lastDelta) * 1e6));
+    }
+    this.#lastDelta = now;
+  }
+
+  add(other) {
+    if (!(other instanceof RecordableHistogram)) {
+      throw new ERR_INVALID_ARG_TYPE('other', 'RecordableHistogram', other);
+    }
+    this._add(other);
+  }
+}
+
+// Samples how late a `resolution` ms timer fires, in nanoseconds. The timer
+// does not keep the event loop alive.
+class IntervalHistogram extends Histogram {
+  #resolution;
+  #timer;
+  #last;
+
+  constructor(resolution) {
+    super();
+    this.#resolution = resolution;
+  }
+
+  enable() {
+    if (this.#timer !== undefined) {
+      return false;
+    }
+    this.#last = performance.now();
+    this.#timer = setInterval(() => {
+      const now = performance.now();
+      this._record(Math.round((now - this.#last) * 1e6));
+      this.#last = now;
+    }, this.#resolution);
+    this.#timer.unref();
+    return true;
+  }
+
+  disable() {
+    if (this.#timer === undefined) {
+      return false;
+    }
+    clearInterval(this.#timer);
+    this.#timer = undefined;
+    return true;
+  }
+}
+
+function monitorEventLoopDelay(options = {}) {
+  validateObject(options, 'options');
+  const { resolution = 10 } = options;
+  if (typeof resolution !== 'number') {
+    throw new ERR_INVALID_ARG_TYPE('options.resolution', 'number', resolution);
+  }
+  if (resolution <= 0 || !Number.isSafeInteger(resolution)) {
+    throw new ERR_OUT_OF_RANGE('options.resolution', '> 0', resolution);
+  }
+  return new IntervalHistogram(resolution);
+}
+
+// `lowest`, `highest` and `figures` are accepted for compatibility; the
+// histogram always keeps 3 significant figures
+function createHistogram(options = {}) {
+  validateObject(options, 'options');
+  return new RecordableHistogram();
+}
+
+export {
+  performance,
+  PerformanceEntry,
+  PerformanceMark,
+  PerformanceMeasure,
+  PerformanceObserver,
+  PerformanceObserverEntryList,
+  constants,
+  monitorEventLoopDelay,
+  createHistogram,
+};
+
+export default {
+  performance,
+  PerformanceEntry,
+  PerformanceMark,
+  PerformanceMeasure,
+  PerformanceObserver,
+  PerformanceObserverEntryList,
+  constants,
+  monitorEventLoopDelay,
+  createHistogram,
+};
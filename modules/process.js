@@ -1,3 +1,8 @@
+import { EventEmitter } from 'events';
+import { memoryUsage as nativeMemoryUsage } from '_node:v8';
+import { hrtime as nativeHrtime } from '_node:perf_hooks';
+import { getActiveResourcesInfo, getActiveHandles, getActiveRequests } from '_node:process';
+
 function unimplemented(name) {
   throw new Error('Node.js process ' + name + ' is not supported');
 }
@@ -35,16 +40,20 @@ var _rawDebug = noop;
 var moduleLoadList = [];
 function _linkedBinding(name) { unimplemented('_linkedBinding'); }
 var domain = {};
-var _exiting = false;
 var config = {};
 function dlopen(name) { unimplemented('dlopen'); }
-function _getActiveRequests() { return []; }
-function _getActiveHandles() { return []; }
+function _getActiveRequests() { return getActiveRequests(); }
+function _getActiveHandles() { return getActiveHandles(); }
 var reallyExit = noop;
 var _kill = noop;
 var cpuUsage = function () { return {}; };
 var resourceUsage = cpuUsage;
-var memoryUsage = cpuUsage;
+// heapTotal/heapUsed are the QuickJS allocator totals, external the ArrayBuffer
+// contents and rss the size of the wasm linear memory
+function memoryUsage() {
+  return nativeMemoryUsage();
+}
+memoryUsage.rss = function () { return nativeMemoryUsage().rss; };
 var kill = noop;
 var exit = globalThis.exit;
 var openStdin = noop;
@@ -82,60 +91,32 @@ var argv0 = 'wasmedge-quickjs';
 var _preload_modules = [];
 var setSourceMapsEnabled = noop;
 
-var _performance = {
-  now: undefined,
-  timing: undefined,
-};
-if (_performance.now === undefined) {
-  var nowOffset = Date.now();
-
-  if (_performance.timing && _performance.timing.navigationStart) {
-    nowOffset = _performance.timing.navigationStart;
-  }
-  _performance.now = function () { return Date.now() - nowOffset; };
-}
-
+// seconds since the runtime started, on the monotonic clock of `performance.now()`
 function uptime() {
-  return _performance.now() / 1000;
+  return globalThis.performance.now() / 1000;
 }
 
 var nanoPerSec = 1000000000;
+// `[seconds, nanoseconds]` on a monotonic clock with nanosecond resolution
 function hrtime(previousTimestamp) {
-  var baseNow = Math.floor((Date.now() - _performance.now()) * 1e-3);
-  var clocktime = _performance.now() * 1e-3;
-  var seconds = Math.floor(clocktime) + baseNow;
-  var nanoseconds = Math.floor((clocktime % 1) * 1e9);
+  var time = nativeHrtime();
   if (previousTimestamp) {
-    seconds = seconds - previousTimestamp[0];
-    nanoseconds = nanoseconds - previousTimestamp[1];
+    var seconds = time[0] - previousTimestamp[0];
+    var nanoseconds = time[1] - previousTimestamp[1];
     if (nanoseconds < 0) {
       seconds--;
       nanoseconds += nanoPerSec;
     }
+    return [seconds, nanoseconds];
   }
-  return [seconds, nanoseconds];
+  return time;
 }
 
-hrtime.bigint = function (time) {
-  var diff = hrtime(time);
-  if (typeof BigInt === 'undefined') {
-    return diff[0] * nanoPerSec + diff[1];
-  }
-  return BigInt(diff[0] * nanoPerSec) + BigInt(diff[1]);
+hrtime.bigint = function () {
+  var time = nativeHrtime();
+  return BigInt(time[0]) * BigInt(nanoPerSec) + BigInt(time[1]);
 };
 
-var _maxListeners = 10;
-var _events = {};
-var _eventsCount = 0;
-function on() { return process } var addListener = on;
-var once = on;
-var off = on;
-var removeListener = on;
-var removeAllListeners = on;
-var emit = noop;
-var prependListener = on;
-var prependOnceListener = on;
-function listeners(name) { return []; }
 var process = {
   version: version,
   versions: versions,
@@ -146,26 +127,15 @@ var process = {
   moduleLoadList: moduleLoadList,
   binding: binding,
   _linkedBinding: _linkedBinding,
-  _events: _events,
-  _eventsCount: _eventsCount,
-  _maxListeners: _maxListeners,
-  on: on,
-  addListener: addListener,
-  once: once,
-  off: off,
-  removeListener: removeListener,
-  removeAllListeners: removeAllListeners,
-  emit: emit,
-  prependListener: prependListener,
-  prependOnceListener: prependOnceListener,
-  listeners: listeners,
   domain: domain,
-  _exiting: _exiting,
+  _exiting: false,
+  exitCode: undefined,
   config: config,
   dlopen: dlopen,
   uptime: uptime,
   _getActiveRequests: _getActiveRequests,
   _getActiveHandles: _getActiveHandles,
+  getActiveResourcesInfo: getActiveResourcesInfo,
   reallyExit: reallyExit,
   _kill: _kill,
   cpuUsage: cpuUsage,
@@ -208,6 +178,29 @@ var process = {
   setSourceMapsEnabled: setSourceMapsEnabled,
 };
 
+Object.setPrototypeOf(process, EventEmitter.prototype);
+EventEmitter.init.call(process);
+// the runtime emits `beforeExit` and `exit` through the global
+Object.defineProperty(globalThis, 'process', {
+  value: process,
+  writable: true,
+  configurable: true,
+});
+
+function bindEmitter(name) {
+  return function () { return EventEmitter.prototype[name].apply(process, arguments); };
+}
+var on = bindEmitter('on');
+var addListener = bindEmitter('addListener');
+var once = bindEmitter('once');
+var off = bindEmitter('off');
+var removeListener = bindEmitter('removeListener');
+var removeAllListeners = bindEmitter('removeAllListeners');
+var emit = bindEmitter('emit');
+var prependListener = bindEmitter('prependListener');
+var prependOnceListener = bindEmitter('prependOnceListener');
+var listeners = bindEmitter('listeners');
+
 const nextTick = globalThis.nextTick;
 
-export { _debugEnd, _debugProcess, _events, _eventsCount, _exiting, _fatalExceptions, _getActiveHandles, _getActiveRequests, _kill, _linkedBinding, _maxListeners, _preload_modules, _rawDebug, _startProfilerIdleNotifier, _stopProfilerIdleNotifier, _tickCallback, abort, addListener, allowedNodeEnvironmentFlags, arch, argv, argv0, assert, binding, chdir, config, cpuUsage, cwd, debugPort, process as default, dlopen, domain, emit, emitWarning, env, execArgv, execPath, exit, features, hasUncaughtExceptionCaptureCallback, hrtime, kill, listeners, memoryUsage, moduleLoadList, nextTick, off, on, once, openStdin, pid, platform, ppid, prependListener, prependOnceListener, reallyExit, release, removeAllListeners, removeListener, resourceUsage, setSourceMapsEnabled, setUncaughtExceptionCaptureCallback, stderr, stdin, stdout, title, umask, uptime, version, versions };
+export { _debugEnd, _debugProcess, _fatalExceptions, _getActiveHandles, _getActiveRequests, _kill, _linkedBinding, _preload_modules, _rawDebug, _startProfilerIdleNotifier, _stopProfilerIdleNotifier, _tickCallback, abort, addListener, allowedNodeEnvironmentFlags, arch, argv, argv0, assert, binding, chdir, config, cpuUsage, cwd, debugPort, process as default, dlopen, domain, emit, emitWarning, env, execArgv, execPath, exit, features, getActiveResourcesInfo, hasUncaughtExceptionCaptureCallback, hrtime, kill, listeners, memoryUsage, moduleLoadList, nextTick, off, on, once, openStdin, pid, platform, ppid, prependListener, prependOnceListener, reallyExit, release, removeAllListeners, removeListener, resourceUsage, setSourceMapsEnabled, setUncaughtExceptionCaptureCallback, stderr, stdin, stdout, title, umask, uptime, version, versions };
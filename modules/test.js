@@ -0,0 +1,855 @@
+// A test runner in the shape of `node:test`.
+//
+// Tests declared at the top level of a file are collected while the file is
+// evaluated and run once it has finished. Results are printed with the TAP
+// reporter, or the spec reporter when WASMEDGE_QUICKJS_TEST_REPORTER=spec, and
+// process.exitCode is set to 1 if any test failed.
+//
+// `only` is honoured among the tests and suites declared next to each other;
+// it does not need a command line flag.
+
+import process from 'process';
+import { inspect } from 'util';
+import { setVirtualClock, advanceClock, runAllTimers } from '_node:timers';
+
+const kPass = 'pass';
+const kFail = 'fail';
+const kSkip = 'skip';
+const kTodo = 'todo';
+
+// test durations are measured in real time, even while mock timers are enabled
+const RealDate = Date;
+
+function now() {
+  return RealDate.now();
+}
+
+class MockFunctionContext {
+  #calls = [];
+  #implementation;
+  #onceImplementations = new Map();
+  #restore;
+
+  constructor(implementation, restore) {
+    this.#implementation = implementation;
+    this.#restore = restore;
+  }
+
+  get calls() {
+    return this.#calls.slice();
+  }
+
+  callCount() {
+    return this.#calls.length;
+  }
+
+  mockImplementation(implementation) {
+    this.#implementation = implementation;
+  }
+
+  mockImplementationOnce(implementation, onCall = this.#calls.length) {
+    this.#onceImplementations.set(onCall, implementation);
+  }
+
+  resetCalls() {
+    this.#calls = [];
+  }
+
+  restore() {
+    if (this.#restore) {
+      this.#restore();
+      this.#restore = undefined;
+    }
+  }
+
+  // used by the mock function itself
+  _nextImplementation() {
+    const call = this.#calls.length;
+    const once = this.#onceImplementations.get(call);
+    if (once !== undefined) {
+      this.#onceImplementations.delete(call);
+      return once;
+    }
+    return this.#implementation;
+  }
+
+  _record(call) {
+    this.#calls.push(call);
+  }
+}
+
+// `mock.timers`: runs the runtime's timers on a virtual clock that only moves
+// when ticked. The timer functions are mocked together, whatever `apis` lists;
+// `Date` only if it is listed.
+class MockTimers {
+  #enabled = false;
+  #dateMocked = false;
+  // the mocked Date.now() at `#performanceAt` on the virtual clock
+  #dateNow = 0;
+  #performanceAt = 0;
+
+  enable(options = {}) {
+    if (this.#enabled) {
+      throw new Error('MockTimers is already enabled!');
+    }
+    const { apis = ['setInterval', 'setTimeout', 'setImmediate', 'Date'], now = 0 } = options;
+    if (!Array.isArray(apis)) {
+      throw new TypeError('The "options.apis" property must be an array');
+    }
+    const nowMs = now instanceof RealDate ? now.getTime() : now;
+    if (typeof nowMs !== 'number' || !(nowMs >= 0)) {
+      throw new RangeError('The "options.now" property must be a positive number or a Date');
+    }
+    setVirtualClock(true);
+    this.#enabled = true;
+    if (apis.includes('Date')) {
+      this.#mockDate(nowMs);
+    }
+  }
+
+  tick(milliseconds = 1) {
+    this.#assertEnabled('tick');
+    if (typeof milliseconds !== 'number' || milliseconds < 0) {
+      throw new RangeError('The "milliseconds" argument must be a positive number');
+    }
+    advanceClock(milliseconds);
+  }
+
+  runAll() {
+    this.#assertEnabled('runAll');
+    runAllTimers();
+  }
+
+  setTime(milliseconds) {
+    this.#assertEnabled('setTime');
+    this.#dateNow = milliseconds;
+    this.#performanceAt = performance.now();
+  }
+
+  reset() {
+    if (!this.#enabled) {
+      return;
+    }
+    if (this.#dateMocked) {
+      globalThis.Date = RealDate;
+      this.#dateMocked = false;
+    }
+    setVirtualClock(false);
+    this.#enabled = false;
+  }
+
+  [Symbol.dispose]() {
+    this.reset();
+  }
+
+  #assertEnabled(method) {
+    if (!this.#enabled) {
+      throw new Error(`You should enable MockTimers first by calling the .enable function before calling .${method}`);
+    }
+  }
+
+  #mockDate(now) {
+    this.#dateNow = now;
+    this.#performanceAt = performance.now();
+    const mockNow = () => this.#dateNow + (performance.now() - this.#performanceAt);
+    function MockDate(...args) {
+      if (new.target === undefined) {
+        return new RealDate(mockNow()).toString();
+      }
+      return Reflect.construct(RealDate, args.length === 0 ? [mockNow()] : args, new.target);
+    }
+    Object.setPrototypeOf(MockDate, RealDate);
+    Object.defineProperty(MockDate, 'prototype', { value: RealDate.prototype });
+    MockDate.now = mockNow;
+    globalThis.Date = MockDate;
+    this.#dateMocked = true;
+  }
+}
+
+class MockTracker {
+  #mocks = [];
+  #timers;
+
+  get timers() {
+    this.#timers ??= new MockTimers();
+    return this.#timers;
+  }
+
+  #create(original, implementation, options, restore) {
+    const ctx = new MockFunctionContext(implementation, restore);
+    const mock = function (...args) {
+      const impl = ctx._nextImplementation();
+      const call = { arguments: args, error: undefined, result: undefined, target: new.target, this: this };
+      try {
+        call.result = new.target ? Reflect.construct(impl, args, new.target) : Reflect.apply(impl, this, args);
+        return call.result;
+      } catch (err) {
+        call.error = err;
+        throw err;
+      } finally {
+        ctx._record(call);
+      }
+    };
+    Object.defineProperty(mock, 'name', { value: original.name, configurable: true });
+    Object.defineProperty(mock, 'length', { value: original.length, configurable: true });
+    Object.defineProperty(mock, 'mock', { value: ctx });
+    if (options.times !== undefined) {
+      // `implementation` for the first `times` calls, then the original
+      for (let i = 0; i < options.times; i++) {
+        ctx.mockImplementationOnce(implementation, i);
+      }
+      ctx.mockImplementation(original);
+    }
+    this.#mocks.push(ctx);
+    return mock;
+  }
+
+  fn(original = function () { }, implementation = original, options = {}) {
+    if (implementation !== null && typeof implementation === 'object') {
+      options = implementation;
+      implementation = original;
+    }
+    return this.#create(original, implementation, options);
+  }
+
+  method(object, methodName, implementation, options = {}) {
+    if (implementation !== null && typeof implementation === 'object') {
+      options = implementation;
+      implementation = undefined;
+    }
+    const { getter = false, setter = false } = options;
+    if (getter && setter) {
+      throw new TypeError('The "options.getter" and "options.setter" options cannot be used together');
+    }
+    let descriptor;
+    for (let owner = object; owner !== null && owner !== undefined; owner = Object.getPrototypeOf(owner)) {
+      descriptor = Object.getOwnPropertyDescriptor(owner, methodName);
+      if (descriptor !== undefined) {
+        break;
+      }
+    }
+    const kind = getter ? 'get' : setter ? 'set' : 'value';
+    const original = descriptor?.[kind];
+    if (typeof original !== 'function') {
+      throw new TypeError(`The argument 'methodName' must be a method. Received ${String(methodName)}`);
+    }
+    const ownDescriptor = Object.getOwnPropertyDescriptor(object, methodName);
+    const restore = () => {
+      if (ownDescriptor === undefined) {
+        delete object[methodName];
+      } else {
+        Object.defineProperty(object, methodName, ownDescriptor);
+      }
+    };
+    const mock = this.#create(original, implementation ?? original, options, restore);
+    Object.defineProperty(object, methodName, { ...descriptor, [kind]: mock });
+    return mock;
+  }
+
+  getter(object, methodName, implementation, options = {}) {
+    return this.method(object, methodName, implementation, { ...options, getter: true });
+  }
+
+  setter(object, methodName, implementation, options = {}) {
+    return this.method(object, methodName, implementation, { ...options, setter: true });
+  }
+
+  reset() {
+    this.restoreAll();
+    this.#mocks = [];
+    this.#timers?.reset();
+  }
+
+  restoreAll() {
+    for (const mock of this.#mocks) {
+      mock.restore();
+    }
+  }
+}
+
+class TestContext {
+  #test;
+
+  constructor(test) {
+    this.#test = test;
+  }
+
+  get name() {
+    return this.#test.name;
+  }
+
+  get fullName() {
+    return this.#test.fullName;
+  }
+
+  get mock() {
+    this.#test.mock ??= new MockTracker();
+    return this.#test.mock;
+  }
+
+  diagnostic(message) {
+    this.#test.diagnostics.push(String(message));
+  }
+
+  skip(message) {
+    this.#test.status = kSkip;
+    this.#test.reason = message;
+  }
+
+  todo(message) {
+    this.#test.status = kTodo;
+    this.#test.reason = message;
+  }
+
+  before(fn, options) {
+    this.#test.hooks.before.push(new Hook(fn, options));
+  }
+
+  after(fn, options) {
+    this.#test.hooks.after.push(new Hook(fn, options));
+  }
+
+  beforeEach(fn, options) {
+    this.#test.hooks.beforeEach.push(new Hook(fn, options));
+  }
+
+  afterEach(fn, options) {
+    this.#test.hooks.afterEach.push(new Hook(fn, options));
+  }
+
+  test(name, options, fn) {
+    return this.#test.subtest(new Test(this.#test, ...parseArgs(name, options, fn)));
+  }
+}
+
+class SuiteContext {
+  #suite;
+
+  constructor(suite) {
+    this.#suite = suite;
+  }
+
+  get name() {
+    return this.#suite.name;
+  }
+
+  get fullName() {
+    return this.#suite.fullName;
+  }
+}
+
+class Hook {
+  constructor(fn, options = {}) {
+    this.fn = fn;
+    this.timeout = options.timeout;
+  }
+
+  run(context) {
+    return runWithTimeout(this.fn, context, this.timeout);
+  }
+}
+
+function parseArgs(name, options, fn) {
+  if (typeof name === 'function') {
+    fn = name;
+    options = {};
+    name = fn.name || '<anonymous>';
+  } else if (typeof options === 'function') {
+    fn = options;
+    options = {};
+  }
+  options ??= {};
+  if (name === undefined) {
+    name = fn?.name || '<anonymous>';
+  }
+  return [String(name), options, fn];
+}
+
+function runWithTimeout(fn, context, timeout, withDone) {
+  return new Promise((resolve, reject) => {
+    let timer;
+    const settle = (err) => {
+      if (timer !== undefined) {
+        clearTimeout(timer);
+      }
+      err === undefined ? resolve() : reject(err);
+    };
+    if (typeof timeout === 'number' && timeout !== Infinity) {
+      timer = setTimeout(() => {
+        timer = undefined;
+        const err = new Error(`test timed out after ${timeout}ms`);
+        err.failureType = 'testTimeoutFailure';
+        reject(err);
+      }, Math.max(1, Math.ceil(timeout)));
+    }
+    try {
+      if (withDone) {
+        fn.call(context, context, (err) => settle(err ?? undefined));
+        return;
+      }
+      const result = fn.call(context, context);
+      if (result !== null && typeof result?.then === 'function') {
+        result.then(() => settle(), (err) => settle(err ?? new Error('rejected with a falsy value')));
+      } else {
+        settle();
+      }
+    } catch (err) {
+      settle(err);
+    }
+  });
+}
+
+class Test {
+  constructor(parent, name, options, fn) {
+    this.parent = parent;
+    this.name = name;
+    this.fn = fn;
+    this.options = options;
+    this.only = Boolean(options.only);
+    this.timeout = options.timeout;
+    this.status = options.todo ? kTodo : options.skip ? kSkip : undefined;
+    const reason = options.todo || options.skip;
+    this.reason = typeof reason === 'string' ? reason : undefined;
+    this.error = undefined;
+    this.duration = 0;
+    this.diagnostics = [];
+    this.hooks = { before: [], after: [], beforeEach: [], afterEach: [] };
+    this.children = [];
+    this.mock = undefined;
+    this.depth = parent ? parent.depth + 1 : -1;
+    this.context = undefined;
+    this.chain = Promise.resolve();
+    this.beforeDone = false;
+  }
+
+  get isSuite() {
+    return false;
+  }
+
+  get fullName() {
+    return this.parent && this.parent.depth >= 0 ? `${this.parent.fullName} > ${this.name}` : this.name;
+  }
+
+  // `t.test()`: subtests run one at a time, in the order they were declared
+  subtest(test) {
+    this.children.push(test);
+    const run = this.chain.then(async () => {
+      if (!this.beforeDone) {
+        this.beforeDone = true;
+        for (const hook of this.hooks.before) {
+          await hook.run(this.context);
+        }
+      }
+      await test.run();
+    });
+    this.chain = run;
+    return run;
+  }
+
+  // the `beforeEach`/`afterEach` hooks of every ancestor, outermost first for `beforeEach`
+  *eachHooks(kind) {
+    const chain = [];
+    for (let test = this.parent; test; test = test.parent) {
+      chain.push(test);
+    }
+    if (kind === 'beforeEach') {
+      chain.reverse();
+    }
+    for (const test of chain) {
+      yield* test.hooks[kind];
+    }
+  }
+
+  async run() {
+    const start = now();
+    reporter.start(this);
+    if (this.status !== kSkip) {
+      const context = new TestContext(this);
+      this.context = context;
+      try {
+        for (const hook of this.eachHooks('beforeEach')) {
+          await hook.run(context);
+        }
+        if (typeof this.fn === 'function') {
+          await runWithTimeout(this.fn, context, this.timeout, this.fn.length >= 2);
+        }
+        await this.chain;
+      } catch (err) {
+        this.error = err;
+      }
+      try {
+        for (const hook of this.hooks.after) {
+          await hook.run(context);
+        }
+        for (const hook of this.eachHooks('afterEach')) {
+          await hook.run(context);
+        }
+      } catch (err) {
+        this.error ??= err;
+      }
+      this.mock?.reset();
+      const failed = this.children.filter((child) => child.status === kFail).length;
+      if (this.error === undefined && failed > 0) {
+        this.error = new Error(`${failed} subtest(s) failed`);
+        this.error.failureType = 'subtestsFailed';
+      }
+      // a todo test stays todo whether or not it failed
+      this.status ??= this.error === undefined ? kPass : kFail;
+    }
+    this.duration = now() - start;
+    reporter.complete(this);
+  }
+}
+
+class Suite extends Test {
+  constructor(parent, name, options, fn) {
+    super(parent, name, options, fn);
+    this.built = undefined;
+    if (typeof fn === 'function' && this.status !== kSkip) {
+      const prev = currentSuite;
+      currentSuite = this;
+      try {
+        const result = fn.call(new SuiteContext(this), new SuiteContext(this));
+        if (result !== null && typeof result?.then === 'function') {
+          this.built = Promise.resolve(result).catch((err) => { this.error = err; });
+        }
+      } catch (err) {
+        this.error = err;
+      } finally {
+        currentSuite = prev;
+      }
+    }
+  }
+
+  get isSuite() {
+    return true;
+  }
+
+  async run() {
+    const start = now();
+    reporter.start(this);
+    await this.built;
+    if (this.status === undefined && this.error === undefined) {
+      const context = new SuiteContext(this);
+      try {
+        for (const hook of this.hooks.before) {
+          await hook.run(context);
+        }
+        const hasOnly = this.children.some((child) => child.only);
+        for (const child of this.children) {
+          if (hasOnly && !child.only && child.status === undefined) {
+            child.status = kSkip;
+            child.reason = "'only' option not set";
+          }
+          await child.run();
+        }
+      } catch (err) {
+        this.error = err;
+      }
+      try {
+        for (const hook of this.hooks.after) {
+          await hook.run(context);
+        }
+      } catch (err) {
+        this.error ??= err;
+      }
+    }
+    if (this.status === undefined) {
+      const failed = this.children.filter((child) => child.status === kFail).length;
+      if (this.error === undefined && failed > 0) {
+        this.error = new Error(`${failed} subtest(s) failed`);
+        this.error.failureType = 'subtestsFailed';
+      }
+      this.status = this.error === undefined ? kPass : kFail;
+    }
+    this.duration = now() - start;
+    reporter.complete(this);
+  }
+}
+
+class Root extends Suite {
+  constructor() {
+    super(undefined, '<root>', {}, undefined);
+    this.started = false;
+  }
+
+  add(test) {
+    this.children.push(test);
+    if (!this.started) {
+      this.started = true;
+      // let the rest of the file declare its tests first
+      setTimeout(() => this.runAll(), 0);
+    }
+  }
+
+  async runAll() {
+    const start = now();
+    reporter.begin();
+    try {
+      for (const hook of this.hooks.before) {
+        await hook.run(undefined);
+      }
+      const hasOnly = this.children.some((child) => child.only);
+      for (let i = 0; i < this.children.length; i++) {
+        const child = this.children[i];
+        if (hasOnly && !child.only && child.status === undefined) {
+          child.status = kSkip;
+          child.reason = "'only' option not set";
+        }
+        await child.run();
+      }
+      for (const hook of this.hooks.after) {
+        await hook.run(undefined);
+      }
+    } catch (err) {
+      this.error = err;
+    }
+    this.duration = now() - start;
+    const counts = reporter.end(this);
+    if (counts.fail > 0 || this.error !== undefined) {
+      process.exitCode = 1;
+    }
+  }
+}
+
+function indent(depth) {
+  return '    '.repeat(Math.max(0, depth));
+}
+
+function errorMessage(err) {
+  if (err instanceof Error) {
+    return err.message;
+  }
+  return inspect(err);
+}
+
+function yamlString(value) {
+  return `'${String(value).replace(/'/g, "''")}'`;
+}
+
+function countTests(test, counts) {
+  for (const child of test.children) {
+    if (child.isSuite) {
+      counts.suites++;
+      countTests(child, counts);
+      if (child.status === kFail && child.children.every((c) => c.status !== kFail)) {
+        counts.fail++;
+      }
+      continue;
+    }
+    counts.tests++;
+    counts[child.status]++;
+  }
+  return counts;
+}
+
+function summary(root) {
+  return countTests(root, { tests: 0, suites: 0, pass: 0, fail: 0, skip: 0, todo: 0 });
+}
+
+class TapReporter {
+  #numbers = new Map();
+
+  begin() {
+    console.log('TAP version 13');
+  }
+
+  start(test) {
+    console.log(`${indent(test.depth)}# Subtest: ${test.name}`);
+  }
+
+  complete(test) {
+    const pad = indent(test.depth);
+    const n = (this.#numbers.get(test.parent) ?? 0) + 1;
+    this.#numbers.set(test.parent, n);
+    if (this.#numbers.has(test)) {
+      console.log(`${indent(test.depth + 1)}1..${this.#numbers.get(test)}`);
+    }
+    const ok = test.error === undefined || test.status === kSkip;
+    let line = `${pad}${ok ? 'ok' : 'not ok'} ${n} - ${test.name}`;
+    if (test.status === kSkip) {
+      line += ' # SKIP' + (test.reason ? ` ${test.reason}` : '');
+    } else if (test.status === kTodo) {
+      line += ' # TODO' + (test.reason ? ` ${test.reason}` : '');
+    }
+    console.log(line);
+    console.log(`${pad}  ---`);
+    console.log(`${pad}  duration_ms: ${test.duration}`);
+    if (test.isSuite) {
+      console.log(`${pad}  type: 'suite'`);
+    }
+    if (test.error !== undefined) {
+      const err = test.error;
+      console.log(`${pad}  failureType: '${err?.failureType ?? (test.isSuite ? 'hookFailed' : 'testCodeFailure')}'`);
+      const message = errorMessage(err);
+      if (message.includes('\n')) {
+        console.log(`${pad}  error: |-`);
+        for (const l of message.split('\n')) {
+          console.log(`${pad}    ${l}`);
+        }
+      } else {
+        console.log(`${pad}  error: ${yamlString(message)}`);
+      }
+      if (err?.code !== undefined) {
+        console.log(`${pad}  code: ${yamlString(err.code)}`);
+      }
+      if (typeof err?.stack === 'string' && err.stack.trim() !== '') {
+        console.log(`${pad}  stack: |-`);
+        for (const l of err.stack.trimEnd().split('\n')) {
+          console.log(`${pad}    ${l.trim()}`);
+        }
+      }
+    }
+    console.log(`${pad}  ...`);
+    for (const diagnostic of test.diagnostics) {
+      console.log(`${pad}# ${diagnostic}`);
+    }
+  }
+
+  end(root) {
+    const counts = summary(root);
+    console.log(`1..${this.#numbers.get(root) ?? 0}`);
+    if (root.error !== undefined) {
+      console.log(`# Error: ${errorMessage(root.error)}`);
+    }
+    for (const key of ['tests', 'suites', 'pass', 'fail', 'skip', 'todo']) {
+      console.log(`# ${key === 'skip' ? 'skipped' : key} ${counts[key]}`);
+    }
+    console.log(`# duration_ms ${root.duration}`);
+    return counts;
+  }
+}
+
+class SpecReporter {
+  #failures = [];
+
+  begin() { }
+
+  start(test) {
+    if (test.isSuite) {
+      console.log(`${'  '.repeat(test.depth)}▶ ${test.name}`);
+    }
+  }
+
+  complete(test) {
+    const pad = '  '.repeat(test.depth);
+    const time = `(${test.duration}ms)`;
+    let mark = { pass: '✔', fail: '✖', skip: '﹣', todo: '✔' }[test.status];
+    let suffix = '';
+    if (test.status === kSkip) {
+      suffix = ' # SKIP' + (test.reason ? ` ${test.reason}` : '');
+    } else if (test.status === kTodo) {
+      suffix = ' # TODO' + (test.reason ? ` ${test.reason}` : '');
+    }
+    if (test.isSuite) {
+      mark = test.status === kFail ? '✖' : '▶';
+    }
+    console.log(`${pad}${mark} ${test.name} ${time}${suffix}`);
+    for (const diagnostic of test.diagnostics) {
+      console.log(`${pad}  ℹ ${diagnostic}`);
+    }
+    if (test.error !== undefined && test.error?.failureType !== 'subtestsFailed') {
+      this.#failures.push(test);
+      const text = test.error instanceof Error ? (test.error.stack || String(test.error)) : inspect(test.error);
+      const message = test.error instanceof Error && !text.includes(test.error.message) ? `${test.error.name}: ${test.error.message}\n${text}` : text;
+      for (const l of message.trimEnd().split('\n')) {
+        console.log(`${pad}  ${l}`);
+      }
+    }
+  }
+
+  end(root) {
+    const counts = summary(root);
+    if (root.error !== undefined) {
+      console.log(`✖ ${errorMessage(root.error)}`);
+    }
+    console.log(`ℹ tests ${counts.tests}`);
+    console.log(`ℹ suites ${counts.suites}`);
+    console.log(`ℹ pass ${counts.pass}`);
+    console.log(`ℹ fail ${counts.fail}`);
+    console.log(`ℹ skipped ${counts.skip}`);
+    console.log(`ℹ todo ${counts.todo}`);
+    console.log(`ℹ duration_ms ${root.duration}`);
+    if (this.#failures.length > 0) {
+      console.log('\n✖ failing tests:\n');
+      for (const test of this.#failures) {
+        console.log(`✖ ${test.fullName} (${test.duration}ms)`);
+        console.log(`  ${errorMessage(test.error).split('\n').join('\n  ')}`);
+      }
+    }
+    return counts;
+  }
+}
+
+const reporter = process.env.WASMEDGE_QUICKJS_TEST_REPORTER === 'spec'
+  ? new SpecReporter()
+  : new TapReporter();
+
+const root = new Root();
+let currentSuite = root;
+
+function declare(test) {
+  const parent = currentSuite;
+  if (parent === root) {
+    root.add(test);
+  } else {
+    parent.children.push(test);
+  }
+}
+
+function withModifiers(fn) {
+  fn.skip = (name, options, body) => {
+    [name, options, body] = parseArgs(name, options, body);
+    return fn(name, { ...options, skip: true }, body);
+  };
+  fn.todo = (name, options, body) => {
+    [name, options, body] = parseArgs(name, options, body);
+    return fn(name, { ...options, todo: true }, body);
+  };
+  fn.only = (name, options, body) => {
+    [name, options, body] = parseArgs(name, options, body);
+    return fn(name, { ...options, only: true }, body);
+  };
+  return fn;
+}
+
+const test = withModifiers(function test(name, options, fn) {
+  declare(new Test(currentSuite, ...parseArgs(name, options, fn)));
+  return Promise.resolve();
+});
+
+const describe = withModifiers(function describe(name, options, fn) {
+  declare(new Suite(currentSuite, ...parseArgs(name, options, fn)));
+  return Promise.resolve();
+});
+
+const it = withModifiers(function it(name, options, fn) {
+  return test(name, options, fn);
+});
+
+const suite = describe;
+
+function hook(kind) {
+  return function (fn, options) {
+    currentSuite.hooks[kind].push(new Hook(fn, options));
+  };
+}
+
+const before = hook('before');
+const after = hook('after');
+const beforeEach = hook('beforeEach');
+const afterEach = hook('afterEach');
+
+const mock = new MockTracker();
+
+test.test = test;
+test.describe = describe;
+test.it = it;
+test.suite = suite;
+test.before = before;
+test.after = after;
+test.beforeEach = beforeEach;
+test.afterEach = afterEach;
+test.mock = mock;
+
+export { test as default, test, describe, it, suite, before, after, beforeEach, afterEach, mock, MockTracker };
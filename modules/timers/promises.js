@@ -1,26 +1,123 @@
-import { promisify } from 'util.js';
-import exports from 'timers.js';
+import { AbortError, ERR_INVALID_ARG_TYPE } from '../internal/errors';
+import { validateAbortSignal, validateObject } from '../internal/validators';
 
-function promisifySetTimeout(delay, value, _option) {
-    return new Promise((resolve, _reject) => {
-        exports.setTimeout(resolve(value), delay)
-    })
+function validateOptions(options) {
+    validateObject(options, 'options');
+    const { signal, ref = true } = options;
+    validateAbortSignal(signal, 'options.signal');
+    if (typeof ref !== 'boolean') {
+        throw new ERR_INVALID_ARG_TYPE('options.ref', 'boolean', ref);
+    }
+    return { signal, ref };
 }
 
-function promisifySetImmediate(delay, value, _option) {
-    return new Promise((resolve, _reject) => {
-        exports.setImmediate(resolve(value), delay)
-    })
+function abortError(signal) {
+    return new AbortError(undefined, { cause: signal.reason });
 }
 
-const setTimeout = promisifySetTimeout,
-    setImmediate = promisifySetImmediate,
-    setInterval = promisify(exports.setInterval);
+// settles with `value` when the timer fires; aborting `signal` clears the timer
+function startTimer(start, clear, value, options) {
+    let signal, ref;
+    try {
+        ({ signal, ref } = validateOptions(options));
+    } catch (err) {
+        return Promise.reject(err);
+    }
+    if (signal?.aborted) {
+        return Promise.reject(abortError(signal));
+    }
+    return new Promise((resolve, reject) => {
+        let onAbort;
+        const timer = start(() => {
+            signal?.removeEventListener('abort', onAbort);
+            resolve(value);
+        });
+        if (!ref) {
+            timer.unref();
+        }
+        if (signal) {
+            onAbort = () => {
+                clear(timer);
+                reject(abortError(signal));
+            };
+            signal.addEventListener('abort', onAbort);
+        }
+    });
+}
+
+function setTimeout(delay, value, options = {}) {
+    return startTimer(
+        (callback) => globalThis.setTimeout(callback, delay),
+        globalThis.clearTimeout,
+        value,
+        options,
+    );
+}
+
+function setImmediate(value, options = {}) {
+    return startTimer(
+        (callback) => globalThis.setImmediate(callback),
+        globalThis.clearImmediate,
+        value,
+        options,
+    );
+}
+
+// yields `value` every `delay` ms; ticks that nobody is waiting for are counted
+// and handed out on the next `next()` calls
+async function* setInterval(delay, value, options = {}) {
+    const { signal, ref } = validateOptions(options);
+    if (signal?.aborted) {
+        throw abortError(signal);
+    }
+    let pending = 0;
+    let wake;
+    let onAbort;
+    const interval = globalThis.setInterval(() => {
+        pending++;
+        wake?.();
+    }, delay);
+    if (!ref) {
+        interval.unref();
+    }
+    if (signal) {
+        onAbort = () => {
+            globalThis.clearInterval(interval);
+            wake?.();
+        };
+        signal.addEventListener('abort', onAbort);
+    }
+    try {
+        while (!signal?.aborted) {
+            if (pending === 0) {
+                await new Promise((resolve) => { wake = resolve; });
+                wake = undefined;
+                continue;
+            }
+            pending--;
+            yield value;
+        }
+        throw abortError(signal);
+    } finally {
+        globalThis.clearInterval(interval);
+        signal?.removeEventListener('abort', onAbort);
+    }
+}
+
+const scheduler = {
+    wait(delay, options) {
+        return setTimeout(delay, undefined, options);
+    },
+    yield() {
+        return setImmediate();
+    },
+};
 
 var promises = {
     setTimeout,
     setImmediate,
-    setInterval
+    setInterval,
+    scheduler
 };
 
-export { promises as default, setImmediate, setInterval, setTimeout };
+export { promises as default, scheduler, setImmediate, setInterval, setTimeout };
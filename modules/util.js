@@ -4,10 +4,12 @@ import types from "util/types";
 import { Buffer } from "buffer";
 import { ERR_INVALID_ARG_TYPE } from "./internal/errors.js";
 import * as encoding from 'encoding';
+import { inspect, format, formatWithOptions } from "./internal/util/inspect.js";
 
 export const debuglog = debuglog
 export const promisify = promisify;
 export const deprecate = deprecate;
+export { inspect, format, formatWithOptions };
 
 class NodeFalsyValueRejectionError extends Error {
     reason;
@@ -222,4 +224,7 @@ export default {
     TextEncoder,
     log,
     debuglog,
+    inspect,
+    format,
+    formatWithOptions,
 };
@@ -0,0 +1,35 @@
+// The serialization format is this runtime's own structured clone encoding,
+// not V8's wire format: data written here can only be read back by v8.deserialize
+// (or any other structured clone consumer such as worker_threads).
+
+import {
+  serialize as nativeSerialize,
+  deserialize as nativeDeserialize,
+  heapStatistics,
+} from '_node:v8';
+import { Buffer } from 'buffer';
+import { ERR_INVALID_ARG_TYPE } from './internal/errors';
+
+export function serialize(value) {
+  return Buffer.from(nativeSerialize(value));
+}
+
+export function deserialize(buffer) {
+  if (!ArrayBuffer.isView(buffer)) {
+    throw new ERR_INVALID_ARG_TYPE('buffer', ['Buffer', 'TypedArray', 'DataView'], buffer);
+  }
+  const data = buffer.buffer.slice(buffer.byteOffset, buffer.byteOffset + buffer.byteLength);
+  return nativeDeserialize(data);
+}
+
+// QuickJS has a single heap, so the fields describe the whole runtime.
+// `total_physical_size` is the size of the wasm linear memory.
+export function getHeapStatistics() {
+  return heapStatistics();
+}
+
+export default {
+  serialize,
+  deserialize,
+  getHeapStatistics,
+};
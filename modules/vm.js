@@ -0,0 +1,318 @@
+// Contexts are separate QuickJS realms on the current runtime. The global of a
+// context is backed by the sandbox object passed to createContext: a Proxy in the
+// global's prototype chain reads and writes the sandbox, and the context's
+// globalThis is a Proxy that also defines and deletes on it, so both sides see
+// changes as they happen. A name found in neither the global nor the sandbox
+// reads as undefined instead of throwing a ReferenceError.
+//
+// var and function declarations still define properties on the real global; the
+// sandbox gets accessors for them after each run.
+
+import {
+  VmContext,
+  VmModule,
+  compileScript,
+  checkCachedData,
+  runScript,
+  moduleRequests,
+} from '_node:vm';
+import { Buffer } from 'buffer';
+import { validateString, validateObject, validateFunction } from './internal/validators';
+import {
+  ERR_INVALID_ARG_TYPE,
+  ERR_VM_MODULE_DIFFERENT_CONTEXT,
+  ERR_VM_MODULE_NOT_MODULE,
+} from './internal/errors';
+
+const kVmContext = Symbol('kVmContext');
+
+function getContext(contextifiedObject) {
+  if (!isContext(contextifiedObject)) {
+    throw new ERR_INVALID_ARG_TYPE('contextifiedObject', 'vm.Context', contextifiedObject);
+  }
+  return contextifiedObject[kVmContext];
+}
+
+function getTimeout(options) {
+  if (options === undefined || options === null || typeof options !== 'object') {
+    return undefined;
+  }
+  return options.timeout;
+}
+
+function hasOwn(object, key) {
+  return Object.prototype.hasOwnProperty.call(object, key);
+}
+
+function backGlobal(sandbox, global) {
+  const proto = Object.getPrototypeOf(global);
+  // names the global doesn't have itself are looked up on the sandbox, then on
+  // the context's Object.prototype
+  Object.setPrototypeOf(global, new Proxy(sandbox, {
+    get: (target, key) => (key in target ? target[key] : Reflect.get(proto, key, global)),
+    set: (target, key, value) => Reflect.set(target, key, value),
+    has: (target, key) => key in target || key in proto,
+  }));
+  const globalProxy = new Proxy(global, {
+    set: (target, key, value) => Reflect.set(target, key, value),
+    defineProperty: (target, key, desc) =>
+      Reflect.defineProperty(hasOwn(target, key) ? target : sandbox, key, desc),
+    deleteProperty: (target, key) => {
+      if (hasOwn(target, key) && !Reflect.deleteProperty(target, key)) {
+        return false;
+      }
+      return Reflect.deleteProperty(sandbox, key);
+    },
+    getOwnPropertyDescriptor: (target, key) => {
+      const desc = Reflect.getOwnPropertyDescriptor(target, key);
+      if (desc !== undefined || key === kVmContext) {
+        return desc;
+      }
+      const sandboxDesc = Reflect.getOwnPropertyDescriptor(sandbox, key);
+      return sandboxDesc && { ...sandboxDesc, configurable: true };
+    },
+    ownKeys: (target) => [
+      ...new Set([...Reflect.ownKeys(target), ...Reflect.ownKeys(sandbox)]),
+    ].filter((key) => key !== kVmContext),
+  });
+  Object.defineProperty(global, 'globalThis', {
+    value: globalProxy, writable: true, configurable: true,
+  });
+}
+
+function linkDeclarations(sandbox, { vmctx, builtins, getters }) {
+  const global = vmctx.global;
+  for (const key of Reflect.ownKeys(global)) {
+    const desc = Reflect.getOwnPropertyDescriptor(sandbox, key);
+    if (builtins.has(key) || (desc?.get && getters.has(desc.get))) {
+      continue;
+    }
+    // once the declaration is gone from the global, the accessor must not look
+    // it up through the sandbox again
+    const get = () => (hasOwn(global, key) ? global[key] : undefined);
+    const set = (value) => {
+      if (hasOwn(global, key)) {
+        global[key] = value;
+      } else {
+        Reflect.defineProperty(sandbox, key, {
+          value, writable: true, enumerable: true, configurable: true,
+        });
+      }
+    };
+    getters.add(get);
+    Reflect.defineProperty(sandbox, key, { get, set, enumerable: true, configurable: true });
+  }
+}
+
+function runInVmContext(contextifiedObject, fn) {
+  const context = getContext(contextifiedObject);
+  try {
+    return fn(context.vmctx);
+  } finally {
+    linkDeclarations(contextifiedObject, context);
+  }
+}
+
+export function isContext(object) {
+  validateObject(object, 'object', { allowArray: true });
+  return Object.prototype.hasOwnProperty.call(object, kVmContext);
+}
+
+export function createContext(contextObject = {}, options) {
+  if (isContext(contextObject)) {
+    return contextObject;
+  }
+  const vmctx = new VmContext();
+  const builtins = new Set(Reflect.ownKeys(vmctx.global));
+  backGlobal(contextObject, vmctx.global);
+  Object.defineProperty(contextObject, kVmContext, {
+    value: { vmctx, builtins, getters: new WeakSet() },
+  });
+  return contextObject;
+}
+
+function toArrayBuffer(data) {
+  if (data instanceof ArrayBuffer) {
+    return data;
+  }
+  if (ArrayBuffer.isView(data)) {
+    return data.buffer.slice(data.byteOffset, data.byteOffset + data.byteLength);
+  }
+  throw new ERR_INVALID_ARG_TYPE('options.cachedData', ['Buffer', 'TypedArray', 'DataView'], data);
+}
+
+export class Script {
+  #code;
+  #bytecode;
+
+  constructor(code, options = {}) {
+    code = `${code}`;
+    if (typeof options === 'string') {
+      options = { filename: options };
+    }
+    validateObject(options, 'options');
+    const { filename = 'evalmachine.<anonymous>', cachedData, produceCachedData = false } = options;
+    validateString(filename, 'options.filename');
+
+    this.#code = code;
+    if (cachedData !== undefined) {
+      const data = toArrayBuffer(cachedData);
+      // data from another engine version or for other source is compiled over
+      this.cachedDataRejected = !checkCachedData(data, code);
+      this.#bytecode = this.cachedDataRejected ? compileScript(code, filename) : data;
+    } else {
+      this.#bytecode = compileScript(code, filename);
+    }
+    if (produceCachedData) {
+      this.cachedData = this.createCachedData();
+    }
+  }
+
+  createCachedData() {
+    return Buffer.from(this.#bytecode.slice(0));
+  }
+
+  runInThisContext(options) {
+    return runScript(this.#bytecode, this.#code, undefined, getTimeout(options));
+  }
+
+  runInContext(contextifiedObject, options) {
+    return runInVmContext(contextifiedObject, (vmctx) =>
+      runScript(this.#bytecode, this.#code, vmctx, getTimeout(options)));
+  }
+
+  runInNewContext(contextObject, options) {
+    return this.runInContext(createContext(contextObject), options);
+  }
+}
+
+export function runInThisContext(code, options) {
+  return new Script(code, options).runInThisContext(options);
+}
+
+export function runInContext(code, contextifiedObject, options) {
+  return new Script(code, options).runInContext(contextifiedObject, options);
+}
+
+export function runInNewContext(code, contextObject, options) {
+  return new Script(code, options).runInNewContext(contextObject, options);
+}
+
+export function compileFunction(code, params = [], options = {}) {
+  const { parsingContext } = options;
+  const src = `(function (${params.join(', ')}) {\n${code}\n})`;
+  const script = new Script(src, options);
+  return parsingContext === undefined
+    ? script.runInThisContext()
+    : script.runInContext(parsingContext);
+}
+
+// link(linker) calls the linker for each import of the module and, recursively,
+// of the unlinked modules it returns, then resolves every import to the module
+// returned for it. Without a linker, imports are resolved by name: first against
+// modules already compiled in the same context under that identifier, then
+// through the runtime's module loader.
+export class SourceTextModule {
+  #module;
+  #context;
+  #code;
+  #dependencySpecifiers;
+  #linking;
+
+  constructor(code, options = {}) {
+    validateString(code, 'code');
+    validateObject(options, 'options');
+    const { identifier = 'vm:module', context } = options;
+    validateString(identifier, 'options.identifier');
+    this.#context = context;
+    this.#code = code;
+    const vmctx = context === undefined ? undefined : getContext(context).vmctx;
+    this.#module = new VmModule(code, identifier, vmctx);
+    this.identifier = identifier;
+  }
+
+  get status() {
+    return this.#module.status;
+  }
+
+  get error() {
+    if (this.status !== 'errored') {
+      throw new Error('Module status must be errored');
+    }
+    return this.#module.error;
+  }
+
+  get namespace() {
+    return this.#module.getNamespace();
+  }
+
+  get context() {
+    return this.#context;
+  }
+
+  get dependencySpecifiers() {
+    if (this.#dependencySpecifiers === undefined) {
+      this.#dependencySpecifiers = Object.freeze(moduleRequests(this.#code));
+    }
+    return this.#dependencySpecifiers;
+  }
+
+  async link(linker) {
+    if (linker === undefined) {
+      this.#module.link();
+      return;
+    }
+    validateFunction(linker, 'linker');
+    if (this.#linking === undefined) {
+      // a failed link can be retried
+      this.#linking = this.#link(linker).catch((e) => {
+        this.#linking = undefined;
+        throw e;
+      });
+    }
+    return this.#linking;
+  }
+
+  async #link(linker) {
+    const resolutions = [];
+    const linked = [];
+    const visit = async (module) => {
+      if (linked.includes(module) || module.status !== 'unlinked') {
+        return;
+      }
+      linked.push(module);
+      for (const specifier of module.dependencySpecifiers) {
+        const dependency = await linker(specifier, module, { attributes: {} });
+        if (!(dependency instanceof SourceTextModule)) {
+          throw new ERR_VM_MODULE_NOT_MODULE();
+        }
+        if (dependency.context !== this.#context) {
+          throw new ERR_VM_MODULE_DIFFERENT_CONTEXT();
+        }
+        resolutions.push([module.identifier, specifier, dependency.identifier]);
+        await visit(dependency);
+      }
+    };
+    await visit(this);
+    this.#module.link(resolutions);
+    // their imports were resolved along with this module's
+    for (const module of linked) {
+      module.#module.link();
+    }
+  }
+
+  async evaluate(options) {
+    await this.#module.evaluate(getTimeout(options));
+  }
+}
+
+export default {
+  Script,
+  SourceTextModule,
+  createContext,
+  isContext,
+  runInContext,
+  runInNewContext,
+  runInThisContext,
+  compileFunction,
+};
@@ -0,0 +1,396 @@
+// Each Worker runs in its own Runtime, driven by a task on the same tokio
+// runtime as its parent. Messages are serialized in the sending runtime and
+// deserialized in the receiving one; transferred ArrayBuffers are detached
+// and SharedArrayBuffers keep sharing their memory.
+
+import { EventEmitter } from 'events';
+import process from 'process';
+import {
+  MessagePort as NativeMessagePort,
+  spawnWorker,
+  takeWorkerInit,
+  detachArrayBuffer,
+} from '_node:worker_threads';
+import { serialize, deserialize } from '_node:v8';
+import { validateObject } from './internal/validators';
+import { ERR_INVALID_ARG_TYPE } from './internal/errors';
+
+const kNative = Symbol('kNative');
+const kOnControl = Symbol('kOnControl');
+const kPortIndex = '\u0000transferredPort';
+
+// Ports can't be serialized, so the value is copied with each port replaced by its
+// index in the transfer list. Plain objects, arrays, Maps and Sets are walked, cycles
+// included; a port found in them that is not being transferred can't be sent.
+function encodePorts(value, ports) {
+  const copies = new Map();
+  const encode = (v) => {
+    if (v === null || typeof v !== 'object') {
+      return v;
+    }
+    if (v instanceof MessagePort) {
+      const i = ports.indexOf(v);
+      if (i < 0) {
+        throw dataCloneError('Object that needs transfer was found in message but not listed in transferList');
+      }
+      return { [kPortIndex]: i };
+    }
+    if (copies.has(v)) {
+      return copies.get(v);
+    }
+    let copy;
+    if (v instanceof Map) {
+      copy = new Map();
+      copies.set(v, copy);
+      for (const [key, item] of v) {
+        copy.set(encode(key), encode(item));
+      }
+    } else if (v instanceof Set) {
+      copy = new Set();
+      copies.set(v, copy);
+      for (const item of v) {
+        copy.add(encode(item));
+      }
+    } else if (Array.isArray(v) || Object.prototype.toString.call(v) === '[object Object]') {
+      copy = Array.isArray(v) ? new Array(v.length) : {};
+      copies.set(v, copy);
+      for (const key of Object.keys(v)) {
+        copy[key] = encode(v[key]);
+      }
+    } else {
+      // Dates, buffers, errors and the like are cloned as they are
+      return v;
+    }
+    return copy;
+  };
+  return encode(value);
+}
+
+function decodePorts(value, ports) {
+  if (ports.length === 0) {
+    return value;
+  }
+  const seen = new Set();
+  const decode = (v) => {
+    if (v === null || typeof v !== 'object' || seen.has(v)) {
+      return v;
+    }
+    if (Object.prototype.toString.call(v) === '[object Object]' && kPortIndex in v) {
+      return ports[v[kPortIndex]];
+    }
+    seen.add(v);
+    if (v instanceof Map) {
+      const entries = [...v];
+      v.clear();
+      for (const [key, item] of entries) {
+        v.set(decode(key), decode(item));
+      }
+    } else if (v instanceof Set) {
+      const items = [...v];
+      v.clear();
+      for (const item of items) {
+        v.add(decode(item));
+      }
+    } else if (Array.isArray(v) || Object.prototype.toString.call(v) === '[object Object]') {
+      for (const key of Object.keys(v)) {
+        v[key] = decode(v[key]);
+      }
+    }
+    return v;
+  };
+  return decode(value);
+}
+
+function dataCloneError(message) {
+  const error = new Error(message);
+  error.name = 'DataCloneError';
+  error.code = 25;
+  return error;
+}
+
+// the same encoding the worker's runtime uses for an uncaught exception
+function serializeError(error) {
+  try {
+    return serialize(error);
+  } catch {
+    return serialize(`${error}`);
+  }
+}
+
+export class MessagePort extends EventEmitter {
+  #receiving = false;
+  #refed = true;
+  #closed = false;
+  #onmessage = null;
+  #onmessageListener = null;
+
+  constructor(native) {
+    if (!(native instanceof NativeMessagePort)) {
+      throw new TypeError('Illegal constructor');
+    }
+    super();
+    this[kNative] = native;
+    this.on('newListener', (event) => {
+      if (event === 'message') {
+        Promise.resolve().then(() => this.start());
+      }
+    });
+  }
+
+  postMessage(value, transferList = []) {
+    if (this.#closed) {
+      return;
+    }
+    if (!Array.isArray(transferList)) {
+      validateObject(transferList, 'options');
+      transferList = transferList.transfer ?? [];
+    }
+    const ports = transferList.filter((t) => t instanceof MessagePort);
+    if (ports.includes(this)) {
+      throw dataCloneError('Transfer list contains source port');
+    }
+    for (const port of ports) {
+      if (port.#closed) {
+        throw dataCloneError('MessagePort in transfer list is already detached');
+      }
+    }
+    this[kNative].postMessage(encodePorts(value, ports), ports.map((port) => port[kNative]));
+    for (const port of ports) {
+      port.#closed = true;
+      port.#receiving = false;
+    }
+    for (const item of transferList) {
+      if (item instanceof ArrayBuffer) {
+        detachArrayBuffer(item);
+      }
+    }
+  }
+
+  start() {
+    if (this.#receiving || this.#closed || !this.#refed) {
+      return;
+    }
+    this.#receiving = true;
+    this.#receiveLoop();
+  }
+
+  ref() {
+    this.#refed = true;
+    if (this.listenerCount('message') > 0) {
+      this.start();
+    }
+    return this;
+  }
+
+  unref() {
+    this.#refed = false;
+    if (this.#receiving) {
+      this.#receiving = false;
+      this[kNative].cancelRecv();
+    }
+    return this;
+  }
+
+  hasRef() {
+    return this.#refed && this.#receiving;
+  }
+
+  close() {
+    if (this.#closed) {
+      return;
+    }
+    this.#closed = true;
+    this.#receiving = false;
+    this[kNative].close();
+    Promise.resolve().then(() => this.emit('close'));
+  }
+
+  get onmessage() {
+    return this.#onmessage;
+  }
+
+  set onmessage(fn) {
+    if (this.#onmessageListener) {
+      this.off('message', this.#onmessageListener);
+      this.#onmessageListener = null;
+    }
+    this.#onmessage = typeof fn === 'function' ? fn : null;
+    if (this.#onmessage) {
+      this.#onmessageListener = (data, ports) => fn.call(this, { data, ports, target: this });
+      this.on('message', this.#onmessageListener);
+    }
+  }
+
+  async #receiveLoop() {
+    try {
+      while (this.#receiving) {
+        const msg = await this[kNative].recv();
+        if (msg === undefined) {
+          break;
+        }
+        if (msg === null) {
+          this.#closed = true;
+          this.emit('close');
+          break;
+        }
+        this.#dispatch(msg);
+      }
+    } catch (e) {
+      // recv() rejects once the port is closed
+      if (!this.#closed) {
+        this.#uncaught(e);
+      }
+    }
+    this.#receiving = false;
+  }
+
+  #dispatch(msg) {
+    if ('messageerror' in msg) {
+      this.emit('messageerror', msg.messageerror);
+      return;
+    }
+    if (!('data' in msg)) {
+      this[kOnControl]?.(msg);
+      return;
+    }
+    const ports = msg.ports.map((native) => new MessagePort(native));
+    const value = decodePorts(msg.data, ports);
+    try {
+      this.emit('message', value, ports);
+    } catch (e) {
+      this.#uncaught(e);
+    }
+  }
+
+  #uncaught(e) {
+    if (!isMainThread) {
+      workerFail(e);
+    } else if (this.listenerCount('error') > 0) {
+      this.emit('error', e);
+    } else {
+      console.error(e);
+    }
+  }
+}
+
+export class MessageChannel {
+  constructor() {
+    const [port1, port2] = NativeMessagePort.createChannel();
+    this.port1 = new MessagePort(port1);
+    this.port2 = new MessagePort(port2);
+  }
+}
+
+export function receiveMessageOnPort(port) {
+  if (!(port instanceof MessagePort)) {
+    throw new ERR_INVALID_ARG_TYPE('port', 'MessagePort', port);
+  }
+  const msg = port[kNative].tryRecv();
+  if (msg === undefined || !('data' in msg)) {
+    return undefined;
+  }
+  const ports = msg.ports.map((native) => new MessagePort(native));
+  return { message: decodePorts(msg.data, ports) };
+}
+
+export class Worker extends EventEmitter {
+  #port;
+  #control;
+  #exitCode;
+
+  constructor(filename, options = {}) {
+    super();
+    validateObject(options, 'options');
+    const { eval: isEval = false, workerData, resourceLimits = {}, transferList = [] } = options;
+    if (filename instanceof URL) {
+      filename = filename.pathname;
+    }
+    if (typeof filename !== 'string') {
+      throw new ERR_INVALID_ARG_TYPE('filename', ['string', 'URL'], filename);
+    }
+    validateObject(resourceLimits, 'options.resourceLimits');
+
+    const [native, control] = spawnWorker(filename, !!isEval, workerData, { ...resourceLimits });
+    for (const item of transferList) {
+      if (item instanceof ArrayBuffer) {
+        detachArrayBuffer(item);
+      }
+    }
+    this.#control = control;
+    this.threadId = control.threadId;
+    this.resourceLimits = { ...resourceLimits };
+
+    this.#port = new MessagePort(native);
+    this.#port[kOnControl] = (msg) => this.#onControl(msg);
+    this.#port.on('message', (value) => this.emit('message', value));
+    this.#port.on('messageerror', (e) => this.emit('messageerror', e));
+    Promise.resolve().then(() => this.emit('online'));
+  }
+
+  #onControl(msg) {
+    if ('error' in msg) {
+      this.emit('error', deserialize(msg.error));
+    } else if ('exit' in msg) {
+      this.#exitCode = msg.exit;
+      this.#port.close();
+      this.emit('exit', msg.exit);
+    }
+  }
+
+  postMessage(value, transferList) {
+    this.#port.postMessage(value, transferList);
+  }
+
+  ref() {
+    this.#port.ref();
+  }
+
+  unref() {
+    this.#port.unref();
+  }
+
+  terminate() {
+    if (this.#exitCode !== undefined) {
+      return Promise.resolve(this.#exitCode);
+    }
+    this.#control.terminate();
+    return new Promise((resolve) => this.once('exit', resolve));
+  }
+}
+
+const init = takeWorkerInit();
+
+export const isMainThread = init === undefined;
+export const threadId = isMainThread ? 0 : init.threadId;
+export const workerData = isMainThread ? null : init.workerData;
+export const parentPort = isMainThread ? null : new MessagePort(init.port);
+export const resourceLimits = isMainThread ? {} : init.resourceLimits;
+
+function workerExit(code = process.exitCode ?? 0) {
+  init.control.exit(code);
+}
+
+function workerFail(e) {
+  init.control.reportError(serializeError(e));
+  workerExit(1);
+}
+
+if (!isMainThread) {
+  process.exit = workerExit;
+  const run = init.eval
+    ? async () => (0, eval)(init.filename)
+    : () => import(init.filename);
+  run().catch(workerFail);
+}
+
+export default {
+  isMainThread,
+  threadId,
+  workerData,
+  parentPort,
+  resourceLimits,
+  MessagePort,
+  MessageChannel,
+  Worker,
+  receiveMessageOnPort,
+};
@@ -0,0 +1,119 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use tokio::time::Instant;
+
+/// The monotonic clock behind timers, `performance.now()` and `process.hrtime()`,
+/// which also tracks how long the event loop has waited for events.
+///
+/// It runs ahead of the real time by however far it has been advanced. A virtual
+/// clock stands still between advances.
+pub(crate) struct Clock {
+    origin: Instant,
+    skew: Duration,
+    /// the time a virtual clock stands at
+    virtual_now: Option<Instant>,
+    /// the wall clock time at `origin`, in ms since the Unix epoch
+    time_origin: f64,
+    /// when the loop was first polled, in real time
+    loop_start: Option<Instant>,
+    idle: Duration,
+    idle_since: Option<Instant>,
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        let time_origin = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs_f64() * 1000.0)
+            .unwrap_or_default();
+        Clock {
+            origin: Instant::now(),
+            skew: Duration::ZERO,
+            virtual_now: None,
+            time_origin,
+            loop_start: None,
+            idle: Duration::ZERO,
+            idle_since: None,
+        }
+    }
+}
+
+impl Clock {
+    pub(crate) fn now(&self) -> Instant {
+        self.virtual_now
+            .unwrap_or_else(|| Instant::now() + self.skew)
+    }
+
+    /// The real time at which the clock will read `instant`, if it is not virtual.
+    pub(crate) fn to_real(&self, instant: Instant) -> Instant {
+        instant.checked_sub(self.skew).unwrap_or(instant)
+    }
+
+    pub(crate) fn is_virtual(&self) -> bool {
+        self.virtual_now.is_some()
+    }
+
+    /// Stops the clock, or lets it run again from where it stands.
+    pub(crate) fn set_virtual(&mut self, enabled: bool) {
+        match (self.virtual_now, enabled) {
+            (None, true) => self.virtual_now = Some(self.now()),
+            (Some(now), false) => {
+                // never run backwards, even if less time was advanced than passed
+                self.skew = self.skew.max(now.saturating_duration_since(Instant::now()));
+                self.virtual_now = None;
+            }
+            _ => {}
+        }
+    }
+
+    /// Moves the clock forward to `instant`, if it is not past it already.
+    pub(crate) fn advance_to(&mut self, instant: Instant) {
+        let ahead = instant.saturating_duration_since(self.now());
+        match &mut self.virtual_now {
+            Some(now) => *now += ahead,
+            None => self.skew += ahead,
+        }
+    }
+
+    /// The time since the runtime started.
+    pub(crate) fn elapsed(&self) -> Duration {
+        self.now() - self.origin
+    }
+
+    /// `performance.now()`: ms since the runtime started, with sub-ms precision.
+    pub(crate) fn now_ms(&self) -> f64 {
+        self.elapsed().as_secs_f64() * 1000.0
+    }
+
+    /// `performance.timeOrigin`: when the runtime started, in ms since the Unix epoch.
+    pub(crate) fn time_origin_ms(&self) -> f64 {
+        self.time_origin
+    }
+
+    /// The real time since the event loop started, after the main script ran.
+    pub(crate) fn loop_time(&self) -> Option<Duration> {
+        self.loop_start.map(|start| Instant::now() - start)
+    }
+
+    /// The real time the loop has waited for events, not counting a wait in progress.
+    pub(crate) fn idle_time(&self) -> Duration {
+        self.idle
+    }
+
+    /// The loop has nothing to run until an event arrives.
+    pub(crate) fn enter_idle(&mut self) {
+        if self.idle_since.is_none() {
+            self.idle_since = Some(Instant::now());
+        }
+    }
+
+    /// The loop was polled, which also starts it the first time.
+    pub(crate) fn leave_idle(&mut self) {
+        if self.loop_start.is_none() {
+            self.loop_start = Some(Instant::now());
+        }
+        if let Some(since) = self.idle_since.take() {
+            self.idle += Instant::now() - since;
+        }
+    }
+}
@@ -0,0 +1,208 @@
+use std::collections::BTreeMap;
+
+use tokio::task::JoinHandle;
+
+use crate::JsValue;
+
+/// What a handle or request is, reported by `process.getActiveResourcesInfo()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResourceKind {
+    TcpServer,
+    TcpSocket,
+    TlsSocket,
+    UdpSocket,
+    /// an `fs` operation waiting for its turn on the loop. Only the `fread`
+    /// and `fwrite` bindings are async; the other callback and promise `fs`
+    /// functions call the sync bindings, so they never show up as requests
+    FsReq,
+    /// a Rust future driven by `Context::future_to_promise`
+    Future,
+}
+
+impl ResourceKind {
+    /// The name Node.js gives the resource.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ResourceKind::TcpServer => "TCPServerWrap",
+            ResourceKind::TcpSocket => "TCPSocketWrap",
+            ResourceKind::TlsSocket => "TLSWrap",
+            ResourceKind::UdpSocket => "UDPWrap",
+            ResourceKind::FsReq => "FSReqCallback",
+            ResourceKind::Future => "FutureWrap",
+        }
+    }
+}
+
+pub type HandleId = u32;
+
+/// A long-lived resource, such as a socket, from when it is opened until it is closed.
+struct Handle {
+    kind: ResourceKind,
+    refed: bool,
+    /// the JS object of the resource
+    object: Option<Box<dyn Fn() -> JsValue>>,
+    /// releases the resource if it is still open at shutdown
+    finalizer: Option<Box<dyn FnOnce()>>,
+}
+
+/// A pending operation, running as a task or waiting in a loop queue.
+struct Request {
+    kind: ResourceKind,
+    /// the handle the operation runs on; it is refed and listed with it
+    owner: Option<HandleId>,
+    /// unset for operations that `finish` themselves
+    task: Option<JoinHandle<()>>,
+}
+
+impl Request {
+    fn is_pending(&self) -> bool {
+        self.task.as_ref().map_or(true, |task| !task.is_finished())
+    }
+}
+
+/// The handles and requests that keep the event loop alive.
+///
+/// A request keeps the loop alive while it is pending, unless the handle it
+/// runs on is unrefed. Handles only keep it alive through their requests.
+#[derive(Default)]
+pub(crate) struct Handles {
+    next_id: HandleId,
+    handles: BTreeMap<HandleId, Handle>,
+    requests: BTreeMap<HandleId, Request>,
+}
+
+impl Handles {
+    fn next_id(&mut self) -> HandleId {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    /// Registers an open resource, refed.
+    pub(crate) fn open(
+        &mut self,
+        kind: ResourceKind,
+        object: Option<Box<dyn Fn() -> JsValue>>,
+        finalizer: Option<Box<dyn FnOnce()>>,
+    ) -> HandleId {
+        let id = self.next_id();
+        self.handles.insert(
+            id,
+            Handle {
+                kind,
+                refed: true,
+                object,
+                finalizer,
+            },
+        );
+        id
+    }
+
+    /// Unregisters a handle that was closed or freed, without running its finalizer.
+    pub(crate) fn close(&mut self, id: HandleId) {
+        self.handles.remove(&id);
+    }
+
+    /// Returns false if the handle is not open.
+    pub(crate) fn set_ref(&mut self, id: HandleId, refed: bool) -> bool {
+        match self.handles.get_mut(&id) {
+            Some(handle) => {
+                handle.refed = refed;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub(crate) fn has_ref(&self, id: HandleId) -> bool {
+        self.handles.get(&id).map_or(false, |handle| handle.refed)
+    }
+
+    /// Tracks a spawned task until it finishes.
+    pub(crate) fn spawn(
+        &mut self,
+        kind: ResourceKind,
+        owner: Option<HandleId>,
+        task: JoinHandle<()>,
+    ) -> HandleId {
+        let id = self.next_id();
+        let task = Some(task);
+        self.requests.insert(id, Request { kind, owner, task });
+        id
+    }
+
+    /// Tracks an operation until `finish` is called with the id returned.
+    pub(crate) fn start(&mut self, kind: ResourceKind) -> HandleId {
+        let id = self.next_id();
+        let request = Request {
+            kind,
+            owner: None,
+            task: None,
+        };
+        self.requests.insert(id, request);
+        id
+    }
+
+    pub(crate) fn finish(&mut self, id: HandleId) {
+        self.requests.remove(&id);
+    }
+
+    /// Drops the finished tasks.
+    pub(crate) fn reap(&mut self) {
+        self.requests.retain(|_, request| request.is_pending());
+    }
+
+    /// True while a pending request keeps the loop alive.
+    pub(crate) fn keep_alive(&self) -> bool {
+        self.requests.values().any(|request| {
+            request.is_pending()
+                && request
+                    .owner
+                    .and_then(|owner| self.handles.get(&owner))
+                    .map_or(true, |handle| handle.refed)
+        })
+    }
+
+    /// The pending requests that don't run on a handle, then the open handles.
+    pub(crate) fn resource_names(&self) -> Vec<&'static str> {
+        let requests = self
+            .requests
+            .values()
+            .filter(|request| request.owner.is_none() && request.is_pending())
+            .map(|request| request.kind);
+        let handles = self.handles.values().map(|handle| handle.kind);
+        requests.chain(handles).map(|kind| kind.name()).collect()
+    }
+
+    /// The kinds of the pending requests that don't run on a handle.
+    pub(crate) fn request_kinds(&self) -> Vec<ResourceKind> {
+        self.requests
+            .values()
+            .filter(|request| request.owner.is_none() && request.is_pending())
+            .map(|request| request.kind)
+            .collect()
+    }
+
+    /// The JS objects of the open handles.
+    pub(crate) fn handle_objects(&self) -> Vec<JsValue> {
+        self.handles
+            .values()
+            .filter_map(|handle| handle.object.as_ref().map(|object| object()))
+            .collect()
+    }
+
+    /// Forgets every request, returning the tasks still running.
+    pub(crate) fn take_tasks(&mut self) -> Vec<JoinHandle<()>> {
+        std::mem::take(&mut self.requests)
+            .into_values()
+            .filter_map(|request| request.task)
+            .collect()
+    }
+
+    /// Forgets every handle, returning their finalizers.
+    pub(crate) fn take_finalizers(&mut self) -> Vec<Box<dyn FnOnce()>> {
+        std::mem::take(&mut self.handles)
+            .into_values()
+            .filter_map(|handle| handle.finalizer)
+            .collect()
+    }
+}
@@ -1,19 +1,23 @@
 mod certs;
+pub(crate) mod clock;
+pub(crate) mod handle;
 mod poll;
+pub(crate) mod timer;
 pub mod wasi_fs;
 mod wasi_sock;
 
 use crate::{quickjs_sys as qjs, Context, JsClassTool, JsValue};
 use std::borrow::BorrowMut;
-use std::cell::RefCell;
-use std::collections::{HashMap, LinkedList};
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, LinkedList, VecDeque};
 use std::convert::TryInto;
 use std::io::{self, Read, Write};
 use std::mem::ManuallyDrop;
 use std::net::{SocketAddr, SocketAddrV4};
 use std::ops::Add;
 use std::os::fd::{AsRawFd, FromRawFd};
-use std::sync::atomic::AtomicUsize;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 
 use rustls::OwnedTrustAnchor;
@@ -26,26 +30,163 @@ pub(crate) enum NetPollEvent {
     Connect,
 }
 
-pub struct AsyncTcpServer(pub(crate) tokio::net::TcpListener);
+/// How `AsyncTcpServer::bind_with` sets up its socket.
+#[derive(Debug, Clone)]
+pub struct TcpBindOptions {
+    /// an IP literal, with or without `[]` around IPv6 ones, or a host name;
+    /// `0.0.0.0` if unset
+    pub host: Option<String>,
+    /// 0 picks a free port
+    pub port: u16,
+    /// WasmEdge has no `IPV6_V6ONLY`, so binding fails with `Unsupported` when
+    /// set; a socket bound to `::` takes IPv4 connections if the host allows it.
+    pub ipv6_only: bool,
+    pub backlog: u32,
+    pub reuse_addr: bool,
+}
+
+impl Default for TcpBindOptions {
+    fn default() -> Self {
+        TcpBindOptions {
+            host: None,
+            port: 0,
+            ipv6_only: false,
+            backlog: 511,
+            reuse_addr: true,
+        }
+    }
+}
+
+impl TcpBindOptions {
+    fn addrs(&self) -> io::Result<Vec<SocketAddr>> {
+        resolve_host(self.host.as_deref().unwrap_or("0.0.0.0"), self.port)
+    }
+}
+
+/// The addresses `host` stands for, in the order to try them. `host` is an IP
+/// literal, with or without `[]` around IPv6 ones, or a host name.
+pub(crate) fn resolve_host(host: &str, port: u16) -> io::Result<Vec<SocketAddr>> {
+    let literal = host.strip_prefix('[').and_then(|h| h.strip_suffix(']'));
+    if let Ok(ip) = literal.unwrap_or(host).parse::<std::net::IpAddr>() {
+        return Ok(vec![SocketAddr::new(ip, port)]);
+    }
+    use wasmedge_wasi_socket::ToSocketAddrs;
+    let addrs: Vec<SocketAddr> = (host, port).to_socket_addrs()?.collect();
+    if addrs.is_empty() {
+        let msg = format!("getaddrinfo ENOTFOUND {}", host);
+        return Err(io::Error::new(io::ErrorKind::NotFound, msg));
+    }
+    Ok(addrs)
+}
+
+/// A socket that `close` lets go of while requests may still use it. Each
+/// request holds an `Arc` of its own, and the fd is closed with the last one.
+pub(crate) struct Closable<T>(std::sync::Mutex<Option<Arc<T>>>);
+
+impl<T> Closable<T> {
+    fn new(socket: T) -> Self {
+        Closable(std::sync::Mutex::new(Some(Arc::new(socket))))
+    }
+
+    /// The socket, or `NotConnected` once it is closed.
+    fn get(&self) -> io::Result<Arc<T>> {
+        self.0.lock().unwrap().clone().ok_or_else(socket_closed)
+    }
+
+    /// The socket for a request that waits on it, and so must not keep it open.
+    fn weak(&self) -> io::Result<std::sync::Weak<T>> {
+        self.get().map(|socket| Arc::downgrade(&socket))
+    }
+
+    fn close(&self) {
+        self.0.lock().unwrap().take();
+    }
+}
+
+fn socket_closed() -> io::Error {
+    io::Error::new(io::ErrorKind::NotConnected, "socket is closed")
+}
+
+/// A TCP server. `close` frees the port right away, even with an accept pending.
+pub struct AsyncTcpServer(pub(crate) Closable<tokio::net::TcpListener>);
 impl AsyncTcpServer {
+    /// Listens on `0.0.0.0:port`.
     pub fn bind(port: u16) -> io::Result<Self> {
+        Self::bind_with(&TcpBindOptions {
+            port,
+            ..Default::default()
+        })
+    }
+
+    /// Listens on the first address of `options.host` that can be bound.
+    pub fn bind_with(options: &TcpBindOptions) -> io::Result<Self> {
+        if options.ipv6_only {
+            let msg = "IPV6_V6ONLY is not supported by WasmEdge";
+            return Err(io::Error::new(io::ErrorKind::Unsupported, msg));
+        }
+        let mut last_err = None;
+        for addr in options.addrs()? {
+            match Self::bind_addr(&addr, options) {
+                Ok(server) => return Ok(server),
+                Err(e) => last_err = Some(e),
+            }
+        }
+        Err(last_err.unwrap_or_else(|| io::Error::from(io::ErrorKind::AddrNotAvailable)))
+    }
+
+    fn bind_addr(addr: &SocketAddr, options: &TcpBindOptions) -> io::Result<Self> {
         use std::os::fd::IntoRawFd;
-        let listener = wasmedge_wasi_socket::TcpListener::bind(("0.0.0.0", port), true)?;
-        let listener = unsafe { std::net::TcpListener::from_raw_fd(listener.into_raw_fd()) };
+        use wasi_sock::{AddressFamily, Socket, SocketOptLevel, SocketOptName, SocketType};
+
+        let family = if addr.is_ipv6() {
+            AddressFamily::Inet6
+        } else {
+            AddressFamily::Inet4
+        };
+        let socket = Socket::new(family, SocketType::Stream)?;
+        if options.reuse_addr {
+            socket.setsockopt(SocketOptLevel::SolSocket, SocketOptName::SoReuseaddr, 1i32)?;
+        }
+        socket.bind(addr)?;
+        socket.listen(options.backlog.min(i32::MAX as u32) as i32)?;
+        socket.set_nonblocking(true)?;
+        let listener = unsafe { std::net::TcpListener::from_raw_fd(socket.into_raw_fd()) };
         let async_listener = tokio::net::TcpListener::from_std(listener)?;
-        Ok(AsyncTcpServer(async_listener))
+        Ok(AsyncTcpServer(Closable::new(async_listener)))
+    }
+
+    /// The address the server is bound to, with the port picked for port 0.
+    pub fn local(&self) -> io::Result<SocketAddr> {
+        self.0.get()?.local_addr()
+    }
+
+    /// Stops listening and frees the port.
+    pub fn close(&self) {
+        self.0.close()
     }
 
     pub async fn accept(
-        &mut self,
+        &self,
         ctx: &mut Context,
         timeout: Option<std::time::Duration>,
     ) -> Result<JsValue, JsValue> {
+        let listener = match self.0.weak() {
+            Ok(listener) => listener,
+            Err(e) => return Err(ctx.new_error(e.to_string().as_str())),
+        };
+        // holds the listener only while polling it
+        let accept = || {
+            let listener = listener.clone();
+            std::future::poll_fn(move |cx| match listener.upgrade() {
+                Some(listener) => listener.poll_accept(cx),
+                None => std::task::Poll::Ready(Err(socket_closed())),
+            })
+        };
         if let Some(duration) = timeout {
-            match tokio::time::timeout(duration, self.0.accept()).await {
+            match tokio::time::timeout(duration, accept()).await {
                 Ok(Ok((conn, addr))) => {
                     log::trace!("tcp accept a socket[{addr}]");
-                    Ok(AsyncTcpConn::wrap_obj(ctx, AsyncTcpConn(conn)))
+                    Ok(AsyncTcpConn::wrap_obj(ctx, AsyncTcpConn::new(conn)))
                 }
                 Ok(Err(e)) => {
                     log::trace!("tcp accept error: {e}");
@@ -57,10 +198,10 @@ impl AsyncTcpServer {
                 }
             }
         } else {
-            match self.0.accept().await {
+            match accept().await {
                 Ok((conn, addr)) => {
                     log::trace!("tcp accept a socket[{addr}]");
-                    Ok(AsyncTcpConn::wrap_obj(ctx, AsyncTcpConn(conn)))
+                    Ok(AsyncTcpConn::wrap_obj(ctx, AsyncTcpConn::new(conn)))
                 }
                 Err(e) => {
                     log::trace!("tcp accept error: {e}");
@@ -71,22 +212,206 @@ impl AsyncTcpServer {
     }
 }
 
-pub struct AsyncTcpConn(pub(crate) tokio::net::TcpStream);
+/// How `AsyncUdpSocket::bind` sets up its socket.
+#[derive(Debug, Clone, Default)]
+pub struct UdpBindOptions {
+    pub ipv6: bool,
+    /// `0.0.0.0`, or `::` for IPv6, if unset
+    pub host: Option<String>,
+    /// 0 picks a free port
+    pub port: u16,
+    pub reuse_addr: bool,
+}
+
+/// A UDP socket. Clones share the socket, so a send can own one while a
+/// receive is pending on another.
+#[derive(Clone)]
+pub struct AsyncUdpSocket(pub(crate) Arc<Closable<tokio::net::UdpSocket>>);
+impl AsyncUdpSocket {
+    /// Binds the first address of `options.host` in the socket's family.
+    pub fn bind(options: &UdpBindOptions) -> io::Result<Self> {
+        let any = if options.ipv6 { "::" } else { "0.0.0.0" };
+        let host = options.host.as_deref().unwrap_or(any);
+        let mut last_err = None;
+        for addr in resolve_host(host, options.port)? {
+            if addr.is_ipv6() != options.ipv6 {
+                continue;
+            }
+            match Self::bind_addr(&addr, options.reuse_addr) {
+                Ok(socket) => return Ok(socket),
+                Err(e) => last_err = Some(e),
+            }
+        }
+        Err(last_err.unwrap_or_else(|| io::Error::from(io::ErrorKind::AddrNotAvailable)))
+    }
+
+    fn bind_addr(addr: &SocketAddr, reuse_addr: bool) -> io::Result<Self> {
+        use std::os::fd::IntoRawFd;
+        use wasi_sock::{AddressFamily, Socket, SocketOptLevel, SocketOptName, SocketType};
+
+        let family = if addr.is_ipv6() {
+            AddressFamily::Inet6
+        } else {
+            AddressFamily::Inet4
+        };
+        let socket = Socket::new(family, SocketType::Datagram)?;
+        if reuse_addr {
+            socket.setsockopt(SocketOptLevel::SolSocket, SocketOptName::SoReuseaddr, 1i32)?;
+        }
+        socket.bind(addr)?;
+        socket.set_nonblocking(true)?;
+        let socket = unsafe { std::net::UdpSocket::from_raw_fd(socket.into_raw_fd()) };
+        let socket = tokio::net::UdpSocket::from_std(socket)?;
+        Ok(AsyncUdpSocket(Arc::new(Closable::new(socket))))
+    }
+
+    /// Sends `buf` as one datagram to the first address of `host` in the socket's family.
+    pub async fn send_to(&self, buf: &[u8], host: &str, port: u16) -> io::Result<usize> {
+        let ipv6 = self.local()?.is_ipv6();
+        let addr = resolve_host(host, port)?
+            .into_iter()
+            .find(|addr| addr.is_ipv6() == ipv6)
+            .ok_or_else(|| io::Error::from(io::ErrorKind::AddrNotAvailable))?;
+        self.0.get()?.send_to(buf, addr).await
+    }
+
+    /// Waits for the next datagram.
+    pub async fn recv_from(&self) -> io::Result<(Vec<u8>, SocketAddr)> {
+        // holds the socket only while polling it, so that `close` frees the port right away
+        let socket = self.0.weak()?;
+        let mut buf = vec![0u8; 64 * 1024];
+        loop {
+            let r = std::future::poll_fn(|cx| match socket.upgrade() {
+                Some(socket) => {
+                    let mut read_buf = tokio::io::ReadBuf::new(&mut buf);
+                    socket
+                        .poll_recv_from(cx, &mut read_buf)
+                        .map_ok(|addr| (read_buf.filled().len(), addr))
+                }
+                None => std::task::Poll::Ready(Err(socket_closed())),
+            })
+            .await;
+            match r {
+                Ok((n, addr)) => {
+                    buf.truncate(n);
+                    return Ok((buf, addr));
+                }
+                // the ICMP reply to an earlier send, which libuv skips as well
+                Err(e)
+                    if matches!(
+                        e.kind(),
+                        io::ErrorKind::ConnectionRefused | io::ErrorKind::ConnectionReset
+                    ) => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    pub fn local(&self) -> io::Result<SocketAddr> {
+        self.0.get()?.local_addr()
+    }
+
+    /// Closes the socket and frees the port; sends still in flight finish first.
+    pub fn close(&self) {
+        self.0.close()
+    }
+
+    pub fn set_broadcast(&self, on: bool) -> io::Result<()> {
+        use wasi_sock::{Socket, SocketOptLevel, SocketOptName};
+        let udp = self.0.get()?;
+        // borrows the fd, which the tokio socket still owns
+        let socket = ManuallyDrop::new(unsafe { Socket::from_raw_fd(udp.as_raw_fd()) });
+        socket.setsockopt(
+            SocketOptLevel::SolSocket,
+            SocketOptName::SoBroadcast,
+            on as i32,
+        )
+    }
+
+    pub fn set_ttl(&self, ttl: u32) -> io::Result<()> {
+        self.0.get()?.set_ttl(ttl)
+    }
+
+    pub fn set_multicast_ttl(&self, ttl: u32) -> io::Result<()> {
+        self.0.get()?.set_multicast_ttl_v4(ttl)
+    }
+
+    pub fn set_multicast_loopback(&self, on: bool) -> io::Result<()> {
+        let socket = self.0.get()?;
+        if socket.local_addr()?.is_ipv6() {
+            socket.set_multicast_loop_v6(on)
+        } else {
+            socket.set_multicast_loop_v4(on)
+        }
+    }
+
+    /// Joins or leaves the multicast `group` on `interface`, or the default one.
+    /// Fails with `Unsupported` where the host has no multicast support.
+    pub fn set_membership(
+        &self,
+        group: std::net::IpAddr,
+        interface: Option<std::net::IpAddr>,
+        join: bool,
+    ) -> io::Result<()> {
+        use std::net::{IpAddr, Ipv4Addr};
+        let socket = self.0.get()?;
+        match (group, interface) {
+            (IpAddr::V4(group), None | Some(IpAddr::V4(_))) => {
+                let interface = match interface {
+                    Some(IpAddr::V4(interface)) => interface,
+                    _ => Ipv4Addr::UNSPECIFIED,
+                };
+                if join {
+                    socket.join_multicast_v4(group, interface)
+                } else {
+                    socket.leave_multicast_v4(group, interface)
+                }
+            }
+            (IpAddr::V6(group), None) => {
+                if join {
+                    socket.join_multicast_v6(&group, 0)
+                } else {
+                    socket.leave_multicast_v6(&group, 0)
+                }
+            }
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "the interface address must be in the family of the group",
+            )),
+        }
+    }
+}
+
+/// A TCP connection. Reads and writes only borrow it shared, so a write can
+/// go out while a read is pending.
+pub struct AsyncTcpConn {
+    stream: Closable<tokio::net::TcpStream>,
+    /// keeps concurrent writes from interleaving
+    write_lock: Arc<tokio::sync::Mutex<()>>,
+}
+
 impl AsyncTcpConn {
+    pub(crate) fn new(stream: tokio::net::TcpStream) -> Self {
+        AsyncTcpConn {
+            stream: Closable::new(stream),
+            write_lock: Arc::new(tokio::sync::Mutex::new(())),
+        }
+    }
+
     pub async fn async_connect<R: tokio::net::ToSocketAddrs>(addr: R) -> io::Result<Self> {
-        tokio::net::TcpStream::connect(addr)
-            .await
-            .map(|conn| AsyncTcpConn(conn))
+        tokio::net::TcpStream::connect(addr).await.map(Self::new)
     }
 
-    pub async fn async_read_all(&mut self) -> io::Result<Vec<u8>> {
+    pub async fn async_read_all(&self) -> io::Result<Vec<u8>> {
         let mut data = vec![];
         let mut buff = [0u8; 1024 * 4];
 
         log::trace!("tcp read_all");
 
+        let stream = self.stream.get()?;
         loop {
-            match self.0.read(&mut buff).await {
+            stream.readable().await?;
+            match stream.try_read(&mut buff) {
                 Ok(0) => {
                     log::trace!("tcp read: 0");
                     return Ok(data);
@@ -100,7 +425,9 @@ impl AsyncTcpConn {
                 }
                 Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
                     log::trace!("tcp read: WouldBlock");
-                    return Ok(data);
+                    if !data.is_empty() {
+                        return Ok(data);
+                    }
                 }
                 Err(e) => {
                     log::trace!("tcp read: {e}");
@@ -110,21 +437,56 @@ impl AsyncTcpConn {
         }
     }
 
-    pub async fn async_write_all(&mut self, buf: &[u8]) -> io::Result<()> {
-        self.0.write_all(buf).await
+    /// Writes all of `buf`. The stream is taken when this is called, so the
+    /// data still goes out if the connection is closed before the write runs.
+    pub fn async_write_all(
+        &self,
+        buf: Vec<u8>,
+    ) -> impl std::future::Future<Output = io::Result<()>> + Send + 'static {
+        let stream = self.stream.get();
+        let write_lock = self.write_lock.clone();
+        async move {
+            let stream = stream?;
+            let _guard = write_lock.lock().await;
+            let mut buf = &buf[..];
+            while !buf.is_empty() {
+                stream.writable().await?;
+                match stream.try_write(buf) {
+                    Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+                    Ok(n) => buf = &buf[n..],
+                    Err(e) if e.kind() == io::ErrorKind::WouldBlock => {}
+                    Err(e) => return Err(e),
+                }
+            }
+            Ok(())
+        }
     }
 
     pub fn local(&self) -> io::Result<SocketAddr> {
-        self.0.local_addr()
+        self.stream.get()?.local_addr()
     }
 
     pub fn peer(&self) -> io::Result<SocketAddr> {
-        self.0.peer_addr()
+        self.stream.get()?.peer_addr()
+    }
+
+    /// Lets go of the stream, which sends the FIN once pending writes are out.
+    pub fn close(&self) {
+        self.stream.close()
     }
 }
 
 #[cfg(feature = "tls")]
-pub struct AsyncTlsConn(pub(crate) tokio_rustls::client::TlsStream<tokio::net::TcpStream>);
+type TlsStream = tokio_rustls::client::TlsStream<tokio::net::TcpStream>;
+
+/// A TLS connection, split so that a write can go out while a read is pending.
+#[cfg(feature = "tls")]
+pub struct AsyncTlsConn {
+    reader: Closable<tokio::sync::Mutex<tokio::io::ReadHalf<TlsStream>>>,
+    writer: Closable<tokio::sync::Mutex<tokio::io::WriteHalf<TlsStream>>>,
+    local: SocketAddr,
+    peer: SocketAddr,
+}
 
 #[cfg(feature = "tls")]
 impl AsyncTlsConn {
@@ -164,19 +526,29 @@ impl AsyncTlsConn {
             .try_into()
             .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
 
+        let local = io.local_addr()?;
+        let peer = io.peer_addr()?;
         let conn = connector.connect(domain, io).await?;
+        let (reader, writer) = tokio::io::split(conn);
 
-        Ok(Self(conn))
+        Ok(AsyncTlsConn {
+            reader: Closable::new(tokio::sync::Mutex::new(reader)),
+            writer: Closable::new(tokio::sync::Mutex::new(writer)),
+            local,
+            peer,
+        })
     }
 
-    pub async fn async_read_all(&mut self) -> io::Result<Vec<u8>> {
+    pub async fn async_read_all(&self) -> io::Result<Vec<u8>> {
         let mut data = vec![];
         let mut buff = [0u8; 1024 * 4];
+        let reader = self.reader.get()?;
+        let mut reader = reader.lock().await;
 
         log::trace!("tls read_all");
 
         loop {
-            match self.0.read(&mut buff).await {
+            match reader.read(&mut buff).await {
                 Ok(0) => {
                     log::trace!("tls read: 0");
                     return Ok(data);
@@ -200,49 +572,337 @@ impl AsyncTlsConn {
         }
     }
 
-    pub async fn async_write_all(&mut self, buf: &[u8]) -> io::Result<()> {
-        self.0.write_all(buf).await
+    /// Writes all of `buf`, taking the stream when called like `AsyncTcpConn::async_write_all`.
+    pub fn async_write_all(
+        &self,
+        buf: Vec<u8>,
+    ) -> impl std::future::Future<Output = io::Result<()>> + Send + 'static {
+        let writer = self.writer.get();
+        async move {
+            let writer = writer?;
+            let mut writer = writer.lock().await;
+            writer.write_all(&buf).await
+        }
+    }
+
+    /// Lets go of both halves; the stream closes once pending reads and writes are done.
+    pub fn close(&self) {
+        self.reader.close();
+        self.writer.close();
     }
 
     pub fn local(&self) -> io::Result<SocketAddr> {
-        self.0.get_ref().0.local_addr()
+        Ok(self.local)
     }
 
     pub fn peer(&self) -> io::Result<SocketAddr> {
-        self.0.get_ref().0.peer_addr()
+        Ok(self.peer)
+    }
+}
+
+pub(crate) type RemoteTask = Box<dyn FnOnce(&mut Context) + Send>;
+
+/// Settles the promise of a finished I/O request, see `EventLoop::io_queue`.
+pub(crate) type IoTask = Box<dyn FnOnce() + Send>;
+
+#[derive(Default)]
+pub(crate) struct RemoteQueue {
+    tasks: std::sync::Mutex<VecDeque<RemoteTask>>,
+    io_tasks: std::sync::Mutex<VecDeque<IoTask>>,
+    waker: std::sync::Mutex<Option<std::task::Waker>>,
+    /// the live `RuntimeHandle`s
+    handle_count: AtomicUsize,
+}
+
+impl RemoteQueue {
+    fn push(&self, task: RemoteTask) {
+        self.tasks.lock().unwrap().push_back(task);
+        self.wake();
+    }
+
+    pub(crate) fn push_io(&self, task: IoTask) {
+        self.io_tasks.lock().unwrap().push_back(task);
+        self.wake();
+    }
+
+    fn wake(&self) {
+        if let Some(waker) = self.waker.lock().unwrap().as_ref() {
+            waker.wake_by_ref();
+        }
+    }
+}
+
+/// A `Send + Clone` handle for queueing work onto the JS thread of a `Runtime`
+/// from other threads, channels or tokio tasks.
+///
+/// The runtime does not finish while any handle is alive.
+pub struct RuntimeHandle(Arc<RemoteQueue>);
+
+impl RuntimeHandle {
+    fn new(remote: Arc<RemoteQueue>) -> Self {
+        remote.handle_count.fetch_add(1, Ordering::AcqRel);
+        RuntimeHandle(remote)
+    }
+
+    /// Runs `f` with the runtime's context on its next loop iteration.
+    pub fn spawn_js<F>(&self, f: F)
+    where
+        F: FnOnce(&mut Context) + Send + 'static,
+    {
+        self.0.push(Box::new(f));
+    }
+
+    /// Delivers a JSON-encoded message to `globalThis.onmessage` as `{ data }`.
+    pub fn post_message<S: Into<String>>(&self, json: S) {
+        let json = json.into();
+        self.spawn_js(move |ctx| {
+            let data = ctx.json_parse(&json, "<postMessage>");
+            if let JsValue::Exception(e) = data {
+                e.dump_error();
+                return;
+            }
+            if let JsValue::Function(onmessage) = ctx.get_global().get("onmessage") {
+                let mut event = ctx.new_object();
+                event.set("data", data);
+                if let JsValue::Exception(e) = onmessage.call(&[event.into()]) {
+                    e.report_uncaught();
+                }
+            } else {
+                log::warn!("post_message: no globalThis.onmessage, message dropped");
+            }
+        });
+    }
+}
+
+impl Clone for RuntimeHandle {
+    fn clone(&self) -> Self {
+        RuntimeHandle::new(self.0.clone())
     }
 }
 
+impl Drop for EventLoop {
+    fn drop(&mut self) {
+        // the completions hold promise functions, which must go before the JS runtime
+        self.remote.io_tasks.lock().unwrap().clear();
+    }
+}
+
+impl Drop for RuntimeHandle {
+    fn drop(&mut self) {
+        // counted down before the wake, so the loop sees it when it polls
+        if self.0.handle_count.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.0.wake();
+        }
+    }
+}
+
+/// What a `setImmediate` callback shares with the `Immediate` handle it returns.
+#[derive(Default)]
+pub(crate) struct ImmediateState {
+    pub(crate) cleared: Cell<bool>,
+    pub(crate) unrefed: Cell<bool>,
+}
+
+impl ImmediateState {
+    /// True while the callback is due to run and keeps the loop alive.
+    fn is_refed(&self) -> bool {
+        !self.cleared.get() && !self.unrefed.get()
+    }
+}
+
+/// A queued immediate; the ones queued by the runtime itself have no handle.
+type QueuedImmediate = (Box<dyn FnOnce()>, Option<Rc<ImmediateState>>);
+
 #[derive(Default)]
 pub struct EventLoop {
     next_tick_queue: LinkedList<Box<dyn FnOnce()>>,
-    immediate_queue: LinkedList<Box<dyn FnOnce()>>,
+    immediate_queue: LinkedList<QueuedImmediate>,
+    close_queue: LinkedList<Box<dyn FnOnce()>>,
+    remote: Arc<RemoteQueue>,
+    pub(crate) deadline: Option<std::time::Instant>,
     pub(crate) waker: Option<std::task::Waker>,
-    pub(crate) sub_tasks: LinkedList<tokio::task::JoinHandle<()>>,
+    pub(crate) handles: handle::Handles,
+    pub(crate) timers: timer::TimerQueue,
+    pub(crate) clock: clock::Clock,
+    /// set once an exception escapes to the loop; the loop stops until it is taken
+    pub(crate) uncaught_exception: bool,
+    pub(crate) profiler: Option<Box<qjs::js_profiler::Profiler>>,
+    /// whether `process.exit()` stops only this runtime, see `Runtime::contain_exit`
+    pub(crate) contain_exit: bool,
+    pub(crate) exit_code: Option<i32>,
+    /// handles a fatal error instead of printing it, e.g. a worker sends it to its parent
+    pub(crate) fatal_error_hook: Option<Box<dyn FnMut(&mut Context, JsValue)>>,
 }
 
 impl EventLoop {
+    /// The code `process.exit()` stopped the runtime with, see `Runtime::contain_exit`.
+    pub fn exit_code(&self) -> Option<i32> {
+        self.exit_code
+    }
+
+    /// True once the loop must run no more callbacks: an exception escaped to
+    /// it, or a contained `process.exit()` stopped the runtime.
+    pub(crate) fn stopped(&self) -> bool {
+        self.uncaught_exception || self.exit_code.is_some()
+    }
+
+    /// Queues `callback` for the check phase, like `setImmediate`.
     pub fn add_immediate_task(&mut self, callback: Box<dyn FnOnce()>) {
-        self.immediate_queue.push_back(callback);
+        self.immediate_queue.push_back((callback, None));
     }
 
+    /// Queues the callback of `setImmediate`; `state` says whether it still
+    /// runs and whether it keeps the loop alive.
+    pub(crate) fn add_immediate(&mut self, callback: Box<dyn FnOnce()>, state: Rc<ImmediateState>) {
+        self.immediate_queue.push_back((callback, Some(state)));
+    }
+
+    /// Queues `callback` for the close phase, where handles emit `close`.
+    pub fn add_close_task(&mut self, callback: Box<dyn FnOnce()>) {
+        self.close_queue.push_back(callback);
+    }
+
+    #[deprecated = "the runtime runs each queue in its own event loop phase"]
     pub fn run_tick_task(&mut self) -> usize {
         let mut i = 0;
-        let mut cb_vec = LinkedList::new();
         while let Some(f) = self.next_tick_queue.pop_front() {
-            cb_vec.push_back(f);
-        }
-        while let Some(f) = self.immediate_queue.pop_front() {
-            cb_vec.push_back(f);
+            f();
+            i += 1;
         }
-        while let Some(f) = cb_vec.pop_front() {
+        for (f, _) in std::mem::take(&mut self.immediate_queue) {
             f();
             i += 1;
         }
         i
     }
 
+    /// Schedules `timer` on the loop's clock and returns its id.
+    pub(crate) fn start_timer(&mut self, timer: timer::SharedTimer) -> u32 {
+        let now = self.clock.now();
+        self.timers.start(timer, now)
+    }
+
+    /// Restarts the countdown of the timer `id`, see `TimerQueue::restart`.
+    pub(crate) fn restart_timer(&mut self, id: u32, timer: timer::SharedTimer) -> bool {
+        let now = self.clock.now();
+        self.timers.restart(id, timer, now)
+    }
+
+    /// True while a timer keeps the loop alive. On a virtual clock none do, as
+    /// they only come due when the clock is advanced.
+    pub(crate) fn timers_keep_alive(&self) -> bool {
+        !self.clock.is_virtual() && self.timers.has_refed()
+    }
+
+    /// Takes the next timer due by `target`, first advancing the clock to its
+    /// deadline. Once none is, the clock is advanced to `target`.
+    pub(crate) fn next_timer_until(
+        &mut self,
+        target: tokio::time::Instant,
+    ) -> Option<(crate::JsFunction, Vec<JsValue>)> {
+        match self.timers.next_deadline() {
+            Some(deadline) if deadline <= target => {
+                self.clock.advance_to(deadline);
+                self.timers.next_expired(self.clock.now())
+            }
+            _ => {
+                self.clock.advance_to(target);
+                None
+            }
+        }
+    }
+
+    pub(crate) fn pop_next_tick(&mut self) -> Option<Box<dyn FnOnce()>> {
+        self.next_tick_queue.pop_front()
+    }
+
+    pub(crate) fn has_next_ticks(&self) -> bool {
+        !self.next_tick_queue.is_empty()
+    }
+
+    /// The `setImmediate` callbacks queued so far; ones they queue wait for the next turn.
+    pub(crate) fn take_immediate_tasks(&mut self) -> LinkedList<Box<dyn FnOnce()>> {
+        std::mem::take(&mut self.immediate_queue)
+            .into_iter()
+            .map(|(f, _)| f)
+            .collect()
+    }
+
+    /// The queued immediates that keep the loop alive: not cleared and not unref'd.
+    fn refed_immediates(&self) -> usize {
+        self.immediate_queue
+            .iter()
+            .filter(|(_, state)| state.as_ref().map_or(true, |state| state.is_refed()))
+            .count()
+    }
+
+    pub(crate) fn take_close_tasks(&mut self) -> LinkedList<Box<dyn FnOnce()>> {
+        std::mem::take(&mut self.close_queue)
+    }
+
+    pub(crate) fn take_remote_tasks(&mut self) -> Vec<RemoteTask> {
+        self.remote.tasks.lock().unwrap().drain(..).collect()
+    }
+
+    /// Where tokio tasks queue the completions of I/O requests, so that their
+    /// promises settle in the poll phase instead of wherever the task ran.
+    pub(crate) fn io_queue(&self) -> Arc<RemoteQueue> {
+        self.remote.clone()
+    }
+
+    /// The I/O completions queued so far; ones that land meanwhile wait for the next turn.
+    pub(crate) fn take_io_tasks(&mut self) -> Vec<IoTask> {
+        self.remote.io_tasks.lock().unwrap().drain(..).collect()
+    }
+
+    /// True if a phase of the next loop turn has callbacks to run right away.
+    pub(crate) fn has_queued_callbacks(&self) -> bool {
+        !self.next_tick_queue.is_empty()
+            || self.refed_immediates() > 0
+            || !self.close_queue.is_empty()
+            || !self.remote.tasks.lock().unwrap().is_empty()
+            || !self.remote.io_tasks.lock().unwrap().is_empty()
+    }
+
     pub fn set_next_tick(&mut self, callback: Box<dyn FnOnce()>) {
         self.next_tick_queue.push_back(callback);
     }
+
+    pub fn runtime_handle(&self) -> RuntimeHandle {
+        RuntimeHandle::new(self.remote.clone())
+    }
+
+    pub(crate) fn set_remote_waker(&mut self, waker: std::task::Waker) {
+        *self.remote.waker.lock().unwrap() = Some(waker);
+    }
+
+    /// True while any `RuntimeHandle` is alive.
+    pub(crate) fn has_runtime_handles(&self) -> bool {
+        self.remote.handle_count.load(Ordering::Acquire) > 0
+    }
+
+    /// True while callbacks are queued, requests are pending or timers keep the loop alive.
+    pub(crate) fn has_pending_tasks(&self) -> bool {
+        self.timers_keep_alive() || self.has_queued_callbacks() || self.handles.keep_alive()
+    }
+
+    /// What `process.getActiveResourcesInfo()` reports: the pending requests,
+    /// the open handles, then a `Timeout` per refed timer and an `Immediate`
+    /// per refed immediate.
+    pub(crate) fn active_resources(&self) -> Vec<&'static str> {
+        let mut names = self.handles.resource_names();
+        names.extend(std::iter::repeat("Timeout").take(self.timers.refed_count()));
+        names.extend(std::iter::repeat("Immediate").take(self.refed_immediates()));
+        names
+    }
+
+    /// Drops everything that is still scheduled: timers and the callbacks
+    /// queued for any phase.
+    pub(crate) fn clear_scheduled(&mut self) {
+        self.timers.clear_all();
+        self.next_tick_queue.clear();
+        self.immediate_queue.clear();
+        self.close_queue.clear();
+        self.remote.tasks.lock().unwrap().clear();
+    }
 }
@@ -0,0 +1,169 @@
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::Poll;
+use std::time::Duration;
+
+use tokio::time::{Instant, Sleep};
+
+use super::clock::Clock;
+use crate::{JsFunction, JsValue};
+
+/// A `setTimeout`/`setInterval` timer, shared by its JS handle and the queue.
+pub(crate) struct Timer {
+    pub(crate) callback: JsFunction,
+    pub(crate) args: Vec<JsValue>,
+    pub(crate) delay: Duration,
+    pub(crate) repeat: bool,
+    /// whether the timer keeps the event loop alive
+    pub(crate) refed: bool,
+    /// cleared timers are never rescheduled, not even by `refresh()`
+    pub(crate) cleared: bool,
+}
+
+pub(crate) type SharedTimer = Rc<RefCell<Timer>>;
+
+/// The active timers, in the order they expire.
+///
+/// Timers with the same deadline fire in the order they were scheduled.
+#[derive(Default)]
+pub(crate) struct TimerQueue {
+    next_id: u32,
+    seq: u64,
+    timers: HashMap<u32, (SharedTimer, (Instant, u64))>,
+    queue: BTreeMap<(Instant, u64), u32>,
+    sleep: Option<Pin<Box<Sleep>>>,
+}
+
+impl TimerQueue {
+    /// Schedules a new timer and returns its id. `now` is the time on the loop's clock.
+    pub(crate) fn start(&mut self, timer: SharedTimer, now: Instant) -> u32 {
+        self.next_id += 1;
+        let id = self.next_id;
+        let deadline = now + timer.borrow().delay;
+        self.schedule(id, timer, deadline);
+        id
+    }
+
+    /// Restarts the timer's countdown from now, scheduling it again if it
+    /// has already fired. Returns false if the timer was cleared.
+    pub(crate) fn restart(&mut self, id: u32, timer: SharedTimer, now: Instant) -> bool {
+        if timer.borrow().cleared {
+            return false;
+        }
+        if let Some((_, key)) = self.timers.remove(&id) {
+            self.queue.remove(&key);
+        }
+        let deadline = now + timer.borrow().delay;
+        self.schedule(id, timer, deadline);
+        true
+    }
+
+    pub(crate) fn clear(&mut self, id: u32) {
+        if let Some((timer, key)) = self.timers.remove(&id) {
+            timer.borrow_mut().cleared = true;
+            self.queue.remove(&key);
+        }
+    }
+
+    pub(crate) fn is_active(&self, id: u32) -> bool {
+        self.timers.contains_key(&id)
+    }
+
+    /// True while a timer that keeps the loop alive is pending.
+    pub(crate) fn has_refed(&self) -> bool {
+        self.timers.values().any(|(timer, _)| timer.borrow().refed)
+    }
+
+    pub(crate) fn refed_count(&self) -> usize {
+        self.timers
+            .values()
+            .filter(|(timer, _)| timer.borrow().refed)
+            .count()
+    }
+
+    /// Clears every timer.
+    pub(crate) fn clear_all(&mut self) {
+        for (_, (timer, _)) in self.timers.drain() {
+            timer.borrow_mut().cleared = true;
+        }
+        self.queue.clear();
+    }
+
+    /// When the earliest timer is due.
+    pub(crate) fn next_deadline(&self) -> Option<Instant> {
+        self.queue.keys().next().map(|(deadline, _)| *deadline)
+    }
+
+    /// When the latest timer is due.
+    pub(crate) fn last_deadline(&self) -> Option<Instant> {
+        self.queue.keys().next_back().map(|(deadline, _)| *deadline)
+    }
+
+    fn schedule(&mut self, id: u32, timer: SharedTimer, deadline: Instant) {
+        self.seq += 1;
+        let key = (deadline, self.seq);
+        self.queue.insert(key, id);
+        self.timers.insert(id, (timer, key));
+    }
+
+    /// Takes the next timer that is due at `now`, returning its callback and arguments.
+    ///
+    /// An interval is rescheduled before its callback runs, so the callback can clear it.
+    pub(crate) fn next_expired(&mut self, now: Instant) -> Option<(JsFunction, Vec<JsValue>)> {
+        let (&key, &id) = self.queue.iter().next()?;
+        if key.0 > now {
+            return None;
+        }
+        self.queue.remove(&key);
+        let (timer, _) = self.timers.remove(&id)?;
+        let (callback, args, delay, repeat) = {
+            let t = timer.borrow();
+            (t.callback.clone(), t.args.clone(), t.delay, t.repeat)
+        };
+        if repeat {
+            // keep to the original schedule rather than drifting by the callbacks'
+            // run time; ticks missed while the loop was busy are skipped
+            let mut next = key.0 + delay;
+            if next <= now {
+                // the first tick after `now`, however many were missed
+                let period = delay.as_nanos().max(1);
+                let late = (now - key.0).as_nanos() % period;
+                next = now + Duration::from_nanos((period - late) as u64);
+            }
+            self.schedule(id, timer, next);
+        }
+        Some((callback, args))
+    }
+
+    /// Ready once the earliest timer is due on `clock`. Otherwise registers `cx`
+    /// to be woken at its deadline, unless the clock is virtual and only moves
+    /// when it is advanced.
+    pub(crate) fn poll_expired(
+        &mut self,
+        cx: &mut std::task::Context<'_>,
+        clock: &Clock,
+    ) -> Poll<()> {
+        let deadline = match self.next_deadline() {
+            Some(deadline) if deadline <= clock.now() => return Poll::Ready(()),
+            Some(_) if clock.is_virtual() => None,
+            deadline => deadline,
+        };
+        let deadline = match deadline {
+            Some(deadline) => clock.to_real(deadline),
+            None => {
+                self.sleep = None;
+                return Poll::Pending;
+            }
+        };
+        let sleep = self
+            .sleep
+            .get_or_insert_with(|| Box::pin(tokio::time::sleep_until(deadline)));
+        if sleep.deadline() != deadline {
+            sleep.as_mut().reset(deadline);
+        }
+        sleep.as_mut().poll(cx)
+    }
+}
@@ -0,0 +1,431 @@
+//! The `AbortController` and `AbortSignal` globals.
+//!
+//! `AbortSignal` extends `EventTarget`. A signal also carries a `tokio::sync::watch` flag, so native async operations
+//! can be cancelled with `abortable` instead of running to completion.
+
+use std::cell::RefCell;
+use std::future::Future;
+use std::mem::ManuallyDrop;
+use std::rc::Rc;
+
+use tokio::sync::watch;
+
+use super::event_target::{dispatch_event, extend_class, Event, EventTarget};
+use crate::event_loop::timer::Timer;
+use crate::quickjs_sys::*;
+use crate::EventLoop;
+
+pub(crate) struct AbortSignal {
+    target: EventTarget,
+    reason: Option<JsValue>,
+    aborted: watch::Sender<bool>,
+    /// signals from `AbortSignal.any()` that abort along with this one
+    dependents: RefCell<Vec<Dependent>>,
+    /// for a signal from `AbortSignal.any()`, the signals it follows
+    sources: Vec<JsValue>,
+}
+
+/// A signal from `AbortSignal.any()`, as its sources see it.
+///
+/// Like Node.js, a source holds it weakly: a long-lived controller must not keep every
+/// `any()` signal made from it alive. The dependent removes itself from its sources when
+/// it is finalized, so `object` never dangles. While it has `abort` listeners it is also
+/// held in `strong`, or the listeners could be collected before they fire.
+struct Dependent {
+    signal: *const AbortSignal,
+    /// the object, without a reference of its own
+    object: ManuallyDrop<JsValue>,
+    strong: Option<JsValue>,
+}
+
+impl Dependent {
+    fn new(object: &JsValue) -> Option<Self> {
+        let signal = AbortSignal::opaque(object)? as *const AbortSignal;
+        // a bitwise copy that is never dropped, so it does not count as a reference
+        let object = ManuallyDrop::new(unsafe { std::ptr::read(object) });
+        Some(Dependent {
+            signal,
+            object,
+            strong: None,
+        })
+    }
+
+    fn upgrade(&self) -> JsValue {
+        (*self.object).clone()
+    }
+}
+
+/// Resolves once the signal it was taken from aborts.
+pub(crate) struct AbortToken(watch::Receiver<bool>);
+
+impl AbortToken {
+    pub(crate) async fn aborted(mut self) {
+        if self.0.wait_for(|aborted| *aborted).await.is_err() {
+            // the signal was collected without aborting
+            std::future::pending::<()>().await;
+        }
+    }
+}
+
+impl AbortSignal {
+    fn new() -> Self {
+        AbortSignal {
+            target: EventTarget::default(),
+            reason: None,
+            aborted: watch::channel(false).0,
+            dependents: RefCell::new(vec![]),
+            sources: vec![],
+        }
+    }
+
+    pub(crate) fn is_aborted(&self) -> bool {
+        self.reason.is_some()
+    }
+
+    /// The token of `signal`, or None if it is not an `AbortSignal`.
+    pub(crate) fn token(signal: &JsValue) -> Option<AbortToken> {
+        Self::opaque(signal).map(|signal| AbortToken(signal.aborted.subscribe()))
+    }
+
+    /// Has the sources of this `AbortSignal.any()` signal hold it strongly while it has
+    /// `abort` listeners, and weakly otherwise.
+    fn keep_alive_while_listened(&self) {
+        let listened = self.target.has_listeners("abort");
+        let this = self as *const AbortSignal;
+        for source in &self.sources {
+            if let Some(source) = AbortSignal::opaque(source) {
+                for d in source.dependents.borrow_mut().iter_mut() {
+                    if d.signal == this {
+                        d.strong = if listened { Some(d.upgrade()) } else { None };
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl AsRef<EventTarget> for AbortSignal {
+    fn as_ref(&self) -> &EventTarget {
+        &self.target
+    }
+}
+
+impl AsMut<EventTarget> for AbortSignal {
+    fn as_mut(&mut self) -> &mut EventTarget {
+        &mut self.target
+    }
+}
+
+/// Runs `fut` unless `signal` aborts first, in which case `fut` is dropped,
+/// cancelling it, and the result is the signal's reason.
+pub(crate) async fn abortable<F>(signal: Option<JsValue>, fut: F) -> Result<JsValue, JsValue>
+where
+    F: Future<Output = Result<JsValue, JsValue>>,
+{
+    let (signal, token) = match signal {
+        Some(signal) => match AbortSignal::token(&signal) {
+            Some(token) => (signal, token),
+            None => return fut.await,
+        },
+        None => return fut.await,
+    };
+    tokio::select! {
+        biased;
+        _ = token.aborted() => {
+            Err(signal.get("reason").unwrap_or(JsValue::UnDefined))
+        }
+        r = fut => r,
+    }
+}
+
+/// An `Error` standing in for the `DOMException` Node.js uses.
+pub(crate) fn new_dom_exception(ctx: &mut Context, msg: &str, name: &str, code: i32) -> JsValue {
+    let mut e = ctx.new_error(msg);
+    if let JsValue::Object(o) = &mut e {
+        let name = ctx.new_string(name);
+        o.set("name", name.into());
+        o.set("code", JsValue::Int(code));
+    }
+    e
+}
+
+/// Aborts `signal` with `reason` and fires its `abort` event, then aborts its dependents.
+/// Does nothing if it has already aborted.
+fn abort_signal(ctx: &mut Context, signal: &JsValue, reason: JsValue) {
+    let mut signal_val = signal.clone();
+    let (this, dependents, sources) = match AbortSignal::opaque_mut(&mut signal_val) {
+        Some(s) if s.reason.is_none() => {
+            s.reason = Some(reason.clone());
+            s.aborted.send_replace(true);
+            // hold the dependents before any JS runs, so none is collected while they wait
+            let dependents: Vec<JsValue> =
+                s.dependents.take().iter().map(Dependent::upgrade).collect();
+            let sources = std::mem::take(&mut s.sources);
+            (s as *const AbortSignal, dependents, sources)
+        }
+        _ => return,
+    };
+
+    // its other sources have nothing left to abort, so they let go of it
+    for source in &sources {
+        if let Some(source) = AbortSignal::opaque(source) {
+            source.dependents.borrow_mut().retain(|d| d.signal != this);
+        }
+    }
+
+    let event = Event::new_trusted(ctx, "abort");
+    if let Err(JsValue::Exception(e)) = dispatch_event(ctx, signal, &event) {
+        e.report_uncaught();
+    }
+
+    for dependent in dependents {
+        abort_signal(ctx, &dependent, reason.clone());
+    }
+}
+
+fn abort_error(ctx: &mut Context) -> JsValue {
+    new_dom_exception(ctx, "This operation was aborted", "AbortError", 20)
+}
+
+/// The callback of the timer `AbortSignal.timeout()` starts.
+fn abort_on_timeout(ctx: &mut Context, _this_val: JsValue, argv: &[JsValue]) -> JsValue {
+    if let Some(signal) = argv.get(0) {
+        let reason = new_dom_exception(
+            ctx,
+            "The operation was aborted due to timeout",
+            "TimeoutError",
+            23,
+        );
+        abort_signal(ctx, signal, reason);
+    }
+    JsValue::UnDefined
+}
+
+fn signal_abort(ctx: &mut Context, _this_val: JsValue, argv: &[JsValue]) -> JsValue {
+    let reason = match argv.get(0) {
+        None | Some(JsValue::UnDefined) => abort_error(ctx),
+        Some(reason) => reason.clone(),
+    };
+    let signal = AbortSignal::wrap_obj(ctx, AbortSignal::new());
+    abort_signal(ctx, &signal, reason);
+    signal
+}
+
+fn signal_timeout(ctx: &mut Context, _this_val: JsValue, argv: &[JsValue]) -> JsValue {
+    let ms = match argv.get(0) {
+        Some(JsValue::Int(ms)) if *ms >= 0 => *ms as u64,
+        Some(JsValue::Float(ms)) if *ms >= 0.0 && ms.is_finite() => *ms as u64,
+        _ => {
+            return ctx
+                .throw_range_error("The \"delay\" argument must be a non-negative number")
+                .into()
+        }
+    };
+    let signal = AbortSignal::wrap_obj(ctx, AbortSignal::new());
+    let callback = ctx.wrap_function("abortOnTimeout", abort_on_timeout);
+    // like Node.js, a pending timeout signal does not keep the process alive
+    let timer = Rc::new(RefCell::new(Timer {
+        callback,
+        args: vec![signal.clone()],
+        delay: std::time::Duration::from_millis(ms),
+        repeat: false,
+        refed: false,
+        cleared: false,
+    }));
+    if let Some(event_loop) = ctx.event_loop() {
+        event_loop.start_timer(timer);
+    }
+    signal
+}
+
+fn signal_any(ctx: &mut Context, _this_val: JsValue, argv: &[JsValue]) -> JsValue {
+    let sources = match argv.get(0) {
+        Some(JsValue::Array(sources)) => match sources.to_vec() {
+            Ok(sources) => sources,
+            Err(e) => return e.into(),
+        },
+        _ => {
+            return ctx
+                .throw_type_error("The \"signals\" argument must be an array of AbortSignal")
+                .into()
+        }
+    };
+    for source in &sources {
+        match AbortSignal::opaque(source) {
+            Some(s) => {
+                if let Some(reason) = s.reason.clone() {
+                    let signal = AbortSignal::wrap_obj(ctx, AbortSignal::new());
+                    abort_signal(ctx, &signal, reason);
+                    return signal;
+                }
+            }
+            None => {
+                return ctx
+                    .throw_type_error("The \"signals\" argument must be an array of AbortSignal")
+                    .into()
+            }
+        }
+    }
+    let mut signal = AbortSignal::wrap_obj(ctx, AbortSignal::new());
+    for source in &sources {
+        if let (Some(s), Some(dependent)) = (AbortSignal::opaque(source), Dependent::new(&signal)) {
+            s.dependents.borrow_mut().push(dependent);
+        }
+    }
+    if let Some(s) = AbortSignal::opaque_mut(&mut signal) {
+        s.sources = sources;
+    }
+    signal
+}
+
+impl ExtendsJsClassDef for AbortSignal {
+    type RefType = AbortSignal;
+
+    type BaseDef = EventTarget;
+
+    const EXT_CLASS_NAME: &'static str = "AbortSignal";
+
+    const CONSTRUCTOR_ARGC: u8 = 0;
+
+    const FIELDS: &'static [JsClassField<Self::RefType>] = &[
+        (
+            "aborted",
+            |this, _ctx| JsValue::Bool(this.reason.is_some()),
+            None,
+        ),
+        (
+            "reason",
+            |this, _ctx| this.reason.clone().unwrap_or(JsValue::UnDefined),
+            None,
+        ),
+        (
+            "onabort",
+            |this, _ctx| this.target.handler("abort"),
+            Some(|this, _ctx, val| {
+                this.target.set_handler("abort", val);
+                this.keep_alive_while_listened();
+            }),
+        ),
+    ];
+
+    const METHODS: &'static [JsClassMethod<Self::RefType>] = &[
+        (
+            "throwIfAborted",
+            0,
+            |this, _this_obj, ctx, _argv| match this.reason.clone() {
+                Some(reason) => ctx.throw_error(reason).into(),
+                None => JsValue::UnDefined,
+            },
+        ),
+        ("addEventListener", 2, |this, _this_obj, ctx, argv| {
+            let r = this.target.add_event_listener(ctx, argv);
+            this.keep_alive_while_listened();
+            r
+        }),
+        ("removeEventListener", 2, |this, _this_obj, ctx, argv| {
+            let r = this.target.remove_event_listener(ctx, argv);
+            this.keep_alive_while_listened();
+            r
+        }),
+    ];
+
+    const STATIC_METHODS: &'static [JsClassStaticMethod] = &[
+        ("abort", 1, signal_abort),
+        ("timeout", 1, signal_timeout),
+        ("any", 1, signal_any),
+    ];
+
+    const SYMBOL_FIELDS: &'static [JsClassSymbolField<Self::RefType>] = &[(
+        JsClassSymbol::ToStringTag,
+        |_this, ctx| ctx.new_string("AbortSignal").into(),
+        None,
+    )];
+
+    unsafe fn mut_class_id_ptr() -> &'static mut u32 {
+        static mut CLASS_ID: u32 = 0;
+        &mut CLASS_ID
+    }
+
+    fn constructor_fn(ctx: &mut Context, _argv: &[JsValue]) -> Result<Self::RefType, JsValue> {
+        Err(ctx.throw_type_error("Illegal constructor").into())
+    }
+
+    fn finalizer(data: &mut Self::RefType, _event_loop: Option<&mut EventLoop>) {
+        let this = data as *const AbortSignal;
+        for source in &data.sources {
+            if let Some(source) = AbortSignal::opaque(source) {
+                source.dependents.borrow_mut().retain(|d| d.signal != this);
+            }
+        }
+    }
+
+    fn gc_mark(data: &Self::RefType, make: &mut dyn Fn(&JsValue)) {
+        if let Some(reason) = &data.reason {
+            make(reason);
+        }
+        for d in data.dependents.borrow().iter() {
+            if let Some(strong) = &d.strong {
+                make(strong);
+            }
+        }
+        data.sources.iter().for_each(|v| make(v));
+    }
+}
+
+pub(crate) struct AbortController {
+    signal: JsValue,
+}
+
+impl JsClassDef for AbortController {
+    type RefType = AbortController;
+
+    const CLASS_NAME: &'static str = "AbortController";
+
+    const CONSTRUCTOR_ARGC: u8 = 0;
+
+    const FIELDS: &'static [JsClassField<Self::RefType>] =
+        &[("signal", |this, _ctx| this.signal.clone(), None)];
+
+    const METHODS: &'static [JsClassMethod<Self::RefType>] =
+        &[("abort", 1, |this, _this_obj, ctx, argv| {
+            let reason = match argv.get(0) {
+                None | Some(JsValue::UnDefined) => abort_error(ctx),
+                Some(reason) => reason.clone(),
+            };
+            let signal = this.signal.clone();
+            abort_signal(ctx, &signal, reason);
+            JsValue::UnDefined
+        })];
+
+    const SYMBOL_FIELDS: &'static [JsClassSymbolField<Self::RefType>] = &[(
+        JsClassSymbol::ToStringTag,
+        |_this, ctx| ctx.new_string("AbortController").into(),
+        None,
+    )];
+
+    unsafe fn mut_class_id_ptr() -> &'static mut u32 {
+        static mut CLASS_ID: u32 = 0;
+        &mut CLASS_ID
+    }
+
+    fn constructor_fn(ctx: &mut Context, _argv: &[JsValue]) -> Result<Self::RefType, JsValue> {
+        Ok(AbortController {
+            signal: AbortSignal::wrap_obj(ctx, AbortSignal::new()),
+        })
+    }
+
+    fn gc_mark(data: &Self::RefType, make: &mut dyn Fn(&JsValue)) {
+        make(&data.signal);
+    }
+}
+
+pub fn init_global(ctx: &mut Context) {
+    let signal_ctor = register_class::<AbortSignal>(ctx);
+    if let Some(target_ctor) = ctx.get_class_constructor(EventTarget::class_id()) {
+        extend_class(ctx, &signal_ctor, &target_ctor);
+    }
+    let controller_ctor = register_class::<AbortController>(ctx);
+    let mut global = ctx.get_global();
+    global.set("AbortSignal", signal_ctor);
+    global.set("AbortController", controller_ctor);
+}
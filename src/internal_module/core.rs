@@ -1,26 +1,75 @@
+use crate::event_loop::timer::{SharedTimer, Timer};
+use crate::event_loop::ImmediateState;
 use crate::quickjs_sys::*;
 use crate::EventLoop;
+use std::cell::RefCell;
+use std::rc::Rc;
 use std::string::FromUtf8Error;
-use std::sync::Arc;
+use std::time::Duration;
 
-#[derive(Debug, Clone)]
-struct TimeoutId(Arc<tokio::sync::Notify>);
-impl TimeoutId {
-    pub fn new() -> Self {
-        TimeoutId(Arc::new(tokio::sync::Notify::new()))
+/// The handle `setTimeout` and `setInterval` return.
+struct Timeout {
+    id: u32,
+    timer: SharedTimer,
+}
+
+impl Timeout {
+    fn clear(&self, ctx: &mut Context) {
+        self.timer.borrow_mut().cleared = true;
+        if let Some(event_loop) = ctx.event_loop() {
+            event_loop.timers.clear(self.id);
+        }
+    }
+
+    fn set_ref(&mut self, this_obj: &mut JsObject, refed: bool) -> JsValue {
+        self.timer.borrow_mut().refed = refed;
+        this_obj.clone().into()
     }
 }
 
-impl JsClassDef for TimeoutId {
-    type RefType = TimeoutId;
+impl JsClassDef for Timeout {
+    type RefType = Timeout;
 
-    const CLASS_NAME: &'static str = "TimeoutId";
+    const CLASS_NAME: &'static str = "Timeout";
 
     const CONSTRUCTOR_ARGC: u8 = 0;
 
     const FIELDS: &'static [JsClassField<Self::RefType>] = &[];
 
-    const METHODS: &'static [JsClassMethod<Self::RefType>] = &[];
+    const METHODS: &'static [JsClassMethod<Self::RefType>] = &[
+        ("ref", 0, |this, this_obj, _ctx, _argv| {
+            this.set_ref(this_obj, true)
+        }),
+        ("unref", 0, |this, this_obj, _ctx, _argv| {
+            this.set_ref(this_obj, false)
+        }),
+        ("hasRef", 0, |this, _this_obj, _ctx, _argv| {
+            JsValue::Bool(this.timer.borrow().refed)
+        }),
+        ("refresh", 0, |this, this_obj, ctx, _argv| {
+            if let Some(event_loop) = ctx.event_loop() {
+                event_loop.restart_timer(this.id, this.timer.clone());
+            }
+            this_obj.clone().into()
+        }),
+        ("close", 0, |this, this_obj, ctx, _argv| {
+            this.clear(ctx);
+            this_obj.clone().into()
+        }),
+    ];
+
+    const SYMBOL_METHODS: &'static [JsClassSymbolMethod<Self::RefType>] = &[
+        // `+timeout` is an id that `clearTimeout` accepts
+        (
+            JsClassSymbol::ToPrimitive,
+            1,
+            |this, _this_obj, _ctx, _argv| JsValue::Int(this.id as i32),
+        ),
+        (JsClassSymbol::Dispose, 0, |this, _this_obj, ctx, _argv| {
+            this.clear(ctx);
+            JsValue::UnDefined
+        }),
+    ];
 
     unsafe fn mut_class_id_ptr() -> &'static mut u32 {
         static mut CLASS_ID: u32 = 0;
@@ -32,67 +81,153 @@ impl JsClassDef for TimeoutId {
     }
 }
 
-fn set_timeout(ctx: &mut Context, _this_val: JsValue, argv: &[JsValue]) -> JsValue {
-    let callback = argv.get(0);
-    let timeout = argv.get(1);
-    let rest_args = argv.get(2..).map(|args| args.to_vec());
-    if let (Some(JsValue::Function(callback)), Some(JsValue::Int(timeout))) = (callback, timeout) {
-        let timeout = *timeout as u64;
-        let callback = callback.clone();
+/// The handle `setImmediate` returns.
+struct Immediate {
+    state: Rc<ImmediateState>,
+}
 
-        if timeout == 0 {
-            ctx.event_loop().map(|event_loop| {
-                event_loop.add_immediate_task(Box::new(move || {
-                    if let Some(rest_args) = rest_args {
-                        callback.call(&rest_args);
-                    } else {
-                        callback.call(&[]);
-                    }
-                }))
-            });
-            JsValue::UnDefined
-        } else {
-            let timeout = std::time::Duration::from_millis(timeout);
-
-            let id = TimeoutId::new();
-            let id_ = id.clone();
-
-            ctx.future_to_promise(async move {
-                log::trace!("async wait timeout {timeout:?}");
-                match tokio::time::timeout(timeout, id_.0.notified()).await {
-                    Ok(_) => {
-                        log::trace!("timer cancel");
-                        Err(JsValue::UnDefined)
-                    }
-                    Err(_) => {
-                        log::trace!("timer timeout");
-                        if let Some(rest_args) = rest_args {
-                            callback.call(&rest_args);
-                        } else {
-                            callback.call(&[]);
-                        };
-                        Ok(JsValue::UnDefined)
-                    }
-                }
-            });
-            TimeoutId::wrap_obj(ctx, id)
+impl JsClassDef for Immediate {
+    type RefType = Immediate;
+
+    const CLASS_NAME: &'static str = "Immediate";
+
+    const CONSTRUCTOR_ARGC: u8 = 0;
+
+    const FIELDS: &'static [JsClassField<Self::RefType>] = &[];
+
+    // an unref'd immediate still runs if the loop turns for other work
+    const METHODS: &'static [JsClassMethod<Self::RefType>] = &[
+        ("ref", 0, |this, this_obj, _ctx, _argv| {
+            this.state.unrefed.set(false);
+            this_obj.clone().into()
+        }),
+        ("unref", 0, |this, this_obj, _ctx, _argv| {
+            this.state.unrefed.set(true);
+            this_obj.clone().into()
+        }),
+        ("hasRef", 0, |this, _this_obj, _ctx, _argv| {
+            JsValue::Bool(!this.state.unrefed.get())
+        }),
+    ];
+
+    unsafe fn mut_class_id_ptr() -> &'static mut u32 {
+        static mut CLASS_ID: u32 = 0;
+        &mut CLASS_ID
+    }
+
+    fn constructor_fn(_ctx: &mut Context, _argv: &[JsValue]) -> Result<Self::RefType, JsValue> {
+        Err(JsValue::UnDefined)
+    }
+}
+
+// the largest delay Node.js accepts, 2^31 - 1 ms
+const TIMEOUT_MAX: f64 = 2147483647.0;
+
+/// Converts a timer delay like Node.js: it is coerced to a number, truncated to
+/// whole milliseconds, and anything below 1 or above `TIMEOUT_MAX` becomes 1.
+fn timer_delay(ctx: &mut Context, delay: Option<&JsValue>) -> Result<Duration, JsValue> {
+    let ms = match delay {
+        None | Some(JsValue::UnDefined) => f64::NAN,
+        Some(JsValue::Int(ms)) => *ms as f64,
+        Some(JsValue::Float(ms)) => *ms,
+        Some(delay) => match ctx.get_global().get("Number") {
+            JsValue::Function(number) => match number.call(&[delay.clone()]) {
+                JsValue::Int(ms) => ms as f64,
+                JsValue::Float(ms) => ms,
+                JsValue::Exception(e) => return Err(e.into()),
+                _ => f64::NAN,
+            },
+            _ => f64::NAN,
+        },
+    };
+    if ms > TIMEOUT_MAX {
+        let msg = format!(
+            "{} does not fit into a 32-bit signed integer.\nTimeout duration was set to 1.",
+            ms
+        );
+        emit_warning(ctx, &msg, "TimeoutOverflowWarning");
+    }
+    // also false for NaN
+    let ms = if ms >= 1.0 && ms <= TIMEOUT_MAX {
+        ms.trunc()
+    } else {
+        1.0
+    };
+    Ok(Duration::from_millis(ms as u64))
+}
+
+/// `process.emitWarning`, or stderr before the `process` module is loaded.
+fn emit_warning(ctx: &mut Context, msg: &str, kind: &str) {
+    if let Some(mut process) = process_object(ctx) {
+        let msg = ctx.new_string(msg).into();
+        let kind = ctx.new_string(kind).into();
+        if let JsValue::Exception(e) = process.invoke("emitWarning", &[msg, kind]) {
+            e.dump_error();
         }
     } else {
-        JsValue::UnDefined
+        eprintln!("{}: {}", kind, msg);
     }
 }
 
-fn set_immediate(ctx: &mut Context, this_val: JsValue, argv: &[JsValue]) -> JsValue {
-    let callback = argv.get(0);
+fn start_timer(ctx: &mut Context, argv: &[JsValue], repeat: bool) -> JsValue {
+    let callback = match argv.get(0) {
+        Some(JsValue::Function(callback)) => callback.clone(),
+        _ => {
+            return ctx
+                .throw_type_error("The \"callback\" argument must be of type function")
+                .into()
+        }
+    };
+    let delay = match timer_delay(ctx, argv.get(1)) {
+        Ok(delay) => delay,
+        Err(e) => return e,
+    };
+    let timer = Rc::new(RefCell::new(Timer {
+        callback,
+        args: argv.get(2..).map(|args| args.to_vec()).unwrap_or_default(),
+        delay,
+        repeat,
+        refed: true,
+        cleared: false,
+    }));
+    let id = match ctx.event_loop() {
+        Some(event_loop) => event_loop.start_timer(timer.clone()),
+        None => return JsValue::UnDefined,
+    };
+    Timeout::wrap_obj(ctx, Timeout { id, timer })
+}
+
+fn set_timeout(ctx: &mut Context, _this_val: JsValue, argv: &[JsValue]) -> JsValue {
+    start_timer(ctx, argv, false)
+}
+
+fn set_interval(ctx: &mut Context, _this_val: JsValue, argv: &[JsValue]) -> JsValue {
+    start_timer(ctx, argv, true)
+}
+
+fn set_immediate(ctx: &mut Context, _this_val: JsValue, argv: &[JsValue]) -> JsValue {
+    let callback = match argv.get(0) {
+        Some(JsValue::Function(callback)) => callback.clone(),
+        _ => {
+            return ctx
+                .throw_type_error("The \"callback\" argument must be of type function")
+                .into()
+        }
+    };
     let rest_args = argv.get(1..).map(|v| v.to_vec());
-    if let Some(JsValue::Function(callback)) = callback {
-        let callback = callback.clone();
-        let mut argv = vec![JsValue::Function(callback), JsValue::Int(0)];
-        argv.extend(rest_args.unwrap_or_default());
-        set_timeout(ctx, this_val, &argv)
-    } else {
-        JsValue::UnDefined
+    let state = Rc::new(ImmediateState::default());
+    let state_ = state.clone();
+    if let Some(event_loop) = ctx.event_loop() {
+        event_loop.add_immediate(
+            Box::new(move || {
+                if !state_.cleared.get() {
+                    call_callback(&callback, rest_args);
+                }
+            }),
+            state.clone(),
+        );
     }
+    Immediate::wrap_obj(ctx, Immediate { state })
 }
 
 fn next_tick(ctx: &mut Context, _this_val: JsValue, argv: &[JsValue]) -> JsValue {
@@ -101,10 +236,7 @@ fn next_tick(ctx: &mut Context, _this_val: JsValue, argv: &[JsValue]) -> JsValue
     if let (Some(JsValue::Function(callback)), Some(event_loop)) = (callback, ctx.event_loop()) {
         let callback = callback.clone();
         event_loop.set_next_tick(Box::new(move || {
-            match args {
-                Some(args) => callback.call(&args),
-                None => callback.call(&[]),
-            };
+            call_callback(&callback, args);
         }));
     }
     JsValue::UnDefined
@@ -131,31 +263,393 @@ fn sleep(ctx: &mut Context, _this_val: JsValue, argv: &[JsValue]) -> JsValue {
     }
 }
 
-fn os_exit(_ctx: &mut Context, _this_val: JsValue, argv: &[JsValue]) -> JsValue {
-    let code = if let Some(JsValue::Int(c)) = argv.get(0) {
-        *c
+/// `globalThis.process`, once the `process` module is loaded.
+fn process_object(ctx: &mut Context) -> Option<JsObject> {
+    match ctx.get_global().get("process") {
+        JsValue::Object(process) => Some(process),
+        _ => None,
+    }
+}
+
+/// `process.exitCode`, or `default` if it is not set.
+pub(crate) fn process_exit_code(ctx: &mut Context, default: i32) -> i32 {
+    match process_object(ctx).map(|process| process.get("exitCode")) {
+        Some(JsValue::Int(code)) => code,
+        Some(JsValue::Float(code)) if code.fract() == 0.0 => code as i32,
+        _ => default,
+    }
+}
+
+/// Emits `beforeExit` on `process`. Returns false if nothing listens for it.
+pub(crate) fn emit_before_exit(ctx: &mut Context) -> bool {
+    let code = process_exit_code(ctx, 0);
+    let mut process = match process_object(ctx) {
+        Some(process) => process,
+        None => return false,
+    };
+    let event = ctx.new_string("beforeExit").into();
+    match process.invoke("emit", &[event, JsValue::Int(code)]) {
+        JsValue::Bool(listened) => listened,
+        JsValue::Exception(e) => {
+            e.report_uncaught();
+            false
+        }
+        _ => false,
+    }
+}
+
+/// Emits `exit` on `process` the first time it is called,
+/// returning the exit code the listeners leave in `process.exitCode`.
+pub(crate) fn emit_exit(ctx: &mut Context, code: i32) -> i32 {
+    let mut process = match process_object(ctx) {
+        Some(process) => process,
+        None => return code,
+    };
+    if let JsValue::Bool(true) = process.get("_exiting") {
+        return code;
+    }
+    process.set("_exiting", JsValue::Bool(true));
+    process.set("exitCode", JsValue::Int(code));
+    let event = ctx.new_string("exit").into();
+    if let JsValue::Exception(e) = process.invoke("emit", &[event, JsValue::Int(code)]) {
+        e.dump_error();
+        return 1;
+    }
+    process_exit_code(ctx, code)
+}
+
+/// Emits `uncaughtExceptionMonitor`, then `uncaughtException` on `process` for an
+/// error that escaped to the event loop. Returns false if nothing listens for
+/// `uncaughtException`, which makes the error fatal; so does a throwing listener.
+pub(crate) fn emit_uncaught_exception(ctx: &mut Context, error: &JsValue) -> bool {
+    let mut process = match process_object(ctx) {
+        Some(process) => process,
+        None => return false,
+    };
+    let origin: JsValue = ctx.new_string("uncaughtException").into();
+    let monitor = ctx.new_string("uncaughtExceptionMonitor").into();
+    if let JsValue::Exception(e) = process.invoke("emit", &[monitor, error.clone(), origin.clone()])
+    {
+        e.report_fatal();
+        return true;
+    }
+    let listened = match process.invoke("listenerCount", &[origin.clone()]) {
+        JsValue::Int(n) => n > 0,
+        _ => false,
+    };
+    if !listened {
+        return false;
+    }
+    if let JsValue::Exception(e) = process.invoke("emit", &[origin.clone(), error.clone(), origin])
+    {
+        // a throwing listener cannot handle its own error
+        e.report_fatal();
+    }
+    true
+}
+
+fn report_error(ctx: &mut Context, _this_val: JsValue, argv: &[JsValue]) -> JsValue {
+    let error = argv.get(0).cloned().unwrap_or(JsValue::UnDefined);
+    if !emit_uncaught_exception(ctx, &error) {
+        ctx.report_fatal_error(error);
+    }
+    JsValue::UnDefined
+}
+
+fn queue_microtask(ctx: &mut Context, _this_val: JsValue, argv: &[JsValue]) -> JsValue {
+    match argv.get(0) {
+        Some(JsValue::Function(callback)) => {
+            ctx.queue_microtask(callback);
+            JsValue::UnDefined
+        }
+        _ => ctx
+            .throw_type_error("The \"callback\" argument must be of type function")
+            .into(),
+    }
+}
+
+/// Calls a timer or tick callback; an exception it throws is uncaught.
+/// Returns false if it threw.
+pub(crate) fn call_callback(callback: &JsFunction, args: Option<Vec<JsValue>>) -> bool {
+    let result = match args {
+        Some(args) => callback.call(&args),
+        None => callback.call(&[]),
+    };
+    if let JsValue::Exception(e) = result {
+        e.report_uncaught();
+        false
     } else {
-        0
+        true
+    }
+}
+
+fn os_exit(ctx: &mut Context, _this_val: JsValue, argv: &[JsValue]) -> JsValue {
+    let code = match argv.get(0) {
+        Some(JsValue::Int(c)) => *c,
+        _ => process_exit_code(ctx, 0),
     };
+    let code = emit_exit(ctx, code);
+    if let Some(event_loop) = ctx.event_loop() {
+        if event_loop.contain_exit {
+            // stop only this runtime, unwinding the JS that is running
+            event_loop.exit_code = Some(code);
+            return ctx.throw_uncatchable_error("process is exiting").into();
+        }
+        crate::quickjs_sys::js_profiler::save_on_exit(event_loop);
+    }
 
+    use std::io::Write;
+    let _ = std::io::stdout().flush();
+    // `console` writes through C stdio
+    unsafe { libc::fflush(std::ptr::null_mut()) };
     std::process::exit(code)
 }
 
-fn clear_timeout(_ctx: &mut Context, _this_val: JsValue, argv: &[JsValue]) -> JsValue {
-    let timeout_id = argv.get(0);
-    if let Some(timeout_id) = timeout_id {
-        let id = TimeoutId::opaque(&timeout_id);
-        if let Some(id) = id {
-            id.0.notify_one()
+fn clear_timeout(ctx: &mut Context, _this_val: JsValue, argv: &[JsValue]) -> JsValue {
+    let handle = match argv.get(0) {
+        Some(handle) => handle,
+        None => return JsValue::UnDefined,
+    };
+    if let Some(immediate) = Immediate::opaque(handle) {
+        immediate.state.cleared.set(true);
+    } else if let Some(timeout) = Timeout::opaque(handle) {
+        timeout.clear(ctx);
+    } else {
+        // the numeric id from `+timeout`
+        let id = match handle {
+            JsValue::Int(id) => *id as u32,
+            JsValue::Float(id) if id.fract() == 0.0 => *id as u32,
+            JsValue::String(id) => match id.as_str().parse::<u32>() {
+                Ok(id) => id,
+                Err(_) => return JsValue::UnDefined,
+            },
+            _ => return JsValue::UnDefined,
+        };
+        if let Some(event_loop) = ctx.event_loop() {
+            event_loop.timers.clear(id);
+        }
+    }
+    JsValue::UnDefined
+}
+
+fn structured_clone(ctx: &mut Context, _this_val: JsValue, argv: &[JsValue]) -> JsValue {
+    let value = argv.get(0).cloned().unwrap_or(JsValue::UnDefined);
+    let transfer = match argv.get(1) {
+        Some(JsValue::Object(options)) => options.get("transfer"),
+        _ => JsValue::UnDefined,
+    };
+    let transfer = match transfer {
+        JsValue::UnDefined | JsValue::Null => vec![],
+        JsValue::Array(list) => match list.to_vec() {
+            Ok(list) => list,
+            Err(e) => return e.into(),
+        },
+        _ => {
+            return ctx
+                .throw_type_error("options.transfer must be an array")
+                .into()
+        }
+    };
+
+    let mut buffers = Vec::with_capacity(transfer.len());
+    for item in transfer {
+        match item {
+            item if SharedBuffer::from_js(ctx, &item).is_some() => {
+                let e = ctx.new_data_clone_error("SharedArrayBuffer can not be transferred");
+                return ctx.throw_error(e).into();
+            }
+            JsValue::ArrayBuffer(buf) if !buffers.contains(&buf) => buffers.push(buf),
+            JsValue::ArrayBuffer(_) => {
+                let e =
+                    ctx.new_data_clone_error("ArrayBuffer is listed in the transfer list twice");
+                return ctx.throw_error(e).into();
+            }
+            _ => {
+                let e = ctx.new_data_clone_error("Value in the transfer list is not transferable");
+                return ctx.throw_error(e).into();
+            }
+        }
+    }
+
+    let (data, shared) = match ctx.serialize_shared(&value) {
+        Ok(r) => r,
+        Err(e) => return ctx.throw_error(e).into(),
+    };
+    // the clone owns a copy of each transferred buffer, so the originals are detached
+    for buf in buffers {
+        buf.detach();
+    }
+    match ctx.deserialize_shared(&data, &shared) {
+        Ok(v) => v,
+        Err(e) => ctx.throw_error(e).into(),
+    }
+}
+
+/// The `Date.now()` of `Runtime::set_virtual_clock`: the wall time the clock
+/// stands at while it is virtual, the real time otherwise.
+fn date_now(ctx: &mut Context, _this_val: JsValue, _argv: &[JsValue]) -> JsValue {
+    let ms = match ctx.event_loop() {
+        Some(event_loop) if event_loop.clock.is_virtual() => {
+            event_loop.clock.time_origin_ms() + event_loop.clock.now_ms()
+        }
+        _ => std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_millis() as f64)
+            .unwrap_or_default(),
+    };
+    JsValue::Float(ms.floor())
+}
+
+/// Routes `Date.now()` through the event loop clock, see `date_now`.
+pub(crate) fn route_date_now(ctx: &mut Context) {
+    if let JsValue::Function(mut date) = ctx.get_global().get("Date") {
+        let now = ctx.wrap_function("now", date_now);
+        date.set("now", now.into());
+    }
+}
+
+/// `setVirtualClock(enabled)`, see `Runtime::set_virtual_clock`.
+fn set_virtual_clock(ctx: &mut Context, _this_val: JsValue, argv: &[JsValue]) -> JsValue {
+    let enabled = matches!(argv.get(0), Some(JsValue::Bool(true)));
+    if let Some(event_loop) = ctx.event_loop() {
+        event_loop.clock.set_virtual(enabled);
+    }
+    JsValue::UnDefined
+}
+
+/// Runs the timers due by `target` in order, advancing the clock to each one's
+/// deadline. Unlike `Runtime::advance_time`, there is no microtask checkpoint
+/// between them, like the mock timers of Node.js.
+fn run_timers_until(ctx: &mut Context, target: tokio::time::Instant) {
+    loop {
+        let timer = match ctx.event_loop() {
+            Some(event_loop) => event_loop.next_timer_until(target),
+            None => return,
+        };
+        match timer {
+            Some((callback, args)) => {
+                call_callback(&callback, Some(args));
+            }
+            None => return,
+        }
+    }
+}
+
+/// `advanceClock(ms)`: moves the clock forward, running the timers that come due.
+fn advance_clock(ctx: &mut Context, _this_val: JsValue, argv: &[JsValue]) -> JsValue {
+    let ms = match argv.get(0) {
+        Some(JsValue::Int(ms)) if *ms >= 0 => *ms as f64,
+        Some(JsValue::Float(ms)) if *ms >= 0.0 && ms.is_finite() => *ms,
+        _ => {
+            return ctx
+                .throw_range_error("The \"milliseconds\" argument must be a non-negative number")
+                .into()
         }
+    };
+    let target = match ctx.event_loop() {
+        Some(event_loop) => event_loop.clock.now() + Duration::from_secs_f64(ms / 1000.0),
+        None => return JsValue::UnDefined,
+    };
+    run_timers_until(ctx, target);
+    JsValue::UnDefined
+}
+
+/// `runAllTimers()`: advances the clock to the latest pending timer, running
+/// every timer on the way.
+fn run_all_timers(ctx: &mut Context, _this_val: JsValue, _argv: &[JsValue]) -> JsValue {
+    let target = ctx
+        .event_loop()
+        .and_then(|event_loop| event_loop.timers.last_deadline());
+    if let Some(target) = target {
+        run_timers_until(ctx, target);
     }
     JsValue::UnDefined
 }
 
 pub fn init_ext_function(_ctx: &mut Context) {}
 
+/// `process.getActiveResourcesInfo()`, see `EventLoop::active_resources`.
+fn get_active_resources_info(ctx: &mut Context, _this_val: JsValue, _argv: &[JsValue]) -> JsValue {
+    let names = match ctx.event_loop() {
+        Some(event_loop) => event_loop.active_resources(),
+        None => vec![],
+    };
+    let mut array = ctx.new_array();
+    for (i, name) in names.into_iter().enumerate() {
+        array.put(i, ctx.new_string(name).into());
+    }
+    array.into()
+}
+
+/// `process._getActiveHandles()`: the objects of the open handles, like sockets.
+fn get_active_handles(ctx: &mut Context, _this_val: JsValue, _argv: &[JsValue]) -> JsValue {
+    let objects = match ctx.event_loop() {
+        Some(event_loop) => event_loop.handles.handle_objects(),
+        None => vec![],
+    };
+    let mut array = ctx.new_array();
+    for (i, object) in objects.into_iter().enumerate() {
+        array.put(i, object);
+    }
+    array.into()
+}
+
+/// `process._getActiveRequests()`: a `{ type }` object per pending request
+/// that does not run on a handle.
+fn get_active_requests(ctx: &mut Context, _this_val: JsValue, _argv: &[JsValue]) -> JsValue {
+    let kinds = match ctx.event_loop() {
+        Some(event_loop) => event_loop.handles.request_kinds(),
+        None => vec![],
+    };
+    let mut array = ctx.new_array();
+    for (i, kind) in kinds.into_iter().enumerate() {
+        let mut request = ctx.new_object();
+        request.set("type", ctx.new_string(kind.name()).into());
+        array.put(i, request.into());
+    }
+    array.into()
+}
+
+pub fn init_module(ctx: &mut Context) {
+    // `_node:process`, the event loop introspection behind `process`
+    ctx.register_fn_module(
+        "_node:process\0",
+        &[
+            "getActiveResourcesInfo",
+            "getActiveHandles",
+            "getActiveRequests",
+        ],
+        |ctx, m| {
+            let f = ctx.wrap_function("getActiveResourcesInfo", get_active_resources_info);
+            m.add_export("getActiveResourcesInfo", f.into());
+
+            let f = ctx.wrap_function("getActiveHandles", get_active_handles);
+            m.add_export("getActiveHandles", f.into());
+
+            let f = ctx.wrap_function("getActiveRequests", get_active_requests);
+            m.add_export("getActiveRequests", f.into());
+        },
+    );
+    // `_node:timers`, the clock controls behind `mock.timers` of `node:test`
+    ctx.register_fn_module(
+        "_node:timers\0",
+        &["setVirtualClock", "advanceClock", "runAllTimers"],
+        |ctx, m| {
+            let f = ctx.wrap_function("setVirtualClock", set_virtual_clock);
+            m.add_export("setVirtualClock", f.into());
+
+            let f = ctx.wrap_function("advanceClock", advance_clock);
+            m.add_export("advanceClock", f.into());
+
+            let f = ctx.wrap_function("runAllTimers", run_all_timers);
+            m.add_export("runAllTimers", f.into());
+        },
+    )
+}
+
 pub fn init_global_function(ctx: &mut Context) {
-    register_class::<TimeoutId>(ctx);
+    register_class::<Timeout>(ctx);
+    register_class::<Immediate>(ctx);
 
     let mut global = ctx.get_global();
     global.set(
@@ -170,9 +664,35 @@ pub fn init_global_function(ctx: &mut Context) {
         "setImmediate",
         ctx.wrap_function("setImmediate", set_immediate).into(),
     );
+    global.set(
+        "setInterval",
+        ctx.wrap_function("setInterval", set_interval).into(),
+    );
+    // `clear_timeout` accepts every kind of timer handle, like the Node.js functions do
+    global.set(
+        "clearInterval",
+        ctx.wrap_function("clearInterval", clear_timeout).into(),
+    );
+    global.set(
+        "clearImmediate",
+        ctx.wrap_function("clearImmediate", clear_timeout).into(),
+    );
     global.set("sleep", ctx.wrap_function("sleep", sleep).into());
     global.set("nextTick", ctx.wrap_function("nextTick", next_tick).into());
+    global.set(
+        "queueMicrotask",
+        ctx.wrap_function("queueMicrotask", queue_microtask).into(),
+    );
+    global.set(
+        "reportError",
+        ctx.wrap_function("reportError", report_error).into(),
+    );
     global.set("exit", ctx.wrap_function("exit", os_exit).into());
+    global.set(
+        "structuredClone",
+        ctx.wrap_function("structuredClone", structured_clone)
+            .into(),
+    );
     global.set("env", env_object(ctx).into());
 }
 
@@ -0,0 +1,663 @@
+//! The `EventTarget`, `Event` and `CustomEvent` globals.
+//!
+//! There is no tree to propagate through, so an event only reaches the target
+//! it is dispatched on: capturing listeners first, then the others, like the
+//! at-target phase of the DOM.
+
+use std::cell::Cell;
+use std::rc::Rc;
+
+use super::abort::AbortSignal;
+use crate::quickjs_sys::*;
+
+const NONE: i32 = 0;
+const AT_TARGET: i32 = 2;
+
+/// JS truthiness, for the dictionary members of event options.
+fn truthy(v: &JsValue) -> bool {
+    match v {
+        JsValue::Bool(b) => *b,
+        JsValue::Int(n) => *n != 0,
+        JsValue::Float(n) => *n != 0.0 && !n.is_nan(),
+        JsValue::String(s) => !s.as_str().is_empty(),
+        JsValue::Null | JsValue::UnDefined => false,
+        _ => true,
+    }
+}
+
+fn to_string(ctx: &mut Context, v: &JsValue) -> Result<String, JsValue> {
+    match ctx.value_to_string(v) {
+        JsValue::String(s) => Ok(s.as_str().to_string()),
+        JsValue::Exception(e) => Err(e.into()),
+        _ => Ok(String::new()),
+    }
+}
+
+fn option(init: Option<&JsValue>, key: &str) -> JsValue {
+    match init {
+        Some(init @ JsValue::Object(_)) => init.get(key).unwrap_or(JsValue::UnDefined),
+        _ => JsValue::UnDefined,
+    }
+}
+
+pub(crate) struct Event {
+    type_: String,
+    bubbles: bool,
+    cancelable: bool,
+    composed: bool,
+    is_trusted: bool,
+    time_stamp: f64,
+    default_prevented: bool,
+    stop_propagation: bool,
+    stop_immediate_propagation: bool,
+    in_passive_listener: bool,
+    dispatching: bool,
+    phase: i32,
+    target: JsValue,
+    current_target: JsValue,
+}
+
+impl Event {
+    fn new(ctx: &mut Context, type_: String) -> Self {
+        // on the clock of `performance.now()`, like Node.js
+        let time_stamp = ctx
+            .event_loop()
+            .map_or(0.0, |event_loop| event_loop.clock.now_ms());
+        Event {
+            type_,
+            bubbles: false,
+            cancelable: false,
+            composed: false,
+            is_trusted: false,
+            time_stamp,
+            default_prevented: false,
+            stop_propagation: false,
+            stop_immediate_propagation: false,
+            in_passive_listener: false,
+            dispatching: false,
+            phase: NONE,
+            target: JsValue::Null,
+            current_target: JsValue::Null,
+        }
+    }
+
+    /// An event the runtime fires itself, like `abort` on an `AbortSignal`.
+    pub(crate) fn new_trusted(ctx: &mut Context, type_: &str) -> JsValue {
+        let mut event = Event::new(ctx, type_.to_string());
+        event.is_trusted = true;
+        Event::wrap_obj(ctx, event)
+    }
+
+    fn from_init(ctx: &mut Context, argv: &[JsValue]) -> Result<Self, JsValue> {
+        let type_ = match argv.get(0) {
+            Some(t) => to_string(ctx, t)?,
+            None => {
+                return Err(ctx
+                    .throw_type_error("The \"type\" argument must be specified")
+                    .into())
+            }
+        };
+        let init = argv.get(1);
+        let mut event = Event::new(ctx, type_);
+        event.bubbles = truthy(&option(init, "bubbles"));
+        event.cancelable = truthy(&option(init, "cancelable"));
+        event.composed = truthy(&option(init, "composed"));
+        Ok(event)
+    }
+
+    fn prevent_default(&mut self) {
+        if self.cancelable && !self.in_passive_listener {
+            self.default_prevented = true;
+        }
+    }
+
+    /// The `Event` behind `event`, which may also be a `CustomEvent`.
+    fn opaque_event(event: &mut JsValue) -> Option<&mut Event> {
+        if let Some(e) = Event::opaque_mut(event) {
+            return Some(e);
+        }
+        CustomEvent::opaque_mut(event).map(|e| &mut e.event)
+    }
+}
+
+impl JsClassDef for Event {
+    type RefType = Event;
+
+    const CLASS_NAME: &'static str = "Event";
+
+    const CONSTRUCTOR_ARGC: u8 = 1;
+
+    const FIELDS: &'static [JsClassField<Self::RefType>] = &[
+        ("type", |this, ctx| ctx.new_string(&this.type_).into(), None),
+        ("bubbles", |this, _ctx| JsValue::Bool(this.bubbles), None),
+        (
+            "cancelable",
+            |this, _ctx| JsValue::Bool(this.cancelable),
+            None,
+        ),
+        ("composed", |this, _ctx| JsValue::Bool(this.composed), None),
+        (
+            "isTrusted",
+            |this, _ctx| JsValue::Bool(this.is_trusted),
+            None,
+        ),
+        (
+            "timeStamp",
+            |this, _ctx| JsValue::Float(this.time_stamp),
+            None,
+        ),
+        ("eventPhase", |this, _ctx| JsValue::Int(this.phase), None),
+        (
+            "defaultPrevented",
+            |this, _ctx| JsValue::Bool(this.default_prevented),
+            None,
+        ),
+        ("target", |this, _ctx| this.target.clone(), None),
+        ("srcElement", |this, _ctx| this.target.clone(), None),
+        (
+            "currentTarget",
+            |this, _ctx| this.current_target.clone(),
+            None,
+        ),
+        (
+            "returnValue",
+            |this, _ctx| JsValue::Bool(!this.default_prevented),
+            Some(|this, _ctx, val| {
+                if !truthy(&val) {
+                    this.prevent_default();
+                }
+            }),
+        ),
+        (
+            "cancelBubble",
+            |this, _ctx| JsValue::Bool(this.stop_propagation),
+            Some(|this, _ctx, val| {
+                if truthy(&val) {
+                    this.stop_propagation = true;
+                }
+            }),
+        ),
+    ];
+
+    const METHODS: &'static [JsClassMethod<Self::RefType>] = &[
+        ("preventDefault", 0, |this, _this_obj, _ctx, _argv| {
+            this.prevent_default();
+            JsValue::UnDefined
+        }),
+        ("stopPropagation", 0, |this, _this_obj, _ctx, _argv| {
+            this.stop_propagation = true;
+            JsValue::UnDefined
+        }),
+        (
+            "stopImmediatePropagation",
+            0,
+            |this, _this_obj, _ctx, _argv| {
+                this.stop_propagation = true;
+                this.stop_immediate_propagation = true;
+                JsValue::UnDefined
+            },
+        ),
+        ("composedPath", 0, |this, _this_obj, ctx, _argv| {
+            let mut path = ctx.new_array();
+            if this.dispatching {
+                path.put(0, this.current_target.clone());
+            }
+            path.into()
+        }),
+    ];
+
+    const STATIC_FIELDS: &'static [JsClassStaticField] = &[
+        ("NONE", |_ctx| JsValue::Int(0)),
+        ("CAPTURING_PHASE", |_ctx| JsValue::Int(1)),
+        ("AT_TARGET", |_ctx| JsValue::Int(2)),
+        ("BUBBLING_PHASE", |_ctx| JsValue::Int(3)),
+    ];
+
+    const SYMBOL_FIELDS: &'static [JsClassSymbolField<Self::RefType>] = &[(
+        JsClassSymbol::ToStringTag,
+        |_this, ctx| ctx.new_string("Event").into(),
+        None,
+    )];
+
+    unsafe fn mut_class_id_ptr() -> &'static mut u32 {
+        static mut CLASS_ID: u32 = 0;
+        &mut CLASS_ID
+    }
+
+    fn constructor_fn(ctx: &mut Context, argv: &[JsValue]) -> Result<Self::RefType, JsValue> {
+        Event::from_init(ctx, argv)
+    }
+
+    fn gc_mark(data: &Self::RefType, make: &mut dyn Fn(&JsValue)) {
+        make(&data.target);
+        make(&data.current_target);
+    }
+}
+
+pub(crate) struct CustomEvent {
+    event: Event,
+    detail: JsValue,
+}
+
+impl AsRef<Event> for CustomEvent {
+    fn as_ref(&self) -> &Event {
+        &self.event
+    }
+}
+
+impl AsMut<Event> for CustomEvent {
+    fn as_mut(&mut self) -> &mut Event {
+        &mut self.event
+    }
+}
+
+impl ExtendsJsClassDef for CustomEvent {
+    type RefType = CustomEvent;
+
+    type BaseDef = Event;
+
+    const EXT_CLASS_NAME: &'static str = "CustomEvent";
+
+    const CONSTRUCTOR_ARGC: u8 = 1;
+
+    const FIELDS: &'static [JsClassField<Self::RefType>] =
+        &[("detail", |this, _ctx| this.detail.clone(), None)];
+
+    const METHODS: &'static [JsClassMethod<Self::RefType>] = &[];
+
+    const SYMBOL_FIELDS: &'static [JsClassSymbolField<Self::RefType>] = &[(
+        JsClassSymbol::ToStringTag,
+        |_this, ctx| ctx.new_string("CustomEvent").into(),
+        None,
+    )];
+
+    unsafe fn mut_class_id_ptr() -> &'static mut u32 {
+        static mut CLASS_ID: u32 = 0;
+        &mut CLASS_ID
+    }
+
+    fn constructor_fn(ctx: &mut Context, argv: &[JsValue]) -> Result<Self::RefType, JsValue> {
+        let event = Event::from_init(ctx, argv)?;
+        let detail = match option(argv.get(1), "detail") {
+            JsValue::UnDefined => JsValue::Null,
+            detail => detail,
+        };
+        Ok(CustomEvent { event, detail })
+    }
+
+    fn gc_mark(data: &Self::RefType, make: &mut dyn Fn(&JsValue)) {
+        make(&data.detail);
+    }
+}
+
+struct Listener {
+    type_: String,
+    /// a function, an object with `handleEvent`, or for an event handler
+    /// attribute like `onabort`, the attribute's name
+    callback: JsValue,
+    is_handler: bool,
+    capture: bool,
+    once: bool,
+    passive: bool,
+    /// the listener is removed when this signal aborts
+    signal: Option<JsValue>,
+    removed: Rc<Cell<bool>>,
+}
+
+impl Listener {
+    fn signal_aborted(&self) -> bool {
+        self.signal
+            .as_ref()
+            .and_then(AbortSignal::opaque)
+            .map_or(false, |signal| signal.is_aborted())
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct EventTarget {
+    listeners: Vec<Listener>,
+    /// values of the event handler attributes, `onabort` and the like
+    handlers: Vec<(String, JsValue)>,
+}
+
+impl EventTarget {
+    /// The `EventTarget` behind `target`, which may also be an `AbortSignal`.
+    fn opaque_target(target: &mut JsValue) -> Option<&mut EventTarget> {
+        if let Some(t) = EventTarget::opaque_mut(target) {
+            return Some(t);
+        }
+        AbortSignal::opaque_mut(target).map(|t| t.as_mut())
+    }
+
+    fn position(&self, type_: &str, callback: &JsValue, capture: bool) -> Option<usize> {
+        self.listeners.iter().position(|l| {
+            !l.is_handler && l.type_ == type_ && &l.callback == callback && l.capture == capture
+        })
+    }
+
+    fn remove_at(&mut self, i: usize) {
+        let l = self.listeners.remove(i);
+        l.removed.set(true);
+    }
+
+    /// The value of the event handler attribute for `type_`, e.g. `onabort` for `abort`.
+    pub(crate) fn handler(&self, type_: &str) -> JsValue {
+        self.handlers
+            .iter()
+            .find(|(t, _)| t == type_)
+            .map(|(_, h)| h.clone())
+            .unwrap_or(JsValue::Null)
+    }
+
+    /// Sets an event handler attribute. Like the DOM, the handler runs in the
+    /// position where an attribute of this type was first set.
+    pub(crate) fn set_handler(&mut self, type_: &str, handler: JsValue) {
+        let handler = match handler {
+            JsValue::Function(_) | JsValue::Object(_) => handler,
+            _ => JsValue::Null,
+        };
+        match self.handlers.iter_mut().find(|(t, _)| t == type_) {
+            Some((_, h)) => *h = handler,
+            None => {
+                self.handlers.push((type_.to_string(), handler));
+                self.listeners.push(Listener {
+                    type_: type_.to_string(),
+                    callback: JsValue::Null,
+                    is_handler: true,
+                    capture: false,
+                    once: false,
+                    passive: false,
+                    signal: None,
+                    removed: Rc::new(Cell::new(false)),
+                });
+            }
+        }
+    }
+
+    /// Whether a listener or the event handler attribute is set for `type_`.
+    pub(crate) fn has_listeners(&self, type_: &str) -> bool {
+        self.listeners
+            .iter()
+            .any(|l| l.type_ == type_ && !l.is_handler)
+            || !matches!(self.handler(type_), JsValue::Null)
+    }
+
+    pub(crate) fn add_event_listener(&mut self, ctx: &mut Context, argv: &[JsValue]) -> JsValue {
+        let type_ = match argv.get(0) {
+            Some(t) => match to_string(ctx, t) {
+                Ok(t) => t,
+                Err(e) => return e,
+            },
+            None => return JsValue::UnDefined,
+        };
+        let callback = match argv.get(1) {
+            Some(c @ JsValue::Function(_)) | Some(c @ JsValue::Object(_)) => c.clone(),
+            None | Some(JsValue::Null) | Some(JsValue::UnDefined) => return JsValue::UnDefined,
+            Some(_) => {
+                return ctx
+                    .throw_type_error("The \"listener\" argument must be an object or function")
+                    .into()
+            }
+        };
+        let options = argv.get(2);
+        let (capture, once, passive, signal) = match options {
+            Some(JsValue::Object(_)) => {
+                let signal = match option(options, "signal") {
+                    JsValue::UnDefined => None,
+                    signal if AbortSignal::opaque(&signal).is_some() => Some(signal),
+                    _ => {
+                        return ctx
+                            .throw_type_error(
+                                "The \"options.signal\" property must be an AbortSignal",
+                            )
+                            .into()
+                    }
+                };
+                (
+                    truthy(&option(options, "capture")),
+                    truthy(&option(options, "once")),
+                    truthy(&option(options, "passive")),
+                    signal,
+                )
+            }
+            Some(capture) => (truthy(capture), false, false, None),
+            None => (false, false, false, None),
+        };
+
+        let listener = Listener {
+            type_,
+            callback,
+            is_handler: false,
+            capture,
+            once,
+            passive,
+            signal,
+            removed: Rc::new(Cell::new(false)),
+        };
+        if listener.signal_aborted() {
+            return JsValue::UnDefined;
+        }
+        if self
+            .position(&listener.type_, &listener.callback, capture)
+            .is_none()
+        {
+            self.listeners.push(listener);
+        }
+        JsValue::UnDefined
+    }
+
+    pub(crate) fn remove_event_listener(&mut self, ctx: &mut Context, argv: &[JsValue]) -> JsValue {
+        let type_ = match argv.get(0) {
+            Some(t) => match to_string(ctx, t) {
+                Ok(t) => t,
+                Err(e) => return e,
+            },
+            None => return JsValue::UnDefined,
+        };
+        let callback = argv.get(1).cloned().unwrap_or(JsValue::UnDefined);
+        let capture = match argv.get(2) {
+            Some(options @ JsValue::Object(_)) => truthy(&option(Some(options), "capture")),
+            Some(capture) => truthy(capture),
+            None => false,
+        };
+        if let Some(i) = self.position(&type_, &callback, capture) {
+            self.remove_at(i);
+        }
+        JsValue::UnDefined
+    }
+}
+
+/// Dispatches `event` to the listeners of `target`.
+///
+/// Returns false if a listener cancelled the event, or the error if `event` is
+/// not an `Event` or is already being dispatched. An exception thrown by a
+/// listener does not stop the dispatch; it is reported as uncaught.
+pub(crate) fn dispatch_event(
+    ctx: &mut Context,
+    target: &JsValue,
+    event: &JsValue,
+) -> Result<bool, JsValue> {
+    let mut event_val = event.clone();
+    let type_ = match Event::opaque_event(&mut event_val) {
+        Some(e) if e.dispatching => {
+            let mut err = ctx.new_error("The event is already being dispatched");
+            if let JsValue::Object(o) = &mut err {
+                o.set("name", ctx.new_string("InvalidStateError").into());
+                o.set("code", JsValue::Int(11));
+            }
+            return Err(ctx.throw_error(err).into());
+        }
+        Some(e) => {
+            e.dispatching = true;
+            e.target = target.clone();
+            e.current_target = target.clone();
+            e.phase = AT_TARGET;
+            e.type_.clone()
+        }
+        None => {
+            return Err(ctx
+                .throw_type_error("The \"event\" argument must be an instance of Event")
+                .into())
+        }
+    };
+
+    // listeners added during the dispatch do not run, removed ones no longer do
+    let mut target_val = target.clone();
+    let snapshot: Vec<(JsValue, bool, bool, bool, Rc<Cell<bool>>)> =
+        match EventTarget::opaque_target(&mut target_val) {
+            Some(t) => {
+                while let Some(i) = t.listeners.iter().position(Listener::signal_aborted) {
+                    t.remove_at(i);
+                }
+                let capturing = t.listeners.iter().filter(|l| l.capture);
+                let bubbling = t.listeners.iter().filter(|l| !l.capture);
+                capturing
+                    .chain(bubbling)
+                    .filter(|l| l.type_ == type_)
+                    .map(|l| {
+                        (
+                            l.callback.clone(),
+                            l.is_handler,
+                            l.once,
+                            l.passive,
+                            l.removed.clone(),
+                        )
+                    })
+                    .collect()
+            }
+            None => vec![],
+        };
+
+    for (callback, is_handler, once, passive, removed) in snapshot {
+        if removed.get() {
+            continue;
+        }
+        let callback = if is_handler {
+            match EventTarget::opaque_target(&mut target_val) {
+                Some(t) => t.handler(&type_),
+                None => continue,
+            }
+        } else {
+            callback
+        };
+        if once {
+            if let Some(t) = EventTarget::opaque_target(&mut target_val) {
+                if let Some(i) = t
+                    .listeners
+                    .iter()
+                    .position(|l| Rc::ptr_eq(&l.removed, &removed))
+                {
+                    t.remove_at(i);
+                }
+            }
+        }
+        if let Some(e) = Event::opaque_event(&mut event_val) {
+            e.in_passive_listener = passive;
+        }
+        let result = match &callback {
+            JsValue::Function(f) => f.call_with_this(target, &[event.clone()]),
+            JsValue::Object(o) => match o.get("handleEvent") {
+                JsValue::Function(f) => f.call_with_this(&callback, &[event.clone()]),
+                _ => continue,
+            },
+            _ => continue,
+        };
+        if let JsValue::Exception(e) = result {
+            e.report_uncaught();
+        }
+        match Event::opaque_event(&mut event_val) {
+            Some(e) => {
+                e.in_passive_listener = false;
+                if e.stop_immediate_propagation {
+                    break;
+                }
+            }
+            None => break,
+        }
+    }
+
+    match Event::opaque_event(&mut event_val) {
+        Some(e) => {
+            e.dispatching = false;
+            e.stop_propagation = false;
+            e.stop_immediate_propagation = false;
+            e.current_target = JsValue::Null;
+            e.phase = NONE;
+            Ok(!e.default_prevented)
+        }
+        None => Ok(true),
+    }
+}
+
+impl JsClassDef for EventTarget {
+    type RefType = EventTarget;
+
+    const CLASS_NAME: &'static str = "EventTarget";
+
+    const CONSTRUCTOR_ARGC: u8 = 0;
+
+    const FIELDS: &'static [JsClassField<Self::RefType>] = &[];
+
+    const METHODS: &'static [JsClassMethod<Self::RefType>] = &[
+        ("addEventListener", 2, |this, _this_obj, ctx, argv| {
+            this.add_event_listener(ctx, argv)
+        }),
+        ("removeEventListener", 2, |this, _this_obj, ctx, argv| {
+            this.remove_event_listener(ctx, argv)
+        }),
+        ("dispatchEvent", 1, |_this, this_obj, ctx, argv| {
+            let event = argv.get(0).cloned().unwrap_or(JsValue::UnDefined);
+            match dispatch_event(ctx, &JsValue::from(this_obj.clone()), &event) {
+                Ok(not_cancelled) => JsValue::Bool(not_cancelled),
+                Err(e) => e,
+            }
+        }),
+    ];
+
+    const SYMBOL_FIELDS: &'static [JsClassSymbolField<Self::RefType>] = &[(
+        JsClassSymbol::ToStringTag,
+        |_this, ctx| ctx.new_string("EventTarget").into(),
+        None,
+    )];
+
+    unsafe fn mut_class_id_ptr() -> &'static mut u32 {
+        static mut CLASS_ID: u32 = 0;
+        &mut CLASS_ID
+    }
+
+    fn constructor_fn(_ctx: &mut Context, _argv: &[JsValue]) -> Result<Self::RefType, JsValue> {
+        Ok(EventTarget::default())
+    }
+
+    fn gc_mark(data: &Self::RefType, make: &mut dyn Fn(&JsValue)) {
+        for l in &data.listeners {
+            make(&l.callback);
+            if let Some(signal) = &l.signal {
+                make(signal);
+            }
+        }
+        for (_, handler) in &data.handlers {
+            make(handler);
+        }
+    }
+}
+
+/// Makes `sub`'s instances and constructor inherit from `base`'s.
+pub(crate) fn extend_class(ctx: &mut Context, sub: &JsValue, base: &JsValue) {
+    if let (Some(sub_proto), Some(base_proto)) = (sub.get("prototype"), base.get("prototype")) {
+        class_extends(ctx, sub_proto, base_proto);
+    }
+    class_extends(ctx, sub.clone(), base.clone());
+}
+
+pub fn init_global(ctx: &mut Context) {
+    let event_target = register_class::<EventTarget>(ctx);
+    let event = register_class::<Event>(ctx);
+    let custom_event = register_class::<CustomEvent>(ctx);
+    extend_class(ctx, &custom_event, &event);
+
+    let mut global = ctx.get_global();
+    global.set("EventTarget", event_target);
+    global.set("Event", event);
+    global.set("CustomEvent", custom_event);
+}
@@ -1,3 +1,4 @@
+use crate::event_loop::handle::ResourceKind;
 use crate::event_loop::wasi_fs;
 use crate::quickjs_sys::*;
 use std::convert::TryInto;
@@ -593,6 +594,8 @@ fn get_js_number(val: Option<&JsValue>) -> Option<i64> {
     }
 }
 
+/// Reads in the check phase of the loop, tracked as an `FsReq` until then.
+/// This and `fwrite` are the only async bindings, see `ResourceKind::FsReq`.
 fn fread(ctx: &mut Context, _this_val: JsValue, arg: &[JsValue]) -> JsValue {
     if let Some(JsValue::Int(fd)) = arg.get(0) {
         if let Some(position) = get_js_number(arg.get(1)) {
@@ -603,8 +606,12 @@ fn fread(ctx: &mut Context, _this_val: JsValue, arg: &[JsValue]) -> JsValue {
                 let fd = *fd;
 
                 if let Some(event_loop) = ctx.event_loop() {
+                    let req = event_loop.handles.start(ResourceKind::FsReq);
                     event_loop.add_immediate_task(Box::new(move || {
                         let mut ctx = nctx;
+                        if let Some(event_loop) = ctx.event_loop() {
+                            event_loop.handles.finish(req);
+                        }
                         let pos = position;
                         let mut buf = vec![0u8; len];
                         let res = if pos >= 0 {
@@ -796,8 +803,12 @@ fn fwrite(ctx: &mut Context, _this_val: JsValue, arg: &[JsValue]) -> JsValue {
     let nctx = ctx.clone();
     let arg = arg.to_vec();
     if let Some(event_loop) = ctx.event_loop() {
+        let req = event_loop.handles.start(ResourceKind::FsReq);
         event_loop.add_immediate_task(Box::new(move || {
             let mut ctx = nctx;
+            if let Some(event_loop) = ctx.event_loop() {
+                event_loop.handles.finish(req);
+            }
             let r = fwrite_sync(&mut ctx, _this_val, &arg);
             match r {
                 JsValue::UnDefined => {
@@ -1,7 +1,9 @@
+pub mod abort;
 pub mod core;
 #[cfg(feature = "nodejs_crypto")]
 pub mod crypto;
 pub mod encoding;
+pub mod event_target;
 pub mod fs;
 #[cfg(feature = "ggml")]
 pub mod ggml;
@@ -9,8 +11,12 @@ pub mod httpx;
 #[cfg(feature = "img")]
 pub mod img_module;
 pub mod os;
+pub mod perf_hooks;
 #[cfg(feature = "tensorflow")]
 pub mod tensorflow_module;
+pub mod v8;
+pub mod vm;
 pub mod wasi_net_module;
 #[cfg(feature = "wasi_nn")]
 pub mod wasi_nn;
+pub mod worker_threads;
@@ -3,6 +3,11 @@ use core::arch;
 use crate::quickjs_sys::*;
 use crate::EventLoop;
 
+/// The size of the wasm linear memory, in bytes.
+pub(crate) fn wasm_memory_bytes() -> usize {
+    arch::wasm32::memory_size::<0>() * 65536
+}
+
 fn memory_size(_ctx: &mut Context, _this_val: JsValue, _argv: &[JsValue]) -> JsValue {
     JsValue::Int(arch::wasm32::memory_size::<0>() as i32)
 }
@@ -0,0 +1,816 @@
+//! The `performance` global, the classes of its entries and observers, and
+//! `_node:perf_hooks`, the native half of `perf_hooks` and `process.hrtime()`.
+//!
+//! Only `mark` and `measure` entries are recorded. Observers are called from the
+//! check phase, like Node.js does with `setImmediate`.
+
+use super::abort::new_dom_exception;
+use super::core::call_callback;
+use crate::quickjs_sys::*;
+
+const SUPPORTED_ENTRY_TYPES: &[&str] = &["mark", "measure"];
+
+fn now(ctx: &mut Context) -> f64 {
+    ctx.event_loop()
+        .map_or(0.0, |event_loop| event_loop.clock.now_ms())
+}
+
+fn to_number(v: &JsValue) -> Option<f64> {
+    match v {
+        JsValue::Int(n) => Some(*n as f64),
+        JsValue::Float(n) => Some(*n),
+        _ => None,
+    }
+}
+
+fn to_string(ctx: &mut Context, v: &JsValue) -> Result<String, JsValue> {
+    match ctx.value_to_string(v) {
+        JsValue::String(s) => Ok(s.as_str().to_string()),
+        JsValue::Exception(e) => Err(e.into()),
+        _ => Ok(String::new()),
+    }
+}
+
+fn option(options: &JsValue, key: &str) -> JsValue {
+    match options {
+        JsValue::Object(o) => o.get(key),
+        _ => JsValue::UnDefined,
+    }
+}
+
+pub(crate) struct PerformanceEntry {
+    name: String,
+    entry_type: &'static str,
+    start_time: f64,
+    duration: f64,
+    detail: JsValue,
+}
+
+impl PerformanceEntry {
+    /// The entry behind `v`, which may be a mark, a measure or a plain entry.
+    fn of(v: &JsValue) -> Option<&PerformanceEntry> {
+        PerformanceEntry::opaque(v)
+            .or_else(|| PerformanceMark::opaque(v).map(|m| &m.entry))
+            .or_else(|| PerformanceMeasure::opaque(v).map(|m| &m.entry))
+    }
+
+    fn to_json(&self, ctx: &mut Context) -> JsValue {
+        let mut obj = ctx.new_object();
+        obj.set("name", ctx.new_string(&self.name).into());
+        obj.set("entryType", ctx.new_string(self.entry_type).into());
+        obj.set("startTime", JsValue::Float(self.start_time));
+        obj.set("duration", JsValue::Float(self.duration));
+        obj.set("detail", self.detail.clone());
+        obj.into()
+    }
+}
+
+/// The entries of `entries` with the given name and type, ordered by start time.
+fn filter_entries(
+    ctx: &mut Context,
+    entries: &[JsValue],
+    name: Option<&str>,
+    entry_type: Option<&str>,
+) -> JsValue {
+    let mut matched: Vec<(f64, &JsValue)> = entries
+        .iter()
+        .filter_map(|v| {
+            let e = PerformanceEntry::of(v)?;
+            let name_matches = name.map_or(true, |name| e.name == name);
+            let type_matches = entry_type.map_or(true, |t| e.entry_type == t);
+            if name_matches && type_matches {
+                Some((e.start_time, v))
+            } else {
+                None
+            }
+        })
+        .collect();
+    matched.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+
+    let mut array = ctx.new_array();
+    for (i, (_, v)) in matched.into_iter().enumerate() {
+        array.put(i, v.clone());
+    }
+    array.into()
+}
+
+/// `getEntries()`, `getEntriesByName(name, type)` and `getEntriesByType(type)`.
+fn get_entries_by(
+    ctx: &mut Context,
+    entries: &[JsValue],
+    by_name: bool,
+    argv: &[JsValue],
+) -> JsValue {
+    let mut args = vec![];
+    for v in argv.iter().take(if by_name { 2 } else { 1 }) {
+        match v {
+            JsValue::UnDefined => args.push(None),
+            v => match to_string(ctx, v) {
+                Ok(s) => args.push(Some(s)),
+                Err(e) => return e,
+            },
+        }
+    }
+    let arg = |i: usize| args.get(i).cloned().flatten();
+    if by_name {
+        filter_entries(ctx, entries, arg(0).as_deref(), arg(1).as_deref())
+    } else {
+        filter_entries(ctx, entries, None, arg(0).as_deref())
+    }
+}
+
+const ENTRY_FIELDS: &[JsClassField<PerformanceEntry>] = &[
+    ("name", |this, ctx| ctx.new_string(&this.name).into(), None),
+    (
+        "entryType",
+        |this, ctx| ctx.new_string(this.entry_type).into(),
+        None,
+    ),
+    (
+        "startTime",
+        |this, _ctx| JsValue::Float(this.start_time),
+        None,
+    ),
+    ("duration", |this, _ctx| JsValue::Float(this.duration), None),
+    ("detail", |this, _ctx| this.detail.clone(), None),
+];
+
+impl JsClassDef for PerformanceEntry {
+    type RefType = PerformanceEntry;
+
+    const CLASS_NAME: &'static str = "PerformanceEntry";
+
+    const CONSTRUCTOR_ARGC: u8 = 0;
+
+    const FIELDS: &'static [JsClassField<Self::RefType>] = ENTRY_FIELDS;
+
+    const METHODS: &'static [JsClassMethod<Self::RefType>] =
+        &[("toJSON", 0, |this, _this_obj, ctx, _argv| this.to_json(ctx))];
+
+    unsafe fn mut_class_id_ptr() -> &'static mut u32 {
+        static mut CLASS_ID: u32 = 0;
+        &mut CLASS_ID
+    }
+
+    fn constructor_fn(ctx: &mut Context, _argv: &[JsValue]) -> Result<Self::RefType, JsValue> {
+        Err(ctx.throw_type_error("Illegal constructor").into())
+    }
+
+    fn gc_mark(data: &Self::RefType, make: &mut dyn Fn(&JsValue)) {
+        make(&data.detail);
+    }
+}
+
+pub(crate) struct PerformanceMark {
+    entry: PerformanceEntry,
+}
+
+impl AsRef<PerformanceEntry> for PerformanceMark {
+    fn as_ref(&self) -> &PerformanceEntry {
+        &self.entry
+    }
+}
+
+impl AsMut<PerformanceEntry> for PerformanceMark {
+    fn as_mut(&mut self) -> &mut PerformanceEntry {
+        &mut self.entry
+    }
+}
+
+impl PerformanceMark {
+    /// `new PerformanceMark(name, { startTime, detail })`, which is also what
+    /// `performance.mark()` records.
+    fn new(ctx: &mut Context, argv: &[JsValue]) -> Result<Self, JsValue> {
+        let name = match argv.get(0) {
+            Some(name) => to_string(ctx, name)?,
+            None => {
+                return Err(ctx
+                    .throw_type_error("The \"name\" argument must be specified")
+                    .into())
+            }
+        };
+        let options = argv.get(1).cloned().unwrap_or(JsValue::UnDefined);
+        let start_time = match option(&options, "startTime") {
+            JsValue::UnDefined => now(ctx),
+            v => match to_number(&v) {
+                Some(t) if t >= 0.0 => t,
+                _ => {
+                    return Err(ctx
+                        .throw_type_error(
+                            "The \"options.startTime\" property must be a non-negative number",
+                        )
+                        .into())
+                }
+            },
+        };
+        let detail = match option(&options, "detail") {
+            JsValue::UnDefined => JsValue::Null,
+            detail => detail,
+        };
+        Ok(PerformanceMark {
+            entry: PerformanceEntry {
+                name,
+                entry_type: "mark",
+                start_time,
+                duration: 0.0,
+                detail,
+            },
+        })
+    }
+}
+
+impl ExtendsJsClassDef for PerformanceMark {
+    type RefType = PerformanceMark;
+
+    type BaseDef = PerformanceEntry;
+
+    const EXT_CLASS_NAME: &'static str = "PerformanceMark";
+
+    const CONSTRUCTOR_ARGC: u8 = 1;
+
+    const FIELDS: &'static [JsClassField<Self::RefType>] = &[];
+
+    const METHODS: &'static [JsClassMethod<Self::RefType>] = &[];
+
+    unsafe fn mut_class_id_ptr() -> &'static mut u32 {
+        static mut CLASS_ID: u32 = 0;
+        &mut CLASS_ID
+    }
+
+    fn constructor_fn(ctx: &mut Context, argv: &[JsValue]) -> Result<Self::RefType, JsValue> {
+        PerformanceMark::new(ctx, argv)
+    }
+}
+
+pub(crate) struct PerformanceMeasure {
+    entry: PerformanceEntry,
+}
+
+impl AsRef<PerformanceEntry> for PerformanceMeasure {
+    fn as_ref(&self) -> &PerformanceEntry {
+        &self.entry
+    }
+}
+
+impl AsMut<PerformanceEntry> for PerformanceMeasure {
+    fn as_mut(&mut self) -> &mut PerformanceEntry {
+        &mut self.entry
+    }
+}
+
+impl ExtendsJsClassDef for PerformanceMeasure {
+    type RefType = PerformanceMeasure;
+
+    type BaseDef = PerformanceEntry;
+
+    const EXT_CLASS_NAME: &'static str = "PerformanceMeasure";
+
+    const CONSTRUCTOR_ARGC: u8 = 0;
+
+    const FIELDS: &'static [JsClassField<Self::RefType>] = &[];
+
+    const METHODS: &'static [JsClassMethod<Self::RefType>] = &[];
+
+    unsafe fn mut_class_id_ptr() -> &'static mut u32 {
+        static mut CLASS_ID: u32 = 0;
+        &mut CLASS_ID
+    }
+
+    fn constructor_fn(ctx: &mut Context, _argv: &[JsValue]) -> Result<Self::RefType, JsValue> {
+        Err(ctx.throw_type_error("Illegal constructor").into())
+    }
+}
+
+pub(crate) struct PerformanceObserverEntryList {
+    entries: Vec<JsValue>,
+}
+
+impl JsClassDef for PerformanceObserverEntryList {
+    type RefType = PerformanceObserverEntryList;
+
+    const CLASS_NAME: &'static str = "PerformanceObserverEntryList";
+
+    const CONSTRUCTOR_ARGC: u8 = 0;
+
+    const FIELDS: &'static [JsClassField<Self::RefType>] = &[];
+
+    const METHODS: &'static [JsClassMethod<Self::RefType>] = &[
+        ("getEntries", 0, |this, _this_obj, ctx, _argv| {
+            filter_entries(ctx, &this.entries, None, None)
+        }),
+        ("getEntriesByName", 2, |this, _this_obj, ctx, argv| {
+            get_entries_by(ctx, &this.entries, true, argv)
+        }),
+        ("getEntriesByType", 1, |this, _this_obj, ctx, argv| {
+            get_entries_by(ctx, &this.entries, false, argv)
+        }),
+    ];
+
+    unsafe fn mut_class_id_ptr() -> &'static mut u32 {
+        static mut CLASS_ID: u32 = 0;
+        &mut CLASS_ID
+    }
+
+    fn constructor_fn(ctx: &mut Context, _argv: &[JsValue]) -> Result<Self::RefType, JsValue> {
+        Err(ctx.throw_type_error("Illegal constructor").into())
+    }
+
+    fn gc_mark(data: &Self::RefType, make: &mut dyn Fn(&JsValue)) {
+        data.entries.iter().for_each(|v| make(v));
+    }
+}
+
+pub(crate) struct PerformanceObserver {
+    callback: JsValue,
+    entry_types: Vec<String>,
+    buffer: Vec<JsValue>,
+    /// a delivery is queued in the check phase
+    pending: bool,
+}
+
+impl PerformanceObserver {
+    fn observe(&mut self, this_obj: &JsObject, ctx: &mut Context, argv: &[JsValue]) -> JsValue {
+        let options = argv.get(0).cloned().unwrap_or(JsValue::UnDefined);
+        let (entry_types, buffered) = match (option(&options, "entryTypes"), option(&options, "type")) {
+            (JsValue::Array(types), JsValue::UnDefined) => {
+                let types = match types.to_vec() {
+                    Ok(types) => types,
+                    Err(e) => return e.into(),
+                };
+                let mut entry_types = vec![];
+                for t in &types {
+                    match to_string(ctx, t) {
+                        Ok(t) => entry_types.push(t),
+                        Err(e) => return e,
+                    }
+                }
+                (entry_types, false)
+            }
+            (JsValue::UnDefined, t) if t != JsValue::UnDefined => match to_string(ctx, &t) {
+                Ok(t) => {
+                    let buffered = matches!(option(&options, "buffered"), JsValue::Bool(true));
+                    (vec![t], buffered)
+                }
+                Err(e) => return e,
+            },
+            _ => {
+                return ctx
+                    .throw_type_error(
+                        "The \"options\" argument must have either an \"entryTypes\" array or a \"type\"",
+                    )
+                    .into()
+            }
+        };
+
+        // like browsers, unsupported types are ignored
+        let entry_types: Vec<String> = entry_types
+            .into_iter()
+            .filter(|t| SUPPORTED_ENTRY_TYPES.contains(&t.as_str()))
+            .collect();
+        for t in entry_types {
+            if !self.entry_types.contains(&t) {
+                self.entry_types.push(t);
+            }
+        }
+
+        let this_val: JsValue = this_obj.clone().into();
+        let mut performance = ctx.get_global().get("performance");
+        if let Some(p) = Performance::opaque_mut(&mut performance) {
+            if !p.observers.contains(&this_val) {
+                p.observers.push(this_val.clone());
+            }
+            if buffered {
+                for entry in &p.entries {
+                    let matches = PerformanceEntry::of(entry).map_or(false, |e| {
+                        self.entry_types.iter().any(|t| t == e.entry_type)
+                    });
+                    if matches {
+                        self.buffer.push(entry.clone());
+                    }
+                }
+            }
+        }
+        if !self.buffer.is_empty() && !self.pending {
+            self.pending = true;
+            schedule_delivery(ctx, this_val);
+        }
+        JsValue::UnDefined
+    }
+
+    fn disconnect(&mut self, this_obj: &JsObject, ctx: &mut Context) -> JsValue {
+        let this_val: JsValue = this_obj.clone().into();
+        let mut performance = ctx.get_global().get("performance");
+        if let Some(p) = Performance::opaque_mut(&mut performance) {
+            p.observers.retain(|o| o != &this_val);
+        }
+        self.entry_types.clear();
+        self.buffer.clear();
+        JsValue::UnDefined
+    }
+}
+
+/// Queues a call of the observer's callback with its buffered entries.
+fn schedule_delivery(ctx: &mut Context, observer: JsValue) {
+    let deliver = ctx.wrap_function("deliverPerformanceEntries", deliver_entries);
+    if let Some(event_loop) = ctx.event_loop() {
+        event_loop.add_immediate_task(Box::new(move || {
+            call_callback(&deliver, Some(vec![observer]));
+        }));
+    }
+}
+
+fn deliver_entries(ctx: &mut Context, _this_val: JsValue, argv: &[JsValue]) -> JsValue {
+    let mut observer = argv.get(0).cloned().unwrap_or(JsValue::UnDefined);
+    let (callback, entries) = match PerformanceObserver::opaque_mut(&mut observer) {
+        Some(o) => {
+            o.pending = false;
+            (o.callback.clone(), std::mem::take(&mut o.buffer))
+        }
+        None => return JsValue::UnDefined,
+    };
+    if entries.is_empty() {
+        return JsValue::UnDefined;
+    }
+    let list =
+        PerformanceObserverEntryList::wrap_obj(ctx, PerformanceObserverEntryList { entries });
+    match callback {
+        JsValue::Function(f) => f.call_with_this(&observer, &[list, observer.clone()]),
+        _ => JsValue::UnDefined,
+    }
+}
+
+/// Hands a new entry to the observers of its type.
+fn enqueue(ctx: &mut Context, observers: Vec<JsValue>, entry: &JsValue, entry_type: &str) {
+    for mut observer in observers {
+        let schedule = match PerformanceObserver::opaque_mut(&mut observer) {
+            Some(o) if o.entry_types.iter().any(|t| t == entry_type) => {
+                o.buffer.push(entry.clone());
+                !std::mem::replace(&mut o.pending, true)
+            }
+            _ => false,
+        };
+        if schedule {
+            schedule_delivery(ctx, observer);
+        }
+    }
+}
+
+impl JsClassDef for PerformanceObserver {
+    type RefType = PerformanceObserver;
+
+    const CLASS_NAME: &'static str = "PerformanceObserver";
+
+    const CONSTRUCTOR_ARGC: u8 = 1;
+
+    const FIELDS: &'static [JsClassField<Self::RefType>] = &[];
+
+    const METHODS: &'static [JsClassMethod<Self::RefType>] = &[
+        ("observe", 1, |this, this_obj, ctx, argv| {
+            this.observe(this_obj, ctx, argv)
+        }),
+        ("disconnect", 0, |this, this_obj, ctx, _argv| {
+            this.disconnect(this_obj, ctx)
+        }),
+        ("takeRecords", 0, |this, _this_obj, ctx, _argv| {
+            let entries = std::mem::take(&mut this.buffer);
+            filter_entries(ctx, &entries, None, None)
+        }),
+    ];
+
+    const STATIC_FIELDS: &'static [JsClassStaticField] = &[("supportedEntryTypes", |ctx| {
+        let mut types = ctx.new_array();
+        for (i, t) in SUPPORTED_ENTRY_TYPES.iter().enumerate() {
+            types.put(i, ctx.new_string(t).into());
+        }
+        types.into()
+    })];
+
+    unsafe fn mut_class_id_ptr() -> &'static mut u32 {
+        static mut CLASS_ID: u32 = 0;
+        &mut CLASS_ID
+    }
+
+    fn constructor_fn(ctx: &mut Context, argv: &[JsValue]) -> Result<Self::RefType, JsValue> {
+        match argv.get(0) {
+            Some(callback @ JsValue::Function(_)) => Ok(PerformanceObserver {
+                callback: callback.clone(),
+                entry_types: vec![],
+                buffer: vec![],
+                pending: false,
+            }),
+            _ => Err(ctx
+                .throw_type_error("The \"callback\" argument must be of type function")
+                .into()),
+        }
+    }
+
+    fn gc_mark(data: &Self::RefType, make: &mut dyn Fn(&JsValue)) {
+        make(&data.callback);
+        data.buffer.iter().for_each(|v| make(v));
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct Performance {
+    /// the marks and measures, in the order they were recorded
+    entries: Vec<JsValue>,
+    observers: Vec<JsValue>,
+}
+
+impl Performance {
+    fn mark(&mut self, ctx: &mut Context, argv: &[JsValue]) -> JsValue {
+        let mark = match PerformanceMark::new(ctx, argv) {
+            Ok(mark) => mark,
+            Err(e) => return e,
+        };
+        let mark = PerformanceMark::wrap_obj(ctx, mark);
+        self.entries.push(mark.clone());
+        enqueue(ctx, self.observers.clone(), &mark, "mark");
+        mark
+    }
+
+    /// The start time of the last mark named `name`.
+    fn mark_time(&self, ctx: &mut Context, name: &str) -> Result<f64, JsValue> {
+        let time = self
+            .entries
+            .iter()
+            .rev()
+            .filter_map(PerformanceEntry::of)
+            .find(|e| e.entry_type == "mark" && e.name == name)
+            .map(|e| e.start_time);
+        match time {
+            Some(time) => Ok(time),
+            None => {
+                let msg = format!("The \"{}\" performance mark has not been set", name);
+                let e = new_dom_exception(ctx, &msg, "SyntaxError", 12);
+                Err(ctx.throw_error(e).into())
+            }
+        }
+    }
+
+    /// A time given to `measure()`, either a mark name or a timestamp.
+    fn measure_time(&self, ctx: &mut Context, v: &JsValue) -> Result<Option<f64>, JsValue> {
+        match v {
+            JsValue::UnDefined => Ok(None),
+            v => match to_number(v) {
+                Some(t) => Ok(Some(t)),
+                None => {
+                    let name = to_string(ctx, v)?;
+                    self.mark_time(ctx, &name).map(Some)
+                }
+            },
+        }
+    }
+
+    fn measure(&mut self, ctx: &mut Context, argv: &[JsValue]) -> JsValue {
+        match self.try_measure(ctx, argv) {
+            Ok(measure) => measure,
+            Err(e) => e,
+        }
+    }
+
+    fn try_measure(&mut self, ctx: &mut Context, argv: &[JsValue]) -> Result<JsValue, JsValue> {
+        let name = match argv.get(0) {
+            Some(name) => to_string(ctx, name)?,
+            None => {
+                return Err(ctx
+                    .throw_type_error("The \"name\" argument must be specified")
+                    .into())
+            }
+        };
+        let start_or_options = argv.get(1).cloned().unwrap_or(JsValue::UnDefined);
+        let end_mark = argv.get(2).cloned().unwrap_or(JsValue::UnDefined);
+
+        let (start, end, duration, detail) = match &start_or_options {
+            JsValue::Object(_) => {
+                let start = self.measure_time(ctx, &option(&start_or_options, "start"))?;
+                let end = self.measure_time(ctx, &option(&start_or_options, "end"))?;
+                let duration = to_number(&option(&start_or_options, "duration"));
+                if start.is_some() && end.is_some() && duration.is_some() {
+                    return Err(ctx
+                        .throw_type_error(
+                            "The \"start\", \"end\" and \"duration\" options can't all be given",
+                        )
+                        .into());
+                }
+                let detail = match option(&start_or_options, "detail") {
+                    JsValue::UnDefined => JsValue::Null,
+                    detail => detail,
+                };
+                (start, end, duration, detail)
+            }
+            start => {
+                let start = self.measure_time(ctx, start)?;
+                let end = self.measure_time(ctx, &end_mark)?;
+                (start, end, None, JsValue::Null)
+            }
+        };
+
+        let end = match (end, start, duration) {
+            (Some(end), _, _) => end,
+            (None, Some(start), Some(duration)) => start + duration,
+            _ => now(ctx),
+        };
+        let start = match (start, duration) {
+            (Some(start), _) => start,
+            (None, Some(duration)) => end - duration,
+            (None, None) => 0.0,
+        };
+
+        let measure = PerformanceMeasure::wrap_obj(
+            ctx,
+            PerformanceMeasure {
+                entry: PerformanceEntry {
+                    name,
+                    entry_type: "measure",
+                    start_time: start,
+                    duration: end - start,
+                    detail,
+                },
+            },
+        );
+        self.entries.push(measure.clone());
+        enqueue(ctx, self.observers.clone(), &measure, "measure");
+        Ok(measure)
+    }
+
+    fn clear(&mut self, ctx: &mut Context, entry_type: &str, argv: &[JsValue]) -> JsValue {
+        let name = match argv.get(0) {
+            None | Some(JsValue::UnDefined) => None,
+            Some(name) => match to_string(ctx, name) {
+                Ok(name) => Some(name),
+                Err(e) => return e,
+            },
+        };
+        self.entries.retain(|v| match PerformanceEntry::of(v) {
+            Some(e) => e.entry_type != entry_type || name.as_ref().map_or(false, |n| &e.name != n),
+            None => false,
+        });
+        JsValue::UnDefined
+    }
+}
+
+fn utilization_of(util: &JsValue) -> (f64, f64) {
+    (
+        to_number(&option(util, "idle")).unwrap_or(0.0),
+        to_number(&option(util, "active")).unwrap_or(0.0),
+    )
+}
+
+fn new_utilization(ctx: &mut Context, idle: f64, active: f64) -> JsValue {
+    let mut obj = ctx.new_object();
+    obj.set("idle", JsValue::Float(idle));
+    obj.set("active", JsValue::Float(active));
+    let utilization = if idle + active > 0.0 {
+        active / (idle + active)
+    } else {
+        0.0
+    };
+    obj.set("utilization", JsValue::Float(utilization));
+    obj.into()
+}
+
+/// `performance.eventLoopUtilization([utilization1[, utilization2]])`
+fn event_loop_utilization(ctx: &mut Context, argv: &[JsValue]) -> JsValue {
+    let util1 = argv.get(0).filter(|v| matches!(v, JsValue::Object(_)));
+    let util2 = argv.get(1).filter(|v| matches!(v, JsValue::Object(_)));
+    if let (Some(util1), Some(util2)) = (util1, util2) {
+        let (idle1, active1) = utilization_of(util1);
+        let (idle2, active2) = utilization_of(util2);
+        return new_utilization(ctx, idle1 - idle2, active1 - active2);
+    }
+
+    let (idle, active) = match ctx.event_loop() {
+        Some(event_loop) => match event_loop.clock.loop_time() {
+            Some(loop_time) => {
+                let idle = event_loop.clock.idle_time();
+                let active = loop_time.saturating_sub(idle);
+                (idle.as_secs_f64() * 1000.0, active.as_secs_f64() * 1000.0)
+            }
+            // the loop has not started yet
+            None => (0.0, 0.0),
+        },
+        None => (0.0, 0.0),
+    };
+    match util1 {
+        Some(util1) => {
+            let (idle1, active1) = utilization_of(util1);
+            new_utilization(ctx, idle - idle1, active - active1)
+        }
+        None => new_utilization(ctx, idle, active),
+    }
+}
+
+fn time_origin(ctx: &mut Context) -> f64 {
+    ctx.event_loop()
+        .map_or(0.0, |event_loop| event_loop.clock.time_origin_ms())
+}
+
+impl JsClassDef for Performance {
+    type RefType = Performance;
+
+    const CLASS_NAME: &'static str = "Performance";
+
+    const CONSTRUCTOR_ARGC: u8 = 0;
+
+    const FIELDS: &'static [JsClassField<Self::RefType>] = &[(
+        "timeOrigin",
+        |_this, ctx| JsValue::Float(time_origin(ctx)),
+        None,
+    )];
+
+    const METHODS: &'static [JsClassMethod<Self::RefType>] = &[
+        ("now", 0, |_this, _this_obj, ctx, _argv| {
+            JsValue::Float(now(ctx))
+        }),
+        ("mark", 2, |this, _this_obj, ctx, argv| this.mark(ctx, argv)),
+        ("measure", 3, |this, _this_obj, ctx, argv| {
+            this.measure(ctx, argv)
+        }),
+        ("getEntries", 0, |this, _this_obj, ctx, _argv| {
+            filter_entries(ctx, &this.entries, None, None)
+        }),
+        ("getEntriesByName", 2, |this, _this_obj, ctx, argv| {
+            get_entries_by(ctx, &this.entries, true, argv)
+        }),
+        ("getEntriesByType", 1, |this, _this_obj, ctx, argv| {
+            get_entries_by(ctx, &this.entries, false, argv)
+        }),
+        ("clearMarks", 1, |this, _this_obj, ctx, argv| {
+            this.clear(ctx, "mark", argv)
+        }),
+        ("clearMeasures", 1, |this, _this_obj, ctx, argv| {
+            this.clear(ctx, "measure", argv)
+        }),
+        ("eventLoopUtilization", 2, |_this, _this_obj, ctx, argv| {
+            event_loop_utilization(ctx, argv)
+        }),
+        ("toJSON", 0, |_this, _this_obj, ctx, _argv| {
+            let mut obj = ctx.new_object();
+            obj.set("timeOrigin", JsValue::Float(time_origin(ctx)));
+            obj.set("eventLoopUtilization", event_loop_utilization(ctx, &[]));
+            obj.into()
+        }),
+    ];
+
+    const SYMBOL_FIELDS: &'static [JsClassSymbolField<Self::RefType>] = &[(
+        JsClassSymbol::ToStringTag,
+        |_this, ctx| ctx.new_string("Performance").into(),
+        None,
+    )];
+
+    unsafe fn mut_class_id_ptr() -> &'static mut u32 {
+        static mut CLASS_ID: u32 = 0;
+        &mut CLASS_ID
+    }
+
+    fn constructor_fn(ctx: &mut Context, _argv: &[JsValue]) -> Result<Self::RefType, JsValue> {
+        Err(ctx.throw_type_error("Illegal constructor").into())
+    }
+
+    fn gc_mark(data: &Self::RefType, make: &mut dyn Fn(&JsValue)) {
+        data.entries.iter().for_each(|v| make(v));
+        data.observers.iter().for_each(|v| make(v));
+    }
+}
+
+/// `[seconds, nanoseconds]` on the monotonic clock, for `process.hrtime()`.
+fn hrtime(ctx: &mut Context, _this_val: JsValue, _argv: &[JsValue]) -> JsValue {
+    let elapsed = ctx
+        .event_loop()
+        .map_or(std::time::Duration::ZERO, |event_loop| {
+            event_loop.clock.elapsed()
+        });
+    let mut time = ctx.new_array();
+    time.put(0, JsValue::Float(elapsed.as_secs() as f64));
+    time.put(1, JsValue::Int(elapsed.subsec_nanos() as i32));
+    time.into()
+}
+
+pub fn init_global(ctx: &mut Context) {
+    let entry_ctor = register_class::<PerformanceEntry>(ctx);
+    let mark_ctor = register_class::<PerformanceMark>(ctx);
+    let measure_ctor = register_class::<PerformanceMeasure>(ctx);
+    super::event_target::extend_class(ctx, &mark_ctor, &entry_ctor);
+    super::event_target::extend_class(ctx, &measure_ctor, &entry_ctor);
+    let observer_ctor = register_class::<PerformanceObserver>(ctx);
+    let list_ctor = register_class::<PerformanceObserverEntryList>(ctx);
+    let performance_ctor = register_class::<Performance>(ctx);
+    let performance = Performance::wrap_obj(ctx, Performance::default());
+
+    let mut global = ctx.get_global();
+    global.set("PerformanceEntry", entry_ctor);
+    global.set("PerformanceMark", mark_ctor);
+    global.set("PerformanceMeasure", measure_ctor);
+    global.set("PerformanceObserver", observer_ctor);
+    global.set("PerformanceObserverEntryList", list_ctor);
+    global.set("Performance", performance_ctor);
+    global.set("performance", performance);
+}
+
+pub fn init_module(ctx: &mut Context) {
+    ctx.register_fn_module("_node:perf_hooks\0", &["hrtime"], |ctx, m| {
+        let f = ctx.wrap_function("hrtime", hrtime);
+        m.add_export("hrtime", f.into());
+    })
+}
@@ -0,0 +1,102 @@
+use crate::quickjs_sys::*;
+
+fn serialize(ctx: &mut Context, _this_val: JsValue, argv: &[JsValue]) -> JsValue {
+    let value = argv.get(0).cloned().unwrap_or(JsValue::UnDefined);
+    match ctx.serialize(&value) {
+        Ok(data) => ctx.new_array_buffer(&data).into(),
+        Err(e) => ctx.throw_error(e).into(),
+    }
+}
+
+fn deserialize(ctx: &mut Context, _this_val: JsValue, argv: &[JsValue]) -> JsValue {
+    if let Some(JsValue::ArrayBuffer(data)) = argv.get(0) {
+        match ctx.deserialize(data.as_ref()) {
+            Ok(v) => v,
+            Err(e) => ctx.throw_error(e).into(),
+        }
+    } else {
+        ctx.throw_type_error("data must be an ArrayBuffer").into()
+    }
+}
+
+// wasm32 can address at most 4GiB
+const MAX_HEAP_SIZE: i64 = 1 << 32;
+
+fn heap_size_limit(usage: &MemoryUsage) -> i64 {
+    if usage.malloc_limit < 0 {
+        MAX_HEAP_SIZE
+    } else {
+        usage.malloc_limit.min(MAX_HEAP_SIZE)
+    }
+}
+
+fn memory_usage(ctx: &mut Context, _this_val: JsValue, _argv: &[JsValue]) -> JsValue {
+    let usage = ctx.memory_usage();
+    let rss = super::os::wasm_memory_bytes() as f64;
+
+    let mut obj = ctx.new_object();
+    obj.set("rss", JsValue::Float(rss));
+    obj.set("heapTotal", JsValue::Float(usage.malloc_size as f64));
+    obj.set("heapUsed", JsValue::Float(usage.memory_used_size as f64));
+    obj.set("external", JsValue::Float(usage.binary_object_size as f64));
+    obj.set(
+        "arrayBuffers",
+        JsValue::Float(usage.binary_object_size as f64),
+    );
+    obj.into()
+}
+
+fn heap_statistics(ctx: &mut Context, _this_val: JsValue, _argv: &[JsValue]) -> JsValue {
+    let usage = ctx.memory_usage();
+    let limit = heap_size_limit(&usage);
+    let rss = super::os::wasm_memory_bytes() as i64;
+
+    let mut obj = ctx.new_object();
+    let mut set = |key: &str, n: i64| obj.set(key, JsValue::Float(n as f64));
+    set("total_heap_size", usage.malloc_size);
+    set("total_heap_size_executable", usage.js_func_code_size);
+    set("total_physical_size", rss);
+    set("total_available_size", (limit - usage.malloc_size).max(0));
+    set("used_heap_size", usage.memory_used_size);
+    set("heap_size_limit", limit);
+    set("malloced_memory", usage.malloc_size);
+    // no `peak_malloced_memory`: QuickJS keeps no high-water mark of its mallocs
+    set("does_zap_garbage", 0);
+    set("number_of_native_contexts", 1);
+    set("number_of_detached_contexts", 0);
+    set("total_global_handles_size", 0);
+    set("used_global_handles_size", 0);
+    set("external_memory", usage.binary_object_size);
+    obj.into()
+}
+
+fn gc(ctx: &mut Context, _this_val: JsValue, _argv: &[JsValue]) -> JsValue {
+    ctx.run_gc();
+    JsValue::UnDefined
+}
+
+/// Defines the global `gc()`.
+pub fn expose_gc(ctx: &mut Context) {
+    let f = ctx.wrap_function("gc", gc);
+    ctx.get_global().set("gc", f.into());
+}
+
+pub fn init_module(ctx: &mut Context) {
+    ctx.register_fn_module(
+        "_node:v8\0",
+        &["serialize", "deserialize", "memoryUsage", "heapStatistics"],
+        |ctx, m| {
+            let f = ctx.wrap_function("serialize", serialize);
+            m.add_export("serialize", f.into());
+
+            let f = ctx.wrap_function("deserialize", deserialize);
+            m.add_export("deserialize", f.into());
+
+            let f = ctx.wrap_function("memoryUsage", memory_usage);
+            m.add_export("memoryUsage", f.into());
+
+            let f = ctx.wrap_function("heapStatistics", heap_statistics);
+            m.add_export("heapStatistics", f.into());
+        },
+    )
+}
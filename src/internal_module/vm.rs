@@ -0,0 +1,345 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::quickjs_sys::*;
+
+struct VmContext;
+
+impl VmContext {
+    fn js_global(data: &Context, _ctx: &mut Context) -> JsValue {
+        data.clone().get_global().into()
+    }
+}
+
+impl JsClassDef for VmContext {
+    type RefType = Context;
+
+    const CLASS_NAME: &'static str = "VmContext";
+
+    const CONSTRUCTOR_ARGC: u8 = 0;
+
+    const FIELDS: &'static [JsClassField<Self::RefType>] = &[("global", Self::js_global, None)];
+
+    const METHODS: &'static [JsClassMethod<Self::RefType>] = &[];
+
+    unsafe fn mut_class_id_ptr() -> &'static mut u32 {
+        static mut CLASS_ID: u32 = 0;
+        &mut CLASS_ID
+    }
+
+    fn constructor_fn(ctx: &mut Context, _argv: &[JsValue]) -> Result<Self::RefType, JsValue> {
+        // the context is owned by a JS object, whose finalizer runs before the
+        // runtime is freed
+        Ok(unsafe { ctx.new_context(ContextOptions::minimal()) })
+    }
+}
+
+fn get_vm_context(ctx: &mut Context, v: Option<&JsValue>) -> Context {
+    v.and_then(VmContext::opaque)
+        .cloned()
+        .unwrap_or_else(|| ctx.clone())
+}
+
+fn get_timeout(v: Option<&JsValue>) -> Option<Duration> {
+    match v {
+        Some(JsValue::Int(ms)) if *ms > 0 => Some(Duration::from_millis(*ms as u64)),
+        Some(JsValue::Float(ms)) if *ms >= 1.0 => Some(Duration::from_millis(*ms as u64)),
+        _ => None,
+    }
+}
+
+fn run_with_timeout<F: FnOnce(&mut Context) -> JsValue>(
+    ctx: &mut Context,
+    timeout: Option<Duration>,
+    f: F,
+) -> JsValue {
+    let timeout = match timeout {
+        Some(timeout) => timeout,
+        None => return f(ctx),
+    };
+    match ctx.with_timeout(timeout, f) {
+        Some(v) => v,
+        None => {
+            let msg = format!("Script execution timed out after {}ms", timeout.as_millis());
+            let mut e = ctx.new_error(&msg);
+            if let JsValue::Object(o) = &mut e {
+                let code = ctx.new_string("ERR_SCRIPT_EXECUTION_TIMEOUT");
+                o.set("code", code.into());
+            }
+            ctx.throw_error(e).into()
+        }
+    }
+}
+
+/// Maps `[referrer, specifier]` to the identifier of the module to import.
+fn resolution_map(
+    ctx: &mut Context,
+    resolutions: &JsArray,
+) -> Result<HashMap<(String, String), String>, JsValue> {
+    let mut map = HashMap::new();
+    for resolution in resolutions.to_vec()? {
+        let parts = match &resolution {
+            JsValue::Array(parts) => parts.to_vec()?,
+            _ => vec![],
+        };
+        match parts.as_slice() {
+            [JsValue::String(referrer), JsValue::String(specifier), JsValue::String(identifier)] => {
+                let key = (referrer.to_string(), specifier.to_string());
+                map.insert(key, identifier.to_string());
+            }
+            _ => return Err(ctx.throw_type_error("invalid module resolution").into()),
+        }
+    }
+    Ok(map)
+}
+
+struct VmModule;
+
+impl VmModule {
+    fn js_status(data: &JsCompiledModule, ctx: &mut Context) -> JsValue {
+        let status = match data.status() {
+            JsModuleStatus::Unlinked => "unlinked",
+            JsModuleStatus::Linked => "linked",
+            JsModuleStatus::Evaluated(_) => "evaluated",
+            JsModuleStatus::Errored(_) => "errored",
+        };
+        ctx.new_string(status).into()
+    }
+
+    fn js_error(data: &JsCompiledModule, _ctx: &mut Context) -> JsValue {
+        match data.status() {
+            JsModuleStatus::Errored(e) => e.clone(),
+            _ => JsValue::UnDefined,
+        }
+    }
+
+    /// `link()` resolves imports through the runtime's module loader;
+    /// `link([[referrer, specifier, identifier], ...])` resolves them to the modules
+    /// the linker returned.
+    fn js_link(
+        data: &mut JsCompiledModule,
+        _this: &mut JsObject,
+        ctx: &mut Context,
+        argv: &[JsValue],
+    ) -> JsValue {
+        let resolved = match argv.get(0) {
+            Some(JsValue::Array(resolutions)) => match resolution_map(ctx, resolutions) {
+                Ok(map) => data.link_with(move |referrer, specifier| {
+                    map.get(&(referrer.to_string(), specifier.to_string()))
+                        .cloned()
+                }),
+                Err(e) => return e,
+            },
+            _ => data.link(),
+        };
+        match resolved {
+            Ok(()) => JsValue::UnDefined,
+            Err(e) => ctx.throw_error(e).into(),
+        }
+    }
+
+    fn js_evaluate(
+        data: &mut JsCompiledModule,
+        _this: &mut JsObject,
+        ctx: &mut Context,
+        argv: &[JsValue],
+    ) -> JsValue {
+        run_with_timeout(ctx, get_timeout(argv.get(0)), |_| match data.evaluate() {
+            Ok(promise) => promise,
+            Err(e) => data.context().throw_error(e).into(),
+        })
+    }
+
+    fn js_namespace(
+        data: &mut JsCompiledModule,
+        _this: &mut JsObject,
+        ctx: &mut Context,
+        _argv: &[JsValue],
+    ) -> JsValue {
+        match data.namespace() {
+            Ok(ns) => ns,
+            Err(e) => ctx.throw_error(e).into(),
+        }
+    }
+}
+
+impl JsClassDef for VmModule {
+    type RefType = JsCompiledModule;
+
+    const CLASS_NAME: &'static str = "VmModule";
+
+    const CONSTRUCTOR_ARGC: u8 = 3;
+
+    const FIELDS: &'static [JsClassField<Self::RefType>] = &[
+        ("status", Self::js_status, None),
+        ("error", Self::js_error, None),
+    ];
+
+    const METHODS: &'static [JsClassMethod<Self::RefType>] = &[
+        ("link", 0, Self::js_link),
+        ("evaluate", 1, Self::js_evaluate),
+        ("getNamespace", 0, Self::js_namespace),
+    ];
+
+    unsafe fn mut_class_id_ptr() -> &'static mut u32 {
+        static mut CLASS_ID: u32 = 0;
+        &mut CLASS_ID
+    }
+
+    fn constructor_fn(ctx: &mut Context, argv: &[JsValue]) -> Result<Self::RefType, JsValue> {
+        let code = if let Some(JsValue::String(code)) = argv.get(0) {
+            code.to_string()
+        } else {
+            return Err(ctx.throw_type_error("code must be a string").into());
+        };
+        let name = if let Some(JsValue::String(name)) = argv.get(1) {
+            name.to_string()
+        } else {
+            "vm:module".to_string()
+        };
+        let mut target = get_vm_context(ctx, argv.get(2));
+        target
+            .compile_module(&code, &name)
+            .map_err(|e| ctx.throw_error(e).into())
+    }
+}
+
+/// Cached data is the script's bytecode behind a header naming the engine
+/// that wrote it and hashing the source it was compiled from, so that stale
+/// or foreign data is rejected instead of being handed to the bytecode reader.
+const CACHED_DATA_MAGIC: &[u8; 4] = b"QJSC";
+const ENGINE_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+fn fnv1a(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf29ce484222325, |hash, b| {
+        (hash ^ *b as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+fn cached_data_header(code: &str, bytecode: &[u8]) -> Vec<u8> {
+    let mut header = CACHED_DATA_MAGIC.to_vec();
+    header.push(ENGINE_VERSION.len() as u8);
+    header.extend_from_slice(ENGINE_VERSION.as_bytes());
+    header.extend_from_slice(&fnv1a(code.as_bytes()).to_le_bytes());
+    header.extend_from_slice(&fnv1a(bytecode).to_le_bytes());
+    header
+}
+
+/// Returns the bytecode in `data` if it was written by this engine for `code`.
+fn check_cached_data<'a>(data: &'a [u8], code: &str) -> Option<&'a [u8]> {
+    let version_len = *data.get(CACHED_DATA_MAGIC.len())? as usize;
+    let header_len = CACHED_DATA_MAGIC.len() + 1 + version_len + 16;
+    if data.len() <= header_len {
+        return None;
+    }
+    let bytecode = &data[header_len..];
+    if data[..header_len] == cached_data_header(code, bytecode)[..] {
+        Some(bytecode)
+    } else {
+        None
+    }
+}
+
+fn compile_script(ctx: &mut Context, _this_val: JsValue, argv: &[JsValue]) -> JsValue {
+    let code = if let Some(JsValue::String(code)) = argv.get(0) {
+        code.to_string()
+    } else {
+        return ctx.throw_type_error("code must be a string").into();
+    };
+    let filename = if let Some(JsValue::String(filename)) = argv.get(1) {
+        filename.to_string()
+    } else {
+        "evalmachine.<anonymous>".to_string()
+    };
+    match ctx.compile_script(&code, &filename) {
+        Ok(bytecode) => {
+            let mut data = cached_data_header(&code, &bytecode);
+            data.extend_from_slice(&bytecode);
+            ctx.new_array_buffer(&data).into()
+        }
+        Err(e) => ctx.throw_error(e).into(),
+    }
+}
+
+fn check_cached_data_fn(ctx: &mut Context, _this_val: JsValue, argv: &[JsValue]) -> JsValue {
+    match (argv.get(0), argv.get(1)) {
+        (Some(JsValue::ArrayBuffer(buf)), Some(JsValue::String(code))) => {
+            JsValue::Bool(check_cached_data(buf.as_ref(), code.as_str()).is_some())
+        }
+        _ => ctx
+            .throw_type_error("expected an ArrayBuffer and the script source")
+            .into(),
+    }
+}
+
+fn module_requests(ctx: &mut Context, _this_val: JsValue, argv: &[JsValue]) -> JsValue {
+    let code = if let Some(JsValue::String(code)) = argv.get(0) {
+        code.to_string()
+    } else {
+        return ctx.throw_type_error("code must be a string").into();
+    };
+    match ctx.module_requests(&code) {
+        Ok(requests) => {
+            let mut array = ctx.new_array();
+            for (i, request) in requests.iter().enumerate() {
+                array.put(i, ctx.new_string(request).into());
+            }
+            array.into()
+        }
+        Err(e) => ctx.throw_error(e).into(),
+    }
+}
+
+fn run_script(ctx: &mut Context, _this_val: JsValue, argv: &[JsValue]) -> JsValue {
+    let (data, code) = match (argv.get(0), argv.get(1)) {
+        (Some(JsValue::ArrayBuffer(buf)), Some(JsValue::String(code))) => {
+            (buf.to_vec(), code.to_string())
+        }
+        _ => {
+            return ctx
+                .throw_type_error("expected an ArrayBuffer and the script source")
+                .into()
+        }
+    };
+    let bytecode = match check_cached_data(&data, &code) {
+        Some(bytecode) => bytecode.to_vec(),
+        None => return ctx.throw_type_error("invalid cached data").into(),
+    };
+    let mut target = get_vm_context(ctx, argv.get(2));
+    run_with_timeout(ctx, get_timeout(argv.get(3)), |_| {
+        target.eval_bytecode(&bytecode)
+    })
+}
+
+pub fn init_module(ctx: &mut Context) {
+    ctx.register_fn_module(
+        "_node:vm\0",
+        &[
+            VmContext::CLASS_NAME,
+            VmModule::CLASS_NAME,
+            "compileScript",
+            "checkCachedData",
+            "runScript",
+            "moduleRequests",
+        ],
+        |ctx, m| {
+            let class_ctor = register_class::<VmContext>(ctx);
+            m.add_export(VmContext::CLASS_NAME, class_ctor);
+
+            let class_ctor = register_class::<VmModule>(ctx);
+            m.add_export(VmModule::CLASS_NAME, class_ctor);
+
+            let f = ctx.wrap_function("compileScript", compile_script);
+            m.add_export("compileScript", f.into());
+
+            let f = ctx.wrap_function("checkCachedData", check_cached_data_fn);
+            m.add_export("checkCachedData", f.into());
+
+            let f = ctx.wrap_function("runScript", run_script);
+            m.add_export("runScript", f.into());
+
+            let f = ctx.wrap_function("moduleRequests", module_requests);
+            m.add_export("moduleRequests", f.into());
+        },
+    )
+}
@@ -1,8 +1,117 @@
 use std::io::Write;
 
-use crate::event_loop::{AsyncTcpConn, AsyncTcpServer, AsyncTlsConn};
+use crate::event_loop::{
+    AsyncTcpConn, AsyncTcpServer, AsyncTlsConn, AsyncUdpSocket, TcpBindOptions, UdpBindOptions,
+};
+use crate::internal_module::abort::abortable;
 use crate::*;
 
+/// `ref()` and `unref()`: whether the pending reads or accepts of the object
+/// keep the event loop alive.
+fn set_ref<T: JsClassDef>(this_obj: &mut JsObject, refed: bool) -> JsValue {
+    let this_obj = JsValue::from(this_obj.clone());
+    T::set_handle_ref(&this_obj, refed);
+    this_obj
+}
+
+fn has_ref<T: JsClassDef>(this_obj: &mut JsObject) -> JsValue {
+    JsValue::Bool(T::handle_has_ref(&this_obj.clone().into()))
+}
+
+/// `{ address, family, port }`, like `server.address()` in Node.js.
+fn address_to_js_object(ctx: &mut Context, addr: std::net::SocketAddr) -> JsValue {
+    let mut obj = ctx.new_object();
+    obj.set("address", ctx.new_string(&addr.ip().to_string()).into());
+    let family = if addr.is_ipv6() { "IPv6" } else { "IPv4" };
+    obj.set("family", ctx.new_string(family).into());
+    obj.set("port", JsValue::Int(addr.port() as i32));
+    obj.into()
+}
+
+fn to_port(ctx: &mut Context, val: &JsValue) -> Result<u16, JsValue> {
+    let port = match val {
+        JsValue::Int(port) => *port as f64,
+        JsValue::Float(port) => *port,
+        _ => {
+            let msg = "The \"port\" option must be of type number";
+            return Err(ctx.throw_type_error(msg).into());
+        }
+    };
+    if port.fract() != 0.0 || !(0.0..=65535.0).contains(&port) {
+        let msg = format!("Port should be >= 0 and < 65536. Received {}.", port);
+        return Err(ctx.throw_range_error(&msg).into());
+    }
+    Ok(port as u16)
+}
+
+/// Node's `ERR_FEATURE_UNAVAILABLE_ON_PLATFORM`, for options WasmEdge can't honour.
+fn feature_unavailable(ctx: &mut Context, feature: &str) -> JsValue {
+    let msg = format!(
+        "The feature {} is unavailable on the current platform, which is being used to run Node.js",
+        feature
+    );
+    let mut e = match ctx.get_global().get("TypeError") {
+        JsValue::Function(type_error) => type_error.call(&[ctx.new_string(&msg).into()]),
+        _ => ctx.new_error(&msg),
+    };
+    if let JsValue::Object(o) = &mut e {
+        let code = ctx.new_string("ERR_FEATURE_UNAVAILABLE_ON_PLATFORM");
+        o.set("code", code.into());
+    }
+    ctx.throw_error(e).into()
+}
+
+/// `new WasiTcpServer(port)` or `new WasiTcpServer({ host, port, ipv6Only, backlog, reuseAddr })`.
+/// `ipv6Only: true` throws, as WasmEdge can't set `IPV6_V6ONLY`.
+fn bind_options(ctx: &mut Context, arg: Option<&JsValue>) -> Result<TcpBindOptions, JsValue> {
+    let mut options = TcpBindOptions::default();
+    let obj = match arg {
+        None | Some(JsValue::UnDefined) => return Ok(options),
+        Some(JsValue::Object(obj)) => obj.clone(),
+        Some(port) => {
+            options.port = to_port(ctx, port)?;
+            return Ok(options);
+        }
+    };
+    match obj.get("host") {
+        JsValue::UnDefined | JsValue::Null => {}
+        JsValue::String(host) => options.host = Some(host.to_string()),
+        _ => {
+            let msg = "The \"host\" option must be of type string";
+            return Err(ctx.throw_type_error(msg).into());
+        }
+    }
+    match obj.get("port") {
+        JsValue::UnDefined => {}
+        port => options.port = to_port(ctx, &port)?,
+    }
+    match obj.get("backlog") {
+        JsValue::UnDefined => {}
+        JsValue::Int(backlog) if backlog >= 0 => options.backlog = backlog as u32,
+        _ => {
+            let msg = "The \"backlog\" option must be a non-negative integer";
+            return Err(ctx.throw_type_error(msg).into());
+        }
+    }
+    for (name, flag) in [
+        ("ipv6Only", &mut options.ipv6_only),
+        ("reuseAddr", &mut options.reuse_addr),
+    ] {
+        match obj.get(name) {
+            JsValue::UnDefined => {}
+            JsValue::Bool(b) => *flag = b,
+            _ => {
+                let msg = format!("The \"{}\" option must be of type boolean", name);
+                return Err(ctx.throw_type_error(&msg).into());
+            }
+        }
+    }
+    if options.ipv6_only {
+        return Err(feature_unavailable(ctx, "ipv6Only"));
+    }
+    Ok(options)
+}
+
 #[cfg(feature = "tls")]
 impl AsyncTlsConn {
     pub fn js_connect(ctx: &mut Context, _this_val: JsValue, argv: &[JsValue]) -> JsValue {
@@ -10,6 +119,7 @@ impl AsyncTlsConn {
         let host = argv.get(0);
         let port = argv.get(1);
         let timeout = argv.get(2);
+        let signal = argv.get(3).cloned();
 
         let nctx = ctx.clone();
 
@@ -24,7 +134,7 @@ impl AsyncTlsConn {
             let port = *port as u16;
 
             let pp = if let Some(duration) = timeout {
-                ctx.future_to_promise(async move {
+                ctx.future_to_promise(abortable(signal, async move {
                     let mut ctx = nctx;
                     match tokio::time::timeout(
                         duration,
@@ -40,15 +150,15 @@ impl AsyncTlsConn {
                             Err(ctx.new_error(err.to_string().as_str()).into())
                         }
                     }
-                })
+                }))
             } else {
-                ctx.future_to_promise(async move {
+                ctx.future_to_promise(abortable(signal, async move {
                     let mut ctx = nctx;
                     match AsyncTlsConn::async_connect((host.as_str(), port), &host).await {
                         Ok(conn) => Ok(Self::wrap_obj(&mut ctx, conn)),
                         Err(e) => Err(ctx.new_error(e.to_string().as_str())),
                     }
-                })
+                }))
             };
             pp
         } else {
@@ -57,7 +167,7 @@ impl AsyncTlsConn {
     }
 
     pub fn on(
-        _this_val: &mut Self,
+        _this_val: &Self,
         _this_obj: &mut JsObject,
         _ctx: &mut Context,
         _argv: &[JsValue],
@@ -65,102 +175,75 @@ impl AsyncTlsConn {
         JsValue::UnDefined
     }
 
-    pub fn js_read(
-        _this_val: &mut Self,
-        this_obj: &mut JsObject,
-        ctx: &mut Context,
-        argv: &[JsValue],
-    ) -> JsValue {
-        let mut js_obj = this_obj.clone().into();
-        let n_ctx = ctx.clone();
-        if let Some(JsValue::Int(timeout)) = argv.get(0) {
-            let duration = std::time::Duration::from_millis((*timeout) as u64);
-            ctx.future_to_promise(async move {
-                let mut ctx = n_ctx;
-                let this_val = Self::opaque_mut(&mut js_obj).unwrap();
-                match tokio::time::timeout(duration, this_val.async_read_all()).await {
-                    Ok(Ok(data)) => {
-                        if data.len() > 0 {
-                            let buff = ctx.new_array_buffer(data.as_slice());
-                            Ok(JsValue::ArrayBuffer(buff))
-                        } else {
-                            Ok(JsValue::UnDefined)
-                        }
-                    }
-                    Ok(Err(err)) => Err(ctx.new_error(err.to_string().as_str()).into()),
-                    Err(e) => {
-                        let err = std::io::Error::new(std::io::ErrorKind::TimedOut, e.to_string());
-                        Err(ctx.new_error(err.to_string().as_str()).into())
-                    }
-                }
-            })
+    pub fn js_read(this: JsClassRef<Self>, mut ctx: Context, argv: Vec<JsValue>) -> JsClassFuture {
+        let timeout = if let Some(JsValue::Int(timeout)) = argv.get(0) {
+            Some(std::time::Duration::from_millis((*timeout) as u64))
         } else {
-            ctx.future_to_promise(async move {
-                let mut ctx = n_ctx;
-                let this_val = Self::opaque_mut(&mut js_obj).unwrap();
-                match this_val.async_read_all().await {
-                    Ok(data) => {
-                        if data.len() > 0 {
-                            let buff = ctx.new_array_buffer(data.as_slice());
-                            log::trace!("async_read_all return ArrayBuffer");
-                            Ok(JsValue::ArrayBuffer(buff))
-                        } else {
-                            Ok(JsValue::UnDefined)
-                        }
+            None
+        };
+        let signal = argv.get(1).cloned();
+        Box::pin(abortable(signal, async move {
+            let r = if let Some(duration) = timeout {
+                match tokio::time::timeout(duration, this.async_read_all()).await {
+                    Ok(r) => r,
+                    Err(e) => Err(std::io::Error::new(
+                        std::io::ErrorKind::TimedOut,
+                        e.to_string(),
+                    )),
+                }
+            } else {
+                this.async_read_all().await
+            };
+            match r {
+                Ok(data) => {
+                    if data.len() > 0 {
+                        let buff = ctx.new_array_buffer(data.as_slice());
+                        log::trace!("async_read_all return ArrayBuffer");
+                        Ok(JsValue::ArrayBuffer(buff))
+                    } else {
+                        Ok(JsValue::UnDefined)
                     }
-                    Err(err) => Err(ctx.new_error(err.to_string().as_str()).into()),
                 }
-            })
-        }
+                Err(err) => Err(ctx.new_error(err.to_string().as_str()).into()),
+            }
+        }))
     }
 
-    pub fn js_write(
-        _this_val: &mut AsyncTlsConn,
+    pub fn js_close(
+        this_val: &Self,
         this_obj: &mut JsObject,
+        _ctx: &mut Context,
+        _argv: &[JsValue],
+    ) -> JsValue {
+        Self::mark_closed(&this_obj.clone().into());
+        this_val.close();
+        JsValue::UnDefined
+    }
+
+    pub fn js_write(
+        this_val: &AsyncTlsConn,
+        _this_obj: &mut JsObject,
         ctx: &mut Context,
         argv: &[JsValue],
     ) -> JsValue {
-        let mut js_obj = JsValue::Object(this_obj.clone());
-        match argv.get(0) {
-            Some(JsValue::String(s)) => {
-                let data = s.to_string();
-                ctx.future_to_promise(async move {
-                    let this_val = Self::opaque_mut(&mut js_obj).unwrap();
-                    this_val.async_write_all(data.as_bytes()).await;
-                    Ok(JsValue::UnDefined)
-                });
-            }
-            Some(JsValue::ArrayBuffer(buff)) => {
-                let data = buff.to_vec();
-                ctx.future_to_promise(async move {
-                    let this_val = Self::opaque_mut(&mut js_obj).unwrap();
-                    this_val.async_write_all(&data).await;
-                    Ok(JsValue::UnDefined)
-                });
-            }
-            Some(JsValue::Object(o)) => {
-                let data = o.to_string();
-                ctx.future_to_promise(async move {
-                    let this_val = Self::opaque_mut(&mut js_obj).unwrap();
-                    this_val.async_write_all(data.as_bytes()).await;
-                    Ok(JsValue::UnDefined)
-                });
-            }
-            Some(JsValue::Symbol(s)) => {
-                let data = format!("{:?}", s);
-                ctx.future_to_promise(async move {
-                    let this_val = Self::opaque_mut(&mut js_obj).unwrap();
-                    this_val.async_write_all(data.as_bytes()).await;
-                    Ok(JsValue::UnDefined)
-                });
-            }
-            _ => {}
+        let data = match argv.get(0) {
+            Some(JsValue::String(s)) => s.to_string().into_bytes(),
+            Some(JsValue::ArrayBuffer(buff)) => buff.to_vec(),
+            Some(JsValue::Object(o)) => o.to_string().into_bytes(),
+            Some(JsValue::Symbol(s)) => format!("{:?}", s).into_bytes(),
+            _ => return JsValue::Bool(true),
         };
+        // takes the stream now, so that a close() right after still sends the data
+        let write = this_val.async_write_all(data);
+        ctx.future_to_promise(async move {
+            let _ = write.await;
+            Ok(JsValue::UnDefined)
+        });
         JsValue::Bool(true)
     }
 
     pub fn js_local(
-        this_val: &mut Self,
+        this_val: &Self,
         _this_obj: &mut JsObject,
         ctx: &mut Context,
         _argv: &[JsValue],
@@ -172,7 +255,7 @@ impl AsyncTlsConn {
     }
 
     pub fn js_peer(
-        this_val: &mut Self,
+        this_val: &Self,
         _this_obj: &mut JsObject,
         ctx: &mut Context,
         _argv: &[JsValue],
@@ -192,15 +275,31 @@ impl JsClassDef for AsyncTlsConn {
 
     const FIELDS: &'static [JsClassField<Self::RefType>] = &[];
 
-    const METHODS: &'static [JsClassMethod<Self::RefType>] = &[
+    const METHODS: &'static [JsClassMethod<Self::RefType>] = &[];
+
+    const SHARED_METHODS: &'static [JsClassSharedMethod<Self::RefType>] = &[
         ("on", 1, Self::on),
-        ("read", 0, Self::js_read),
         ("write", 1, Self::js_write),
         ("end", 1, Self::js_write),
+        ("close", 0, Self::js_close),
         ("local", 0, Self::js_local),
         ("peer", 0, Self::js_peer),
+        ("ref", 0, |_, this_obj, _, _| {
+            set_ref::<Self>(this_obj, true)
+        }),
+        ("unref", 0, |_, this_obj, _, _| {
+            set_ref::<Self>(this_obj, false)
+        }),
+        ("hasRef", 0, |_, this_obj, _, _| has_ref::<Self>(this_obj)),
     ];
 
+    const ASYNC_METHODS: &'static [JsClassAsyncMethod<Self::RefType>] =
+        &[("read", 2, Self::js_read)];
+
+    const STATIC_METHODS: &'static [JsClassStaticMethod] = &[("connect", 4, Self::js_connect)];
+
+    const RESOURCE_KIND: Option<ResourceKind> = Some(ResourceKind::TlsSocket);
+
     unsafe fn mut_class_id_ptr() -> &'static mut u32 {
         static mut CLASS_ID: u32 = 0;
         &mut CLASS_ID
@@ -217,6 +316,7 @@ impl AsyncTcpConn {
         let host = argv.get(0);
         let port = argv.get(1);
         let timeout = argv.get(2);
+        let signal = argv.get(3).cloned();
 
         let nctx = ctx.clone();
 
@@ -231,7 +331,7 @@ impl AsyncTcpConn {
             let port = *port as u16;
 
             let pp = if let Some(duration) = timeout {
-                ctx.future_to_promise(async move {
+                ctx.future_to_promise(abortable(signal, async move {
                     let mut ctx = nctx;
                     match tokio::time::timeout(duration, AsyncTcpConn::async_connect((host, port)))
                         .await
@@ -244,15 +344,15 @@ impl AsyncTcpConn {
                             Err(ctx.new_error(err.to_string().as_str()).into())
                         }
                     }
-                })
+                }))
             } else {
-                ctx.future_to_promise(async move {
+                ctx.future_to_promise(abortable(signal, async move {
                     let mut ctx = nctx;
                     match AsyncTcpConn::async_connect((host, port)).await {
                         Ok(conn) => Ok(Self::wrap_obj(&mut ctx, conn)),
                         Err(e) => Err(ctx.new_error(e.to_string().as_str())),
                     }
-                })
+                }))
             };
             pp
         } else {
@@ -261,7 +361,7 @@ impl AsyncTcpConn {
     }
 
     pub fn on(
-        _this_val: &mut AsyncTcpConn,
+        _this_val: &AsyncTcpConn,
         _this_obj: &mut JsObject,
         _ctx: &mut Context,
         _argv: &[JsValue],
@@ -269,102 +369,75 @@ impl AsyncTcpConn {
         JsValue::UnDefined
     }
 
-    pub fn js_read(
-        _this_val: &mut AsyncTcpConn,
-        this_obj: &mut JsObject,
-        ctx: &mut Context,
-        argv: &[JsValue],
-    ) -> JsValue {
-        let mut js_obj = this_obj.clone().into();
-        let n_ctx = ctx.clone();
-        if let Some(JsValue::Int(timeout)) = argv.get(0) {
-            let duration = std::time::Duration::from_millis((*timeout) as u64);
-            ctx.future_to_promise(async move {
-                let mut ctx = n_ctx;
-                let this_val = Self::opaque_mut(&mut js_obj).unwrap();
-                match tokio::time::timeout(duration, this_val.async_read_all()).await {
-                    Ok(Ok(data)) => {
-                        if data.len() > 0 {
-                            let buff = ctx.new_array_buffer(data.as_slice());
-                            Ok(JsValue::ArrayBuffer(buff))
-                        } else {
-                            Ok(JsValue::UnDefined)
-                        }
-                    }
-                    Ok(Err(err)) => Err(ctx.new_error(err.to_string().as_str()).into()),
-                    Err(e) => {
-                        let err = std::io::Error::new(std::io::ErrorKind::TimedOut, e.to_string());
-                        Err(ctx.new_error(err.to_string().as_str()).into())
-                    }
-                }
-            })
+    pub fn js_read(this: JsClassRef<Self>, mut ctx: Context, argv: Vec<JsValue>) -> JsClassFuture {
+        let timeout = if let Some(JsValue::Int(timeout)) = argv.get(0) {
+            Some(std::time::Duration::from_millis((*timeout) as u64))
         } else {
-            ctx.future_to_promise(async move {
-                let mut ctx = n_ctx;
-                let this_val = Self::opaque_mut(&mut js_obj).unwrap();
-                match this_val.async_read_all().await {
-                    Ok(data) => {
-                        if data.len() > 0 {
-                            let buff = ctx.new_array_buffer(data.as_slice());
-                            log::trace!("async_read_all return ArrayBuffer");
-                            Ok(JsValue::ArrayBuffer(buff))
-                        } else {
-                            Ok(JsValue::UnDefined)
-                        }
+            None
+        };
+        let signal = argv.get(1).cloned();
+        Box::pin(abortable(signal, async move {
+            let r = if let Some(duration) = timeout {
+                match tokio::time::timeout(duration, this.async_read_all()).await {
+                    Ok(r) => r,
+                    Err(e) => Err(std::io::Error::new(
+                        std::io::ErrorKind::TimedOut,
+                        e.to_string(),
+                    )),
+                }
+            } else {
+                this.async_read_all().await
+            };
+            match r {
+                Ok(data) => {
+                    if data.len() > 0 {
+                        let buff = ctx.new_array_buffer(data.as_slice());
+                        log::trace!("async_read_all return ArrayBuffer");
+                        Ok(JsValue::ArrayBuffer(buff))
+                    } else {
+                        Ok(JsValue::UnDefined)
                     }
-                    Err(err) => Err(ctx.new_error(err.to_string().as_str()).into()),
                 }
-            })
-        }
+                Err(err) => Err(ctx.new_error(err.to_string().as_str()).into()),
+            }
+        }))
     }
 
-    pub fn js_write(
-        _this_val: &mut AsyncTcpConn,
+    pub fn js_close(
+        this_val: &Self,
         this_obj: &mut JsObject,
+        _ctx: &mut Context,
+        _argv: &[JsValue],
+    ) -> JsValue {
+        Self::mark_closed(&this_obj.clone().into());
+        this_val.close();
+        JsValue::UnDefined
+    }
+
+    pub fn js_write(
+        this_val: &AsyncTcpConn,
+        _this_obj: &mut JsObject,
         ctx: &mut Context,
         argv: &[JsValue],
     ) -> JsValue {
-        let mut js_obj = JsValue::Object(this_obj.clone());
-        match argv.get(0) {
-            Some(JsValue::String(s)) => {
-                let data = s.to_string();
-                ctx.future_to_promise(async move {
-                    let this_val = AsyncTcpConn::opaque_mut(&mut js_obj).unwrap();
-                    this_val.async_write_all(data.as_bytes()).await;
-                    Ok(JsValue::UnDefined)
-                });
-            }
-            Some(JsValue::ArrayBuffer(buff)) => {
-                let data = buff.to_vec();
-                ctx.future_to_promise(async move {
-                    let this_val = AsyncTcpConn::opaque_mut(&mut js_obj).unwrap();
-                    this_val.async_write_all(&data).await;
-                    Ok(JsValue::UnDefined)
-                });
-            }
-            Some(JsValue::Object(o)) => {
-                let data = o.to_string();
-                ctx.future_to_promise(async move {
-                    let this_val = AsyncTcpConn::opaque_mut(&mut js_obj).unwrap();
-                    this_val.async_write_all(data.as_bytes()).await;
-                    Ok(JsValue::UnDefined)
-                });
-            }
-            Some(JsValue::Symbol(s)) => {
-                let data = format!("{:?}", s);
-                ctx.future_to_promise(async move {
-                    let this_val = AsyncTcpConn::opaque_mut(&mut js_obj).unwrap();
-                    this_val.async_write_all(data.as_bytes()).await;
-                    Ok(JsValue::UnDefined)
-                });
-            }
-            _ => {}
+        let data = match argv.get(0) {
+            Some(JsValue::String(s)) => s.to_string().into_bytes(),
+            Some(JsValue::ArrayBuffer(buff)) => buff.to_vec(),
+            Some(JsValue::Object(o)) => o.to_string().into_bytes(),
+            Some(JsValue::Symbol(s)) => format!("{:?}", s).into_bytes(),
+            _ => return JsValue::Bool(true),
         };
+        // takes the stream now, so that a close() right after still sends the data
+        let write = this_val.async_write_all(data);
+        ctx.future_to_promise(async move {
+            let _ = write.await;
+            Ok(JsValue::UnDefined)
+        });
         JsValue::Bool(true)
     }
 
     pub fn js_local(
-        this_val: &mut AsyncTcpConn,
+        this_val: &AsyncTcpConn,
         _this_obj: &mut JsObject,
         ctx: &mut Context,
         _argv: &[JsValue],
@@ -376,7 +449,7 @@ impl AsyncTcpConn {
     }
 
     pub fn js_peer(
-        this_val: &mut AsyncTcpConn,
+        this_val: &AsyncTcpConn,
         _this_obj: &mut JsObject,
         ctx: &mut Context,
         _argv: &[JsValue],
@@ -395,15 +468,31 @@ impl JsClassDef for AsyncTcpConn {
 
     const FIELDS: &'static [JsClassField<Self::RefType>] = &[];
 
-    const METHODS: &'static [JsClassMethod<Self::RefType>] = &[
+    const METHODS: &'static [JsClassMethod<Self::RefType>] = &[];
+
+    const SHARED_METHODS: &'static [JsClassSharedMethod<Self::RefType>] = &[
         ("on", 1, Self::on),
-        ("read", 0, Self::js_read),
         ("write", 1, Self::js_write),
         ("end", 1, Self::js_write),
+        ("close", 0, Self::js_close),
         ("local", 0, Self::js_local),
         ("peer", 0, Self::js_peer),
+        ("ref", 0, |_, this_obj, _, _| {
+            set_ref::<Self>(this_obj, true)
+        }),
+        ("unref", 0, |_, this_obj, _, _| {
+            set_ref::<Self>(this_obj, false)
+        }),
+        ("hasRef", 0, |_, this_obj, _, _| has_ref::<Self>(this_obj)),
     ];
 
+    const ASYNC_METHODS: &'static [JsClassAsyncMethod<Self::RefType>] =
+        &[("read", 2, Self::js_read)];
+
+    const STATIC_METHODS: &'static [JsClassStaticMethod] = &[("connect", 4, Self::js_connect)];
+
+    const RESOURCE_KIND: Option<ResourceKind> = Some(ResourceKind::TcpSocket);
+
     unsafe fn mut_class_id_ptr() -> &'static mut u32 {
         static mut CLASS_ID: u32 = 0;
         &mut CLASS_ID
@@ -416,23 +505,34 @@ impl JsClassDef for AsyncTcpConn {
 
 impl AsyncTcpServer {
     pub fn js_accept(
-        &mut self,
-        this: &mut JsObject,
-        ctx: &mut Context,
-        argv: &[JsValue],
-    ) -> JsValue {
+        this: JsClassRef<Self>,
+        mut ctx: Context,
+        argv: Vec<JsValue>,
+    ) -> JsClassFuture {
         let timeout = if let Some(JsValue::Int(timeout)) = argv.get(0) {
             Some(std::time::Duration::from_millis((*timeout) as u64))
         } else {
             None
         };
-        let n_ctx = ctx.clone();
-        let mut js_obj = this.clone().into();
-        ctx.future_to_promise(async move {
-            let this = Self::opaque_mut(&mut js_obj).unwrap();
-            let mut ctx = n_ctx;
-            this.accept(&mut ctx, timeout).await
-        })
+        Box::pin(async move { this.accept(&mut ctx, timeout).await })
+    }
+
+    pub fn js_close(&self, this: &mut JsObject, _ctx: &mut Context, _argv: &[JsValue]) -> JsValue {
+        Self::mark_closed(&this.clone().into());
+        self.close();
+        JsValue::UnDefined
+    }
+
+    pub fn js_local_address(
+        &self,
+        _this: &mut JsObject,
+        ctx: &mut Context,
+        _argv: &[JsValue],
+    ) -> JsValue {
+        match self.local() {
+            Ok(addr) => address_to_js_object(ctx, addr),
+            Err(e) => ctx.throw_internal_type_error(e.to_string().as_str()).into(),
+        }
     }
 }
 
@@ -444,7 +544,24 @@ impl JsClassDef for AsyncTcpServer {
 
     const FIELDS: &'static [JsClassField<Self::RefType>] = &[];
 
-    const METHODS: &'static [JsClassMethod<Self::RefType>] = &[("accept", 0, Self::js_accept)];
+    const METHODS: &'static [JsClassMethod<Self::RefType>] = &[];
+
+    const SHARED_METHODS: &'static [JsClassSharedMethod<Self::RefType>] = &[
+        ("close", 0, Self::js_close),
+        ("localAddress", 0, Self::js_local_address),
+        ("ref", 0, |_, this_obj, _, _| {
+            set_ref::<Self>(this_obj, true)
+        }),
+        ("unref", 0, |_, this_obj, _, _| {
+            set_ref::<Self>(this_obj, false)
+        }),
+        ("hasRef", 0, |_, this_obj, _, _| has_ref::<Self>(this_obj)),
+    ];
+
+    const ASYNC_METHODS: &'static [JsClassAsyncMethod<Self::RefType>] =
+        &[("accept", 0, Self::js_accept)];
+
+    const RESOURCE_KIND: Option<ResourceKind> = Some(ResourceKind::TcpServer);
 
     unsafe fn mut_class_id_ptr() -> &'static mut u32 {
         static mut CLASS_ID: u32 = 0;
@@ -452,17 +569,287 @@ impl JsClassDef for AsyncTcpServer {
     }
 
     fn constructor_fn(ctx: &mut Context, argv: &[JsValue]) -> Result<Self::RefType, JsValue> {
-        let port = argv.get(0).ok_or_else(|| JsValue::UnDefined)?;
-        if let JsValue::Int(port) = port {
-            match Self::bind(*port as u16) {
-                Ok(tcp_server) => Ok(tcp_server),
-                Err(e) => {
-                    log::trace!("tcp_listen err: {e}");
-                    Err(ctx.throw_internal_type_error(e.to_string().as_str()).into())
+        let options = bind_options(ctx, argv.get(0))?;
+        match Self::bind_with(&options) {
+            Ok(tcp_server) => Ok(tcp_server),
+            Err(e) => {
+                log::trace!("tcp_listen err: {e}");
+                Err(ctx.throw_internal_type_error(e.to_string().as_str()).into())
+            }
+        }
+    }
+}
+
+/// `new WasiUdpSocket({ type, host, port, reuseAddr })`, `type` being `udp4` or `udp6`.
+fn udp_bind_options(ctx: &mut Context, arg: Option<&JsValue>) -> Result<UdpBindOptions, JsValue> {
+    let mut options = UdpBindOptions::default();
+    let obj = match arg {
+        None | Some(JsValue::UnDefined) => return Ok(options),
+        Some(JsValue::Object(obj)) => obj.clone(),
+        Some(_) => {
+            let msg = "The \"options\" argument must be of type object";
+            return Err(ctx.throw_type_error(msg).into());
+        }
+    };
+    match obj.get("type") {
+        JsValue::UnDefined => {}
+        JsValue::String(t) if t.as_str() == "udp4" => {}
+        JsValue::String(t) if t.as_str() == "udp6" => options.ipv6 = true,
+        _ => {
+            let msg = "Bad socket type specified. Valid types are: udp4, udp6";
+            return Err(ctx.throw_type_error(msg).into());
+        }
+    }
+    match obj.get("host") {
+        JsValue::UnDefined | JsValue::Null => {}
+        JsValue::String(host) => options.host = Some(host.to_string()),
+        _ => {
+            let msg = "The \"host\" option must be of type string";
+            return Err(ctx.throw_type_error(msg).into());
+        }
+    }
+    match obj.get("port") {
+        JsValue::UnDefined => {}
+        port => options.port = to_port(ctx, &port)?,
+    }
+    match obj.get("reuseAddr") {
+        JsValue::UnDefined => {}
+        JsValue::Bool(b) => options.reuse_addr = b,
+        _ => {
+            let msg = "The \"reuseAddr\" option must be of type boolean";
+            return Err(ctx.throw_type_error(msg).into());
+        }
+    }
+    Ok(options)
+}
+
+fn to_ip_addr(ctx: &mut Context, name: &str, val: &JsValue) -> Result<std::net::IpAddr, JsValue> {
+    let addr = match val {
+        JsValue::String(addr) => addr.as_str().parse().ok(),
+        _ => None,
+    };
+    addr.ok_or_else(|| {
+        let msg = format!("The \"{}\" argument must be an IP address", name);
+        ctx.throw_type_error(&msg).into()
+    })
+}
+
+fn to_u32(ctx: &mut Context, name: &str, val: Option<&JsValue>) -> Result<u32, JsValue> {
+    match val {
+        Some(JsValue::Int(n)) if *n >= 0 => Ok(*n as u32),
+        _ => {
+            let msg = format!("The \"{}\" argument must be a non-negative integer", name);
+            Err(ctx.throw_type_error(&msg).into())
+        }
+    }
+}
+
+fn io_result(ctx: &mut Context, r: std::io::Result<()>) -> JsValue {
+    match r {
+        Ok(()) => JsValue::UnDefined,
+        Err(e) => ctx.throw_internal_type_error(e.to_string().as_str()).into(),
+    }
+}
+
+impl AsyncUdpSocket {
+    /// Resolves `{ data, address, family, port, size }` with the next datagram.
+    pub fn js_recv_from(
+        this: JsClassRef<Self>,
+        mut ctx: Context,
+        _argv: Vec<JsValue>,
+    ) -> JsClassFuture {
+        Box::pin(async move {
+            match this.recv_from().await {
+                Ok((data, addr)) => {
+                    let mut info = address_to_js_object(&mut ctx, addr);
+                    if let JsValue::Object(info) = &mut info {
+                        let buff = ctx.new_array_buffer(data.as_slice());
+                        info.set("data", JsValue::ArrayBuffer(buff));
+                        info.set("size", JsValue::Int(data.len() as i32));
+                    }
+                    Ok(info)
                 }
+                Err(e) => Err(ctx.new_error(e.to_string().as_str())),
+            }
+        })
+    }
+
+    /// `sendTo(data, host, port)` resolves with the number of bytes sent.
+    ///
+    /// Not an async method, so that sends don't wait for a pending `recvFrom`.
+    pub fn js_send_to(
+        &self,
+        _this_obj: &mut JsObject,
+        ctx: &mut Context,
+        argv: &[JsValue],
+    ) -> JsValue {
+        let data = match argv.get(0) {
+            Some(JsValue::String(s)) => s.to_string().into_bytes(),
+            Some(JsValue::ArrayBuffer(buff)) => buff.to_vec(),
+            _ => {
+                let msg = "The \"data\" argument must be of type string or ArrayBuffer";
+                return ctx.throw_type_error(msg).into();
+            }
+        };
+        let host = match argv.get(1) {
+            Some(JsValue::String(host)) => host.to_string(),
+            _ => {
+                let msg = "The \"host\" argument must be of type string";
+                return ctx.throw_type_error(msg).into();
+            }
+        };
+        let port = match to_port(ctx, argv.get(2).unwrap_or(&JsValue::UnDefined)) {
+            Ok(port) => port,
+            Err(e) => return e,
+        };
+        let socket = self.clone();
+        let mut nctx = ctx.clone();
+        ctx.future_to_promise(async move {
+            match socket.send_to(&data, &host, port).await {
+                Ok(n) => Ok(JsValue::Int(n as i32)),
+                Err(e) => Err(nctx.new_error(e.to_string().as_str())),
+            }
+        })
+    }
+
+    pub fn js_close(&self, this: &mut JsObject, _ctx: &mut Context, _argv: &[JsValue]) -> JsValue {
+        Self::mark_closed(&this.clone().into());
+        self.close();
+        JsValue::UnDefined
+    }
+
+    pub fn js_local_address(
+        &self,
+        _this: &mut JsObject,
+        ctx: &mut Context,
+        _argv: &[JsValue],
+    ) -> JsValue {
+        match self.local() {
+            Ok(addr) => address_to_js_object(ctx, addr),
+            Err(e) => ctx.throw_internal_type_error(e.to_string().as_str()).into(),
+        }
+    }
+
+    pub fn js_set_broadcast(
+        &self,
+        _this: &mut JsObject,
+        ctx: &mut Context,
+        argv: &[JsValue],
+    ) -> JsValue {
+        let on = matches!(argv.get(0), Some(JsValue::Bool(true)));
+        let r = self.set_broadcast(on);
+        io_result(ctx, r)
+    }
+
+    pub fn js_set_ttl(&self, _this: &mut JsObject, ctx: &mut Context, argv: &[JsValue]) -> JsValue {
+        match to_u32(ctx, "ttl", argv.get(0)) {
+            Ok(ttl) => {
+                let r = self.set_ttl(ttl);
+                io_result(ctx, r)
+            }
+            Err(e) => e,
+        }
+    }
+
+    pub fn js_set_multicast_ttl(
+        &self,
+        _this: &mut JsObject,
+        ctx: &mut Context,
+        argv: &[JsValue],
+    ) -> JsValue {
+        match to_u32(ctx, "ttl", argv.get(0)) {
+            Ok(ttl) => {
+                let r = self.set_multicast_ttl(ttl);
+                io_result(ctx, r)
+            }
+            Err(e) => e,
+        }
+    }
+
+    pub fn js_set_multicast_loopback(
+        &self,
+        _this: &mut JsObject,
+        ctx: &mut Context,
+        argv: &[JsValue],
+    ) -> JsValue {
+        let on = matches!(argv.get(0), Some(JsValue::Bool(true)));
+        let r = self.set_multicast_loopback(on);
+        io_result(ctx, r)
+    }
+
+    /// `addMembership(group, interface)` and `dropMembership(group, interface)`.
+    fn set_membership_with(&self, ctx: &mut Context, argv: &[JsValue], join: bool) -> JsValue {
+        let group = match to_ip_addr(
+            ctx,
+            "multicastAddress",
+            argv.get(0).unwrap_or(&JsValue::UnDefined),
+        ) {
+            Ok(group) => group,
+            Err(e) => return e,
+        };
+        let interface = match argv.get(1) {
+            None | Some(JsValue::UnDefined) | Some(JsValue::Null) => None,
+            Some(interface) => match to_ip_addr(ctx, "interfaceAddress", interface) {
+                Ok(interface) => Some(interface),
+                Err(e) => return e,
+            },
+        };
+        let r = self.set_membership(group, interface, join);
+        io_result(ctx, r)
+    }
+}
+
+impl JsClassDef for AsyncUdpSocket {
+    const CLASS_NAME: &'static str = "WasiUdpSocket";
+    const CONSTRUCTOR_ARGC: u8 = 1;
+
+    type RefType = AsyncUdpSocket;
+
+    const FIELDS: &'static [JsClassField<Self::RefType>] = &[];
+
+    const METHODS: &'static [JsClassMethod<Self::RefType>] = &[];
+
+    const SHARED_METHODS: &'static [JsClassSharedMethod<Self::RefType>] = &[
+        ("sendTo", 3, Self::js_send_to),
+        ("close", 0, Self::js_close),
+        ("localAddress", 0, Self::js_local_address),
+        ("setBroadcast", 1, Self::js_set_broadcast),
+        ("setTTL", 1, Self::js_set_ttl),
+        ("setMulticastTTL", 1, Self::js_set_multicast_ttl),
+        ("setMulticastLoopback", 1, Self::js_set_multicast_loopback),
+        ("addMembership", 2, |this, _, ctx, argv| {
+            this.set_membership_with(ctx, argv, true)
+        }),
+        ("dropMembership", 2, |this, _, ctx, argv| {
+            this.set_membership_with(ctx, argv, false)
+        }),
+        ("ref", 0, |_, this_obj, _, _| {
+            set_ref::<Self>(this_obj, true)
+        }),
+        ("unref", 0, |_, this_obj, _, _| {
+            set_ref::<Self>(this_obj, false)
+        }),
+        ("hasRef", 0, |_, this_obj, _, _| has_ref::<Self>(this_obj)),
+    ];
+
+    const ASYNC_METHODS: &'static [JsClassAsyncMethod<Self::RefType>] =
+        &[("recvFrom", 0, Self::js_recv_from)];
+
+    const RESOURCE_KIND: Option<ResourceKind> = Some(ResourceKind::UdpSocket);
+
+    unsafe fn mut_class_id_ptr() -> &'static mut u32 {
+        static mut CLASS_ID: u32 = 0;
+        &mut CLASS_ID
+    }
+
+    fn constructor_fn(ctx: &mut Context, argv: &[JsValue]) -> Result<Self::RefType, JsValue> {
+        let options = udp_bind_options(ctx, argv.get(0))?;
+        match Self::bind(&options) {
+            Ok(socket) => Ok(socket),
+            Err(e) => {
+                log::trace!("udp_bind err: {e}");
+                Err(ctx.throw_internal_type_error(e.to_string().as_str()).into())
             }
-        } else {
-            Err(JsValue::UnDefined)
         }
     }
 }
@@ -494,6 +881,7 @@ pub fn init_module(ctx: &mut Context) {
         &[
             AsyncTcpServer::CLASS_NAME,
             AsyncTcpConn::CLASS_NAME,
+            AsyncUdpSocket::CLASS_NAME,
             #[cfg(feature = "tls")]
             AsyncTlsConn::CLASS_NAME,
             "nsloopup",
@@ -502,20 +890,15 @@ pub fn init_module(ctx: &mut Context) {
             let class_ctor = register_class::<AsyncTcpServer>(ctx);
             m.add_export(AsyncTcpServer::CLASS_NAME, class_ctor);
 
-            let mut class_ctor = register_class::<AsyncTcpConn>(ctx);
-            if let JsValue::Function(tcp_conn_ctor) = &mut class_ctor {
-                let conn = ctx.wrap_function("connect", AsyncTcpConn::js_connect);
-                tcp_conn_ctor.set("connect", conn.into());
-            }
+            let class_ctor = register_class::<AsyncTcpConn>(ctx);
             m.add_export(AsyncTcpConn::CLASS_NAME, class_ctor);
 
+            let class_ctor = register_class::<AsyncUdpSocket>(ctx);
+            m.add_export(AsyncUdpSocket::CLASS_NAME, class_ctor);
+
             #[cfg(feature = "tls")]
             {
-                let mut class_ctor = register_class::<AsyncTlsConn>(ctx);
-                if let JsValue::Function(tls_conn_ctor) = &mut class_ctor {
-                    let conn = ctx.wrap_function("tls_connect", AsyncTlsConn::js_connect);
-                    tls_conn_ctor.set("connect", conn.into());
-                }
+                let class_ctor = register_class::<AsyncTlsConn>(ctx);
                 m.add_export(AsyncTlsConn::CLASS_NAME, class_ctor);
             }
 
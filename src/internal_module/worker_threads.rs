@@ -0,0 +1,510 @@
+use std::cell::RefCell;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicI32, AtomicU32, Ordering};
+use std::sync::Arc;
+use std::task::Poll;
+
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use tokio::sync::{Mutex, Notify};
+
+use crate::quickjs_sys::*;
+
+pub(crate) enum PortMessage {
+    Data(Vec<u8>, Vec<SharedBuffer>, Vec<PortChannel>),
+    Error(Vec<u8>),
+    Exit(i32),
+}
+
+/// One end of a message channel, moved between runtimes when a port is transferred.
+pub(crate) struct PortChannel {
+    tx: Option<UnboundedSender<PortMessage>>,
+    rx: Arc<Mutex<UnboundedReceiver<PortMessage>>>,
+}
+
+impl PortChannel {
+    fn new(tx: UnboundedSender<PortMessage>, rx: UnboundedReceiver<PortMessage>) -> Self {
+        PortChannel {
+            tx: Some(tx),
+            rx: Arc::new(Mutex::new(rx)),
+        }
+    }
+
+    fn pair() -> (Self, Self) {
+        let (tx1, rx1) = unbounded_channel();
+        let (tx2, rx2) = unbounded_channel();
+        (PortChannel::new(tx1, rx2), PortChannel::new(tx2, rx1))
+    }
+}
+
+struct MessagePort {
+    /// taken when the port is closed or transferred
+    channel: RefCell<Option<PortChannel>>,
+    cancel: Arc<Notify>,
+}
+
+impl MessagePort {
+    fn new(channel: PortChannel) -> Self {
+        MessagePort {
+            channel: RefCell::new(Some(channel)),
+            cancel: Arc::new(Notify::new()),
+        }
+    }
+
+    fn message_to_js(ctx: &mut Context, msg: PortMessage) -> JsValue {
+        let mut obj = ctx.new_object();
+        match msg {
+            PortMessage::Data(data, shared, channels) => {
+                let mut ports = ctx.new_array();
+                for (i, channel) in channels.into_iter().enumerate() {
+                    let port = Self::wrap_obj(ctx, MessagePort::new(channel));
+                    ports.put(i, port);
+                }
+                obj.set("ports", ports.into());
+                match ctx.deserialize_shared(&data, &shared) {
+                    Ok(data) => obj.set("data", data),
+                    Err(e) => obj.set("messageerror", e),
+                };
+            }
+            PortMessage::Error(error) => {
+                let error = ctx.new_array_buffer(&error);
+                obj.set("error", error.into());
+            }
+            PortMessage::Exit(code) => {
+                obj.set("exit", JsValue::Int(code));
+            }
+        }
+        obj.into()
+    }
+
+    fn js_post_message(
+        &self,
+        _this: &mut JsObject,
+        ctx: &mut Context,
+        argv: &[JsValue],
+    ) -> JsValue {
+        let value = argv.get(0).cloned().unwrap_or(JsValue::UnDefined);
+        let (data, shared) = match ctx.serialize_shared(&value) {
+            Ok(r) => r,
+            Err(e) => return ctx.throw_error(e).into(),
+        };
+
+        let mut channels = vec![];
+        if let Some(JsValue::Array(ports)) = argv.get(1) {
+            if let Ok(ports) = ports.to_vec() {
+                for port in ports {
+                    let channel = Self::opaque(&port).and_then(|p| p.channel.take());
+                    match channel {
+                        Some(channel) => channels.push(channel),
+                        None => {
+                            let msg = "MessagePort in transfer list is already detached";
+                            return ctx.throw_type_error(msg).into();
+                        }
+                    }
+                    Self::mark_closed(&port);
+                }
+            }
+        }
+
+        if let Some(tx) = self.channel.borrow().as_ref().and_then(|c| c.tx.as_ref()) {
+            let _ = tx.send(PortMessage::Data(data, shared, channels));
+        }
+        JsValue::UnDefined
+    }
+
+    fn js_try_recv(&self, _this: &mut JsObject, ctx: &mut Context, _argv: &[JsValue]) -> JsValue {
+        let msg = self
+            .channel
+            .borrow()
+            .as_ref()
+            .and_then(|c| c.rx.try_lock().ok())
+            .and_then(|mut rx| rx.try_recv().ok());
+        match msg {
+            Some(msg) => Self::message_to_js(ctx, msg),
+            None => JsValue::UnDefined,
+        }
+    }
+
+    fn js_cancel_recv(
+        &self,
+        _this: &mut JsObject,
+        _ctx: &mut Context,
+        _argv: &[JsValue],
+    ) -> JsValue {
+        self.cancel.notify_waiters();
+        JsValue::UnDefined
+    }
+
+    fn js_close(&self, this: &mut JsObject, _ctx: &mut Context, _argv: &[JsValue]) -> JsValue {
+        self.channel.take();
+        Self::mark_closed(&this.clone().into());
+        JsValue::UnDefined
+    }
+
+    /// Resolves to the next message, `null` once the other side is gone, or
+    /// `undefined` if cancelled by `cancelRecv()`.
+    fn js_recv(this: JsClassRef<Self>, mut ctx: Context, _argv: Vec<JsValue>) -> JsClassFuture {
+        let rx = this.channel.borrow().as_ref().map(|c| c.rx.clone());
+        let cancel = this.cancel.clone();
+        Box::pin(async move {
+            let _this = this;
+            let rx = match rx {
+                Some(rx) => rx,
+                None => return Ok(JsValue::Null),
+            };
+            let cancelled = cancel.notified();
+            tokio::pin!(cancelled);
+            let msg = tokio::select! {
+                msg = async { rx.lock().await.recv().await } => msg,
+                _ = &mut cancelled => return Ok(JsValue::UnDefined),
+            };
+            match msg {
+                Some(msg) => Ok(Self::message_to_js(&mut ctx, msg)),
+                None => Ok(JsValue::Null),
+            }
+        })
+    }
+
+    fn js_create_channel(ctx: &mut Context, _this: JsValue, _argv: &[JsValue]) -> JsValue {
+        let (port1, port2) = PortChannel::pair();
+        let mut ports = ctx.new_array();
+        ports.put(0, Self::wrap_obj(ctx, MessagePort::new(port1)));
+        ports.put(1, Self::wrap_obj(ctx, MessagePort::new(port2)));
+        ports.into()
+    }
+}
+
+impl JsClassDef for MessagePort {
+    type RefType = Self;
+
+    const CLASS_NAME: &'static str = "MessagePort";
+
+    const CONSTRUCTOR_ARGC: u8 = 0;
+
+    const FIELDS: &'static [JsClassField<Self::RefType>] = &[];
+
+    const METHODS: &'static [JsClassMethod<Self::RefType>] = &[];
+
+    // `cancelRecv()` and `close()` must work while a `recv()` is pending
+    const SHARED_METHODS: &'static [JsClassSharedMethod<Self::RefType>] = &[
+        ("postMessage", 2, Self::js_post_message),
+        ("tryRecv", 0, Self::js_try_recv),
+        ("cancelRecv", 0, Self::js_cancel_recv),
+        ("close", 0, Self::js_close),
+    ];
+
+    const STATIC_METHODS: &'static [JsClassStaticMethod] =
+        &[("createChannel", 0, Self::js_create_channel)];
+
+    const ASYNC_METHODS: &'static [JsClassAsyncMethod<Self::RefType>] =
+        &[("recv", 0, Self::js_recv)];
+
+    unsafe fn mut_class_id_ptr() -> &'static mut u32 {
+        static mut CLASS_ID: u32 = 0;
+        &mut CLASS_ID
+    }
+
+    fn constructor_fn(ctx: &mut Context, _argv: &[JsValue]) -> Result<Self::RefType, JsValue> {
+        Err(ctx.throw_type_error("Illegal constructor").into())
+    }
+}
+
+const MB: usize = 1024 * 1024;
+
+static NEXT_THREAD_ID: AtomicU32 = AtomicU32::new(1);
+
+/// State shared by a worker and the `Worker` object that started it.
+struct WorkerShared {
+    thread_id: u32,
+    exit_code: AtomicI32,
+    terminate: Notify,
+    to_parent: UnboundedSender<PortMessage>,
+}
+
+struct WorkerControl(Arc<WorkerShared>);
+
+impl WorkerControl {
+    fn js_thread_id(data: &Self, _ctx: &mut Context) -> JsValue {
+        JsValue::Int(data.0.thread_id as i32)
+    }
+
+    fn js_terminate(
+        &mut self,
+        _this: &mut JsObject,
+        _ctx: &mut Context,
+        _argv: &[JsValue],
+    ) -> JsValue {
+        self.0.exit_code.store(1, Ordering::SeqCst);
+        self.0.terminate.notify_one();
+        JsValue::UnDefined
+    }
+
+    /// `process.exit()` inside the worker: stop the worker and interrupt the running JS.
+    fn js_exit(&mut self, _this: &mut JsObject, ctx: &mut Context, argv: &[JsValue]) -> JsValue {
+        let code = match argv.get(0) {
+            Some(JsValue::Int(code)) => *code,
+            _ => 0,
+        };
+        self.0.exit_code.store(code, Ordering::SeqCst);
+        self.0.terminate.notify_one();
+        if let Some(event_loop) = ctx.event_loop() {
+            event_loop.deadline = Some(std::time::Instant::now());
+        }
+        ctx.throw_uncatchable_error("worker is exiting").into()
+    }
+
+    fn js_report_error(
+        &mut self,
+        _this: &mut JsObject,
+        _ctx: &mut Context,
+        argv: &[JsValue],
+    ) -> JsValue {
+        if let Some(JsValue::ArrayBuffer(error)) = argv.get(0) {
+            let _ = self.0.to_parent.send(PortMessage::Error(error.to_vec()));
+        }
+        JsValue::UnDefined
+    }
+}
+
+impl JsClassDef for WorkerControl {
+    type RefType = Self;
+
+    const CLASS_NAME: &'static str = "WorkerControl";
+
+    const CONSTRUCTOR_ARGC: u8 = 0;
+
+    const FIELDS: &'static [JsClassField<Self::RefType>] =
+        &[("threadId", Self::js_thread_id, None)];
+
+    const METHODS: &'static [JsClassMethod<Self::RefType>] = &[
+        ("terminate", 0, Self::js_terminate),
+        ("exit", 1, Self::js_exit),
+        ("reportError", 1, Self::js_report_error),
+    ];
+
+    unsafe fn mut_class_id_ptr() -> &'static mut u32 {
+        static mut CLASS_ID: u32 = 0;
+        &mut CLASS_ID
+    }
+
+    fn constructor_fn(ctx: &mut Context, _argv: &[JsValue]) -> Result<Self::RefType, JsValue> {
+        Err(ctx.throw_type_error("Illegal constructor").into())
+    }
+}
+
+struct WorkerInit {
+    filename: String,
+    eval: bool,
+    worker_data: (Vec<u8>, Vec<SharedBuffer>),
+    resource_limits: Vec<u8>,
+    port: PortChannel,
+    shared: Arc<WorkerShared>,
+}
+
+thread_local! {
+    // handed from `run_worker` to the worker's `worker_threads` module while it loads
+    static PENDING_WORKER_INIT: RefCell<Option<WorkerInit>> = RefCell::new(None);
+}
+
+fn take_worker_init(ctx: &mut Context, _this: JsValue, _argv: &[JsValue]) -> JsValue {
+    let init = match PENDING_WORKER_INIT.with(|init| init.borrow_mut().take()) {
+        Some(init) => init,
+        None => return JsValue::UnDefined,
+    };
+    let (data, shared) = &init.worker_data;
+    let worker_data = match ctx.deserialize_shared(data, shared) {
+        Ok(v) => v,
+        Err(e) => return ctx.throw_error(e).into(),
+    };
+    let resource_limits = match ctx.deserialize(&init.resource_limits) {
+        Ok(v) => v,
+        Err(e) => return ctx.throw_error(e).into(),
+    };
+    let mut obj = ctx.new_object();
+    obj.set("filename", ctx.new_string(&init.filename).into());
+    obj.set("eval", JsValue::Bool(init.eval));
+    obj.set("workerData", worker_data);
+    obj.set("resourceLimits", resource_limits);
+    obj.set("threadId", JsValue::Int(init.shared.thread_id as i32));
+    obj.set(
+        "port",
+        MessagePort::wrap_obj(ctx, MessagePort::new(init.port)),
+    );
+    obj.set(
+        "control",
+        WorkerControl::wrap_obj(ctx, WorkerControl(init.shared)),
+    );
+    obj.into()
+}
+
+/// A worker's future, which owns its `Runtime` and so is not `Send`.
+///
+/// Workers run on the same thread as their parent, like everything else driven by the
+/// current-thread tokio runtime a `Runtime` requires; `spawn_worker` asserts that, as a
+/// multi-threaded runtime could move the future to another thread.
+struct LocalFuture<F>(F);
+
+unsafe impl<F> Send for LocalFuture<F> {}
+
+impl<F: Future> Future for LocalFuture<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> Poll<Self::Output> {
+        unsafe { self.map_unchecked_mut(|f| &mut f.0) }.poll(cx)
+    }
+}
+
+/// Serializes an error for the parent's `error` event, as a string if it can't be cloned.
+fn serialize_error(ctx: &mut Context, error: &JsValue) -> Vec<u8> {
+    ctx.serialize(error)
+        .or_else(|_| {
+            let s = ctx.value_to_string(error);
+            ctx.serialize(&s)
+        })
+        .unwrap_or_default()
+}
+
+const WORKER_BOOTSTRAP: &str = "import 'worker_threads';\n";
+
+async fn run_worker(init: WorkerInit, memory_limit: usize, stack_size: usize) {
+    let shared = init.shared.clone();
+    let mut rt = Runtime::new();
+    if memory_limit > 0 {
+        rt.set_memory_limit(memory_limit);
+    }
+    if stack_size > 0 {
+        rt.set_max_stack_size(stack_size);
+    }
+    // an uncaught exception becomes an `error` event on the `Worker`, sent before the exit
+    rt.run_with_context(|ctx| {
+        if let Some(event_loop) = ctx.event_loop() {
+            let to_parent = shared.to_parent.clone();
+            event_loop.fatal_error_hook = Some(Box::new(move |ctx, error| {
+                let error = serialize_error(ctx, &error);
+                let _ = to_parent.send(PortMessage::Error(error));
+            }));
+        }
+    });
+
+    PENDING_WORKER_INIT.with(|pending| pending.borrow_mut().replace(init));
+    rt.run_with_context(|ctx| {
+        ctx.eval_module_str(WORKER_BOOTSTRAP.to_string(), "[worker]");
+    });
+    PENDING_WORKER_INIT.with(|pending| pending.borrow_mut().take());
+
+    tokio::select! {
+        _ = &mut rt => {},
+        _ = shared.terminate.notified() => {},
+    }
+    if rt.take_uncaught_exception() {
+        shared.exit_code.store(1, Ordering::SeqCst);
+    }
+    rt.cancel_pending_tasks().await;
+    drop(rt);
+
+    let code = shared.exit_code.load(Ordering::SeqCst);
+    log::trace!("worker {} exit {}", shared.thread_id, code);
+    let _ = shared.to_parent.send(PortMessage::Exit(code));
+}
+
+/// `spawnWorker(filename, eval, workerData, resourceLimits)` clones `workerData` and
+/// `resourceLimits` into the worker and returns `[port, control]` for the parent side.
+/// The memory limit is the sum of the old and young generation sizes.
+fn spawn_worker(ctx: &mut Context, _this: JsValue, argv: &[JsValue]) -> JsValue {
+    let filename = if let Some(JsValue::String(filename)) = argv.get(0) {
+        filename.to_string()
+    } else {
+        return ctx.throw_type_error("filename must be a string").into();
+    };
+    let eval = matches!(argv.get(1), Some(JsValue::Bool(true)));
+    let worker_data = argv.get(2).cloned().unwrap_or(JsValue::UnDefined);
+    let worker_data = match ctx.serialize_shared(&worker_data) {
+        Ok(r) => r,
+        Err(e) => return ctx.throw_error(e).into(),
+    };
+    let resource_limits = argv.get(3).cloned().unwrap_or(JsValue::UnDefined);
+    let (memory_limit, stack_size) = if let JsValue::Object(limits) = &resource_limits {
+        let mb = |v: JsValue| match v {
+            JsValue::Int(n) if n > 0 => (n as usize).saturating_mul(MB),
+            JsValue::Float(n) if n > 0.0 => (n * MB as f64) as usize,
+            _ => 0,
+        };
+        let memory_limit = mb(limits.get("maxOldGenerationSizeMb"))
+            .saturating_add(mb(limits.get("maxYoungGenerationSizeMb")));
+        (memory_limit, mb(limits.get("stackSizeMb")))
+    } else {
+        (0, 0)
+    };
+    let resource_limits = match ctx.serialize(&resource_limits) {
+        Ok(r) => r,
+        Err(e) => return ctx.throw_error(e).into(),
+    };
+
+    let (to_worker, from_parent) = unbounded_channel();
+    let (to_parent, from_worker) = unbounded_channel();
+    let shared = Arc::new(WorkerShared {
+        thread_id: NEXT_THREAD_ID.fetch_add(1, Ordering::SeqCst),
+        exit_code: AtomicI32::new(0),
+        terminate: Notify::new(),
+        to_parent: to_parent.clone(),
+    });
+    let init = WorkerInit {
+        filename,
+        eval,
+        worker_data,
+        resource_limits,
+        port: PortChannel::new(to_parent, from_parent),
+        shared: shared.clone(),
+    };
+    assert_eq!(
+        tokio::runtime::Handle::current().runtime_flavor(),
+        tokio::runtime::RuntimeFlavor::CurrentThread,
+        "workers need a current-thread tokio runtime"
+    );
+    tokio::task::spawn(LocalFuture(run_worker(init, memory_limit, stack_size)));
+
+    let mut r = ctx.new_array();
+    let port = MessagePort::wrap_obj(
+        ctx,
+        MessagePort::new(PortChannel::new(to_worker, from_worker)),
+    );
+    r.put(0, port);
+    r.put(1, WorkerControl::wrap_obj(ctx, WorkerControl(shared)));
+    r.into()
+}
+
+fn detach_array_buffer(_ctx: &mut Context, _this: JsValue, argv: &[JsValue]) -> JsValue {
+    if let Some(JsValue::ArrayBuffer(buf)) = argv.get(0) {
+        buf.detach();
+    }
+    JsValue::UnDefined
+}
+
+pub fn init_module(ctx: &mut Context) {
+    ctx.register_fn_module(
+        "_node:worker_threads\0",
+        &[
+            MessagePort::CLASS_NAME,
+            WorkerControl::CLASS_NAME,
+            "spawnWorker",
+            "takeWorkerInit",
+            "detachArrayBuffer",
+        ],
+        |ctx, m| {
+            let class_ctor = register_class::<MessagePort>(ctx);
+            m.add_export(MessagePort::CLASS_NAME, class_ctor);
+
+            let class_ctor = register_class::<WorkerControl>(ctx);
+            m.add_export(WorkerControl::CLASS_NAME, class_ctor);
+
+            let f = ctx.wrap_function("spawnWorker", spawn_worker);
+            m.add_export("spawnWorker", f.into());
+
+            let f = ctx.wrap_function("takeWorkerInit", take_worker_init);
+            m.add_export("takeWorkerInit", f.into());
+
+            let f = ctx.wrap_function("detachArrayBuffer", detach_array_buffer);
+            m.add_export("detachArrayBuffer", f.into());
+        },
+    )
+}
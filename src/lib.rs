@@ -6,6 +6,7 @@ mod event_loop;
 mod internal_module;
 mod quickjs_sys;
 
-pub use event_loop::EventLoop;
+pub use event_loop::handle::{HandleId, ResourceKind};
+pub use event_loop::{EventLoop, RuntimeHandle};
 
 pub use quickjs_sys::*;
@@ -1,47 +1,573 @@
 #![allow(dead_code, unused_imports, unused_must_use)]
 
+mod repl;
+
 use std::borrow::{Borrow, BorrowMut};
+use std::io::Read;
 use wasmedge_quickjs::*;
 
-fn args_parse() -> (String, Vec<String>) {
+// `eval_buf` flags
+const EVAL_TYPE_GLOBAL: u32 = 0;
+const EVAL_TYPE_MODULE: u32 = 1;
+
+#[derive(Default)]
+struct Args {
+    file: Option<String>,
+    eval: Option<String>,
+    print: Option<String>,
+    check: bool,
+    input_type: String,
+    repl: bool,
+    test: bool,
+    test_reporter: String,
+    cpu_prof: bool,
+    cpu_prof_dir: String,
+    cpu_prof_name: String,
+    cpu_prof_interval: u64,
+    expose_gc: bool,
+    rest: Vec<String>,
+}
+
+/// Parses the command line `argv`, including the program name.
+fn args_parse(argv: Vec<String>) -> Args {
     use argparse::ArgumentParser;
+    let mut args = Args::default();
+    args.cpu_prof_interval = 1000;
     let mut file_path = String::new();
-    let mut res_args: Vec<String> = vec![];
     {
         let mut ap = ArgumentParser::new();
+        ap.refer(&mut args.repl).add_option(
+            &["-i", "--repl"],
+            argparse::StoreTrue,
+            "start the interactive REPL",
+        );
+        ap.refer(&mut args.eval).add_option(
+            &["-e", "--eval"],
+            argparse::StoreOption,
+            "evaluate script",
+        );
+        ap.refer(&mut args.print).add_option(
+            &["-p", "--print"],
+            argparse::StoreOption,
+            "evaluate script and print result",
+        );
+        ap.refer(&mut args.check).add_option(
+            &["-c", "--check"],
+            argparse::StoreTrue,
+            "syntax check script without executing",
+        );
+        ap.refer(&mut args.input_type).add_option(
+            &["--input-type"],
+            argparse::Store,
+            "set module type for string input: module or commonjs",
+        );
+        ap.refer(&mut args.test).add_option(
+            &["--test"],
+            argparse::StoreTrue,
+            "run the *.test.js files found under the given paths (default: .)",
+        );
+        ap.refer(&mut args.test_reporter).add_option(
+            &["--test-reporter"],
+            argparse::Store,
+            "test output format: tap or spec",
+        );
+        ap.refer(&mut args.cpu_prof).add_option(
+            &["--cpu-prof"],
+            argparse::StoreTrue,
+            "write a .cpuprofile of the script's CPU usage on exit",
+        );
+        ap.refer(&mut args.cpu_prof_dir).add_option(
+            &["--cpu-prof-dir"],
+            argparse::Store,
+            "directory for --cpu-prof output (default: .)",
+        );
+        ap.refer(&mut args.cpu_prof_name).add_option(
+            &["--cpu-prof-name"],
+            argparse::Store,
+            "file name for --cpu-prof output",
+        );
+        ap.refer(&mut args.cpu_prof_interval).add_option(
+            &["--cpu-prof-interval"],
+            argparse::Store,
+            "sampling interval of --cpu-prof in microseconds (default: 1000)",
+        );
+        ap.refer(&mut args.expose_gc).add_option(
+            &["--expose-gc"],
+            argparse::StoreTrue,
+            "define a global gc() that runs the garbage collector",
+        );
         ap.refer(&mut file_path)
-            .add_argument("file", argparse::Store, "js file")
-            .required();
-        ap.refer(&mut res_args)
+            .add_argument("file", argparse::Store, "js file, or - for stdin");
+        ap.refer(&mut args.rest)
             .add_argument("arg", argparse::List, "arg");
-        ap.parse_args_or_exit();
+        if let Err(code) = ap.parse(argv, &mut std::io::stdout(), &mut std::io::stderr()) {
+            std::process::exit(code);
+        }
+    }
+    if !file_path.is_empty() {
+        args.file = Some(file_path);
+    }
+    args
+}
+
+/// Reads the program to run, as `(name, code, args)` where `args[0]` is the name.
+fn load_source(
+    args: &mut Args,
+    stdin: &mut dyn Read,
+) -> Option<std::io::Result<(String, String, Vec<String>)>> {
+    let mut rest = std::mem::take(&mut args.rest);
+    if let Some(code) = args.print.take().or(args.eval.take()) {
+        // with inline code, every positional argument is passed to the script
+        if let Some(file) = args.file.take() {
+            rest.insert(0, file);
+        }
+        rest.insert(0, "[eval]".to_string());
+        return Some(Ok(("[eval]".to_string(), code, rest)));
+    }
+    let file = args.file.take()?;
+    let (name, code) = if file == "-" {
+        let mut code = String::new();
+        match stdin.read_to_string(&mut code) {
+            Ok(_) => ("[stdin]".to_string(), code),
+            Err(e) => return Some(Err(e)),
+        }
+    } else {
+        match std::fs::read_to_string(&file) {
+            Ok(code) => (file, code),
+            Err(e) => return Some(Err(e)),
+        }
+    };
+    rest.insert(0, name.clone());
+    Some(Ok((name, code, rest)))
+}
+
+/// How the main program is evaluated, see `program_kind`.
+enum ProgramKind {
+    Module,
+    Script,
+    /// a global script, with the bytecode compiled while detecting its kind
+    CompiledScript(Vec<u8>),
+}
+
+/// Picks module or global script evaluation for `code`.
+///
+/// `--input-type` wins, then the `.mjs`/`.cjs` extension. Otherwise `import`/`export`
+/// statements, or syntax that only parses as a module (like top-level `await`), make it a module.
+fn program_kind(ctx: &mut Context, input_type: &str, name: &str, code: &str) -> ProgramKind {
+    match input_type {
+        "module" => return ProgramKind::Module,
+        "commonjs" => return ProgramKind::Script,
+        _ => {}
+    }
+    match std::path::Path::new(name)
+        .extension()
+        .and_then(|ext| ext.to_str())
+    {
+        Some("mjs") => ProgramKind::Module,
+        Some("cjs") => ProgramKind::Script,
+        _ if detect_module(code) => ProgramKind::Module,
+        _ => match ctx.compile_script(code, name) {
+            Ok(bytecode) => ProgramKind::CompiledScript(bytecode),
+            Err(_) => ProgramKind::Module,
+        },
+    }
+}
+
+fn report_error(ctx: &mut Context, e: &JsValue) {
+    let msg = match ctx.value_to_string(e) {
+        JsValue::String(s) => s.to_string(),
+        _ => String::new(),
+    };
+    eprintln!("{}", msg);
+    if let JsValue::Object(obj) = e {
+        if let JsValue::String(stack) = obj.get("stack") {
+            eprint!("{}", stack.to_string());
+        }
+    }
+}
+
+fn print_value(ctx: &mut Context, value: JsValue) {
+    if let JsValue::Function(log) = ctx
+        .get_global()
+        .get("console")
+        .get("log")
+        .unwrap_or(JsValue::UnDefined)
+    {
+        log.call(&[value]);
+    }
+}
+
+/// True once `process.exit()` stopped the runtime of a `--test` file.
+fn exited(ctx: &mut Context) -> bool {
+    ctx.event_loop()
+        .map_or(false, |event_loop| event_loop.exit_code().is_some())
+}
+
+/// Evaluates the main program. Returns false if it threw, after reporting the error.
+fn run_source(ctx: &mut Context, input_type: &str, name: &str, code: &str, print: bool) -> bool {
+    // `-p` prints the completion value, which only global scripts have
+    let kind = if print {
+        ProgramKind::Script
+    } else {
+        program_kind(ctx, input_type, name, code)
+    };
+    let value = match kind {
+        ProgramKind::Module => ctx.eval_buf(code.as_bytes().to_vec(), name, EVAL_TYPE_MODULE),
+        ProgramKind::Script => ctx.eval_buf(code.as_bytes().to_vec(), name, EVAL_TYPE_GLOBAL),
+        ProgramKind::CompiledScript(bytecode) => {
+            let value = ctx.eval_bytecode(&bytecode);
+            if let JsValue::Exception(e) = &value {
+                if !exited(ctx) {
+                    e.dump_error();
+                }
+            }
+            value
+        }
+    };
+    if print && !matches!(value, JsValue::Exception(_)) {
+        print_value(ctx, value.clone());
+    }
+    log::info!("{value:?}");
+    match value {
+        // a contained `process.exit()` interrupted it, see `Runtime::contain_exit`
+        _ if exited(ctx) => true,
+        // already reported
+        JsValue::Exception(_) => false,
+        // a module that threw while evaluating
+        JsValue::Promise(p) if p.state() == JsPromiseState::Rejected => {
+            report_error(ctx, &p.get_result());
+            false
+        }
+        // a top-level `await` that rejects later is uncaught, like an error thrown by a callback
+        JsValue::Promise(mut p) if p.state() == JsPromiseState::Pending => {
+            if let JsValue::Function(report) = ctx.get_global().get("reportError") {
+                p.invoke("then", &[JsValue::UnDefined, report.into()]);
+            }
+            true
+        }
+        _ => true,
+    }
+}
+
+fn is_test_file(path: &std::path::Path) -> bool {
+    let name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("");
+    [".test.js", ".test.mjs", ".test.cjs"]
+        .iter()
+        .any(|ext| name.ends_with(ext))
+}
+
+/// Collects the test files under `path`, skipping `node_modules` and hidden directories.
+fn find_test_files(path: &std::path::Path, files: &mut Vec<String>) {
+    if path.is_file() {
+        files.push(path.to_string_lossy().into_owned());
+        return;
+    }
+    let entries = match std::fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(e) => {
+            eprintln!("{}: {}", path.display(), e);
+            return;
+        }
+    };
+    let mut entries: Vec<_> = entries.filter_map(|e| e.ok()).map(|e| e.path()).collect();
+    entries.sort();
+    for entry in entries {
+        let name = entry
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or("");
+        if entry.is_dir() {
+            if name != "node_modules" && !name.starts_with('.') {
+                find_test_files(&entry, files);
+            }
+        } else if is_test_file(&entry) {
+            files.push(entry.to_string_lossy().into_owned());
+        }
     }
-    (file_path, res_args)
+}
+
+/// `--test`: runs each test file in a fresh runtime. Returns the exit code.
+async fn run_tests(paths: Vec<String>, expose_gc: bool) -> i32 {
+    let mut files = vec![];
+    if paths.is_empty() {
+        find_test_files(std::path::Path::new("."), &mut files);
+    }
+    for path in paths {
+        find_test_files(std::path::Path::new(&path), &mut files);
+    }
+    if files.is_empty() {
+        eprintln!("no test files found");
+        return 1;
+    }
+
+    let mut failed = vec![];
+    for file in files {
+        let code = match std::fs::read_to_string(&file) {
+            Ok(code) => code,
+            Err(e) => {
+                eprintln!("{}: {}", file, e);
+                failed.push(file);
+                continue;
+            }
+        };
+        let mut rt = Runtime::new();
+        if expose_gc {
+            rt.expose_gc();
+        }
+        // a file that calls `process.exit()` ends, not the whole run
+        rt.contain_exit();
+        let ok = rt.run_with_context(|ctx| {
+            ctx.put_args(vec![file.clone()]);
+            run_source(ctx, "", &file, &code, false)
+        });
+        let exit_code = rt.run_until_exit(!ok).await;
+        rt.cancel_pending_tasks().await;
+        if exit_code != 0 {
+            failed.push(file);
+        }
+    }
+    for file in &failed {
+        eprintln!("failed: {}", file);
+    }
+    if failed.is_empty() {
+        0
+    } else {
+        1
+    }
+}
+
+/// Where `--cpu-prof` writes the profile.
+fn cpu_profile_path(args: &Args) -> std::path::PathBuf {
+    let name = if args.cpu_prof_name.is_empty() {
+        let millis = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        format!("CPU.{}.cpuprofile", millis)
+    } else {
+        args.cpu_prof_name.clone()
+    };
+    std::path::Path::new(&args.cpu_prof_dir).join(name)
+}
+
+/// Runs the program `args` select, reading `-` from `stdin`. Returns the exit code.
+async fn run(mut args: Args, stdin: &mut dyn Read) -> i32 {
+    use wasmedge_quickjs as q;
+
+    if !matches!(args.input_type.as_str(), "" | "module" | "commonjs") {
+        eprintln!("--input-type must be \"module\" or \"commonjs\"");
+        return 1;
+    }
+    if !args.input_type.is_empty()
+        && args.eval.is_none()
+        && args.print.is_none()
+        && args.file.as_deref() != Some("-")
+    {
+        eprintln!("--input-type can only be used with string input via --eval, --print, or STDIN");
+        return 1;
+    }
+    let print = args.print.is_some();
+    let check = args.check;
+    let force_repl = args.repl;
+
+    if args.test {
+        match args.test_reporter.as_str() {
+            "" | "tap" | "spec" => {}
+            _ => {
+                eprintln!("--test-reporter must be \"tap\" or \"spec\"");
+                return 1;
+            }
+        }
+        if !args.test_reporter.is_empty() {
+            // read by the `test` module
+            std::env::set_var("WASMEDGE_QUICKJS_TEST_REPORTER", &args.test_reporter);
+        }
+        let mut paths = std::mem::take(&mut args.rest);
+        if let Some(file) = args.file.take() {
+            paths.insert(0, file);
+        }
+        return run_tests(paths, args.expose_gc).await;
+    }
+
+    let mut rt = q::Runtime::new();
+    if args.expose_gc {
+        rt.expose_gc();
+    }
+
+    let (name, code, rest_arg) = match load_source(&mut args, stdin) {
+        Some(Ok(source)) => source,
+        Some(Err(e)) => {
+            eprintln!("{}", e.to_string());
+            return 1;
+        }
+        None => {
+            rt.run_with_context(|ctx| ctx.put_args(vec![String::new()]));
+            repl::run(&mut rt).await;
+            return 0;
+        }
+    };
+    let input_type = args.input_type.clone();
+
+    if check {
+        let result = rt.run_with_context(|ctx| {
+            let result = match program_kind(ctx, &input_type, &name, &code) {
+                ProgramKind::Module => ctx.compile_module(&code, &name).map(|_| ()),
+                ProgramKind::Script => ctx.compile_script(&code, &name).map(|_| ()),
+                ProgramKind::CompiledScript(_) => Ok(()),
+            };
+            result.map_err(|e| report_error(ctx, &e))
+        });
+        return if result.is_err() { 1 } else { 0 };
+    }
+
+    let cpu_profile = if args.cpu_prof {
+        let path = cpu_profile_path(&args);
+        rt.start_profiling(std::time::Duration::from_micros(
+            args.cpu_prof_interval.max(1),
+        ));
+        rt.save_profile_on_exit(path.clone());
+        Some(path)
+    } else {
+        None
+    };
+
+    let failed = rt.run_with_context(|ctx| {
+        ctx.put_args(&rest_arg);
+        !run_source(ctx, &input_type, &name, &code, print)
+    });
+
+    if force_repl && !failed {
+        // `--repl` with a script: continue interactively once the script has finished
+        (&mut rt).await;
+        repl::run(&mut rt).await;
+        return 0;
+    }
+
+    let code = rt.run_until_exit(failed).await;
+    if let (Some(path), Some(profile)) = (cpu_profile, rt.stop_profiling()) {
+        if let Err(e) = profile.save(&path) {
+            eprintln!("{}: {}", path.display(), e);
+        }
+    }
+    code
 }
 
 #[tokio::main(flavor = "current_thread")]
 async fn main() {
-    use wasmedge_quickjs as q;
     env_logger::init();
 
-    let mut rt = q::Runtime::new();
+    let args = args_parse(std::env::args().collect());
+    let code = run(args, &mut std::io::stdin()).await;
+    if code != 0 {
+        std::process::exit(code);
+    }
+}
 
-    let r = rt
-        .async_run_with_context(Box::new(|ctx| {
-            let (file_path, mut rest_arg) = args_parse();
-            let code = std::fs::read_to_string(&file_path);
-            match code {
-                Ok(code) => {
-                    rest_arg.insert(0, file_path.clone());
-                    ctx.put_args(rest_arg);
-                    ctx.eval_buf(code.into_bytes(), &file_path, 1)
-                }
-                Err(e) => {
-                    eprintln!("{}", e.to_string());
-                    JsValue::UnDefined
-                }
-            }
-        }))
-        .await;
-    log::info!("{r:?}");
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Runs the CLI with `argv` and `stdin`, returning the exit code.
+    fn run_cli(argv: &[&str], stdin: &str) -> i32 {
+        let mut args = vec!["wasmedge_quickjs".to_string()];
+        args.extend(argv.iter().map(|arg| arg.to_string()));
+        let args = args_parse(args);
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+            .block_on(run(args, &mut stdin.as_bytes()))
+    }
+
+    #[test]
+    fn test_missing_script() {
+        assert_eq!(run_cli(&["test/does-not-exist.js"], ""), 1);
+    }
+
+    #[test]
+    fn test_top_level_await_exit_code() {
+        let late_reject =
+            "await new Promise((resolve, reject) => setTimeout(() => reject(new Error('late')), 1))";
+        assert_eq!(run_cli(&["-e", late_reject], ""), 1);
+
+        // reported as uncaught, so `uncaughtException` listeners see it
+        let handled = format!(
+            "import process from 'process';
+            process.on('uncaughtException', (e) => {{ process.exitCode = e.message === 'late' ? 3 : 4 }});
+            {}",
+            late_reject
+        );
+        assert_eq!(run_cli(&["-e", &handled], ""), 3);
+
+        let resolved = "import process from 'process';
+            await new Promise((resolve) => setTimeout(resolve, 1));
+            process.exitCode = 2";
+        assert_eq!(run_cli(&["-e", resolved], ""), 2);
+    }
+
+    #[test]
+    fn test_eval_and_print() {
+        let eval = "import process from 'process'; process.exitCode = process.argv.includes('extra') ? 3 : 4";
+        assert_eq!(run_cli(&["-e", eval, "extra"], ""), 3);
+        assert_eq!(run_cli(&["--eval", "throw new Error('x')"], ""), 1);
+
+        // `-p` hands the completion value to console.log
+        let print =
+            "console.log = (v) => import('process').then(({ default: p }) => { p.exitCode = v }); 6 * 7";
+        assert_eq!(run_cli(&["-p", print], ""), 42);
+    }
+
+    #[test]
+    fn test_stdin() {
+        let code = "import process from 'process'; process.exitCode = 5";
+        assert_eq!(run_cli(&["-"], code), 5);
+        assert_eq!(run_cli(&["-"], "syntax error ("), 1);
+    }
+
+    #[test]
+    fn test_check() {
+        // only compiled: the exit code is never set
+        let code = "import process from 'process'; process.exitCode = 5";
+        assert_eq!(run_cli(&["--check", "-"], code), 0);
+        assert_eq!(run_cli(&["-c", "-"], "await 1; process.exitCode = 5"), 0);
+        assert_eq!(run_cli(&["--check", "-"], "let a = ;"), 1);
+        assert_eq!(run_cli(&["--check", "test/does-not-exist.js"], ""), 1);
+    }
+
+    #[test]
+    fn test_process_exit_in_test_file() {
+        let exit_0 = "test/test_runner/exit/exit-0.js";
+        let exit_3 = "test/test_runner/exit/exit-3.js";
+        let pass = "test/test_runner/test-test-pass.js";
+        let fail = "test/test_runner/test-test-fail.js";
+        // the runner goes on to the next file
+        assert_eq!(run_cli(&["--test", exit_0, pass], ""), 0);
+        assert_eq!(run_cli(&["--test", exit_0, fail], ""), 1);
+        assert_eq!(run_cli(&["--test", exit_3, pass], ""), 1);
+    }
+
+    #[test]
+    fn test_input_type_and_detection() {
+        let this_is = "import('process').then(({ default: p }) => { p.exitCode = this === undefined ? 6 : 7 })";
+        // detected as a script, where `this` is the global object
+        assert_eq!(run_cli(&["-e", this_is], ""), 7);
+        assert_eq!(run_cli(&["--input-type", "commonjs", "-e", this_is], ""), 7);
+        assert_eq!(run_cli(&["--input-type", "module", "-e", this_is], ""), 6);
+        assert_eq!(run_cli(&["--input-type", "module", "-"], this_is), 6);
+        // top-level `await` is a syntax error in a script
+        assert_eq!(
+            run_cli(&["--input-type", "commonjs", "-e", "await 1"], ""),
+            1
+        );
+
+        assert_eq!(run_cli(&["--input-type", "json", "-e", "1"], ""), 1);
+        // files go by their extension and content, never the flag
+        assert_eq!(
+            run_cli(&["--input-type", "module", "test/common.js"], ""),
+            1
+        );
+    }
 }
@@ -1,3 +1,5 @@
+use crate::event_loop::handle::{HandleId, ResourceKind};
+use crate::quickjs_sys::make_c_string;
 use crate::quickjs_sys::qjs::*;
 use crate::{Context, EventLoop, JsObject, JsRef, JsValue};
 
@@ -12,6 +14,32 @@ fn parse_c_string(s: &mut String) {
     }
 }
 
+/// Why `cell` can't be used: it is closed, or `needs_mut` and an async method holds it.
+fn cell_unavailable<Def: JsClassDef>(
+    cell: &JsClassCell<Def::RefType>,
+    needs_mut: bool,
+) -> Option<String> {
+    if cell.closed.get() {
+        Some(format!("{} is closed", Def::CLASS_NAME))
+    } else if needs_mut && cell.busy.get() {
+        Some(format!("{} is busy", Def::CLASS_NAME))
+    } else {
+        None
+    }
+}
+
+/// Throws a `TypeError` if `cell` can't be used, see `cell_unavailable`.
+fn check_cell<Def: JsClassDef>(
+    ctx: &mut Context,
+    cell: &JsClassCell<Def::RefType>,
+    needs_mut: bool,
+) -> Result<(), JSValue> {
+    match cell_unavailable::<Def>(cell, needs_mut) {
+        Some(msg) => Err(JsValue::Exception(ctx.throw_type_error(&msg)).into_qjs_value()),
+        None => Ok(()),
+    }
+}
+
 unsafe extern "C" fn js_method_magic_trampoline<Def: JsClassDef>(
     ctx: *mut JSContext,
     this_val: JSValue,
@@ -22,9 +50,15 @@ unsafe extern "C" fn js_method_magic_trampoline<Def: JsClassDef>(
     let mut n_ctx = std::mem::ManuallyDrop::new(Context { ctx });
 
     let class_id = Def::class_id();
-    let data = JS_GetOpaque(this_val, class_id) as *mut Def::RefType;
-    if data.is_null() {
-        return JsValue::Exception(n_ctx.throw_type_error("Invalid Class")).into_qjs_value();
+    let cell_ptr = JS_GetOpaque(this_val, class_id) as *mut JsClassCell<Def::RefType>;
+    let cell = match cell_ptr.as_ref() {
+        Some(cell) => cell,
+        None => {
+            return JsValue::Exception(n_ctx.throw_type_error("Invalid Class")).into_qjs_value()
+        }
+    };
+    if let Err(e) = check_cell::<Def>(&mut n_ctx, cell, true) {
+        return e;
     }
 
     let mut arg_vec = vec![];
@@ -35,7 +69,7 @@ unsafe extern "C" fn js_method_magic_trampoline<Def: JsClassDef>(
         arg_vec.push(v);
     }
 
-    let data = data.as_mut().unwrap();
+    let data = &mut (*cell_ptr).data;
     let mut this_obj = JsValue::from_qjs_value(ctx, JS_DupValue_real(ctx, this_val))
         .to_obj()
         .unwrap();
@@ -44,6 +78,46 @@ unsafe extern "C" fn js_method_magic_trampoline<Def: JsClassDef>(
     r.into_qjs_value()
 }
 
+unsafe extern "C" fn js_shared_method_magic_trampoline<Def: JsClassDef>(
+    ctx: *mut JSContext,
+    this_val: JSValue,
+    len: i32,
+    argv: *mut JSValue,
+    magic: i32,
+) -> JSValue {
+    let mut n_ctx = std::mem::ManuallyDrop::new(Context { ctx });
+
+    let class_id = Def::class_id();
+    let cell_ptr = JS_GetOpaque(this_val, class_id) as *mut JsClassCell<Def::RefType>;
+    let cell = match cell_ptr.as_ref() {
+        Some(cell) => cell,
+        None => {
+            return JsValue::Exception(n_ctx.throw_type_error("Invalid Class")).into_qjs_value()
+        }
+    };
+    if let Err(e) = check_cell::<Def>(&mut n_ctx, cell, false) {
+        return e;
+    }
+
+    let mut arg_vec = vec![];
+    for i in 0..len {
+        let arg = argv.offset(i as isize);
+        let v = *arg;
+        let v = JsValue::from_qjs_value(ctx, JS_DupValue_real(ctx, v));
+        arg_vec.push(v);
+    }
+
+    let mut this_obj = JsValue::from_qjs_value(ctx, JS_DupValue_real(ctx, this_val))
+        .to_obj()
+        .unwrap();
+
+    if let Some((_, _, f)) = Def::SHARED_METHODS.get(magic as usize) {
+        f(&cell.data, &mut this_obj, &mut n_ctx, &arg_vec).into_qjs_value()
+    } else {
+        js_undefined()
+    }
+}
+
 unsafe extern "C" fn getter_magic_trampoline<Def: JsClassDef>(
     ctx: *mut JSContext,
     this_val: JSValue,
@@ -52,12 +126,18 @@ unsafe extern "C" fn getter_magic_trampoline<Def: JsClassDef>(
     let mut n_ctx = std::mem::ManuallyDrop::new(Context { ctx });
 
     let class_id = Def::class_id();
-    let data = JS_GetOpaque(this_val, class_id) as *mut Def::RefType;
-    if data.is_null() {
-        return JsValue::Exception(n_ctx.throw_type_error("Invalid Class")).into_qjs_value();
+    let cell_ptr = JS_GetOpaque(this_val, class_id) as *mut JsClassCell<Def::RefType>;
+    let cell = match cell_ptr.as_ref() {
+        Some(cell) => cell,
+        None => {
+            return JsValue::Exception(n_ctx.throw_type_error("Invalid Class")).into_qjs_value()
+        }
+    };
+    if let Err(e) = check_cell::<Def>(&mut n_ctx, cell, false) {
+        return e;
     }
 
-    let data = data.as_mut().unwrap();
+    let data = &cell.data;
     let r = Def::field_get(data, magic as usize, &mut n_ctx);
     r.into_qjs_value()
 }
@@ -71,18 +151,238 @@ unsafe extern "C" fn setter_magic_trampoline<Def: JsClassDef>(
     let mut n_ctx = std::mem::ManuallyDrop::new(Context { ctx });
 
     let class_id = Def::class_id();
-    let data = JS_GetOpaque(this_val, class_id) as *mut Def::RefType;
-    if data.is_null() {
-        return JsValue::Exception(n_ctx.throw_type_error("Invalid Class")).into_qjs_value();
+    let cell_ptr = JS_GetOpaque(this_val, class_id) as *mut JsClassCell<Def::RefType>;
+    let cell = match cell_ptr.as_ref() {
+        Some(cell) => cell,
+        None => {
+            return JsValue::Exception(n_ctx.throw_type_error("Invalid Class")).into_qjs_value()
+        }
+    };
+    if let Err(e) = check_cell::<Def>(&mut n_ctx, cell, true) {
+        return e;
     }
 
-    let data = data.as_mut().unwrap();
+    let data = &mut (*cell_ptr).data;
     let val = JsValue::from_qjs_value(ctx, JS_DupValue_real(ctx, val));
 
     Def::field_set(data, magic as usize, &mut n_ctx, val);
     js_undefined()
 }
 
+unsafe extern "C" fn js_static_method_magic_trampoline<Def: JsClassDef>(
+    ctx: *mut JSContext,
+    this_val: JSValue,
+    len: i32,
+    argv: *mut JSValue,
+    magic: i32,
+) -> JSValue {
+    let mut n_ctx = std::mem::ManuallyDrop::new(Context { ctx });
+
+    let mut arg_vec = vec![];
+    for i in 0..len {
+        let arg = argv.offset(i as isize);
+        let v = *arg;
+        let v = JsValue::from_qjs_value(ctx, JS_DupValue_real(ctx, v));
+        arg_vec.push(v);
+    }
+
+    let this_val = JsValue::from_qjs_value(ctx, JS_DupValue_real(ctx, this_val));
+    if let Some((_, _, f)) = Def::STATIC_METHODS.get(magic as usize) {
+        f(&mut n_ctx, this_val, &arg_vec).into_qjs_value()
+    } else {
+        js_undefined()
+    }
+}
+
+unsafe extern "C" fn js_symbol_method_magic_trampoline<Def: JsClassDef>(
+    ctx: *mut JSContext,
+    this_val: JSValue,
+    len: i32,
+    argv: *mut JSValue,
+    magic: i32,
+) -> JSValue {
+    let mut n_ctx = std::mem::ManuallyDrop::new(Context { ctx });
+
+    let class_id = Def::class_id();
+    let cell_ptr = JS_GetOpaque(this_val, class_id) as *mut JsClassCell<Def::RefType>;
+    let cell = match cell_ptr.as_ref() {
+        Some(cell) => cell,
+        None => {
+            return JsValue::Exception(n_ctx.throw_type_error("Invalid Class")).into_qjs_value()
+        }
+    };
+    if let Err(e) = check_cell::<Def>(&mut n_ctx, cell, true) {
+        return e;
+    }
+
+    let mut arg_vec = vec![];
+    for i in 0..len {
+        let arg = argv.offset(i as isize);
+        let v = *arg;
+        let v = JsValue::from_qjs_value(ctx, JS_DupValue_real(ctx, v));
+        arg_vec.push(v);
+    }
+
+    let data = &mut (*cell_ptr).data;
+    let mut this_obj = JsValue::from_qjs_value(ctx, JS_DupValue_real(ctx, this_val))
+        .to_obj()
+        .unwrap();
+
+    if let Some((_, _, f)) = Def::SYMBOL_METHODS.get(magic as usize) {
+        f(data, &mut this_obj, &mut n_ctx, &arg_vec).into_qjs_value()
+    } else {
+        js_undefined()
+    }
+}
+
+unsafe extern "C" fn js_symbol_getter_magic_trampoline<Def: JsClassDef>(
+    ctx: *mut JSContext,
+    this_val: JSValue,
+    _len: i32,
+    _argv: *mut JSValue,
+    magic: i32,
+) -> JSValue {
+    let mut n_ctx = std::mem::ManuallyDrop::new(Context { ctx });
+
+    let class_id = Def::class_id();
+    let cell_ptr = JS_GetOpaque(this_val, class_id) as *mut JsClassCell<Def::RefType>;
+    let cell = match cell_ptr.as_ref() {
+        // `Object.prototype.toString.call(Class.prototype)` reads the getter
+        // through the prototype itself, which has no opaque data.
+        None => return js_undefined(),
+        Some(cell) => cell,
+    };
+
+    let data = &cell.data;
+    if let Some((_, getter, _)) = Def::SYMBOL_FIELDS.get(magic as usize) {
+        getter(data, &mut n_ctx).into_qjs_value()
+    } else {
+        js_undefined()
+    }
+}
+
+unsafe extern "C" fn js_symbol_setter_magic_trampoline<Def: JsClassDef>(
+    ctx: *mut JSContext,
+    this_val: JSValue,
+    len: i32,
+    argv: *mut JSValue,
+    magic: i32,
+) -> JSValue {
+    let mut n_ctx = std::mem::ManuallyDrop::new(Context { ctx });
+
+    let class_id = Def::class_id();
+    let cell_ptr = JS_GetOpaque(this_val, class_id) as *mut JsClassCell<Def::RefType>;
+    let cell = match cell_ptr.as_ref() {
+        Some(cell) => cell,
+        None => {
+            return JsValue::Exception(n_ctx.throw_type_error("Invalid Class")).into_qjs_value()
+        }
+    };
+    if let Err(e) = check_cell::<Def>(&mut n_ctx, cell, true) {
+        return e;
+    }
+
+    let data = &mut (*cell_ptr).data;
+    let val = if len > 0 {
+        JsValue::from_qjs_value(ctx, JS_DupValue_real(ctx, *argv))
+    } else {
+        JsValue::UnDefined
+    };
+
+    if let Some((_, _, Some(setter))) = Def::SYMBOL_FIELDS.get(magic as usize) {
+        setter(data, &mut n_ctx, val);
+    }
+    js_undefined()
+}
+
+fn rejected_promise(ctx: &mut Context, msg: &str) -> JsValue {
+    let (promise, _, reject) = ctx.new_promise();
+    let err = ctx.new_error(msg);
+    if let JsValue::Function(reject) = reject {
+        reject.call(&[err]);
+    }
+    promise
+}
+
+struct JsClassCellPtr<T>(*mut JsClassCell<T>);
+unsafe impl<T> Send for JsClassCellPtr<T> {}
+
+unsafe extern "C" fn js_async_method_magic_trampoline<Def: JsClassDef>(
+    ctx: *mut JSContext,
+    this_val: JSValue,
+    len: i32,
+    argv: *mut JSValue,
+    magic: i32,
+) -> JSValue {
+    let mut n_ctx = std::mem::ManuallyDrop::new(Context { ctx });
+
+    let class_id = Def::class_id();
+    let cell_ptr = JS_GetOpaque(this_val, class_id) as *mut JsClassCell<Def::RefType>;
+    let cell = match cell_ptr.as_ref() {
+        Some(cell) => cell,
+        None => {
+            return JsValue::Exception(n_ctx.throw_type_error("Invalid Class")).into_qjs_value()
+        }
+    };
+
+    let f = match Def::ASYNC_METHODS.get(magic as usize) {
+        Some((_, _, f)) => *f,
+        None => return js_undefined(),
+    };
+
+    if let Some(msg) = cell_unavailable::<Def>(cell, true) {
+        return rejected_promise(&mut n_ctx, &msg).into_qjs_value();
+    }
+
+    let mut arg_vec = vec![];
+    for i in 0..len {
+        let arg = argv.offset(i as isize);
+        let v = *arg;
+        let v = JsValue::from_qjs_value(ctx, JS_DupValue_real(ctx, v));
+        arg_vec.push(v);
+    }
+
+    let this_obj = JsValue::from_qjs_value(ctx, JS_DupValue_real(ctx, this_val))
+        .to_obj()
+        .unwrap();
+
+    cell.busy.set(true);
+    let handle = JsClassRef {
+        cell: cell_ptr,
+        this_obj: this_obj.clone(),
+    };
+    let fut = f(handle, (*n_ctx).clone(), arg_vec);
+
+    spawn_async_method(&mut n_ctx, Def::CLASS_NAME, cell_ptr, this_obj, fut).into_qjs_value()
+}
+
+fn spawn_async_method<T: 'static>(
+    ctx: &mut Context,
+    class_name: &'static str,
+    cell_ptr: *mut JsClassCell<T>,
+    this_obj: JsObject,
+    fut: JsClassFuture,
+) -> JsValue {
+    let owner = unsafe { (*cell_ptr).handle.get() };
+    let cell_ptr = JsClassCellPtr(cell_ptr);
+    let mut err_ctx = ctx.clone();
+    ctx.future_to_promise_with(ResourceKind::Future, owner, async move {
+        // keeps the cell alive until the close notification is dropped
+        let _this_obj = this_obj;
+        let cell_ptr = cell_ptr;
+        if unsafe { (*cell_ptr.0).closed.get() } {
+            return Err(err_ctx.new_error(&format!("{} is closed", class_name)));
+        }
+        let close_notify = unsafe { &(*cell_ptr.0).close_notify };
+        tokio::select! {
+            r = fut => r,
+            _ = close_notify.notified() => {
+                Err(err_ctx.new_error(&format!("{} is closed", class_name)))
+            }
+        }
+    })
+}
+
 #[derive(Debug, Default)]
 pub struct JsClassProto {
     methods: HashMap<String, (u8, usize)>,
@@ -118,30 +418,159 @@ fn into_proto_function_list<Def: JsClassDef>(p: JsClassProto) -> &'static [JSCFu
         std::mem::forget(field_name);
     }
 
-    for (mut method_name, (argc, i)) in methods {
-        parse_c_string(&mut method_name);
-        let e = JSCFunctionListEntry {
-            name: method_name.as_ptr().cast(),
-            prop_flags: (JS_PROP_WRITABLE | JS_PROP_CONFIGURABLE) as u8,
-            def_type: JS_DEF_CFUNC as u8,
-            magic: i as i16,
-            u: JSCFunctionListEntry__bindgen_ty_1 {
-                func: JSCFunctionListEntry__bindgen_ty_1__bindgen_ty_1 {
-                    length: argc,
-                    cproto: JSCFunctionEnum_JS_CFUNC_generic_magic as u8,
-                    cfunc: JSCFunctionType {
-                        generic_magic: Some(js_method_magic_trampoline::<Def>),
-                    },
+    for (method_name, (argc, i)) in methods {
+        entry_vec.push(method_entry(
+            &method_name,
+            argc,
+            i,
+            js_method_magic_trampoline::<Def>,
+        ));
+    }
+
+    Vec::leak(entry_vec)
+}
+
+type MagicTrampoline = unsafe extern "C" fn(
+    ctx: *mut JSContext,
+    this_val: JSValue,
+    argc: i32,
+    argv: *mut JSValue,
+    magic: i32,
+) -> JSValue;
+
+/// A method that calls `trampoline` with `magic`.
+fn method_entry(
+    name: &str,
+    argc: u8,
+    magic: usize,
+    trampoline: MagicTrampoline,
+) -> JSCFunctionListEntry {
+    let mut method_name = name.to_string();
+    parse_c_string(&mut method_name);
+    let e = JSCFunctionListEntry {
+        name: method_name.as_ptr().cast(),
+        prop_flags: (JS_PROP_WRITABLE | JS_PROP_CONFIGURABLE) as u8,
+        def_type: JS_DEF_CFUNC as u8,
+        magic: magic as i16,
+        u: JSCFunctionListEntry__bindgen_ty_1 {
+            func: JSCFunctionListEntry__bindgen_ty_1__bindgen_ty_1 {
+                length: argc,
+                cproto: JSCFunctionEnum_JS_CFUNC_generic_magic as u8,
+                cfunc: JSCFunctionType {
+                    generic_magic: Some(trampoline),
                 },
             },
-        };
-        entry_vec.push(e);
-        std::mem::forget(method_name);
-    }
+        },
+    };
+    std::mem::forget(method_name);
+    e
+}
 
+/// The methods of a `(name, argc, f)` table, each calling `trampoline` with its index.
+fn into_function_list<F>(
+    methods: &[(&'static str, u8, F)],
+    trampoline: MagicTrampoline,
+) -> &'static [JSCFunctionListEntry] {
+    let entry_vec = methods
+        .iter()
+        .enumerate()
+        .map(|(i, (name, argc, _))| method_entry(name, *argc, i, trampoline))
+        .collect();
     Vec::leak(entry_vec)
 }
 
+/// What the opaque pointer of every class object points to.
+pub struct JsClassCell<T> {
+    data: T,
+    busy: std::cell::Cell<bool>,
+    closed: std::cell::Cell<bool>,
+    close_notify: tokio::sync::Notify,
+    /// the event loop handle, see `JsClassDef::RESOURCE_KIND`
+    handle: std::cell::Cell<Option<HandleId>>,
+}
+
+impl<T> JsClassCell<T> {
+    fn new(data: T) -> Self {
+        JsClassCell {
+            data,
+            busy: std::cell::Cell::new(false),
+            closed: std::cell::Cell::new(false),
+            close_notify: tokio::sync::Notify::new(),
+            handle: std::cell::Cell::new(None),
+        }
+    }
+
+    fn close(&self) {
+        self.closed.set(true);
+        self.close_notify.notify_waiters();
+    }
+}
+
+/// Registers `obj` as an event loop handle if its class has a `RESOURCE_KIND`.
+unsafe fn open_handle<Def: JsClassDef>(
+    ctx: *mut JSContext,
+    obj: JSValue,
+    cell: *mut JsClassCell<Def::RefType>,
+) {
+    let kind = match Def::RESOURCE_KIND {
+        Some(kind) => kind,
+        None => return,
+    };
+    let event_loop = match (JS_GetRuntimeOpaque(JS_GetRuntime(ctx)) as *mut EventLoop).as_mut() {
+        Some(event_loop) => event_loop,
+        None => return,
+    };
+    // neither holds a reference: the handle is closed before the object is freed
+    let object = Box::new(move || JsValue::from_qjs_value(ctx, JS_DupValue_real(ctx, obj)));
+    let finalizer = Box::new(move || (*cell).close());
+    let id = event_loop.handles.open(kind, Some(object), Some(finalizer));
+    (*cell).handle.set(Some(id));
+}
+
+/// The event loop of the runtime `js_obj` belongs to.
+unsafe fn event_loop_of(js_obj: &JsValue) -> Option<&mut EventLoop> {
+    match js_obj {
+        JsValue::Object(JsObject(JsRef { ctx, .. })) => {
+            (JS_GetRuntimeOpaque(JS_GetRuntime(*ctx)) as *mut EventLoop).as_mut()
+        }
+        _ => None,
+    }
+}
+
+/// Shared handle to the `RefType` of an object, held by an async method
+/// across await points. It keeps the JS object alive and releases the
+/// borrow when dropped; until then, methods that need `&mut` throw as busy.
+pub struct JsClassRef<T: 'static> {
+    cell: *mut JsClassCell<T>,
+    this_obj: JsObject,
+}
+
+unsafe impl<T> Send for JsClassRef<T> {}
+
+impl<T> JsClassRef<T> {
+    pub fn this_obj(&self) -> &JsObject {
+        &self.this_obj
+    }
+
+    pub fn is_closed(&self) -> bool {
+        unsafe { (*self.cell).closed.get() }
+    }
+}
+
+impl<T> Deref for JsClassRef<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { &(*self.cell).data }
+    }
+}
+
+impl<T> Drop for JsClassRef<T> {
+    fn drop(&mut self) {
+        unsafe { (*self.cell).busy.set(false) }
+    }
+}
+
 pub struct SelfRefJsValue<R, T> {
     data: T,
     val: JsValue,
@@ -182,8 +611,12 @@ pub trait JsClassTool: JsClassDef {
     {
         unsafe {
             let class_id = Self::class_id();
-            let ptr = JS_GetOpaque(js_obj.get_qjs_value(), class_id) as *mut Self::RefType;
-            let r: &'static mut <Self as JsClassDef>::RefType = ptr.as_mut()?;
+            let ptr =
+                JS_GetOpaque(js_obj.get_qjs_value(), class_id) as *mut JsClassCell<Self::RefType>;
+            if ptr.as_ref()?.busy.get() {
+                return None;
+            }
+            let r: &'static mut <Self as JsClassDef>::RefType = &mut (*ptr).data;
             match f(r) {
                 Ok(data) => Some(Ok(SelfRefJsValue {
                     data,
@@ -198,16 +631,81 @@ pub trait JsClassTool: JsClassDef {
     fn opaque_mut(js_obj: &mut JsValue) -> Option<&mut Self::RefType> {
         unsafe {
             let class_id = Self::class_id();
-            let ptr = JS_GetOpaque(js_obj.get_qjs_value(), class_id) as *mut Self::RefType;
-            ptr.as_mut()
+            let ptr =
+                JS_GetOpaque(js_obj.get_qjs_value(), class_id) as *mut JsClassCell<Self::RefType>;
+            match ptr.as_ref() {
+                Some(cell) if !cell.busy.get() => Some(&mut (*ptr).data),
+                _ => None,
+            }
         }
     }
 
     fn opaque(js_obj: &JsValue) -> Option<&Self::RefType> {
         unsafe {
             let class_id = Self::class_id();
-            let ptr = JS_GetOpaque(js_obj.get_qjs_value(), class_id) as *mut Self::RefType;
-            ptr.as_ref()
+            let ptr =
+                JS_GetOpaque(js_obj.get_qjs_value(), class_id) as *mut JsClassCell<Self::RefType>;
+            ptr.as_ref().map(|cell| &cell.data)
+        }
+    }
+
+    /// Marks the object closed: pending async methods reject and any later
+    /// method call throws. Returns false if `js_obj` is not of this class.
+    fn mark_closed(js_obj: &JsValue) -> bool {
+        unsafe {
+            let class_id = Self::class_id();
+            let ptr =
+                JS_GetOpaque(js_obj.get_qjs_value(), class_id) as *mut JsClassCell<Self::RefType>;
+            if let Some(cell) = ptr.as_ref() {
+                cell.close();
+                if let (Some(id), Some(event_loop)) = (cell.handle.take(), event_loop_of(js_obj)) {
+                    event_loop.handles.close(id);
+                }
+                true
+            } else {
+                false
+            }
+        }
+    }
+
+    /// Sets whether the object's handle keeps the event loop alive while it
+    /// has requests pending. Returns false if it has no open handle.
+    fn set_handle_ref(js_obj: &JsValue, refed: bool) -> bool {
+        unsafe {
+            let class_id = Self::class_id();
+            let ptr =
+                JS_GetOpaque(js_obj.get_qjs_value(), class_id) as *mut JsClassCell<Self::RefType>;
+            match (
+                ptr.as_ref().and_then(|cell| cell.handle.get()),
+                event_loop_of(js_obj),
+            ) {
+                (Some(id), Some(event_loop)) => event_loop.handles.set_ref(id, refed),
+                _ => false,
+            }
+        }
+    }
+
+    fn handle_has_ref(js_obj: &JsValue) -> bool {
+        unsafe {
+            let class_id = Self::class_id();
+            let ptr =
+                JS_GetOpaque(js_obj.get_qjs_value(), class_id) as *mut JsClassCell<Self::RefType>;
+            match (
+                ptr.as_ref().and_then(|cell| cell.handle.get()),
+                event_loop_of(js_obj),
+            ) {
+                (Some(id), Some(event_loop)) => event_loop.handles.has_ref(id),
+                _ => false,
+            }
+        }
+    }
+
+    fn is_closed(js_obj: &JsValue) -> bool {
+        unsafe {
+            let class_id = Self::class_id();
+            let ptr =
+                JS_GetOpaque(js_obj.get_qjs_value(), class_id) as *mut JsClassCell<Self::RefType>;
+            ptr.as_ref().map(|cell| cell.closed.get()).unwrap_or(true)
         }
     }
 
@@ -219,8 +717,9 @@ pub trait JsClassTool: JsClassDef {
             if JS_IsException_real(obj) > 0 {
                 JsValue::from_qjs_value(ctx.ctx, obj)
             } else {
-                let ptr_data = Box::leak(Box::new(data));
-                JS_SetOpaque(obj, (ptr_data as *mut Self::RefType).cast());
+                let ptr_data = Box::leak(Box::new(JsClassCell::new(data)));
+                JS_SetOpaque(obj, (ptr_data as *mut JsClassCell<Self::RefType>).cast());
+                open_handle::<Self>(ctx.ctx, obj, ptr_data);
                 JsValue::from_qjs_value(ctx.ctx, obj)
             }
         }
@@ -246,6 +745,15 @@ pub trait ExtendsJsClassDef {
     const FIELDS: &'static [JsClassField<Self::RefType>];
     const METHODS: &'static [JsClassMethod<Self::RefType>];
 
+    /// Like `JsClassDef::STATIC_FIELDS`; the base class's are not inherited.
+    const STATIC_FIELDS: &'static [JsClassStaticField] = &[];
+    /// Like `JsClassDef::STATIC_METHODS`; the base class's are not inherited.
+    const STATIC_METHODS: &'static [JsClassStaticMethod] = &[];
+    /// Like `JsClassDef::SYMBOL_FIELDS`; the base class's are not inherited.
+    const SYMBOL_FIELDS: &'static [JsClassSymbolField<Self::RefType>] = &[];
+    /// Like `JsClassDef::SYMBOL_METHODS`; the base class's are not inherited.
+    const SYMBOL_METHODS: &'static [JsClassSymbolMethod<Self::RefType>] = &[];
+
     unsafe fn mut_class_id_ptr() -> &'static mut u32;
 
     fn constructor_fn(ctx: &mut Context, argv: &[JsValue]) -> Result<Self::RefType, JsValue>;
@@ -266,6 +774,17 @@ impl<S: ExtendsJsClassDef> JsClassDef for S {
 
     const METHODS: &'static [JsClassMethod<Self::RefType>] = <Self as ExtendsJsClassDef>::METHODS;
 
+    const STATIC_FIELDS: &'static [JsClassStaticField] = <Self as ExtendsJsClassDef>::STATIC_FIELDS;
+
+    const STATIC_METHODS: &'static [JsClassStaticMethod] =
+        <Self as ExtendsJsClassDef>::STATIC_METHODS;
+
+    const SYMBOL_FIELDS: &'static [JsClassSymbolField<Self::RefType>] =
+        <Self as ExtendsJsClassDef>::SYMBOL_FIELDS;
+
+    const SYMBOL_METHODS: &'static [JsClassSymbolMethod<Self::RefType>] =
+        <Self as ExtendsJsClassDef>::SYMBOL_METHODS;
+
     unsafe fn mut_class_id_ptr() -> &'static mut u32 {
         <Self as ExtendsJsClassDef>::mut_class_id_ptr()
     }
@@ -316,7 +835,7 @@ impl<S: ExtendsJsClassDef> JsClassDef for S {
         if i < base_fields_len {
             <<Self as ExtendsJsClassDef>::BaseDef as JsClassDef>::field_get(this.as_ref(), i, ctx)
         } else {
-            if let Some((_, getter, _)) = Self::FIELDS.get(i) {
+            if let Some((_, getter, _)) = Self::FIELDS.get(i - base_fields_len) {
                 getter(this, ctx)
             } else {
                 JsValue::UnDefined
@@ -333,7 +852,7 @@ impl<S: ExtendsJsClassDef> JsClassDef for S {
                 val,
             )
         } else {
-            if let Some((_, _, Some(setter))) = Self::FIELDS.get(i) {
+            if let Some((_, _, Some(setter))) = Self::FIELDS.get(i - base_fields_len) {
                 setter(this, ctx, val)
             }
         }
@@ -413,6 +932,98 @@ pub type JsClassMethod<T> = (
     fn(&mut T, &mut JsObject, &mut Context, &[JsValue]) -> JsValue,
 );
 
+pub type JsClassStaticField = (&'static str, fn(&mut Context) -> JsValue);
+
+pub type JsClassStaticMethod = (
+    &'static str,
+    u8,
+    fn(&mut Context, JsValue, &[JsValue]) -> JsValue,
+);
+
+/// Well-known symbols that can key members of a `JsClassDef`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JsClassSymbol {
+    Iterator,
+    AsyncIterator,
+    ToStringTag,
+    ToPrimitive,
+    Dispose,
+    AsyncDispose,
+}
+
+impl JsClassSymbol {
+    fn property_name(&self) -> &'static str {
+        match self {
+            JsClassSymbol::Iterator => "iterator",
+            JsClassSymbol::AsyncIterator => "asyncIterator",
+            JsClassSymbol::ToStringTag => "toStringTag",
+            JsClassSymbol::ToPrimitive => "toPrimitive",
+            JsClassSymbol::Dispose => "dispose",
+            JsClassSymbol::AsyncDispose => "asyncDispose",
+        }
+    }
+
+    fn description(&self) -> String {
+        format!("Symbol.{}", self.property_name())
+    }
+
+    /// Returns the symbol atom, creating `Symbol.dispose`/`Symbol.asyncDispose`
+    /// on the global `Symbol` when the engine does not provide them yet.
+    unsafe fn new_atom(&self, ctx: &mut Context) -> Option<JSAtom> {
+        let symbol_ctor = ctx.get_global().get("Symbol");
+        let mut symbol = symbol_ctor.get(self.property_name())?;
+
+        if let (JsValue::UnDefined, JsValue::Function(f)) = (&symbol, &symbol_ctor) {
+            let desc = ctx.new_string(&self.description());
+            let new_symbol = f.call(&[desc.into()]);
+            if let JsValue::Symbol(_) = &new_symbol {
+                let name = make_c_string(self.property_name());
+                JS_DefinePropertyValueStr(
+                    ctx.ctx,
+                    symbol_ctor.get_qjs_value(),
+                    name.as_ptr(),
+                    JS_DupValue_real(ctx.ctx, new_symbol.get_qjs_value()),
+                    0,
+                );
+            }
+            symbol = new_symbol;
+        }
+
+        if let JsValue::Symbol(_) = &symbol {
+            Some(JS_ValueToAtom(ctx.ctx, symbol.get_qjs_value()))
+        } else {
+            None
+        }
+    }
+}
+
+pub type JsClassSymbolField<T> = (
+    JsClassSymbol,
+    fn(&T, &mut Context) -> JsValue,
+    Option<fn(&mut T, &mut Context, JsValue)>,
+);
+
+pub type JsClassSymbolMethod<T> = (
+    JsClassSymbol,
+    u8,
+    fn(&mut T, &mut JsObject, &mut Context, &[JsValue]) -> JsValue,
+);
+
+pub type JsClassSharedMethod<T> = (
+    &'static str,
+    u8,
+    fn(&T, &mut JsObject, &mut Context, &[JsValue]) -> JsValue,
+);
+
+pub type JsClassFuture =
+    std::pin::Pin<Box<dyn std::future::Future<Output = Result<JsValue, JsValue>> + Send>>;
+
+pub type JsClassAsyncMethod<T> = (
+    &'static str,
+    u8,
+    fn(JsClassRef<T>, Context, Vec<JsValue>) -> JsClassFuture,
+);
+
 pub trait JsClassDef {
     type RefType: Sized + 'static;
 
@@ -423,6 +1034,35 @@ pub trait JsClassDef {
 
     const METHODS: &'static [JsClassMethod<Self::RefType>];
 
+    /// Properties set on the constructor, evaluated once at `register_class`.
+    const STATIC_FIELDS: &'static [JsClassStaticField] = &[];
+
+    /// Methods set on the constructor, e.g. `WasiTcpConn.connect`.
+    const STATIC_METHODS: &'static [JsClassStaticMethod] = &[];
+
+    /// Symbol-keyed accessors on the prototype, e.g. `[Symbol.toStringTag]`.
+    const SYMBOL_FIELDS: &'static [JsClassSymbolField<Self::RefType>] = &[];
+
+    /// Symbol-keyed methods on the prototype, e.g. `[Symbol.iterator]()`.
+    const SYMBOL_METHODS: &'static [JsClassSymbolMethod<Self::RefType>] = &[];
+
+    /// Methods that only borrow `RefType` shared, so they stay callable while
+    /// an async method is pending, e.g. a socket's `write()` or `close()`.
+    /// Not inherited by `ExtendsJsClassDef` classes.
+    const SHARED_METHODS: &'static [JsClassSharedMethod<Self::RefType>] = &[];
+
+    /// Methods that always return a Promise. Each call holds a `JsClassRef`
+    /// until its future completes; a second async call on the same object
+    /// meanwhile rejects as busy, and `mark_closed` rejects the pending one.
+    /// Meanwhile `METHODS`, field setters and symbol methods throw as busy,
+    /// as they take `&mut RefType`; getters and `SHARED_METHODS` don't.
+    /// Not inherited by `ExtendsJsClassDef` classes.
+    const ASYNC_METHODS: &'static [JsClassAsyncMethod<Self::RefType>] = &[];
+
+    /// If set, every object is registered as an event loop handle of this kind
+    /// until it is closed or freed, and its async methods run as requests on it.
+    const RESOURCE_KIND: Option<ResourceKind> = None;
+
     unsafe fn mut_class_id_ptr() -> &'static mut u32;
 
     fn constructor_fn(ctx: &mut Context, argv: &[JsValue]) -> Result<Self::RefType, JsValue>;
@@ -506,20 +1146,23 @@ unsafe extern "C" fn gc_mark<Def: JsClassDef>(
     val: JSValue,
     mark_func: Option<unsafe extern "C" fn(*mut JSRuntime, *mut JSGCObjectHeader)>,
 ) {
-    let ptr = JS_GetOpaque(val, Def::class_id()) as *mut Def::RefType;
-    if let Some(ptr) = ptr.as_ref() {
-        Def::gc_mark(&ptr, &mut |v| gc_mark_value(rt, v, mark_func));
+    let ptr = JS_GetOpaque(val, Def::class_id()) as *mut JsClassCell<Def::RefType>;
+    if let Some(cell) = ptr.as_ref() {
+        Def::gc_mark(&cell.data, &mut |v| gc_mark_value(rt, v, mark_func));
     }
 }
 
 unsafe extern "C" fn finalizer<Def: JsClassDef>(rt: *mut JSRuntime, val: JSValue) {
     let class_id = Def::class_id();
 
-    let s = JS_GetOpaque(val, class_id) as *mut Def::RefType;
+    let s = JS_GetOpaque(val, class_id) as *mut JsClassCell<Def::RefType>;
     if !s.is_null() {
         let mut s = Box::from_raw(s);
         let event_loop_ptr = JS_GetRuntimeOpaque(rt) as *mut crate::EventLoop;
-        Def::finalizer(&mut s, event_loop_ptr.as_mut());
+        if let (Some(id), Some(event_loop)) = (s.handle.get(), event_loop_ptr.as_mut()) {
+            event_loop.handles.close(id);
+        }
+        Def::finalizer(&mut s.data, event_loop_ptr.as_mut());
     }
 }
 
@@ -554,8 +1197,9 @@ unsafe extern "C" fn constructor<Def: JsClassDef>(
             if JS_IsException_real(obj) != 0 {
                 JS_Throw(ctx, obj)
             } else {
-                let ptr_data = Box::leak(Box::new(data));
-                JS_SetOpaque(obj, (ptr_data as *mut Def::RefType).cast());
+                let ptr_data = Box::leak(Box::new(JsClassCell::new(data)));
+                JS_SetOpaque(obj, (ptr_data as *mut JsClassCell<Def::RefType>).cast());
+                open_handle::<Def>(ctx, obj, ptr_data);
                 obj
             }
         }
@@ -584,6 +1228,7 @@ pub fn register_class<Def: JsClassDef>(ctx: &mut Context) -> JsValue {
             };
             JS_NewClass(rt, class_id, &js_def);
         }
+        super::js_serialize::register_host_class(class_id, Def::CLASS_NAME);
 
         let mut proto_ref = JsClassProto::default();
         Def::property_keys_init(&mut proto_ref);
@@ -600,6 +1245,28 @@ pub fn register_class<Def: JsClassDef>(ctx: &mut Context) -> JsValue {
             function_list.len() as i32,
         );
 
+        let shared_function_list = into_function_list(
+            Def::SHARED_METHODS,
+            js_shared_method_magic_trampoline::<Def>,
+        );
+        JS_SetPropertyFunctionList(
+            ctx.ctx,
+            proto,
+            shared_function_list.as_ptr(),
+            shared_function_list.len() as i32,
+        );
+
+        let async_function_list =
+            into_function_list(Def::ASYNC_METHODS, js_async_method_magic_trampoline::<Def>);
+        JS_SetPropertyFunctionList(
+            ctx.ctx,
+            proto,
+            async_function_list.as_ptr(),
+            async_function_list.len() as i32,
+        );
+
+        define_symbol_members::<Def>(ctx, proto);
+
         let js_ctor = JS_NewCFunction2(
             ctx.ctx,
             Some(constructor::<Def>),
@@ -609,12 +1276,95 @@ pub fn register_class<Def: JsClassDef>(ctx: &mut Context) -> JsValue {
             0,
         );
 
+        let static_function_list = into_function_list(
+            Def::STATIC_METHODS,
+            js_static_method_magic_trampoline::<Def>,
+        );
+        JS_SetPropertyFunctionList(
+            ctx.ctx,
+            js_ctor,
+            static_function_list.as_ptr(),
+            static_function_list.len() as i32,
+        );
+
+        for (name, f) in Def::STATIC_FIELDS {
+            let v = f(ctx);
+            let name = make_c_string(*name);
+            JS_DefinePropertyValueStr(
+                ctx.ctx,
+                js_ctor,
+                name.as_ptr(),
+                v.into_qjs_value(),
+                JS_PROP_C_W_E as i32,
+            );
+        }
+
         JS_SetConstructor(ctx.ctx, js_ctor, proto);
         JS_SetClassProto(ctx.ctx, class_id, proto);
         JsValue::from_qjs_value(ctx.ctx, js_ctor)
     }
 }
 
+unsafe fn define_symbol_members<Def: JsClassDef>(ctx: &mut Context, proto: JSValue) {
+    for (i, (symbol, argc, _)) in Def::SYMBOL_METHODS.iter().enumerate() {
+        if let Some(atom) = symbol.new_atom(ctx) {
+            let name = make_c_string(format!("[{}]", symbol.description()));
+            let f = JS_NewCFunctionMagic_real(
+                ctx.ctx,
+                Some(js_symbol_method_magic_trampoline::<Def>),
+                name.as_ptr(),
+                *argc as i32,
+                JSCFunctionEnum_JS_CFUNC_generic_magic,
+                i as i32,
+            );
+            JS_DefinePropertyValue(
+                ctx.ctx,
+                proto,
+                atom,
+                f,
+                (JS_PROP_WRITABLE | JS_PROP_CONFIGURABLE) as i32,
+            );
+            JS_FreeAtom(ctx.ctx, atom);
+        }
+    }
+
+    for (i, (symbol, _, setter)) in Def::SYMBOL_FIELDS.iter().enumerate() {
+        if let Some(atom) = symbol.new_atom(ctx) {
+            let name = make_c_string(format!("get [{}]", symbol.description()));
+            let getter_fn = JS_NewCFunctionMagic_real(
+                ctx.ctx,
+                Some(js_symbol_getter_magic_trampoline::<Def>),
+                name.as_ptr(),
+                0,
+                JSCFunctionEnum_JS_CFUNC_generic_magic,
+                i as i32,
+            );
+            let setter_fn = if setter.is_some() {
+                let name = make_c_string(format!("set [{}]", symbol.description()));
+                JS_NewCFunctionMagic_real(
+                    ctx.ctx,
+                    Some(js_symbol_setter_magic_trampoline::<Def>),
+                    name.as_ptr(),
+                    1,
+                    JSCFunctionEnum_JS_CFUNC_generic_magic,
+                    i as i32,
+                )
+            } else {
+                js_undefined()
+            };
+            JS_DefinePropertyGetSet(
+                ctx.ctx,
+                proto,
+                atom,
+                getter_fn,
+                setter_fn,
+                JS_PROP_CONFIGURABLE as i32,
+            );
+            JS_FreeAtom(ctx.ctx, atom);
+        }
+    }
+}
+
 pub fn class_extends(ctx: &mut Context, proto: JsValue, base_proto: JsValue) -> bool {
     unsafe { JS_SetPrototype(ctx.ctx, proto.get_qjs_value(), base_proto.get_qjs_value()) > 0 }
 }
@@ -0,0 +1,401 @@
+//! A sampling CPU profiler.
+//!
+//! QuickJS calls the runtime's interrupt handler every few thousand bytecode
+//! operations. While profiling, the handler takes a sample whenever the
+//! sampling interval has passed, by constructing an `Error`, which captures
+//! the backtrace without throwing. Samples are collected into the
+//! `.cpuprofile` format that Chrome DevTools and most profiling tools read.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::{EventLoop, Runtime};
+
+use super::qjs::*;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct CallFrame {
+    function_name: String,
+    url: String,
+    // 0-based, -1 if unknown
+    line: i32,
+    column: i32,
+}
+
+impl CallFrame {
+    fn special(name: &str) -> Self {
+        CallFrame {
+            function_name: name.to_string(),
+            url: String::new(),
+            line: -1,
+            column: -1,
+        }
+    }
+
+    /// Parses one `    at f (file.js:3:5)` line of a QuickJS backtrace.
+    fn parse(line: &str) -> Option<Self> {
+        let line = line.trim().strip_prefix("at ")?;
+        let (function_name, location) = match line.rfind(" (") {
+            Some(i) if line.ends_with(')') => (&line[..i], &line[i + 2..line.len() - 1]),
+            _ => ("", line),
+        };
+        let (url, line, column) = split_location(location);
+        let function_name = if function_name.is_empty() || function_name == "<anonymous>" {
+            "(anonymous)"
+        } else {
+            function_name
+        };
+        Some(CallFrame {
+            function_name: function_name.to_string(),
+            url: if url == "native" {
+                String::new()
+            } else {
+                url.to_string()
+            },
+            line,
+            column,
+        })
+    }
+}
+
+/// Splits `file:line:col` or `file:line` into 0-based numbers; `native` has neither.
+fn split_location(location: &str) -> (&str, i32, i32) {
+    let mut url = location;
+    let mut numbers = vec![];
+    while numbers.len() < 2 {
+        let i = match url.rfind(':') {
+            Some(i) => i,
+            None => break,
+        };
+        match url[i + 1..].parse::<i32>() {
+            Ok(n) => {
+                numbers.push(n);
+                url = &url[..i];
+            }
+            Err(_) => break,
+        }
+    }
+    match numbers[..] {
+        [column, line] => (url, line - 1, column - 1),
+        [line] => (url, line - 1, -1),
+        _ => (url, -1, -1),
+    }
+}
+
+struct Node {
+    frame: CallFrame,
+    children: Vec<usize>,
+    hit_count: u32,
+}
+
+/// The state of a running profiler, kept on the event loop.
+pub(crate) struct Profiler {
+    ctx: *mut JSContext,
+    /// the intrinsic `Error`, taken before a script can replace the global
+    error_ctor: JSValue,
+    interval: Duration,
+    start: Instant,
+    start_wall: Duration,
+    last_sample: Instant,
+    nodes: Vec<Node>,
+    index: HashMap<(usize, CallFrame), usize>,
+    samples: Vec<usize>,
+    time_deltas: Vec<i64>,
+    idle: bool,
+    pub(crate) exit_path: Option<PathBuf>,
+}
+
+const ROOT: usize = 0;
+
+/// `JS_CLASS_ERROR` of the QuickJS class enum, which the bindings don't export.
+const JS_CLASS_ERROR: JSClassID = 3;
+
+impl Profiler {
+    fn new(ctx: *mut JSContext, interval: Duration) -> Self {
+        let now = Instant::now();
+        let error_ctor = unsafe {
+            let proto = JS_GetClassProto(ctx, JS_CLASS_ERROR);
+            let ctor = JS_GetPropertyStr(ctx, proto, "constructor\0".as_ptr().cast());
+            JS_FreeValue_real(ctx, proto);
+            ctor
+        };
+        let mut profiler = Profiler {
+            ctx,
+            error_ctor,
+            interval,
+            start: now,
+            start_wall: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default(),
+            last_sample: now,
+            nodes: vec![],
+            index: HashMap::new(),
+            samples: vec![],
+            time_deltas: vec![],
+            idle: false,
+            exit_path: None,
+        };
+        profiler.nodes.push(Node {
+            frame: CallFrame::special("(root)"),
+            children: vec![],
+            hit_count: 0,
+        });
+        profiler
+    }
+
+    fn child(&mut self, parent: usize, frame: CallFrame) -> usize {
+        if let Some(id) = self.index.get(&(parent, frame.clone())) {
+            return *id;
+        }
+        let id = self.nodes.len();
+        self.nodes.push(Node {
+            frame: frame.clone(),
+            children: vec![],
+            hit_count: 0,
+        });
+        self.nodes[parent].children.push(id);
+        self.index.insert((parent, frame), id);
+        id
+    }
+
+    /// Records a sample of `stack`, outermost frame first.
+    fn record(&mut self, stack: Vec<CallFrame>, now: Instant) {
+        let mut node = ROOT;
+        for frame in stack {
+            node = self.child(node, frame);
+        }
+        self.nodes[node].hit_count += 1;
+        self.samples.push(node);
+        self.time_deltas
+            .push(now.duration_since(self.last_sample).as_micros() as i64);
+        self.last_sample = now;
+    }
+
+    /// Called from the interrupt handler while JS is running.
+    unsafe fn sample(&mut self) {
+        let now = Instant::now();
+        if !self.idle && now.duration_since(self.last_sample) < self.interval {
+            return;
+        }
+        self.idle = false;
+
+        // a new error's backtrace is the current JS stack. Nothing is thrown,
+        // so the exception state of the interrupted code is left alone.
+        let ctx = self.ctx;
+        if JS_IsFunction(ctx, self.error_ctor) == 0 {
+            return;
+        }
+        let error = JS_CallConstructor(ctx, self.error_ctor, 0, std::ptr::null_mut());
+        if JS_IsException_real(error) != 0 {
+            // out of memory; the next sample may fare better
+            JS_FreeValue_real(ctx, JS_GetException(ctx));
+            return;
+        }
+        let stack = JS_GetPropertyStr(ctx, error, "stack\0".as_ptr().cast());
+        JS_FreeValue_real(ctx, error);
+        if JS_IsString_real(stack) == 0 {
+            JS_FreeValue_real(ctx, stack);
+            return;
+        }
+        let mut len = 0;
+        let s = JS_ToCStringLen2(ctx, &mut len, stack, 0);
+        JS_FreeValue_real(ctx, stack);
+        if s.is_null() {
+            JS_FreeValue_real(ctx, JS_GetException(ctx));
+            return;
+        }
+        let bytes = std::slice::from_raw_parts(s as *const u8, len);
+        let mut frames: Vec<CallFrame> = String::from_utf8_lossy(bytes)
+            .lines()
+            .filter_map(CallFrame::parse)
+            .collect();
+        JS_FreeCString(ctx, s);
+        // engines that don't skip the constructor's own frame list it first
+        if frames.first() == Some(&CallFrame::special("Error")) {
+            frames.remove(0);
+        }
+        if frames.is_empty() {
+            frames.push(CallFrame::special("(program)"));
+        }
+        frames.reverse();
+        self.record(frames, now);
+    }
+
+    /// Called when the event loop starts waiting for I/O or timers.
+    fn mark_idle(&mut self) {
+        if !self.idle {
+            self.record(vec![CallFrame::special("(idle)")], Instant::now());
+            self.idle = true;
+        }
+    }
+
+    fn finish(mut self) -> CpuProfile {
+        let end = Instant::now();
+        CpuProfile {
+            nodes: std::mem::take(&mut self.nodes),
+            samples: std::mem::take(&mut self.samples),
+            time_deltas: std::mem::take(&mut self.time_deltas),
+            start_time: self.start_wall.as_micros() as i64,
+            end_time: (self.start_wall + end.duration_since(self.start)).as_micros() as i64,
+        }
+    }
+}
+
+impl Drop for Profiler {
+    fn drop(&mut self) {
+        unsafe { JS_FreeValue_real(self.ctx, self.error_ctor) };
+    }
+}
+
+/// Takes a sample if the profiler is running and the interval has passed.
+pub(crate) unsafe fn on_interrupt(event_loop: &mut EventLoop) {
+    if let Some(profiler) = event_loop.profiler.as_mut() {
+        profiler.sample();
+    }
+}
+
+/// Records the loop going idle, so the wait is not attributed to the last JS stack.
+pub(crate) fn on_idle(event_loop: &mut EventLoop) {
+    if let Some(profiler) = event_loop.profiler.as_mut() {
+        profiler.mark_idle();
+    }
+}
+
+/// Writes the profile to the path given to `Runtime::save_profile_on_exit`,
+/// for `exit()` which ends the process without returning.
+pub(crate) fn save_on_exit(event_loop: &mut EventLoop) {
+    if let Some(profiler) = event_loop.profiler.take() {
+        if let Some(path) = profiler.exit_path.clone() {
+            if let Err(e) = profiler.finish().save(&path) {
+                eprintln!("{}: {}", path.display(), e);
+            }
+        }
+    }
+}
+
+/// A CPU profile in the format of the DevTools protocol `Profiler.Profile`.
+pub struct CpuProfile {
+    nodes: Vec<Node>,
+    samples: Vec<usize>,
+    time_deltas: Vec<i64>,
+    start_time: i64,
+    end_time: i64,
+}
+
+fn json_string(s: &str, out: &mut String) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+fn json_list<T: ToString>(items: impl Iterator<Item = T>, out: &mut String) {
+    out.push('[');
+    for (i, item) in items.enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        out.push_str(&item.to_string());
+    }
+    out.push(']');
+}
+
+impl CpuProfile {
+    /// The number of samples taken.
+    pub fn sample_count(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// Names of the functions that were sampled, including the synthetic
+    /// `(root)`, `(program)` and `(idle)` nodes.
+    pub fn function_names(&self) -> Vec<&str> {
+        self.nodes
+            .iter()
+            .map(|node| node.frame.function_name.as_str())
+            .collect()
+    }
+
+    /// Serializes the profile as a `.cpuprofile` JSON document.
+    pub fn to_json(&self) -> String {
+        let mut script_ids: HashMap<&str, usize> = HashMap::new();
+        let mut out = String::from("{\"nodes\":[");
+        for (id, node) in self.nodes.iter().enumerate() {
+            if id > 0 {
+                out.push(',');
+            }
+            let next_id = script_ids.len() + 1;
+            let script_id = if node.frame.url.is_empty() {
+                0
+            } else {
+                *script_ids.entry(node.frame.url.as_str()).or_insert(next_id)
+            };
+            out.push_str(&format!(
+                "{{\"id\":{},\"callFrame\":{{\"functionName\":",
+                id + 1
+            ));
+            json_string(&node.frame.function_name, &mut out);
+            out.push_str(&format!(",\"scriptId\":\"{}\",\"url\":", script_id));
+            json_string(&node.frame.url, &mut out);
+            out.push_str(&format!(
+                ",\"lineNumber\":{},\"columnNumber\":{}}},\"hitCount\":{},\"children\":",
+                node.frame.line, node.frame.column, node.hit_count
+            ));
+            json_list(node.children.iter().map(|child| child + 1), &mut out);
+            out.push('}');
+        }
+        out.push_str(&format!(
+            "],\"startTime\":{},\"endTime\":{},\"samples\":",
+            self.start_time, self.end_time
+        ));
+        json_list(self.samples.iter().map(|node| node + 1), &mut out);
+        out.push_str(",\"timeDeltas\":");
+        json_list(self.time_deltas.iter(), &mut out);
+        out.push('}');
+        out
+    }
+
+    /// Writes the profile to `path`, conventionally a `.cpuprofile` file.
+    pub fn save<P: AsRef<std::path::Path>>(&self, path: P) -> std::io::Result<()> {
+        std::fs::write(path, self.to_json())
+    }
+}
+
+impl Runtime {
+    /// Starts sampling the JS call stack every `interval`, replacing any running profile.
+    ///
+    /// Samples are taken while JS runs, so a long native call shows up as the JS
+    /// function that made it; time spent waiting in the event loop is `(idle)`.
+    pub fn start_profiling(&mut self, interval: Duration) {
+        let ctx = self.ctx.ctx;
+        if let Some(event_loop) = self.ctx.event_loop() {
+            event_loop.profiler = Some(Box::new(Profiler::new(ctx, interval)));
+        }
+    }
+
+    /// Stops the profiler, returning what it recorded.
+    pub fn stop_profiling(&mut self) -> Option<CpuProfile> {
+        let profiler = self.ctx.event_loop()?.profiler.take()?;
+        Some(profiler.finish())
+    }
+
+    /// Writes the running profile to `path` if the script ends the process with `exit()`.
+    pub fn save_profile_on_exit(&mut self, path: PathBuf) {
+        if let Some(profiler) = self
+            .ctx
+            .event_loop()
+            .and_then(|event_loop| event_loop.profiler.as_mut())
+        {
+            profiler.exit_path = Some(path);
+        }
+    }
+}
@@ -1,25 +1,61 @@
 use std::{future::Future, sync::atomic::Ordering, task::Poll};
 
-use crate::{quickjs_sys::qjs::JS_ExecutePendingJob, Context, EventLoop, JsValue, Runtime};
+use crate::event_loop::handle::{HandleId, ResourceKind};
+use crate::{
+    quickjs_sys::qjs::JS_ExecutePendingJob, Context, EventLoop, JsFunction, JsValue, Runtime,
+};
 
 use super::{
-    qjs::{js_std_dump_error, JSContext, JS_GetRuntimeOpaque},
+    qjs::{
+        js_std_dump_error, js_undefined, JSContext, JSValue, JS_Call, JS_EnqueueJob,
+        JS_GetRuntimeOpaque,
+    },
     RuntimeResult,
 };
 
+/// The job `queue_microtask` enqueues: calls `argv[0]` with no arguments.
+unsafe extern "C" fn call_microtask(
+    ctx: *mut JSContext,
+    _argc: std::os::raw::c_int,
+    argv: *mut JSValue,
+) -> JSValue {
+    JS_Call(ctx, *argv, js_undefined(), 0, std::ptr::null_mut())
+}
+
 impl Context {
+    /// Queues `callback` on the job queue, after the promise reactions already queued.
+    ///
+    /// An exception it throws is uncaught, see `Runtime::run_microtasks`.
+    pub fn queue_microtask(&mut self, callback: &JsFunction) {
+        unsafe {
+            // the job holds its own reference to the callback
+            let mut argv = [callback.0.v];
+            JS_EnqueueJob(self.ctx, Some(call_microtask), 1, argv.as_mut_ptr());
+        }
+    }
+
     pub fn future_to_promise(
         &mut self,
         f: impl Future<Output = Result<JsValue, JsValue>> + std::marker::Send + 'static,
     ) -> JsValue {
-        let waker = self
-            .event_loop()
-            .and_then(|event_loop| event_loop.waker.clone());
+        self.future_to_promise_with(ResourceKind::Future, None, f)
+    }
+
+    /// Like `future_to_promise`, tracking the future as a `kind` request,
+    /// or as one on the `owner` handle, which it is refed with.
+    pub(crate) fn future_to_promise_with(
+        &mut self,
+        kind: ResourceKind,
+        owner: Option<HandleId>,
+        f: impl Future<Output = Result<JsValue, JsValue>> + std::marker::Send + 'static,
+    ) -> JsValue {
+        let io_queue = self.event_loop().map(|event_loop| event_loop.io_queue());
 
         let (promise, resolve, reject) = self.new_promise();
 
         let handle = tokio::task::spawn(async move {
-            match f.await {
+            let result = f.await;
+            let settle = move || match result {
                 Ok(value) => {
                     if let JsValue::Function(f) = resolve {
                         f.call(&[value]);
@@ -30,14 +66,17 @@ impl Context {
                         f.call(&[err]);
                     }
                 }
+            };
+            // settled in the poll phase of the loop, after the timers that are due
+            match io_queue {
+                Some(io_queue) => io_queue.push_io(Box::new(settle)),
+                None => settle(),
             }
-            log::trace!("rt {:?} wake", waker);
-            waker.map(|waker| waker.wake());
             tokio::task::spawn(tokio::task::yield_now());
         });
 
         self.event_loop().map(|event_loop| {
-            event_loop.sub_tasks.push_back(handle);
+            event_loop.handles.spawn(kind, owner, handle);
         });
         promise
     }
@@ -56,31 +95,53 @@ impl Future for Runtime {
             let event_loop = { (JS_GetRuntimeOpaque(rt) as *mut EventLoop).as_mut() };
             if let Some(event_loop) = event_loop {
                 let waker = cx.waker().clone();
+                event_loop.set_remote_waker(waker.clone());
                 event_loop.waker.insert(waker);
+                event_loop.clock.leave_idle();
 
-                if self.run_loop_without_io() < 0 {
-                    log::trace!("Runtime Ready io < 0");
-                    return Poll::Ready(());
-                }
                 loop {
-                    match event_loop.sub_tasks.pop_front() {
-                        Some(task) => {
-                            if task.is_finished() {
-                                continue;
-                            } else {
-                                event_loop.sub_tasks.push_front(task);
-                                log::trace!("Runtime Pending");
-                                return Poll::Pending;
-                            }
-                        }
-                        None => {
-                            log::trace!("Runtime Ready sub_tasks empty");
-                            return Poll::Ready(());
-                        }
+                    if self.run_loop_once() < 0 || event_loop.uncaught_exception {
+                        log::trace!("Runtime Ready uncaught exception");
+                        return Poll::Ready(());
+                    }
+                    if event_loop.exit_code.is_some() {
+                        log::trace!("Runtime Ready exit");
+                        return Poll::Ready(());
                     }
+                    if event_loop.has_queued_callbacks() {
+                        // let I/O make progress before the next turn, like the poll phase does
+                        log::trace!("Runtime Pending on next turn");
+                        cx.waker().wake_by_ref();
+                        return Poll::Pending;
+                    }
+                    // `poll_expired` also arms the wakeup for the next timer
+                    if event_loop
+                        .timers
+                        .poll_expired(cx, &event_loop.clock)
+                        .is_pending()
+                    {
+                        break;
+                    }
+                }
+                event_loop.handles.reap();
+                if event_loop.has_queued_callbacks() {
+                    // an I/O request completed after the turn's poll phase
+                    cx.waker().wake_by_ref();
+                    Poll::Pending
+                } else if event_loop.handles.keep_alive()
+                    || event_loop.has_runtime_handles()
+                    || event_loop.timers_keep_alive()
+                {
+                    super::js_profiler::on_idle(event_loop);
+                    event_loop.clock.enter_idle();
+                    log::trace!("Runtime Pending");
+                    Poll::Pending
+                } else {
+                    log::trace!("Runtime Ready no pending requests");
+                    Poll::Ready(())
                 }
             } else {
-                log::trace!("Runtime Ready sub_tasks empty");
+                log::trace!("Runtime Ready no event loop");
                 Poll::Ready(())
             }
         }
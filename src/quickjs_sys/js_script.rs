@@ -0,0 +1,344 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use crate::{Context, ContextOptions, EventLoop, JsValue};
+
+use super::make_c_string;
+use super::qjs::*;
+
+/// Stops running JS once the innermost `Context::with_timeout` deadline has passed,
+/// and takes the profiler's samples.
+pub(crate) unsafe extern "C" fn interrupt_handler(
+    rt: *mut JSRuntime,
+    _opaque: *mut ::std::os::raw::c_void,
+) -> ::std::os::raw::c_int {
+    let mut event_loop = (JS_GetRuntimeOpaque(rt) as *mut EventLoop).as_mut();
+    if let Some(event_loop) = event_loop.as_deref_mut() {
+        super::js_profiler::on_interrupt(event_loop);
+    }
+    match event_loop.and_then(|event_loop| event_loop.deadline) {
+        Some(deadline) if Instant::now() >= deadline => 1,
+        _ => 0,
+    }
+}
+
+type ModuleResolver = Box<dyn FnMut(&str, &str) -> Option<String>>;
+
+thread_local! {
+    // consulted for every import while `resolve_module_with` runs
+    static MODULE_RESOLVER: RefCell<Option<ModuleResolver>> = RefCell::new(None);
+}
+
+/// The module normalize function while a resolver is installed: maps the name of the
+/// importing module and the specifier to the name of the module to import.
+unsafe extern "C" fn resolver_normalize(
+    ctx: *mut JSContext,
+    base_name: *const ::std::os::raw::c_char,
+    name: *const ::std::os::raw::c_char,
+    _opaque: *mut ::std::os::raw::c_void,
+) -> *mut ::std::os::raw::c_char {
+    let base = std::ffi::CStr::from_ptr(base_name).to_string_lossy();
+    let specifier = std::ffi::CStr::from_ptr(name).to_string_lossy();
+    let resolved = MODULE_RESOLVER.with(|resolver| {
+        resolver
+            .borrow_mut()
+            .as_mut()
+            .and_then(|resolve| resolve(&base, &specifier))
+    });
+    match resolved {
+        Some(resolved) => js_strdup(ctx, make_c_string(resolved).as_ptr()),
+        None => {
+            JS_ThrowReferenceError(ctx, "could not resolve module '%s'\0".as_ptr().cast(), name);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Loads every module as an empty one, for `Context::module_requests`.
+unsafe extern "C" fn empty_module_loader(
+    ctx: *mut JSContext,
+    module_name: *const ::std::os::raw::c_char,
+    _opaque: *mut ::std::os::raw::c_void,
+) -> *mut JSModuleDef {
+    let m = JS_Eval(
+        ctx,
+        "\0".as_ptr().cast(),
+        0,
+        module_name,
+        (JS_EVAL_TYPE_MODULE | JS_EVAL_FLAG_COMPILE_ONLY) as i32,
+    );
+    if JS_IsException_real(m) != 0 {
+        return std::ptr::null_mut();
+    }
+    let def = JS_VALUE_GET_PTR_real(m);
+    JS_FreeValue_real(ctx, m);
+    def.cast()
+}
+
+/// `JS_ResolveModule` with the imports of `m`, and of the modules it pulls in that are
+/// not resolved yet, named by `resolver` and loaded by `loader` if not loaded already.
+unsafe fn resolve_module_with(
+    ctx: *mut JSContext,
+    m: JSValue,
+    resolver: ModuleResolver,
+    loader: JSModuleLoaderFunc,
+) -> std::os::raw::c_int {
+    let rt = JS_GetRuntime(ctx);
+    MODULE_RESOLVER.with(|r| r.borrow_mut().replace(resolver));
+    JS_SetModuleLoaderFunc(rt, Some(resolver_normalize), loader, std::ptr::null_mut());
+    let ret = JS_ResolveModule(ctx, m);
+    JS_SetModuleLoaderFunc(rt, None, Some(super::module_loader), std::ptr::null_mut());
+    MODULE_RESOLVER.with(|r| r.borrow_mut().take());
+    ret
+}
+
+/// Guesses whether `code` is an ES module, i.e. whether it starts with `import` or `export`.
+pub fn detect_module(code: &str) -> bool {
+    let code = make_c_string(code);
+    unsafe { JS_DetectModule(code.as_ptr(), code.as_bytes().len()) != 0 }
+}
+
+/// A module compiled by `Context::compile_module`.
+///
+/// The module itself is owned by its context, so this only keeps the context alive.
+/// QuickJS frees modules that fail to link, after which only the error is kept.
+pub struct JsCompiledModule {
+    ctx: Context,
+    m: JSValue,
+    status: JsModuleStatus,
+}
+
+#[derive(Debug, Clone)]
+pub enum JsModuleStatus {
+    Unlinked,
+    Linked,
+    Evaluated(JsValue),
+    Errored(JsValue),
+}
+
+impl JsCompiledModule {
+    pub fn context(&mut self) -> &mut Context {
+        &mut self.ctx
+    }
+
+    pub fn status(&self) -> &JsModuleStatus {
+        &self.status
+    }
+
+    /// Resolves the module's imports through the runtime's module loader.
+    pub fn link(&mut self) -> Result<(), JsValue> {
+        self.link_inner(None)
+    }
+
+    /// Resolves the module's imports with `resolve(referrer, specifier)`, which names
+    /// the module of this context to import, or returns None if there is none. It also
+    /// resolves the imports of the modules it names that are not linked yet.
+    pub fn link_with<F>(&mut self, resolve: F) -> Result<(), JsValue>
+    where
+        F: FnMut(&str, &str) -> Option<String> + 'static,
+    {
+        self.link_inner(Some(Box::new(resolve)))
+    }
+
+    fn link_inner(&mut self, resolver: Option<ModuleResolver>) -> Result<(), JsValue> {
+        match &self.status {
+            JsModuleStatus::Unlinked => unsafe {
+                let ret = match resolver {
+                    Some(resolver) => resolve_module_with(
+                        self.ctx.ctx,
+                        self.m,
+                        resolver,
+                        Some(super::module_loader),
+                    ),
+                    None => JS_ResolveModule(self.ctx.ctx, self.m),
+                };
+                if ret < 0 {
+                    let e = JS_GetException(self.ctx.ctx);
+                    let e = JsValue::from_qjs_value(self.ctx.ctx, e);
+                    self.status = JsModuleStatus::Errored(e.clone());
+                    Err(e)
+                } else {
+                    self.status = JsModuleStatus::Linked;
+                    Ok(())
+                }
+            },
+            JsModuleStatus::Errored(e) => Err(e.clone()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Evaluates the module, returning the promise of its (possibly async) evaluation.
+    /// Evaluating it again returns the same promise.
+    pub fn evaluate(&mut self) -> Result<JsValue, JsValue> {
+        match &self.status {
+            JsModuleStatus::Unlinked | JsModuleStatus::Linked => unsafe {
+                let v = JS_EvalFunction(self.ctx.ctx, self.m);
+                if JS_IsException_real(v) > 0 {
+                    let e = JS_GetException(self.ctx.ctx);
+                    let e = JsValue::from_qjs_value(self.ctx.ctx, e);
+                    self.status = JsModuleStatus::Errored(e.clone());
+                    Err(e)
+                } else {
+                    let v = JsValue::from_qjs_value(self.ctx.ctx, v);
+                    self.status = JsModuleStatus::Evaluated(v.clone());
+                    Ok(v)
+                }
+            },
+            JsModuleStatus::Evaluated(v) => Ok(v.clone()),
+            JsModuleStatus::Errored(e) => Err(e.clone()),
+        }
+    }
+
+    /// The module namespace object, available once the module is linked.
+    pub fn namespace(&mut self) -> Result<JsValue, JsValue> {
+        match &self.status {
+            JsModuleStatus::Linked | JsModuleStatus::Evaluated(_) => unsafe {
+                let m = JS_VALUE_GET_PTR_real(self.m) as *mut JSModuleDef;
+                let v = JS_GetModuleNamespace(self.ctx.ctx, m);
+                let v = JsValue::from_qjs_value(self.ctx.ctx, v);
+                if let JsValue::Exception(_) = v {
+                    let e = JS_GetException(self.ctx.ctx);
+                    Err(JsValue::from_qjs_value(self.ctx.ctx, e))
+                } else {
+                    Ok(v)
+                }
+            },
+            JsModuleStatus::Unlinked => Err(self.ctx.new_error("module is not linked")),
+            JsModuleStatus::Errored(e) => Err(e.clone()),
+        }
+    }
+}
+
+impl Context {
+    /// Compiles a global script into bytecode, which `eval_bytecode` can run
+    /// in any context of the same runtime.
+    pub fn compile_script(&mut self, code: &str, filename: &str) -> Result<Vec<u8>, JsValue> {
+        unsafe {
+            let ctx = self.ctx;
+            let len = code.len();
+            let f = JS_Eval(
+                ctx,
+                make_c_string(code).as_ptr(),
+                len,
+                make_c_string(filename).as_ptr(),
+                (JS_EVAL_TYPE_GLOBAL | JS_EVAL_FLAG_COMPILE_ONLY) as i32,
+            );
+            if JS_IsException_real(f) > 0 {
+                let e = JS_GetException(ctx);
+                return Err(JsValue::from_qjs_value(ctx, e));
+            }
+
+            let mut size = 0;
+            let buf = JS_WriteObject(ctx, &mut size, f, JS_WRITE_OBJ_BYTECODE as i32);
+            JS_FreeValue_real(ctx, f);
+            if buf.is_null() {
+                let e = JS_GetException(ctx);
+                return Err(JsValue::from_qjs_value(ctx, e));
+            }
+            let bytecode = std::slice::from_raw_parts(buf, size).to_vec();
+            js_free(ctx, buf.cast());
+            Ok(bytecode)
+        }
+    }
+
+    /// Runs bytecode from `compile_script` with this context as its realm.
+    pub fn eval_bytecode(&mut self, bytecode: &[u8]) -> JsValue {
+        unsafe {
+            let ctx = self.ctx;
+            let f = JS_ReadObject(
+                ctx,
+                bytecode.as_ptr(),
+                bytecode.len(),
+                JS_READ_OBJ_BYTECODE as i32,
+            );
+            if JS_IsException_real(f) > 0 {
+                return JsValue::from_qjs_value(ctx, f);
+            }
+            let v = JS_EvalFunction(ctx, f);
+            JsValue::from_qjs_value(ctx, v)
+        }
+    }
+
+    /// Compiles an ES module named `name` without evaluating it.
+    ///
+    /// Other modules of this context can import it by that name.
+    pub fn compile_module(&mut self, code: &str, name: &str) -> Result<JsCompiledModule, JsValue> {
+        unsafe {
+            let ctx = self.ctx;
+            let len = code.len();
+            let m = JS_Eval(
+                ctx,
+                make_c_string(code).as_ptr(),
+                len,
+                make_c_string(name).as_ptr(),
+                (JS_EVAL_TYPE_MODULE | JS_EVAL_FLAG_COMPILE_ONLY) as i32,
+            );
+            if JS_IsException_real(m) > 0 {
+                let e = JS_GetException(ctx);
+                return Err(JsValue::from_qjs_value(ctx, e));
+            }
+            Ok(JsCompiledModule {
+                ctx: self.clone(),
+                m,
+                status: JsModuleStatus::Unlinked,
+            })
+        }
+    }
+
+    /// The specifiers the ES module `code` imports, in order, without loading them.
+    pub fn module_requests(&mut self, code: &str) -> Result<Vec<String>, JsValue> {
+        // resolved in a context of its own, which frees the module and the empty ones
+        // standing in for its imports when it is dropped
+        let mut scratch = unsafe { self.new_context(ContextOptions::minimal()) };
+        let m = match scratch.compile_module(code, "<requests>") {
+            Ok(m) => m,
+            Err(_) => return Err(self.new_error("the module has a syntax error")),
+        };
+        let requests = Rc::new(RefCell::new(vec![]));
+        let recorded = requests.clone();
+        let resolver: ModuleResolver = Box::new(move |_referrer, specifier| {
+            recorded.borrow_mut().push(specifier.to_string());
+            Some("<request>".to_string())
+        });
+        let loader: JSModuleLoaderFunc = Some(empty_module_loader);
+        if unsafe { resolve_module_with(scratch.ctx, m.m, resolver, loader) } < 0 {
+            unsafe { JS_FreeValue_real(scratch.ctx, JS_GetException(scratch.ctx)) };
+            return Err(self.new_error("could not read the module's imports"));
+        }
+        drop(m);
+        let requests = requests.borrow().clone();
+        Ok(requests)
+    }
+
+    /// Runs `f`, interrupting any JS it runs once `timeout` has elapsed.
+    ///
+    /// Returns `None` if it was interrupted; the pending exception is cleared.
+    pub fn with_timeout<F: FnOnce(&mut Context) -> JsValue>(
+        &mut self,
+        timeout: Duration,
+        f: F,
+    ) -> Option<JsValue> {
+        let deadline = Instant::now() + timeout;
+        let prev = self
+            .event_loop()
+            .and_then(|event_loop| event_loop.deadline.replace(deadline));
+        if let (Some(prev), Some(event_loop)) = (prev, self.event_loop()) {
+            event_loop.deadline = Some(prev.min(deadline));
+        }
+
+        let r = f(self);
+
+        if let Some(event_loop) = self.event_loop() {
+            event_loop.deadline = prev;
+        }
+
+        if let JsValue::Exception(_) = r {
+            if Instant::now() >= deadline {
+                unsafe { JS_FreeValue_real(self.ctx, JS_GetException(self.ctx)) };
+                return None;
+            }
+        }
+        Some(r)
+    }
+}
@@ -0,0 +1,828 @@
+//! A structured clone format for moving values between contexts and runtimes.
+//!
+//! Unlike `JS_WriteObject`, the encoding only contains data: functions, symbols and
+//! objects owned by Rust classes are rejected with a `DataCloneError` instead of
+//! being copied by reference. Objects are classified by their `Object.prototype.toString`
+//! tag and rebuilt with the constructors of the receiving context.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use crate::{AsObject, Context, JsObject, JsValue};
+
+use super::js_shared::SharedBuffer;
+use super::qjs::*;
+
+const MAGIC: u8 = 0xFF;
+const VERSION: u8 = 1;
+const MAX_DEPTH: usize = 2048;
+
+const TAG_UNDEFINED: u8 = b'U';
+const TAG_NULL: u8 = b'0';
+const TAG_TRUE: u8 = b'T';
+const TAG_FALSE: u8 = b'F';
+const TAG_INT: u8 = b'I';
+const TAG_NUMBER: u8 = b'N';
+const TAG_BIGINT: u8 = b'Z';
+const TAG_STRING: u8 = b'S';
+const TAG_REFERENCE: u8 = b'R';
+const TAG_OBJECT: u8 = b'o';
+const TAG_ARRAY: u8 = b'a';
+const TAG_DATE: u8 = b'D';
+const TAG_REGEXP: u8 = b'r';
+const TAG_MAP: u8 = b';';
+const TAG_SET: u8 = b'\'';
+const TAG_ARRAY_BUFFER: u8 = b'B';
+const TAG_VIEW: u8 = b'V';
+const TAG_ERROR: u8 = b'E';
+const TAG_BOXED: u8 = b'x';
+const TAG_SHARED: u8 = b'H';
+
+const VIEW_KINDS: &[&str] = &[
+    "Int8Array",
+    "Uint8Array",
+    "Uint8ClampedArray",
+    "Int16Array",
+    "Uint16Array",
+    "Int32Array",
+    "Uint32Array",
+    "Float32Array",
+    "Float64Array",
+    "BigInt64Array",
+    "BigUint64Array",
+    "DataView",
+];
+
+const ERROR_KINDS: &[&str] = &[
+    "Error",
+    "EvalError",
+    "RangeError",
+    "ReferenceError",
+    "SyntaxError",
+    "TypeError",
+    "URIError",
+];
+
+const UNCLONEABLE_TAGS: &[&str] = &[
+    "WeakMap",
+    "WeakSet",
+    "WeakRef",
+    "FinalizationRegistry",
+    "Generator",
+    "AsyncGenerator",
+    "Symbol",
+];
+
+static HOST_CLASSES: Mutex<Vec<(JSClassID, &'static str)>> = Mutex::new(Vec::new());
+
+/// Records a class registered by `register_class` so its instances are refused by `serialize`.
+pub(crate) fn register_host_class(class_id: JSClassID, name: &'static str) {
+    let mut classes = HOST_CLASSES.lock().unwrap();
+    if !classes.iter().any(|(id, _)| *id == class_id) {
+        classes.push((class_id, name));
+    }
+}
+
+fn host_class_name(v: JSValue) -> Option<&'static str> {
+    let classes = HOST_CLASSES.lock().unwrap();
+    classes
+        .iter()
+        .find(|(id, _)| unsafe { !JS_GetOpaque(v, *id).is_null() })
+        .map(|(_, name)| *name)
+}
+
+fn raw_ctx(v: &JsValue) -> Option<*mut JSContext> {
+    match v {
+        JsValue::Object(o) => Some(o.js_ref().ctx),
+        JsValue::Array(o) => Some(o.js_ref().ctx),
+        JsValue::ArrayBuffer(o) => Some(o.0.ctx),
+        JsValue::Function(o) => Some(o.js_ref().ctx),
+        JsValue::Promise(o) => Some(o.js_ref().ctx),
+        _ => None,
+    }
+}
+
+/// Calls `f` with an explicit receiver, like `Function.prototype.call`.
+fn call_with_this(f: &JsValue, this: &JsValue, argv: &[JsValue]) -> JsValue {
+    match raw_ctx(f) {
+        Some(ctx) => unsafe {
+            let mut argv: Vec<JSValue> = argv.iter().map(|v| v.get_qjs_value()).collect();
+            let v = JS_Call(
+                ctx,
+                f.get_qjs_value(),
+                this.get_qjs_value(),
+                argv.len() as i32,
+                argv.as_mut_ptr(),
+            );
+            JsValue::from_qjs_value(ctx, v)
+        },
+        None => JsValue::UnDefined,
+    }
+}
+
+fn construct(ctor: &JsValue, argv: &[JsValue]) -> JsValue {
+    match raw_ctx(ctor) {
+        Some(ctx) => unsafe {
+            let mut argv: Vec<JSValue> = argv.iter().map(|v| v.get_qjs_value()).collect();
+            let v = JS_CallConstructor(
+                ctx,
+                ctor.get_qjs_value(),
+                argv.len() as i32,
+                argv.as_mut_ptr(),
+            );
+            JsValue::from_qjs_value(ctx, v)
+        },
+        None => JsValue::UnDefined,
+    }
+}
+
+fn string_bytes(ctx: *mut JSContext, v: &JsValue) -> Vec<u8> {
+    unsafe {
+        let mut len = 0;
+        let ptr = JS_ToCStringLen2(ctx, &mut len, v.get_qjs_value(), 0);
+        if ptr.is_null() {
+            return Vec::new();
+        }
+        let bytes = std::slice::from_raw_parts(ptr.cast::<u8>(), len).to_vec();
+        JS_FreeCString(ctx, ptr);
+        bytes
+    }
+}
+
+fn new_string_from_bytes(ctx: *mut JSContext, bytes: &[u8]) -> JsValue {
+    unsafe {
+        let v = JS_NewStringLen(ctx, bytes.as_ptr().cast(), bytes.len());
+        JsValue::from_qjs_value(ctx, v)
+    }
+}
+
+fn own_enumerable_keys(ctx: *mut JSContext, obj: &JsValue) -> Result<Vec<JsValue>, JsValue> {
+    unsafe {
+        let mut properties: *mut JSPropertyEnum = std::ptr::null_mut();
+        let mut count: u32 = 0;
+        let flags = (JS_GPN_STRING_MASK | JS_GPN_ENUM_ONLY) as i32;
+        let ret =
+            JS_GetOwnPropertyNames(ctx, &mut properties, &mut count, obj.get_qjs_value(), flags);
+        if ret != 0 {
+            return Err(JsValue::from_qjs_value(ctx, JS_GetException(ctx)));
+        }
+        let mut keys = Vec::with_capacity(count as usize);
+        for index in 0..count {
+            let prop = properties.offset(index as isize);
+            keys.push(JsValue::from_qjs_value(
+                ctx,
+                JS_AtomToString(ctx, (*prop).atom),
+            ));
+            JS_FreeAtom(ctx, (*prop).atom);
+        }
+        js_free(ctx, properties.cast());
+        Ok(keys)
+    }
+}
+
+fn get_property(ctx: *mut JSContext, obj: &JsValue, key: &JsValue) -> JsValue {
+    unsafe {
+        let atom = JS_ValueToAtom(ctx, key.get_qjs_value());
+        let obj = obj.get_qjs_value();
+        let v = JS_GetPropertyInternal(ctx, obj, atom, obj, 0);
+        JS_FreeAtom(ctx, atom);
+        JsValue::from_qjs_value(ctx, v)
+    }
+}
+
+/// Defines an own data property, so keys such as `__proto__` don't reach setters.
+fn define_property(ctx: *mut JSContext, obj: &JsValue, key: &JsValue, value: JsValue) {
+    unsafe {
+        let atom = JS_ValueToAtom(ctx, key.get_qjs_value());
+        JS_DefinePropertyValue(
+            ctx,
+            obj.get_qjs_value(),
+            atom,
+            value.into_qjs_value(),
+            JS_PROP_C_W_E as i32,
+        );
+        JS_FreeAtom(ctx, atom);
+    }
+}
+
+/// The `Object.prototype.toString` tag of `v`, without the `[object ...]` wrapper.
+pub(super) fn object_tag(ctx: &mut Context, v: &JsValue) -> String {
+    Intrinsics::new(ctx).tag(v)
+}
+
+fn check(v: JsValue) -> Result<JsValue, JsValue> {
+    if let JsValue::Exception(e) = &v {
+        let ctx = e.0.ctx;
+        return Err(unsafe { JsValue::from_qjs_value(ctx, JS_GetException(ctx)) });
+    }
+    Ok(v)
+}
+
+impl Context {
+    /// Creates an `Error` named `DataCloneError`, matching the DOMException code 25.
+    pub fn new_data_clone_error(&mut self, msg: &str) -> JsValue {
+        let mut e = self.new_error(msg);
+        if let JsValue::Object(o) = &mut e {
+            let name = self.new_string("DataCloneError");
+            o.set("name", name.into());
+            o.set("code", JsValue::Int(25));
+        }
+        e
+    }
+
+    /// Serializes `value` with the structured clone algorithm.
+    ///
+    /// Shared and cyclic references are preserved. The error is a `DataCloneError`
+    /// for values that cannot be cloned, or whatever a getter threw while reading.
+    /// `SharedArrayBuffer`s are refused, see `serialize_shared`.
+    pub fn serialize(&mut self, value: &JsValue) -> Result<Vec<u8>, JsValue> {
+        self.serialize_inner(value, None).map(|(data, _)| data)
+    }
+
+    /// Like `serialize`, but `SharedArrayBuffer`s are passed by reference alongside the data,
+    /// so the clone shares their memory. Used for messages between runtimes.
+    pub fn serialize_shared(
+        &mut self,
+        value: &JsValue,
+    ) -> Result<(Vec<u8>, Vec<SharedBuffer>), JsValue> {
+        self.serialize_inner(value, Some(vec![]))
+            .map(|(data, shared)| (data, shared.unwrap_or_default()))
+    }
+
+    fn serialize_inner(
+        &mut self,
+        value: &JsValue,
+        shared: Option<Vec<SharedBuffer>>,
+    ) -> Result<(Vec<u8>, Option<Vec<SharedBuffer>>), JsValue> {
+        let mut serializer = Serializer {
+            ctx: self.clone(),
+            intrinsics: Intrinsics::new(self),
+            buf: vec![MAGIC, VERSION],
+            seen: HashMap::new(),
+            shared,
+            depth: 0,
+        };
+        serializer.write_value(value)?;
+        Ok((serializer.buf, serializer.shared))
+    }
+
+    /// Reads data produced by `serialize`, possibly in another context or runtime.
+    pub fn deserialize(&mut self, data: &[u8]) -> Result<JsValue, JsValue> {
+        self.deserialize_shared(data, &[])
+    }
+
+    /// Reads data produced by `serialize_shared`, together with its shared buffers.
+    pub fn deserialize_shared(
+        &mut self,
+        data: &[u8],
+        shared: &[SharedBuffer],
+    ) -> Result<JsValue, JsValue> {
+        if data.len() < 2 || data[0] != MAGIC || data[1] != VERSION {
+            return Err(self.new_data_clone_error("Unable to deserialize cloned data."));
+        }
+        let mut deserializer = Deserializer {
+            ctx: self.clone(),
+            intrinsics: Intrinsics::new(self),
+            data,
+            pos: 2,
+            objects: Vec::new(),
+            shared,
+            depth: 0,
+        };
+        let value = deserializer.read_value()?;
+        if deserializer.pos != data.len() {
+            return Err(self.new_data_clone_error("Unable to deserialize cloned data."));
+        }
+        Ok(value)
+    }
+
+    /// Equivalent to `deserialize(serialize(value))` in the same context,
+    /// except that `SharedArrayBuffer`s keep sharing their memory.
+    pub fn structured_clone(&mut self, value: &JsValue) -> Result<JsValue, JsValue> {
+        let (data, shared) = self.serialize_shared(value)?;
+        self.deserialize_shared(&data, &shared)
+    }
+}
+
+struct Intrinsics {
+    global: JsObject,
+    object_to_string: JsValue,
+}
+
+impl Intrinsics {
+    fn new(ctx: &mut Context) -> Self {
+        let global = ctx.get_global();
+        let object_to_string = global
+            .get("Object")
+            .get("prototype")
+            .and_then(|proto| proto.get("toString"))
+            .unwrap_or(JsValue::UnDefined);
+        Intrinsics {
+            global,
+            object_to_string,
+        }
+    }
+
+    fn get(&self, name: &str) -> JsValue {
+        self.global.get(name)
+    }
+
+    /// `Ctor.prototype.method`, taken from the global constructor.
+    fn proto_method(&self, ctor: &str, method: &str) -> JsValue {
+        self.get(ctor)
+            .get("prototype")
+            .and_then(|proto| proto.get(method))
+            .unwrap_or(JsValue::UnDefined)
+    }
+
+    fn tag(&self, v: &JsValue) -> String {
+        match call_with_this(&self.object_to_string, v, &[]) {
+            JsValue::String(s) => {
+                let s = s.to_string();
+                s.trim_start_matches("[object ")
+                    .trim_end_matches(']')
+                    .to_string()
+            }
+            _ => String::new(),
+        }
+    }
+}
+
+struct Serializer {
+    ctx: Context,
+    intrinsics: Intrinsics,
+    buf: Vec<u8>,
+    // the objects written so far by address, kept alive so that no address is reused
+    seen: HashMap<usize, (u32, JsValue)>,
+    shared: Option<Vec<SharedBuffer>>,
+    depth: usize,
+}
+
+impl Serializer {
+    fn error(&mut self, msg: &str) -> JsValue {
+        self.ctx.new_data_clone_error(msg)
+    }
+
+    fn write_u32(&mut self, n: u32) {
+        self.buf.extend_from_slice(&n.to_le_bytes());
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) {
+        self.write_u32(bytes.len() as u32);
+        self.buf.extend_from_slice(bytes);
+    }
+
+    fn write_string(&mut self, s: &JsValue) {
+        let bytes = string_bytes(self.ctx.ctx, s);
+        self.write_bytes(&bytes);
+    }
+
+    /// Writes `v` as a string, or an empty string when it isn't one.
+    fn write_string_field(&mut self, v: JsValue) {
+        match v {
+            JsValue::String(_) => self.write_string(&v),
+            _ => self.write_bytes(&[]),
+        }
+    }
+
+    fn write_value(&mut self, v: &JsValue) -> Result<(), JsValue> {
+        match v {
+            JsValue::UnDefined => self.buf.push(TAG_UNDEFINED),
+            JsValue::Null => self.buf.push(TAG_NULL),
+            JsValue::Bool(true) => self.buf.push(TAG_TRUE),
+            JsValue::Bool(false) => self.buf.push(TAG_FALSE),
+            JsValue::Int(n) => {
+                self.buf.push(TAG_INT);
+                self.buf.extend_from_slice(&n.to_le_bytes());
+            }
+            JsValue::Float(n) => {
+                self.buf.push(TAG_NUMBER);
+                self.buf.extend_from_slice(&n.to_le_bytes());
+            }
+            JsValue::String(_) => {
+                self.buf.push(TAG_STRING);
+                self.write_string(v);
+            }
+            JsValue::BigNum(n) => {
+                if unsafe { JS_IsBigInt_real(n.0.ctx, n.0.v) } == 0 {
+                    return Err(self.error("BigFloat could not be cloned."));
+                }
+                let s = self.ctx.value_to_string(v);
+                self.buf.push(TAG_BIGINT);
+                self.write_string(&s);
+            }
+            JsValue::Symbol(_) => return Err(self.error("Symbol() could not be cloned.")),
+            JsValue::Function(f) => {
+                let name = match f.get("name") {
+                    JsValue::String(s) if !s.to_string().is_empty() => s.to_string(),
+                    _ => "function".to_string(),
+                };
+                return Err(self.error(&format!("{} could not be cloned.", name)));
+            }
+            JsValue::Promise(_) => return Err(self.error("#<Promise> could not be cloned.")),
+            JsValue::Object(_) | JsValue::Array(_) | JsValue::ArrayBuffer(_) => {
+                self.write_object(v)?
+            }
+            _ => return Err(self.error("Value could not be cloned.")),
+        }
+        Ok(())
+    }
+
+    fn write_object(&mut self, v: &JsValue) -> Result<(), JsValue> {
+        let raw = v.get_qjs_value();
+        let ptr = unsafe { JS_VALUE_GET_PTR_real(raw) } as usize;
+        if let Some((id, _)) = self.seen.get(&ptr) {
+            let id = *id;
+            self.buf.push(TAG_REFERENCE);
+            self.write_u32(id);
+            return Ok(());
+        }
+        if let Some(name) = host_class_name(raw) {
+            return Err(self.error(&format!("{} object could not be cloned.", name)));
+        }
+        if self.depth >= MAX_DEPTH {
+            return Err(self.error("Object graph is too deep to be cloned."));
+        }
+        let id = self.seen.len() as u32;
+        self.seen.insert(ptr, (id, v.clone()));
+
+        self.depth += 1;
+        let r = self.write_object_body(v);
+        self.depth -= 1;
+        r
+    }
+
+    fn write_object_body(&mut self, v: &JsValue) -> Result<(), JsValue> {
+        let ctx = self.ctx.ctx;
+        if let JsValue::Array(arr) = v {
+            let len = arr.get_length();
+            self.buf.push(TAG_ARRAY);
+            self.write_u32(len as u32);
+            for i in 0..len {
+                let item = check(arr.take(i))?;
+                self.write_value(&item)?;
+            }
+            return Ok(());
+        }
+        let tag = self.intrinsics.tag(v);
+        if let JsValue::ArrayBuffer(buf) = v {
+            if tag != "SharedArrayBuffer" {
+                self.buf.push(TAG_ARRAY_BUFFER);
+                self.write_bytes(buf.as_ref());
+                return Ok(());
+            }
+        }
+        match tag.as_str() {
+            "Date" => {
+                let get_time = self.intrinsics.proto_method("Date", "getTime");
+                let t = match check(call_with_this(&get_time, v, &[]))? {
+                    JsValue::Int(n) => n as f64,
+                    JsValue::Float(n) => n,
+                    _ => f64::NAN,
+                };
+                self.buf.push(TAG_DATE);
+                self.buf.extend_from_slice(&t.to_le_bytes());
+            }
+            "RegExp" => {
+                let source = check(v.get("source").unwrap_or(JsValue::UnDefined))?;
+                let flags = check(v.get("flags").unwrap_or(JsValue::UnDefined))?;
+                self.buf.push(TAG_REGEXP);
+                self.write_string_field(source);
+                self.write_string_field(flags);
+            }
+            "Map" | "Set" => {
+                let method = if tag == "Map" { "entries" } else { "values" };
+                let iter = self.intrinsics.proto_method(&tag, method);
+                let iter = check(call_with_this(&iter, v, &[]))?;
+                let array = self.intrinsics.get("Array");
+                let from = array.get("from").unwrap_or(JsValue::UnDefined);
+                let items = match check(call_with_this(&from, &array, &[iter]))? {
+                    JsValue::Array(items) => items,
+                    _ => return Err(self.error("Value could not be cloned.")),
+                };
+                let len = items.get_length();
+                if tag == "Map" {
+                    self.buf.push(TAG_MAP);
+                    self.write_u32(len as u32);
+                    for i in 0..len {
+                        let entry = items.take(i);
+                        self.write_value(&entry.index(0).unwrap_or(JsValue::UnDefined))?;
+                        self.write_value(&entry.index(1).unwrap_or(JsValue::UnDefined))?;
+                    }
+                } else {
+                    self.buf.push(TAG_SET);
+                    self.write_u32(len as u32);
+                    for i in 0..len {
+                        self.write_value(&items.take(i))?;
+                    }
+                }
+            }
+            "Error" => {
+                let name = check(v.get("name").unwrap_or(JsValue::UnDefined))?;
+                let name = match &name {
+                    JsValue::String(s) if ERROR_KINDS.contains(&s.to_string().as_str()) => name,
+                    _ => self.ctx.new_string("Error").into(),
+                };
+                let message = check(v.get("message").unwrap_or(JsValue::UnDefined))?;
+                let stack = check(v.get("stack").unwrap_or(JsValue::UnDefined))?;
+                self.buf.push(TAG_ERROR);
+                self.write_string(&name);
+                self.write_string_field(message);
+                self.write_string_field(stack);
+            }
+            "Number" | "Boolean" | "String" | "BigInt" => {
+                let value_of = self.intrinsics.proto_method(&tag, "valueOf");
+                let prim = check(call_with_this(&value_of, v, &[]))?;
+                self.buf.push(TAG_BOXED);
+                self.write_value(&prim)?;
+            }
+            "SharedArrayBuffer" => {
+                let buf = match SharedBuffer::from_js(&mut self.ctx, v) {
+                    Some(buf) if self.shared.is_some() => buf,
+                    _ => return Err(self.error("#<SharedArrayBuffer> could not be cloned.")),
+                };
+                let shared = self.shared.as_mut().unwrap();
+                shared.push(buf);
+                let index = shared.len() as u32 - 1;
+                self.buf.push(TAG_SHARED);
+                self.write_u32(index);
+            }
+            t if UNCLONEABLE_TAGS.contains(&t) => {
+                return Err(self.error(&format!("#<{}> could not be cloned.", t)));
+            }
+            t if VIEW_KINDS.contains(&t) => {
+                let kind = VIEW_KINDS.iter().position(|k| *k == t).unwrap();
+                let length_key = if t == "DataView" {
+                    "byteLength"
+                } else {
+                    "length"
+                };
+                let buffer = check(v.get("buffer").unwrap_or(JsValue::UnDefined))?;
+                let offset = check(v.get("byteOffset").unwrap_or(JsValue::UnDefined))?;
+                let length = check(v.get(length_key).unwrap_or(JsValue::UnDefined))?;
+                self.buf.push(TAG_VIEW);
+                self.buf.push(kind as u8);
+                self.write_value(&buffer)?;
+                self.write_u32(to_index(&offset));
+                self.write_u32(to_index(&length));
+            }
+            _ => {
+                let keys = own_enumerable_keys(ctx, v)?;
+                self.buf.push(TAG_OBJECT);
+                self.write_u32(keys.len() as u32);
+                for key in keys {
+                    let value = check(get_property(ctx, v, &key))?;
+                    self.write_string(&key);
+                    self.write_value(&value)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn to_index(v: &JsValue) -> u32 {
+    match v {
+        JsValue::Int(n) => *n as u32,
+        JsValue::Float(n) => *n as u32,
+        _ => 0,
+    }
+}
+
+struct Deserializer<'a> {
+    ctx: Context,
+    intrinsics: Intrinsics,
+    data: &'a [u8],
+    pos: usize,
+    objects: Vec<JsValue>,
+    shared: &'a [SharedBuffer],
+    depth: usize,
+}
+
+impl<'a> Deserializer<'a> {
+    fn error(&mut self) -> JsValue {
+        self.ctx
+            .new_data_clone_error("Unable to deserialize cloned data.")
+    }
+
+    fn read_u8(&mut self) -> Result<u8, JsValue> {
+        match self.data.get(self.pos) {
+            Some(b) => {
+                self.pos += 1;
+                Ok(*b)
+            }
+            None => Err(self.error()),
+        }
+    }
+
+    fn read_slice(&mut self, len: usize) -> Result<&'a [u8], JsValue> {
+        let data = self.data;
+        match self
+            .pos
+            .checked_add(len)
+            .and_then(|end| data.get(self.pos..end))
+        {
+            Some(s) => {
+                self.pos += len;
+                Ok(s)
+            }
+            None => Err(self.error()),
+        }
+    }
+
+    fn read_u32(&mut self) -> Result<u32, JsValue> {
+        let mut b = [0; 4];
+        b.copy_from_slice(self.read_slice(4)?);
+        Ok(u32::from_le_bytes(b))
+    }
+
+    fn read_f64(&mut self) -> Result<f64, JsValue> {
+        let mut b = [0; 8];
+        b.copy_from_slice(self.read_slice(8)?);
+        Ok(f64::from_le_bytes(b))
+    }
+
+    fn read_bytes(&mut self) -> Result<&'a [u8], JsValue> {
+        let len = self.read_u32()? as usize;
+        self.read_slice(len)
+    }
+
+    fn read_string(&mut self) -> Result<JsValue, JsValue> {
+        let bytes = self.read_bytes()?;
+        Ok(new_string_from_bytes(self.ctx.ctx, bytes))
+    }
+
+    /// Reserves the next object id; children may refer back to it before it is filled.
+    fn reserve(&mut self, v: JsValue) -> usize {
+        self.objects.push(v);
+        self.objects.len() - 1
+    }
+
+    fn read_value(&mut self) -> Result<JsValue, JsValue> {
+        let tag = self.read_u8()?;
+        let v = match tag {
+            TAG_UNDEFINED => JsValue::UnDefined,
+            TAG_NULL => JsValue::Null,
+            TAG_TRUE => JsValue::Bool(true),
+            TAG_FALSE => JsValue::Bool(false),
+            TAG_INT => {
+                let mut b = [0; 4];
+                b.copy_from_slice(self.read_slice(4)?);
+                JsValue::Int(i32::from_le_bytes(b))
+            }
+            TAG_NUMBER => JsValue::Float(self.read_f64()?),
+            TAG_STRING => self.read_string()?,
+            TAG_BIGINT => {
+                let s = self.read_string()?;
+                let bigint = self.intrinsics.get("BigInt");
+                check(call_with_this(&bigint, &JsValue::UnDefined, &[s]))?
+            }
+            TAG_REFERENCE => {
+                let id = self.read_u32()? as usize;
+                match self.objects.get(id) {
+                    Some(v) => v.clone(),
+                    None => return Err(self.error()),
+                }
+            }
+            _ => {
+                if self.depth >= MAX_DEPTH {
+                    return Err(self.error());
+                }
+                self.depth += 1;
+                let r = self.read_object(tag);
+                self.depth -= 1;
+                r?
+            }
+        };
+        Ok(v)
+    }
+
+    fn read_object(&mut self, tag: u8) -> Result<JsValue, JsValue> {
+        let ctx = self.ctx.ctx;
+        let v = match tag {
+            TAG_OBJECT => {
+                let obj: JsValue = self.ctx.new_object().into();
+                self.reserve(obj.clone());
+                let count = self.read_u32()?;
+                for _ in 0..count {
+                    let key = self.read_string()?;
+                    let value = self.read_value()?;
+                    define_property(ctx, &obj, &key, value);
+                }
+                obj
+            }
+            TAG_ARRAY => {
+                let mut arr = self.ctx.new_array();
+                self.reserve(arr.clone().into());
+                let len = self.read_u32()? as usize;
+                arr.set_length(len);
+                for i in 0..len {
+                    let value = self.read_value()?;
+                    arr.put(i, value);
+                }
+                arr.into()
+            }
+            TAG_ARRAY_BUFFER => {
+                let bytes = self.read_bytes()?;
+                let buf: JsValue = self.ctx.new_array_buffer(bytes).into();
+                self.reserve(buf.clone());
+                buf
+            }
+            TAG_SHARED => {
+                let index = self.read_u32()? as usize;
+                let buf = match self.shared.get(index) {
+                    Some(buf) => buf.to_js(&mut self.ctx),
+                    None => return Err(self.error()),
+                };
+                self.reserve(buf.clone());
+                buf
+            }
+            TAG_MAP | TAG_SET => {
+                let name = if tag == TAG_MAP { "Map" } else { "Set" };
+                let ctor = self.intrinsics.get(name);
+                let obj = check(construct(&ctor, &[]))?;
+                self.reserve(obj.clone());
+                let add = if tag == TAG_MAP { "set" } else { "add" };
+                let add = self.intrinsics.proto_method(name, add);
+                let count = self.read_u32()?;
+                for _ in 0..count {
+                    if tag == TAG_MAP {
+                        let key = self.read_value()?;
+                        let value = self.read_value()?;
+                        check(call_with_this(&add, &obj, &[key, value]))?;
+                    } else {
+                        let value = self.read_value()?;
+                        check(call_with_this(&add, &obj, &[value]))?;
+                    }
+                }
+                obj
+            }
+            TAG_DATE => {
+                let id = self.reserve(JsValue::UnDefined);
+                let t = self.read_f64()?;
+                let ctor = self.intrinsics.get("Date");
+                let date = check(construct(&ctor, &[JsValue::Float(t)]))?;
+                self.objects[id] = date.clone();
+                date
+            }
+            TAG_REGEXP => {
+                let id = self.reserve(JsValue::UnDefined);
+                let source = self.read_string()?;
+                let flags = self.read_string()?;
+                let ctor = self.intrinsics.get("RegExp");
+                let re = check(construct(&ctor, &[source, flags]))?;
+                self.objects[id] = re.clone();
+                re
+            }
+            TAG_ERROR => {
+                let id = self.reserve(JsValue::UnDefined);
+                let name = self.read_string()?;
+                let message = self.read_string()?;
+                let stack = self.read_string()?;
+                let ctor = match &name {
+                    JsValue::String(s) if ERROR_KINDS.contains(&s.to_string().as_str()) => {
+                        self.intrinsics.get(&s.to_string())
+                    }
+                    _ => self.intrinsics.get("Error"),
+                };
+                let error = check(construct(&ctor, &[message]))?;
+                let stack_key: JsValue = self.ctx.new_string("stack").into();
+                define_property(ctx, &error, &stack_key, stack);
+                self.objects[id] = error.clone();
+                error
+            }
+            TAG_BOXED => {
+                let id = self.reserve(JsValue::UnDefined);
+                let prim = self.read_value()?;
+                let object = self.intrinsics.get("Object");
+                let boxed = check(call_with_this(&object, &JsValue::UnDefined, &[prim]))?;
+                self.objects[id] = boxed.clone();
+                boxed
+            }
+            TAG_VIEW => {
+                let id = self.reserve(JsValue::UnDefined);
+                let kind = self.read_u8()? as usize;
+                let name = match VIEW_KINDS.get(kind) {
+                    Some(name) => *name,
+                    None => return Err(self.error()),
+                };
+                let buffer = self.read_value()?;
+                let offset = self.read_u32()?;
+                let length = self.read_u32()?;
+                let ctor = self.intrinsics.get(name);
+                let view = check(construct(
+                    &ctor,
+                    &[
+                        buffer,
+                        JsValue::Float(offset as f64),
+                        JsValue::Float(length as f64),
+                    ],
+                ))?;
+                self.objects[id] = view.clone();
+                view
+            }
+            _ => return Err(self.error()),
+        };
+        Ok(v)
+    }
+}
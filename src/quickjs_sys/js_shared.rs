@@ -0,0 +1,760 @@
+//! `SharedArrayBuffer` memory that can be shared between runtimes, and the `Atomics` global.
+//!
+//! Every `Runtime` allocates shared buffers through `sab_alloc`, which puts a reference
+//! count in front of the data. A `SharedBuffer` holds one of those references, so the
+//! memory outlives the runtime that created it for as long as another runtime uses it.
+//!
+//! Runtimes in this crate usually share one thread, so a blocking `Atomics.wait` can only
+//! be woken by a runtime on another thread. It throws unless the memory is currently shared
+//! with another thread, like it does on a browser's main thread; `Atomics.waitAsync` is
+//! the way for workers to coordinate.
+
+use std::alloc::{alloc_zeroed, dealloc, Layout};
+use std::sync::atomic::{AtomicU16, AtomicU32, AtomicU64, AtomicU8, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, ThreadId};
+use std::time::Duration;
+
+use crate::{AsObject, Context, JsValue};
+
+use super::js_serialize::object_tag;
+use super::qjs::*;
+use super::{JsException, JsRef};
+
+const SAB_ALIGN: usize = 16;
+
+#[repr(C)]
+struct SabHeader {
+    refs: AtomicUsize,
+    size: usize,
+    // how many of the references each thread holds; while it has one it may notify a waiter
+    threads: Mutex<Vec<(ThreadId, usize)>>,
+}
+
+const SAB_HEADER_SIZE: usize =
+    (std::mem::size_of::<SabHeader>() + SAB_ALIGN - 1) & !(SAB_ALIGN - 1);
+
+fn sab_layout(size: usize) -> Layout {
+    Layout::from_size_align(SAB_HEADER_SIZE + size, SAB_ALIGN).unwrap()
+}
+
+unsafe fn sab_header(ptr: *mut u8) -> *mut SabHeader {
+    ptr.sub(SAB_HEADER_SIZE).cast()
+}
+
+unsafe fn sab_new(size: usize) -> *mut u8 {
+    let base = alloc_zeroed(sab_layout(size));
+    if base.is_null() {
+        return base;
+    }
+    base.cast::<SabHeader>().write(SabHeader {
+        refs: AtomicUsize::new(1),
+        size,
+        threads: Mutex::new(vec![(thread::current().id(), 1)]),
+    });
+    base.add(SAB_HEADER_SIZE)
+}
+
+/// Takes a reference on behalf of `thread`.
+unsafe fn sab_ref(ptr: *mut u8, thread: ThreadId) {
+    let header = sab_header(ptr);
+    (*header).refs.fetch_add(1, Ordering::SeqCst);
+    let mut threads = (*header).threads.lock().unwrap();
+    match threads.iter_mut().find(|(id, _)| *id == thread) {
+        Some((_, refs)) => *refs += 1,
+        None => threads.push((thread, 1)),
+    }
+}
+
+/// Whether a thread other than this one holds the memory, and so could wake a waiter.
+unsafe fn sab_shared_across_threads(ptr: *mut u8) -> bool {
+    let current = thread::current().id();
+    let threads = (*sab_header(ptr)).threads.lock().unwrap();
+    threads.iter().any(|(id, _)| *id != current)
+}
+
+/// Drops a reference `thread` took, freeing the memory with the last one.
+unsafe fn sab_unref(ptr: *mut u8, thread: ThreadId) {
+    let header = sab_header(ptr);
+    {
+        let mut threads = (*header).threads.lock().unwrap();
+        if let Some(i) = threads.iter().position(|(id, _)| *id == thread) {
+            threads[i].1 -= 1;
+            if threads[i].1 == 0 {
+                threads.swap_remove(i);
+            }
+        }
+    }
+    if (*header).refs.fetch_sub(1, Ordering::SeqCst) == 1 {
+        let size = (*header).size;
+        std::ptr::drop_in_place(header);
+        dealloc(header.cast(), sab_layout(size));
+    }
+}
+
+unsafe extern "C" fn sab_alloc(
+    _opaque: *mut ::std::os::raw::c_void,
+    size: usize,
+) -> *mut ::std::os::raw::c_void {
+    sab_new(size).cast()
+}
+
+unsafe extern "C" fn sab_free(
+    _opaque: *mut ::std::os::raw::c_void,
+    ptr: *mut ::std::os::raw::c_void,
+) {
+    // a runtime frees its buffers on the thread it runs on
+    sab_unref(ptr.cast(), thread::current().id())
+}
+
+unsafe extern "C" fn sab_dup(
+    _opaque: *mut ::std::os::raw::c_void,
+    ptr: *mut ::std::os::raw::c_void,
+) {
+    sab_ref(ptr.cast(), thread::current().id())
+}
+
+pub(crate) unsafe fn init_shared_array_buffer(rt: *mut JSRuntime) {
+    let sf = JSSharedArrayBufferFunctions {
+        sab_alloc: Some(sab_alloc),
+        sab_free: Some(sab_free),
+        sab_dup: Some(sab_dup),
+        sab_opaque: std::ptr::null_mut(),
+    };
+    JS_SetSharedArrayBufferFunctions(rt, &sf);
+}
+
+/// A reference to the memory of a `SharedArrayBuffer`, usable from any runtime or thread.
+pub struct SharedBuffer {
+    ptr: *mut u8,
+    len: usize,
+    // the thread the reference is counted for, wherever it is dropped
+    thread: ThreadId,
+}
+
+unsafe impl Send for SharedBuffer {}
+unsafe impl Sync for SharedBuffer {}
+
+impl SharedBuffer {
+    /// Allocates zeroed shared memory.
+    pub fn new(len: usize) -> Self {
+        let ptr = unsafe { sab_new(len.max(1)) };
+        assert!(!ptr.is_null(), "out of memory");
+        SharedBuffer {
+            ptr,
+            len,
+            thread: thread::current().id(),
+        }
+    }
+
+    /// Takes a reference to the memory of a `SharedArrayBuffer`.
+    pub fn from_js(ctx: &mut Context, v: &JsValue) -> Option<Self> {
+        if object_tag(ctx, v) != "SharedArrayBuffer" {
+            return None;
+        }
+        unsafe {
+            let mut len = 0;
+            let ptr = JS_GetArrayBuffer(ctx.ctx, &mut len, v.get_qjs_value());
+            if ptr.is_null() {
+                return None;
+            }
+            let thread = thread::current().id();
+            sab_ref(ptr, thread);
+            Some(SharedBuffer { ptr, len, thread })
+        }
+    }
+
+    /// Creates a `SharedArrayBuffer` in `ctx` backed by this memory.
+    pub fn to_js(&self, ctx: &mut Context) -> JsValue {
+        unsafe {
+            sab_ref(self.ptr, thread::current().id());
+            let v = JS_NewArrayBuffer(ctx.ctx, self.ptr, self.len, None, std::ptr::null_mut(), 1);
+            JsValue::from_qjs_value(ctx.ctx, v)
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn as_ptr(&self) -> *mut u8 {
+        self.ptr
+    }
+}
+
+impl Clone for SharedBuffer {
+    fn clone(&self) -> Self {
+        let thread = thread::current().id();
+        unsafe { sab_ref(self.ptr, thread) };
+        SharedBuffer {
+            ptr: self.ptr,
+            len: self.len,
+            thread,
+        }
+    }
+}
+
+impl Drop for SharedBuffer {
+    fn drop(&mut self) {
+        unsafe { sab_unref(self.ptr, self.thread) }
+    }
+}
+
+impl std::fmt::Debug for SharedBuffer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SharedBuffer")
+            .field("ptr", &self.ptr)
+            .field("len", &self.len)
+            .finish()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ElementKind {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    I64,
+    U64,
+}
+
+impl ElementKind {
+    fn from_tag(tag: &str) -> Option<Self> {
+        Some(match tag {
+            "Int8Array" => ElementKind::I8,
+            "Uint8Array" => ElementKind::U8,
+            "Int16Array" => ElementKind::I16,
+            "Uint16Array" => ElementKind::U16,
+            "Int32Array" => ElementKind::I32,
+            "Uint32Array" => ElementKind::U32,
+            "BigInt64Array" => ElementKind::I64,
+            "BigUint64Array" => ElementKind::U64,
+            _ => return None,
+        })
+    }
+
+    fn is_bigint(self) -> bool {
+        matches!(self, ElementKind::I64 | ElementKind::U64)
+    }
+
+    fn can_wait(self) -> bool {
+        matches!(self, ElementKind::I32 | ElementKind::I64)
+    }
+}
+
+/// One element of an integer typed array, as addressed by an `Atomics` call.
+struct Element {
+    kind: ElementKind,
+    /// the start of the buffer's memory
+    base: *mut u8,
+    ptr: *mut u8,
+    shared: bool,
+}
+
+#[derive(Clone, Copy)]
+enum Op {
+    Load,
+    Store,
+    Exchange,
+    CompareExchange(u64),
+    Add,
+    Sub,
+    And,
+    Or,
+    Xor,
+}
+
+macro_rules! atomic_op {
+    ($atomic:ty, $int:ty, $ptr:expr, $op:expr, $v:expr) => {{
+        let a = &*($ptr as *const $atomic);
+        let v = $v as $int;
+        let old = match $op {
+            Op::Load => a.load(Ordering::SeqCst),
+            Op::Store => {
+                a.store(v, Ordering::SeqCst);
+                v
+            }
+            Op::Exchange => a.swap(v, Ordering::SeqCst),
+            Op::CompareExchange(expected) => {
+                match a.compare_exchange(expected as $int, v, Ordering::SeqCst, Ordering::SeqCst) {
+                    Ok(old) | Err(old) => old,
+                }
+            }
+            Op::Add => a.fetch_add(v, Ordering::SeqCst),
+            Op::Sub => a.fetch_sub(v, Ordering::SeqCst),
+            Op::And => a.fetch_and(v, Ordering::SeqCst),
+            Op::Or => a.fetch_or(v, Ordering::SeqCst),
+            Op::Xor => a.fetch_xor(v, Ordering::SeqCst),
+        };
+        old as u64
+    }};
+}
+
+impl Element {
+    /// Applies `op` and returns the previous value, zero-extended to 64 bits.
+    fn apply(&self, op: Op, v: u64) -> u64 {
+        unsafe {
+            match self.kind {
+                ElementKind::I8 | ElementKind::U8 => atomic_op!(AtomicU8, u8, self.ptr, op, v),
+                ElementKind::I16 | ElementKind::U16 => {
+                    atomic_op!(AtomicU16, u16, self.ptr, op, v)
+                }
+                ElementKind::I32 | ElementKind::U32 => {
+                    atomic_op!(AtomicU32, u32, self.ptr, op, v)
+                }
+                ElementKind::I64 | ElementKind::U64 => {
+                    atomic_op!(AtomicU64, u64, self.ptr, op, v)
+                }
+            }
+        }
+    }
+
+    fn load(&self) -> u64 {
+        self.apply(Op::Load, 0)
+    }
+
+    fn to_js(&self, ctx: &mut Context, bits: u64) -> JsValue {
+        match self.kind {
+            ElementKind::I8 => JsValue::Int(bits as u8 as i8 as i32),
+            ElementKind::U8 => JsValue::Int(bits as u8 as i32),
+            ElementKind::I16 => JsValue::Int(bits as u16 as i16 as i32),
+            ElementKind::U16 => JsValue::Int(bits as u16 as i32),
+            ElementKind::I32 => JsValue::Int(bits as u32 as i32),
+            ElementKind::U32 => {
+                let n = bits as u32;
+                if n <= i32::MAX as u32 {
+                    JsValue::Int(n as i32)
+                } else {
+                    JsValue::Float(n as f64)
+                }
+            }
+            ElementKind::I64 => unsafe {
+                JsValue::from_qjs_value(ctx.ctx, JS_NewBigInt64(ctx.ctx, bits as i64))
+            },
+            ElementKind::U64 => unsafe {
+                JsValue::from_qjs_value(ctx.ctx, JS_NewBigUint64(ctx.ctx, bits))
+            },
+        }
+    }
+
+    fn key(&self) -> usize {
+        self.ptr as usize
+    }
+}
+
+fn pending_exception(ctx: &mut Context) -> JsValue {
+    JsValue::Exception(JsException(JsRef {
+        ctx: ctx.ctx,
+        v: js_exception(),
+    }))
+}
+
+/// Converts an `Atomics` operand to the element's bits, wrapping like a typed array store.
+fn to_bits(ctx: &mut Context, kind: ElementKind, v: Option<&JsValue>) -> Result<u64, JsValue> {
+    let v = v.cloned().unwrap_or(JsValue::UnDefined);
+    let mut n = 0_i64;
+    let r = unsafe {
+        if kind.is_bigint() {
+            JS_ToBigInt64(ctx.ctx, &mut n, v.get_qjs_value())
+        } else {
+            JS_ToInt64Ext(ctx.ctx, &mut n, v.get_qjs_value())
+        }
+    };
+    if r != 0 {
+        return Err(pending_exception(ctx));
+    }
+    Ok(n as u64)
+}
+
+fn get_element(ctx: &mut Context, argv: &[JsValue]) -> Result<Element, JsValue> {
+    let array = argv.get(0).cloned().unwrap_or(JsValue::UnDefined);
+    let kind = match ElementKind::from_tag(&object_tag(ctx, &array)) {
+        Some(kind) => kind,
+        None => {
+            let msg = "Atomics operations require an integer TypedArray";
+            return Err(ctx.throw_type_error(msg).into());
+        }
+    };
+    unsafe {
+        let mut offset = 0;
+        let mut byte_length = 0;
+        let mut bytes_per_element = 0;
+        let buffer = JS_GetTypedArrayBuffer(
+            ctx.ctx,
+            array.get_qjs_value(),
+            &mut offset,
+            &mut byte_length,
+            &mut bytes_per_element,
+        );
+        let buffer = JsValue::from_qjs_value(ctx.ctx, buffer);
+        if let JsValue::Exception(_) = buffer {
+            return Err(buffer);
+        }
+        let mut size = 0;
+        let base = JS_GetArrayBuffer(ctx.ctx, &mut size, buffer.get_qjs_value());
+        if base.is_null() {
+            return Err(ctx.throw_type_error("TypedArray is detached").into());
+        }
+        let shared = object_tag(ctx, &buffer) == "SharedArrayBuffer";
+
+        let mut index = 0_u64;
+        let index_value = argv.get(1).cloned().unwrap_or(JsValue::UnDefined);
+        if JS_ToIndex(ctx.ctx, &mut index, index_value.get_qjs_value()) != 0 {
+            return Err(pending_exception(ctx));
+        }
+        if index >= (byte_length / bytes_per_element.max(1)) as u64 {
+            return Err(ctx.throw_range_error("out-of-bound access").into());
+        }
+        Ok(Element {
+            kind,
+            base,
+            ptr: base.add(offset + index as usize * bytes_per_element),
+            shared,
+        })
+    }
+}
+
+fn atomics_rmw(ctx: &mut Context, argv: &[JsValue], op: Op) -> JsValue {
+    let element = match get_element(ctx, argv) {
+        Ok(element) => element,
+        Err(e) => return e,
+    };
+    let op = match op {
+        Op::CompareExchange(_) => match to_bits(ctx, element.kind, argv.get(2)) {
+            Ok(expected) => Op::CompareExchange(expected),
+            Err(e) => return e,
+        },
+        op => op,
+    };
+    let value_index = if let Op::CompareExchange(_) = op {
+        3
+    } else {
+        2
+    };
+    let bits = match op {
+        Op::Load => 0,
+        _ => match to_bits(ctx, element.kind, argv.get(value_index)) {
+            Ok(bits) => bits,
+            Err(e) => return e,
+        },
+    };
+    let old = element.apply(op, bits);
+    match op {
+        // store returns the value it was given, not the wrapped one
+        Op::Store if element.kind.is_bigint() => argv.get(2).cloned().unwrap_or(JsValue::UnDefined),
+        Op::Store => match argv.get(2) {
+            Some(JsValue::Int(n)) => JsValue::Int(*n),
+            Some(JsValue::Float(n)) if n.is_nan() => JsValue::Int(0),
+            Some(JsValue::Float(n)) => JsValue::Float(n.trunc() + 0.0),
+            _ => element.to_js(ctx, bits),
+        },
+        _ => element.to_js(ctx, old),
+    }
+}
+
+fn atomics_load(ctx: &mut Context, _this: JsValue, argv: &[JsValue]) -> JsValue {
+    atomics_rmw(ctx, argv, Op::Load)
+}
+
+fn atomics_store(ctx: &mut Context, _this: JsValue, argv: &[JsValue]) -> JsValue {
+    atomics_rmw(ctx, argv, Op::Store)
+}
+
+fn atomics_exchange(ctx: &mut Context, _this: JsValue, argv: &[JsValue]) -> JsValue {
+    atomics_rmw(ctx, argv, Op::Exchange)
+}
+
+fn atomics_compare_exchange(ctx: &mut Context, _this: JsValue, argv: &[JsValue]) -> JsValue {
+    atomics_rmw(ctx, argv, Op::CompareExchange(0))
+}
+
+fn atomics_add(ctx: &mut Context, _this: JsValue, argv: &[JsValue]) -> JsValue {
+    atomics_rmw(ctx, argv, Op::Add)
+}
+
+fn atomics_sub(ctx: &mut Context, _this: JsValue, argv: &[JsValue]) -> JsValue {
+    atomics_rmw(ctx, argv, Op::Sub)
+}
+
+fn atomics_and(ctx: &mut Context, _this: JsValue, argv: &[JsValue]) -> JsValue {
+    atomics_rmw(ctx, argv, Op::And)
+}
+
+fn atomics_or(ctx: &mut Context, _this: JsValue, argv: &[JsValue]) -> JsValue {
+    atomics_rmw(ctx, argv, Op::Or)
+}
+
+fn atomics_xor(ctx: &mut Context, _this: JsValue, argv: &[JsValue]) -> JsValue {
+    atomics_rmw(ctx, argv, Op::Xor)
+}
+
+fn atomics_is_lock_free(_ctx: &mut Context, _this: JsValue, argv: &[JsValue]) -> JsValue {
+    let size = match argv.get(0) {
+        Some(JsValue::Int(n)) => *n as f64,
+        Some(JsValue::Float(n)) => *n,
+        _ => 0.0,
+    };
+    JsValue::Bool([1.0, 2.0, 4.0, 8.0].contains(&size))
+}
+
+/// A waiter blocked in `Atomics.wait` or pending in `Atomics.waitAsync`.
+struct Waiter {
+    key: usize,
+    woken: Mutex<bool>,
+    cond: Condvar,
+    notify: tokio::sync::Notify,
+}
+
+impl Waiter {
+    fn new(key: usize) -> Arc<Self> {
+        Arc::new(Waiter {
+            key,
+            woken: Mutex::new(false),
+            cond: Condvar::new(),
+            notify: tokio::sync::Notify::new(),
+        })
+    }
+
+    fn wake(&self) {
+        *self.woken.lock().unwrap() = true;
+        self.cond.notify_all();
+        self.notify.notify_one();
+    }
+}
+
+// process-wide, since the memory may be shared by runtimes on different threads
+static WAITERS: Mutex<Vec<Arc<Waiter>>> = Mutex::new(Vec::new());
+
+/// Removes `waiter` after a timeout. Returns false if it was woken in the meantime.
+fn remove_waiter(waiter: &Arc<Waiter>) -> bool {
+    let mut waiters = WAITERS.lock().unwrap();
+    match waiters.iter().position(|w| Arc::ptr_eq(w, waiter)) {
+        Some(i) => {
+            waiters.remove(i);
+            true
+        }
+        None => false,
+    }
+}
+
+fn get_wait_element(ctx: &mut Context, argv: &[JsValue]) -> Result<Element, JsValue> {
+    let element = get_element(ctx, argv)?;
+    if !element.kind.can_wait() {
+        let msg = "Atomics.wait requires an Int32Array or BigInt64Array";
+        return Err(ctx.throw_type_error(msg).into());
+    }
+    Ok(element)
+}
+
+/// `undefined`, `NaN` and `Infinity` (or anything past ~30000 years) wait forever; negative timeouts don't wait at all.
+fn get_wait_timeout(ctx: &mut Context, v: Option<&JsValue>) -> Result<Option<Duration>, JsValue> {
+    let v = match v {
+        None | Some(JsValue::UnDefined) => return Ok(None),
+        Some(v) => v,
+    };
+    let mut ms = 0_f64;
+    if unsafe { JS_ToFloat64(ctx.ctx, &mut ms, v.get_qjs_value()) } != 0 {
+        return Err(pending_exception(ctx));
+    }
+    if ms.is_nan() || ms > 1e15 {
+        Ok(None)
+    } else {
+        Ok(Some(Duration::from_secs_f64(ms.max(0.0) / 1000.0)))
+    }
+}
+
+/// Registers a waiter unless the element no longer holds `expected`.
+fn start_wait(element: &Element, expected: u64) -> Option<Arc<Waiter>> {
+    let mut waiters = WAITERS.lock().unwrap();
+    let current = element.load();
+    let expected = if element.kind == ElementKind::I32 {
+        expected as u32 as u64
+    } else {
+        expected
+    };
+    if current != expected {
+        return None;
+    }
+    let waiter = Waiter::new(element.key());
+    waiters.push(waiter.clone());
+    Some(waiter)
+}
+
+fn atomics_wait(ctx: &mut Context, _this: JsValue, argv: &[JsValue]) -> JsValue {
+    let element = match get_wait_element(ctx, argv) {
+        Ok(element) => element,
+        Err(e) => return e,
+    };
+    if !element.shared {
+        let msg = "Atomics.wait requires a shared typed array";
+        return ctx.throw_type_error(msg).into();
+    }
+    let expected = match to_bits(ctx, element.kind, argv.get(2)) {
+        Ok(bits) => bits,
+        Err(e) => return e,
+    };
+    let timeout = match get_wait_timeout(ctx, argv.get(3)) {
+        Ok(timeout) => timeout,
+        Err(e) => return e,
+    };
+    // nothing on this thread can run while it is blocked
+    if !unsafe { sab_shared_across_threads(element.base) } {
+        let msg = "Atomics.wait cannot be called in this context";
+        return ctx.throw_type_error(msg).into();
+    }
+
+    let waiter = match start_wait(&element, expected) {
+        Some(waiter) => waiter,
+        None => return ctx.new_string("not-equal").into(),
+    };
+    let woken = waiter.woken.lock().unwrap();
+    let woken = match timeout {
+        Some(timeout) => {
+            waiter
+                .cond
+                .wait_timeout_while(woken, timeout, |woken| !*woken)
+                .unwrap()
+                .0
+        }
+        None => waiter.cond.wait_while(woken, |woken| !*woken).unwrap(),
+    };
+    let timed_out = !*woken && {
+        drop(woken);
+        remove_waiter(&waiter)
+    };
+    ctx.new_string(if timed_out { "timed-out" } else { "ok" })
+        .into()
+}
+
+fn wait_async_result(ctx: &mut Context, is_async: bool, value: JsValue) -> JsValue {
+    let mut result = ctx.new_object();
+    result.set("async", JsValue::Bool(is_async));
+    result.set("value", value);
+    result.into()
+}
+
+fn atomics_wait_async(ctx: &mut Context, _this: JsValue, argv: &[JsValue]) -> JsValue {
+    let element = match get_wait_element(ctx, argv) {
+        Ok(element) => element,
+        Err(e) => return e,
+    };
+    if !element.shared {
+        let msg = "Atomics.waitAsync requires a shared typed array";
+        return ctx.throw_type_error(msg).into();
+    }
+    let expected = match to_bits(ctx, element.kind, argv.get(2)) {
+        Ok(bits) => bits,
+        Err(e) => return e,
+    };
+    let timeout = match get_wait_timeout(ctx, argv.get(3)) {
+        Ok(timeout) => timeout,
+        Err(e) => return e,
+    };
+
+    let waiter = match start_wait(&element, expected) {
+        Some(waiter) => waiter,
+        None => {
+            let value = ctx.new_string("not-equal").into();
+            return wait_async_result(ctx, false, value);
+        }
+    };
+    if timeout == Some(Duration::ZERO) && remove_waiter(&waiter) {
+        let value = ctx.new_string("timed-out").into();
+        return wait_async_result(ctx, false, value);
+    }
+
+    let mut async_ctx = ctx.clone();
+    let promise = ctx.future_to_promise(async move {
+        let notified = waiter.notify.notified();
+        let timed_out = match timeout {
+            Some(timeout) => tokio::time::timeout(timeout, notified).await.is_err(),
+            None => {
+                notified.await;
+                false
+            }
+        };
+        let timed_out = timed_out && remove_waiter(&waiter);
+        Ok(async_ctx
+            .new_string(if timed_out { "timed-out" } else { "ok" })
+            .into())
+    });
+    wait_async_result(ctx, true, promise)
+}
+
+fn atomics_notify(ctx: &mut Context, _this: JsValue, argv: &[JsValue]) -> JsValue {
+    let element = match get_wait_element(ctx, argv) {
+        Ok(element) => element,
+        Err(e) => return e,
+    };
+    let count = match argv.get(2) {
+        None | Some(JsValue::UnDefined) => usize::MAX,
+        Some(v) => {
+            let mut n = 0_f64;
+            if unsafe { JS_ToFloat64(ctx.ctx, &mut n, v.get_qjs_value()) } != 0 {
+                return pending_exception(ctx);
+            }
+            if n.is_nan() {
+                0
+            } else {
+                n.max(0.0).min(usize::MAX as f64) as usize
+            }
+        }
+    };
+    if !element.shared {
+        return JsValue::Int(0);
+    }
+
+    let key = element.key();
+    let mut woken = vec![];
+    {
+        let mut waiters = WAITERS.lock().unwrap();
+        let mut i = 0;
+        while i < waiters.len() && woken.len() < count {
+            if waiters[i].key == key {
+                woken.push(waiters.remove(i));
+            } else {
+                i += 1;
+            }
+        }
+    }
+    for waiter in &woken {
+        waiter.wake();
+    }
+    JsValue::Int(woken.len() as i32)
+}
+
+pub(crate) fn init_atomics(ctx: &mut Context) {
+    let mut atomics = ctx.new_object();
+    atomics.set("load", ctx.wrap_function("load", atomics_load).into());
+    atomics.set("store", ctx.wrap_function("store", atomics_store).into());
+    atomics.set(
+        "exchange",
+        ctx.wrap_function("exchange", atomics_exchange).into(),
+    );
+    atomics.set(
+        "compareExchange",
+        ctx.wrap_function("compareExchange", atomics_compare_exchange)
+            .into(),
+    );
+    atomics.set("add", ctx.wrap_function("add", atomics_add).into());
+    atomics.set("sub", ctx.wrap_function("sub", atomics_sub).into());
+    atomics.set("and", ctx.wrap_function("and", atomics_and).into());
+    atomics.set("or", ctx.wrap_function("or", atomics_or).into());
+    atomics.set("xor", ctx.wrap_function("xor", atomics_xor).into());
+    atomics.set(
+        "isLockFree",
+        ctx.wrap_function("isLockFree", atomics_is_lock_free).into(),
+    );
+    atomics.set("wait", ctx.wrap_function("wait", atomics_wait).into());
+    atomics.set(
+        "waitAsync",
+        ctx.wrap_function("waitAsync", atomics_wait_async).into(),
+    );
+    atomics.set("notify", ctx.wrap_function("notify", atomics_notify).into());
+
+    let mut global = ctx.get_global();
+    global.set("Atomics", atomics.into());
+}
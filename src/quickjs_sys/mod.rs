@@ -2,12 +2,19 @@
 mod macros;
 pub mod js_class;
 pub mod js_module;
+pub mod js_profiler;
 pub mod js_promise;
+pub mod js_script;
+mod js_serialize;
+pub mod js_shared;
 
 use std::collections::HashMap;
 
 pub use js_class::*;
 pub use js_module::{JsModuleDef, ModuleInit};
+pub use js_profiler::CpuProfile;
+pub use js_script::{detect_module, JsCompiledModule, JsModuleStatus};
+pub use js_shared::SharedBuffer;
 
 #[allow(warnings)]
 mod qjs {
@@ -92,7 +99,7 @@ unsafe extern "C" fn module_loader(
         "" => {
             path.set_extension("js");
         }
-        "js" => {}
+        "js" | "mjs" => {}
         _ => {
             JS_ThrowReferenceError(
                 ctx,
@@ -149,16 +156,128 @@ impl Drop for InnerRuntime {
         unsafe { JS_FreeRuntime(self.0) };
     }
 }
+
+/// A QuickJS runtime and its event loop.
+///
+/// It must be driven by a current-thread tokio runtime: the tasks it spawns hold JS values,
+/// which are marked `Send` only so they can be spawned, and must not leave the thread.
 pub struct Runtime {
     ctx: Context,
     rt: InnerRuntime,
 }
 
+/// Selects the internal modules installed into a new `Context`.
+///
+/// The core globals (`setTimeout`, `print`, `__dirname`, ...) are always installed.
+#[derive(Debug, Clone)]
+pub struct ContextOptions {
+    /// the global `console`
+    pub console: bool,
+    /// the `std` and `qjs:os` modules
+    pub std: bool,
+    /// `TextEncoder`/`TextDecoder`
+    pub encoding: bool,
+    /// `wasi_net` and `wasi_http`
+    pub net: bool,
+    /// `_node:os`
+    pub os: bool,
+    /// `_node:fs`
+    pub fs: bool,
+    /// `_node:crypto`, only with the `nodejs_crypto` feature
+    pub crypto: bool,
+    /// modules from the `img`, `tensorflow`, `wasi_nn` and `ggml` features
+    pub plugins: bool,
+}
+
+impl Default for ContextOptions {
+    fn default() -> Self {
+        ContextOptions {
+            console: true,
+            std: true,
+            encoding: true,
+            net: true,
+            os: true,
+            fs: true,
+            crypto: true,
+            plugins: true,
+        }
+    }
+}
+
+impl ContextOptions {
+    /// Only the core globals, no internal modules.
+    pub fn minimal() -> Self {
+        ContextOptions {
+            console: false,
+            std: false,
+            encoding: false,
+            net: false,
+            os: false,
+            fs: false,
+            crypto: false,
+            plugins: false,
+        }
+    }
+}
+
+/// A snapshot of the runtime's heap, from `JS_ComputeMemoryUsage`. Sizes are in bytes.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MemoryUsage {
+    /// bytes allocated by the runtime's allocator, including its overhead
+    pub malloc_size: i64,
+    /// the limit set with `set_memory_limit`, or -1 if there is none
+    pub malloc_limit: i64,
+    /// bytes used by live objects, strings, functions and other GC data
+    pub memory_used_size: i64,
+    pub malloc_count: i64,
+    pub atom_count: i64,
+    pub atom_size: i64,
+    pub str_count: i64,
+    pub str_size: i64,
+    pub obj_count: i64,
+    pub obj_size: i64,
+    pub js_func_count: i64,
+    pub js_func_size: i64,
+    /// bytecode size of the JS functions
+    pub js_func_code_size: i64,
+    pub c_func_count: i64,
+    pub array_count: i64,
+    /// `ArrayBuffer`s and typed arrays
+    pub binary_object_count: i64,
+    /// bytes held by `ArrayBuffer`s
+    pub binary_object_size: i64,
+}
+
+impl From<JSMemoryUsage> for MemoryUsage {
+    fn from(s: JSMemoryUsage) -> Self {
+        MemoryUsage {
+            malloc_size: s.malloc_size,
+            malloc_limit: s.malloc_limit,
+            memory_used_size: s.memory_used_size,
+            malloc_count: s.malloc_count,
+            atom_count: s.atom_count,
+            atom_size: s.atom_size,
+            str_count: s.str_count,
+            str_size: s.str_size,
+            obj_count: s.obj_count,
+            obj_size: s.obj_size,
+            js_func_count: s.js_func_count,
+            js_func_size: s.js_func_size,
+            js_func_code_size: s.js_func_code_size,
+            c_func_count: s.c_func_count,
+            array_count: s.array_count,
+            binary_object_count: s.binary_object_count,
+            binary_object_size: s.binary_object_size,
+        }
+    }
+}
+
 impl Runtime {
     pub fn new() -> Self {
         unsafe {
             let raw_rt = JS_NewRuntime();
-            let ctx = Context::new_with_rt(raw_rt);
+            js_shared::init_shared_array_buffer(raw_rt);
+            let ctx = Context::new_with_rt(raw_rt, &ContextOptions::default());
             JS_SetModuleLoaderFunc(raw_rt, None, Some(module_loader), std::ptr::null_mut());
 
             let mut rt = Runtime {
@@ -175,6 +294,11 @@ impl Runtime {
             let event_loop = Box::new(super::EventLoop::default());
             let event_loop_ptr: &'static mut super::EventLoop = Box::leak(event_loop);
             JS_SetRuntimeOpaque(self.rt.0, (event_loop_ptr as *mut super::EventLoop).cast());
+            JS_SetInterruptHandler(
+                self.rt.0,
+                Some(js_script::interrupt_handler),
+                std::ptr::null_mut(),
+            );
         }
     }
     fn drop_event_loop(&mut self) {
@@ -186,38 +310,291 @@ impl Runtime {
         }
     }
 
+    /// Creates another context (realm) on this runtime, with its own global object.
+    ///
+    /// It shares the event loop and GC with the runtime's main context, so its
+    /// promises and timers are driven by awaiting the `Runtime`.
+    ///
+    /// # Safety
+    ///
+    /// The returned `Context`, and every clone of it, must be dropped before
+    /// the `Runtime`; freeing the runtime under a live context is a use after free.
+    pub unsafe fn new_context(&mut self, options: ContextOptions) -> Context {
+        self.ctx.new_context(options)
+    }
+
+    /// Makes `process.exit()` stop this runtime instead of the whole process,
+    /// for running several scripts in one process, as `--test` does. It emits
+    /// `exit` and interrupts the running JS; `run_until_exit` returns its code.
+    pub fn contain_exit(&mut self) {
+        if let Some(event_loop) = self.ctx.event_loop() {
+            event_loop.contain_exit = true;
+        }
+    }
+
+    /// The code a contained `process.exit()` stopped the runtime with, see `contain_exit`.
+    pub fn exit_code(&mut self) -> Option<i32> {
+        self.ctx
+            .event_loop()
+            .and_then(|event_loop| event_loop.exit_code())
+    }
+
+    /// Limits the memory the runtime may allocate, in bytes.
+    pub fn set_memory_limit(&mut self, limit: usize) {
+        unsafe { JS_SetMemoryLimit(self.rt.0, limit) }
+    }
+
+    /// Limits the JS stack size, in bytes.
+    pub fn set_max_stack_size(&mut self, size: usize) {
+        unsafe { JS_SetMaxStackSize(self.rt.0, size) }
+    }
+
+    /// Measures the runtime's heap. This walks every GC object, so it is not free.
+    pub fn memory_usage(&mut self) -> MemoryUsage {
+        self.ctx.memory_usage()
+    }
+
+    /// Runs a full garbage collection cycle.
+    pub fn run_gc(&mut self) {
+        self.ctx.run_gc()
+    }
+
+    /// Defines a global `gc()` function that runs the garbage collector, like `node --expose-gc`.
+    pub fn expose_gc(&mut self) {
+        super::internal_module::v8::expose_gc(&mut self.ctx)
+    }
+
+    /// Aborts the runtime's pending async tasks and waits until they are dropped,
+    /// so the runtime can be freed without finishing them.
+    pub async fn cancel_pending_tasks(&mut self) {
+        let tasks = match self.ctx.event_loop() {
+            Some(event_loop) => event_loop.handles.take_tasks(),
+            None => return,
+        };
+        for task in tasks.iter() {
+            task.abort();
+        }
+        for task in tasks {
+            let _ = task.await;
+        }
+    }
+
+    /// Stops the runtime for good. It keeps running for up to `grace` so
+    /// pending work can finish, then aborts the async tasks left, closes the
+    /// handles still open and drops the timers and queued callbacks.
+    pub async fn shutdown(&mut self, grace: std::time::Duration) {
+        if !grace.is_zero() {
+            let _ = tokio::time::timeout(grace, &mut *self).await;
+        }
+        self.cancel_pending_tasks().await;
+        let finalizers = match self.ctx.event_loop() {
+            Some(event_loop) => {
+                event_loop.clear_scheduled();
+                event_loop.handles.take_finalizers()
+            }
+            None => return,
+        };
+        for finalizer in finalizers {
+            finalizer();
+        }
+    }
+
+    /// Switches timers, `performance.now()`, `process.hrtime()` and `Date.now()`
+    /// to a virtual clock that stands still until `advance_time` is called, or
+    /// back to real time. `new Date()` keeps reading the real time.
+    ///
+    /// On a virtual clock, pending timers don't keep the runtime running.
+    pub fn set_virtual_clock(&mut self, enabled: bool) {
+        if let Some(event_loop) = self.ctx.event_loop() {
+            event_loop.clock.set_virtual(enabled);
+        }
+        if enabled {
+            super::internal_module::core::route_date_now(&mut self.ctx);
+        }
+    }
+
+    /// Moves the clock forward by `duration` and runs the timers that come due
+    /// on the way, in order. Each runs with the clock at its deadline and is
+    /// followed by a microtask checkpoint.
+    pub fn advance_time(&mut self, duration: std::time::Duration) {
+        let target = match self.ctx.event_loop() {
+            Some(event_loop) => event_loop.clock.now() + duration,
+            None => return,
+        };
+        loop {
+            let timer = match self.ctx.event_loop() {
+                Some(event_loop) => event_loop.next_timer_until(target),
+                None => return,
+            };
+            match timer {
+                Some((callback, args)) => {
+                    super::internal_module::core::call_callback(&callback, Some(args));
+                    if !unsafe { self.run_microtasks() } {
+                        return;
+                    }
+                }
+                None => return,
+            }
+        }
+    }
+
+    /// Returns a `Send` handle for scheduling work on this runtime.
+    pub fn handle(&mut self) -> crate::RuntimeHandle {
+        self.ctx.event_loop().unwrap().runtime_handle()
+    }
+
     pub fn run_with_context<F: FnMut(&mut Context) -> R, R>(&mut self, mut f: F) -> R {
         f(&mut self.ctx)
     }
 
-    unsafe fn run_loop_without_io(&mut self) -> i32 {
-        log::trace!("Runtime run loop without io");
-        use crate::EventLoop;
-        use qjs::JS_ExecutePendingJob;
-
+    /// The microtask checkpoint that follows the main script and every callback:
+    /// the `nextTick` queue is drained, then the promise jobs and `queueMicrotask`
+    /// callbacks, until neither has anything left.
+    /// Returns false once an uncaught exception or a contained exit has stopped the loop.
+    unsafe fn run_microtasks(&mut self) -> bool {
         let rt = self.rt.0;
-        let event_loop = { (JS_GetRuntimeOpaque(rt) as *mut EventLoop).as_mut() }.unwrap();
+        let event_loop = { (JS_GetRuntimeOpaque(rt) as *mut super::EventLoop).as_mut() }.unwrap();
         let mut pctx: *mut JSContext = 0 as *mut JSContext;
 
         loop {
-            'pending: loop {
+            while let Some(f) = event_loop.pop_next_tick() {
+                f();
+                if event_loop.stopped() {
+                    return false;
+                }
+            }
+            loop {
                 log::trace!("Runtime JS_ExecutePendingJob");
                 let err = JS_ExecutePendingJob(rt, (&mut pctx) as *mut *mut JSContext);
-                if err <= 0 {
-                    if err < 0 {
-                        js_std_dump_error(pctx);
-                        return err;
+                if err < 0 {
+                    report_uncaught_exception(pctx);
+                    if event_loop.stopped() {
+                        return false;
                     }
-                    break 'pending;
+                } else if err == 0 {
+                    break;
                 }
             }
+            if !event_loop.has_next_ticks() {
+                return !event_loop.stopped();
+            }
+        }
+    }
+
+    /// Runs one turn of the event loop, in the phases libuv gives Node.js:
+    /// the expired timers, then the completed I/O requests (the poll phase),
+    /// then the work queued from other tasks and threads,
+    /// then the `setImmediate` callbacks, then the close callbacks.
+    /// Each callback is followed by a microtask checkpoint.
+    ///
+    /// Returns how many callbacks ran, or -1 once an exception has escaped to the loop.
+    unsafe fn run_loop_once(&mut self) -> i32 {
+        log::trace!("Runtime run loop once");
+        let rt = self.rt.0;
+        let event_loop = { (JS_GetRuntimeOpaque(rt) as *mut super::EventLoop).as_mut() }.unwrap();
+        let mut n = 0;
+
+        // promise jobs and ticks queued by the main script or the previous turn
+        if !self.run_microtasks() {
+            return -1;
+        }
+
+        let now = event_loop.clock.now();
+        while let Some((callback, args)) = event_loop.timers.next_expired(now) {
+            super::internal_module::core::call_callback(&callback, Some(args));
+            n += 1;
+            if !self.run_microtasks() {
+                return -1;
+            }
+        }
+
+        // poll phase: settle the promises of the I/O requests that completed
+        for f in event_loop.take_io_tasks() {
+            f();
+            n += 1;
+            if !self.run_microtasks() {
+                return -1;
+            }
+        }
+
+        for f in event_loop.take_remote_tasks() {
+            f(&mut self.ctx);
+            n += 1;
+            if !self.run_microtasks() {
+                return -1;
+            }
+        }
+
+        for f in event_loop.take_immediate_tasks() {
+            f();
+            n += 1;
+            if !self.run_microtasks() {
+                return -1;
+            }
+        }
+
+        for f in event_loop.take_close_tasks() {
+            f();
+            n += 1;
+            if !self.run_microtasks() {
+                return -1;
+            }
+        }
+        n
+    }
+
+    /// Returns whether an exception escaped to the event loop since the last call,
+    /// e.g. from a promise job or a timer callback. It was printed when it happened,
+    /// and the loop stops running until this is called.
+    pub fn take_uncaught_exception(&mut self) -> bool {
+        match self.ctx.event_loop() {
+            Some(event_loop) => std::mem::take(&mut event_loop.uncaught_exception),
+            None => false,
+        }
+    }
 
-            if event_loop.run_tick_task() == 0 {
+    /// True while the event loop has work left: promise jobs, queued callbacks or async tasks.
+    pub fn has_pending_tasks(&mut self) -> bool {
+        let jobs_pending = unsafe { JS_IsJobPending(self.rt.0) > 0 };
+        jobs_pending
+            || self
+                .ctx
+                .event_loop()
+                .map_or(false, |event_loop| event_loop.has_pending_tasks())
+    }
+
+    /// Ends a script run the way Node.js does, returning the process exit code.
+    ///
+    /// Runs the event loop and emits `beforeExit` on `process` each time it drains,
+    /// until the listeners schedule no more work, then emits `exit`. The code is
+    /// `process.exitCode`, or 1 if `failed` or an exception escaped to the loop,
+    /// unless a contained `process.exit()` stopped the runtime with its own code.
+    pub async fn run_until_exit(&mut self, failed: bool) -> i32 {
+        let mut failed = failed;
+        while !failed && self.exit_code().is_none() {
+            (&mut *self).await;
+            if self.exit_code().is_some() {
                 break;
             }
-            log::trace!("Runtime JS_ExecutePendingJob continue");
+            failed = self.take_uncaught_exception();
+            if failed
+                || !super::internal_module::core::emit_before_exit(&mut self.ctx)
+                || !self.has_pending_tasks()
+            {
+                break;
+            }
+        }
+        if let Some(code) = self.exit_code() {
+            // `process.exit()` has emitted `exit`
+            return code;
         }
-        0
+        let failed = failed || self.take_uncaught_exception();
+        let code = if failed {
+            1
+        } else {
+            super::internal_module::core::process_exit_code(&mut self.ctx, 0)
+        };
+        super::internal_module::core::emit_exit(&mut self.ctx, code)
     }
 
     pub fn async_run_with_context(
@@ -361,53 +738,91 @@ impl Context {
         JS_GetRuntime(self.ctx)
     }
 
+    /// See `Runtime::memory_usage`.
+    pub fn memory_usage(&mut self) -> MemoryUsage {
+        unsafe {
+            let mut usage = std::mem::zeroed::<JSMemoryUsage>();
+            JS_ComputeMemoryUsage(self.rt(), &mut usage);
+            usage.into()
+        }
+    }
+
+    /// See `Runtime::run_gc`.
+    pub fn run_gc(&mut self) {
+        unsafe { JS_RunGC(self.rt()) }
+    }
+
     unsafe fn clone_(&mut self) -> std::mem::ManuallyDrop<Self> {
         std::mem::ManuallyDrop::new(Context { ctx: self.ctx })
     }
 
-    unsafe fn new_with_rt(rt: *mut JSRuntime) -> Context {
+    pub(crate) unsafe fn new_with_rt(rt: *mut JSRuntime, options: &ContextOptions) -> Context {
         let ctx = JS_NewContext(rt);
         JS_AddIntrinsicBigFloat(ctx);
         JS_AddIntrinsicBigDecimal(ctx);
         JS_AddIntrinsicOperators(ctx);
         JS_EnableBignumExt(ctx, 1);
-        js_std_add_console(ctx);
-        js_init_module_std(ctx, "std\0".as_ptr() as *const i8);
-        js_init_module_os(ctx, "qjs:os\0".as_ptr() as *const i8);
+        if options.console {
+            js_std_add_console(ctx);
+        }
+        if options.std {
+            js_init_module_std(ctx, "std\0".as_ptr() as *const i8);
+            js_init_module_os(ctx, "qjs:os\0".as_ptr() as *const i8);
+        }
         let mut ctx = Context { ctx };
 
-        #[cfg(feature = "img")]
-        super::internal_module::img_module::init_module(&mut ctx);
+        if options.plugins {
+            #[cfg(feature = "img")]
+            super::internal_module::img_module::init_module(&mut ctx);
 
-        #[cfg(feature = "tensorflow")]
-        {
-            super::internal_module::tensorflow_module::init_module_tensorflow(&mut ctx);
-            super::internal_module::tensorflow_module::init_module_tensorflow_lite(&mut ctx);
-        }
+            #[cfg(feature = "tensorflow")]
+            {
+                super::internal_module::tensorflow_module::init_module_tensorflow(&mut ctx);
+                super::internal_module::tensorflow_module::init_module_tensorflow_lite(&mut ctx);
+            }
+
+            #[cfg(feature = "wasi_nn")]
+            super::internal_module::wasi_nn::init_module(&mut ctx);
 
-        #[cfg(feature = "wasi_nn")]
-        super::internal_module::wasi_nn::init_module(&mut ctx);
+            #[cfg(feature = "ggml")]
+            {
+                super::internal_module::ggml::init_wasi_nn_ggml_module(&mut ctx);
+                super::internal_module::ggml::init_ggml_template_module(&mut ctx);
+            }
+        }
 
         js_init_dirname(&mut ctx);
+        js_shared::init_atomics(&mut ctx);
 
         super::internal_module::core::init_global_function(&mut ctx);
         super::internal_module::core::init_ext_function(&mut ctx);
-        super::internal_module::encoding::init_encoding_module(&mut ctx);
-        super::internal_module::wasi_net_module::init_module(&mut ctx);
-        super::internal_module::httpx::init_module(&mut ctx);
-        super::internal_module::os::init_module(&mut ctx);
-        super::internal_module::fs::init_module(&mut ctx);
+        super::internal_module::core::init_module(&mut ctx);
+        super::internal_module::event_target::init_global(&mut ctx);
+        super::internal_module::abort::init_global(&mut ctx);
+        super::internal_module::perf_hooks::init_global(&mut ctx);
+        super::internal_module::perf_hooks::init_module(&mut ctx);
+        super::internal_module::v8::init_module(&mut ctx);
+        super::internal_module::vm::init_module(&mut ctx);
+        super::internal_module::worker_threads::init_module(&mut ctx);
+        if options.encoding {
+            super::internal_module::encoding::init_encoding_module(&mut ctx);
+        }
+        if options.net {
+            super::internal_module::wasi_net_module::init_module(&mut ctx);
+            super::internal_module::httpx::init_module(&mut ctx);
+        }
+        if options.os {
+            super::internal_module::os::init_module(&mut ctx);
+        }
+        if options.fs {
+            super::internal_module::fs::init_module(&mut ctx);
+        }
 
         #[cfg(feature = "nodejs_crypto")]
-        {
+        if options.crypto {
             super::internal_module::crypto::init_module(&mut ctx);
         }
 
-        #[cfg(feature = "ggml")]
-        {
-            super::internal_module::ggml::init_wasi_nn_ggml_module(&mut ctx);
-            super::internal_module::ggml::init_ggml_template_module(&mut ctx);
-        }
         ctx
     }
 
@@ -462,17 +877,56 @@ impl Context {
                     eval_flags as i32,
                 )
             };
-            if JS_IsException_real(val) > 0 {
+            if JS_IsException_real(val) > 0 && !is_uncatchable(ctx) {
                 js_std_dump_error(ctx);
             }
             JsValue::from_qjs_value(ctx, val)
         }
     }
 
+    pub fn json_parse(&mut self, json: &str, filename: &str) -> JsValue {
+        unsafe {
+            let ctx = self.ctx;
+            let len = json.len();
+            let v = JS_ParseJSON(
+                ctx,
+                make_c_string(json).as_ptr(),
+                len,
+                make_c_string(filename).as_ptr(),
+            );
+            JsValue::from_qjs_value(ctx, v)
+        }
+    }
+
+    /// Creates a sibling context on the same runtime, see `Runtime::new_context`.
+    ///
+    /// # Safety
+    ///
+    /// Same as `Runtime::new_context`: the new context must not outlive the runtime.
+    pub unsafe fn new_context(&mut self, options: ContextOptions) -> Context {
+        Context::new_with_rt(self.rt(), &options)
+    }
+
+    pub fn runtime_handle(&mut self) -> Option<crate::RuntimeHandle> {
+        self.event_loop()
+            .map(|event_loop| event_loop.runtime_handle())
+    }
+
     pub fn eval_global_str(&mut self, code: String) -> JsValue {
         self.eval_buf(code.into_bytes(), "<evalScript>", JS_EVAL_TYPE_GLOBAL)
     }
 
+    /// Evaluates a global script that may use top-level `await`.
+    ///
+    /// Returns a promise for `{ value }`, where `value` is the script's completion value.
+    pub fn eval_global_async(&mut self, code: String, filename: &str) -> JsValue {
+        self.eval_buf(
+            code.into_bytes(),
+            filename,
+            JS_EVAL_TYPE_GLOBAL | JS_EVAL_FLAG_ASYNC,
+        )
+    }
+
     pub fn eval_module_str(&mut self, code: String, filename: &str) {
         self.eval_buf(code.into_bytes(), filename, JS_EVAL_TYPE_MODULE);
     }
@@ -562,6 +1016,14 @@ impl Context {
         error_obj
     }
 
+    /// Prints `error` as uncaught and stops the event loop.
+    pub(crate) fn report_fatal_error(&mut self, error: JsValue) {
+        unsafe {
+            JS_Throw(self.ctx, error.into_qjs_value());
+            report_fatal_exception(self.ctx);
+        }
+    }
+
     pub fn throw_type_error(&mut self, msg: &str) -> JsException {
         unsafe {
             let v = JS_ThrowTypeError(self.ctx, make_c_string(msg).as_ptr());
@@ -583,6 +1045,18 @@ impl Context {
         }
     }
 
+    /// Throws an `InternalError` that `try`/`catch` and `finally` don't intercept,
+    /// unwinding all the JS that is running.
+    pub fn throw_uncatchable_error(&mut self, msg: &str) -> JsException {
+        unsafe {
+            let v = JS_ThrowInternalError(self.ctx, make_c_string(msg).as_ptr());
+            let error = JS_GetException(self.ctx);
+            JS_SetUncatchableError(self.ctx, error, 1);
+            JS_Throw(self.ctx, error);
+            JsException(JsRef { ctx: self.ctx, v })
+        }
+    }
+
     pub fn throw_reference_error(&mut self, msg: &str) -> JsException {
         unsafe {
             let v = JS_ThrowReferenceError(self.ctx, make_c_string(msg).as_ptr());
@@ -618,10 +1092,9 @@ impl Context {
 
             loop {
                 let err = JS_ExecutePendingJob(rt, (&mut pctx) as *mut *mut JSContext);
-                if err <= 0 {
-                    if err < 0 {
-                        js_std_dump_error(pctx);
-                    }
+                if err < 0 {
+                    report_uncaught_exception(pctx);
+                } else if err == 0 {
                     break;
                 }
             }
@@ -857,12 +1330,39 @@ impl JsFunction {
             JsValue::from_qjs_value(ctx, v)
         }
     }
+
+    /// Calls the function with `this` bound to `this_val`.
+    pub fn call_with_this(&self, this_val: &JsValue, argv: &[JsValue]) -> JsValue {
+        unsafe {
+            let ctx = self.0.ctx;
+            let mut argv: Vec<JSValue> = argv.iter().map(|v| v.get_qjs_value()).collect();
+            let f = self.0.v;
+            let this_val = this_val.get_qjs_value();
+            let v = JS_Call(ctx, f, this_val, argv.len() as i32, argv.as_mut_ptr());
+            JsValue::from_qjs_value(ctx, v)
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct JsPromise(JsRef);
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JsPromiseState {
+    Pending,
+    Fulfilled,
+    Rejected,
+}
+
 impl JsPromise {
+    pub fn state(&self) -> JsPromiseState {
+        match unsafe { JS_PromiseState(self.0.ctx, self.0.v) } {
+            JSPromiseStateEnum_JS_PROMISE_FULFILLED => JsPromiseState::Fulfilled,
+            JSPromiseStateEnum_JS_PROMISE_REJECTED => JsPromiseState::Rejected,
+            _ => JsPromiseState::Pending,
+        }
+    }
+
     pub fn get_result(&self) -> JsValue {
         unsafe {
             let ctx = self.0.ctx;
@@ -958,6 +1458,10 @@ impl JsArrayBuffer {
         let buf = self.as_ref();
         buf.to_vec()
     }
+    /// Detaches the buffer after its contents were transferred elsewhere.
+    pub fn detach(&self) {
+        unsafe { JS_DetachArrayBuffer(self.0.ctx, self.0.v) }
+    }
     pub fn get_mut_ptr(&self) -> (*mut u8, usize) {
         unsafe {
             let r = &self.0;
@@ -1055,6 +1559,63 @@ impl JsException {
     pub fn dump_error(&self) {
         unsafe { js_std_dump_error(self.0.ctx) }
     }
+
+    /// Handles the exception as uncaught, see `report_uncaught_exception`.
+    pub(crate) fn report_uncaught(&self) {
+        unsafe { report_uncaught_exception(self.0.ctx) }
+    }
+
+    /// Prints the exception and stops the event loop.
+    pub(crate) fn report_fatal(&self) {
+        unsafe { report_fatal_exception(self.0.ctx) }
+    }
+}
+
+/// True if the pending exception of `ctx` can't be caught by JS: the interrupt
+/// of a `with_timeout` deadline or a terminated worker, or a contained `process.exit()`.
+unsafe fn is_uncatchable(ctx: *mut JSContext) -> bool {
+    let error = JS_GetException(ctx);
+    let uncatchable = JS_IsUncatchableError(ctx, error) != 0;
+    JS_Throw(ctx, error);
+    uncatchable
+}
+
+/// Handles the pending exception of `ctx` after it escaped to the event loop.
+///
+/// It is emitted as `uncaughtException` on `process` if anything listens for it.
+/// Otherwise it is fatal, see `report_fatal_exception`.
+pub(crate) unsafe fn report_uncaught_exception(ctx: *mut JSContext) {
+    if is_uncatchable(ctx) {
+        // not a script error
+        JS_FreeValue_real(ctx, JS_GetException(ctx));
+        return;
+    }
+
+    let error = JsValue::from_qjs_value(ctx, JS_GetException(ctx));
+    let mut n_ctx = std::mem::ManuallyDrop::new(Context { ctx });
+    if !super::internal_module::core::emit_uncaught_exception(&mut n_ctx, &error) {
+        n_ctx.report_fatal_error(error);
+    }
+}
+
+/// Prints the pending exception of `ctx`, or hands it to the loop's `fatal_error_hook`,
+/// and stops the event loop, see `Runtime::take_uncaught_exception`.
+unsafe fn report_fatal_exception(ctx: *mut JSContext) {
+    let event_loop = JS_GetRuntimeOpaque(JS_GetRuntime(ctx)) as *mut super::EventLoop;
+    let event_loop = match event_loop.as_mut() {
+        Some(event_loop) => event_loop,
+        None => return js_std_dump_error(ctx),
+    };
+    event_loop.uncaught_exception = true;
+    match event_loop.fatal_error_hook.take() {
+        Some(mut hook) => {
+            let error = JsValue::from_qjs_value(ctx, JS_GetException(ctx));
+            let mut n_ctx = std::mem::ManuallyDrop::new(Context { ctx });
+            hook(&mut n_ctx, error);
+            event_loop.fatal_error_hook = Some(hook);
+        }
+        None => js_std_dump_error(ctx),
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -0,0 +1,458 @@
+//! The interactive REPL, started when no script is given.
+//!
+//! Each entry is evaluated as an async global script, so `await` works at the top level
+//! and declarations stay visible to later entries. Reading stdin blocks the thread, so
+//! timers and sockets only make progress while an entry is being evaluated.
+
+use std::future::Future;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::task::Poll;
+
+use wasmedge_quickjs::*;
+
+const REPL_BOOTSTRAP: &str =
+    "import { inspect } from 'util';\nglobalThis.__replInspect = inspect;\n";
+
+const HELP: &str = "\
+.break    Discard the current multi-line input
+.exit     Exit the REPL
+.help     Print this help message
+.history  Print the entries of this and earlier sessions
+.load     Load JS from a file into the REPL session
+.save     Save all evaluated commands in this REPL session to a file
+
+Press Ctrl+D to exit the REPL";
+
+/// How many entries the history file keeps, like Node.js's `NODE_REPL_HISTORY_SIZE`.
+const HISTORY_SIZE: usize = 1000;
+
+struct Repl {
+    inspect: Option<JsFunction>,
+    session: Vec<String>,
+    /// entries of earlier sessions, then of this one
+    history: Vec<String>,
+    history_file: Option<std::fs::File>,
+}
+
+fn history_path() -> Option<PathBuf> {
+    if let Ok(path) = std::env::var("WASMEDGE_QUICKJS_REPL_HISTORY") {
+        return if path.is_empty() {
+            None
+        } else {
+            Some(path.into())
+        };
+    }
+    let home = std::env::var("HOME").ok()?;
+    Some(Path::new(&home).join(".wasmedge_quickjs_repl_history"))
+}
+
+/// Reads the last `HISTORY_SIZE` entries from `path`, and rewrites the file with
+/// just those so it doesn't grow without bound. Returns the file opened for appending.
+fn load_history(path: &Path) -> (Vec<String>, Option<std::fs::File>) {
+    let mut entries: Vec<String> = std::fs::read_to_string(path)
+        .unwrap_or_default()
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(str::to_string)
+        .collect();
+    if entries.len() > HISTORY_SIZE {
+        entries.drain(..entries.len() - HISTORY_SIZE);
+        let mut content = entries.join("\n");
+        content.push('\n');
+        let _ = std::fs::write(path, content);
+    }
+    let file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .ok();
+    (entries, file)
+}
+
+/// Returns true if `src` ends inside a string, comment, template or open bracket.
+fn is_incomplete(src: &str) -> bool {
+    #[derive(PartialEq)]
+    enum State {
+        Code,
+        Str(char),
+        Template,
+        LineComment,
+        BlockComment,
+        Regex { in_class: bool },
+    }
+
+    let chars: Vec<char> = src.chars().collect();
+    let mut state = State::Code;
+    // open brackets; '`' marks a `${` inside a template literal
+    let mut stack: Vec<char> = vec![];
+    let mut prev = None;
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).cloned();
+        match state {
+            State::Code => match c {
+                '\'' | '"' => state = State::Str(c),
+                '`' => state = State::Template,
+                '/' if next == Some('/') => state = State::LineComment,
+                '/' if next == Some('*') => {
+                    state = State::BlockComment;
+                    i += 1;
+                }
+                '/' if prev.map_or(true, |p: char| "(,=:[!&|?{};+-*%<>~^".contains(p)) => {
+                    state = State::Regex { in_class: false }
+                }
+                '(' | '[' | '{' => stack.push(c),
+                ')' | ']' | '}' => {
+                    let open = match c {
+                        ')' => '(',
+                        ']' => '[',
+                        _ => '{',
+                    };
+                    match stack.pop() {
+                        Some('`') if c == '}' => state = State::Template,
+                        Some(top) if top == open => {}
+                        // unbalanced: let the parser report it
+                        _ => return false,
+                    }
+                }
+                _ => {}
+            },
+            State::Str(quote) => match c {
+                '\\' => i += 1,
+                '\n' => state = State::Code,
+                _ if c == quote => state = State::Code,
+                _ => {}
+            },
+            State::Template => match c {
+                '\\' => i += 1,
+                '`' => state = State::Code,
+                '$' if next == Some('{') => {
+                    stack.push('`');
+                    state = State::Code;
+                    i += 1;
+                }
+                _ => {}
+            },
+            State::LineComment => {
+                if c == '\n' {
+                    state = State::Code;
+                }
+            }
+            State::BlockComment => {
+                if c == '*' && next == Some('/') {
+                    state = State::Code;
+                    i += 1;
+                }
+            }
+            State::Regex { in_class } => match c {
+                '\\' => i += 1,
+                '[' => state = State::Regex { in_class: true },
+                ']' => state = State::Regex { in_class: false },
+                '/' if !in_class => state = State::Code,
+                '\n' => state = State::Code,
+                _ => {}
+            },
+        }
+        if !c.is_whitespace() && state == State::Code {
+            prev = Some(c);
+        }
+        i += 1;
+    }
+    match state {
+        State::Template | State::BlockComment => true,
+        State::Str(_) => src.ends_with("\\\n"),
+        _ => !stack.is_empty() || src.trim_end().ends_with('\\'),
+    }
+}
+
+/// Drives the runtime until `promise` settles, or until nothing is left to run.
+async fn wait_for(rt: &mut Runtime, promise: &JsPromise) {
+    std::future::poll_fn(|cx| {
+        let idle = Pin::new(&mut *rt).poll(cx).is_ready();
+        if idle || promise.state() != JsPromiseState::Pending {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    })
+    .await
+}
+
+impl Repl {
+    async fn new(rt: &mut Runtime, history_path: Option<&Path>) -> Self {
+        rt.run_with_context(|ctx| {
+            ctx.eval_module_str(REPL_BOOTSTRAP.to_string(), "[repl]");
+        });
+        (&mut *rt).await;
+        let inspect = rt.run_with_context(|ctx| {
+            let mut global = ctx.get_global();
+            let inspect = global.get("__replInspect").to_function();
+            global.delete("__replInspect");
+            inspect
+        });
+        let (history, history_file) = match history_path {
+            Some(path) => load_history(path),
+            None => (vec![], None),
+        };
+        Repl {
+            inspect,
+            session: vec![],
+            history,
+            history_file,
+        }
+    }
+
+    fn format(&self, ctx: &mut Context, value: JsValue) -> String {
+        let s = match &self.inspect {
+            Some(inspect) => inspect.call(&[value]),
+            None => ctx.value_to_string(&value),
+        };
+        match s {
+            JsValue::String(s) => s.to_string(),
+            _ => String::new(),
+        }
+    }
+
+    async fn eval(&mut self, rt: &mut Runtime, out: &mut impl Write, code: String) {
+        let result = rt.run_with_context(|ctx| ctx.eval_global_async(code.clone(), "<repl>"));
+        let promise = match result {
+            JsValue::Promise(promise) => promise,
+            // syntax errors have already been reported by eval_buf
+            _ => return,
+        };
+        wait_for(rt, &promise).await;
+        // already printed; keep the session going
+        rt.take_uncaught_exception();
+
+        let output = rt.run_with_context(|ctx| {
+            let mut global = ctx.get_global();
+            match promise.state() {
+                JsPromiseState::Fulfilled => {
+                    let value = promise
+                        .get_result()
+                        .get("value")
+                        .unwrap_or(JsValue::UnDefined);
+                    global.set("_", value.clone());
+                    Some(self.format(ctx, value))
+                }
+                JsPromiseState::Rejected => {
+                    let error = promise.get_result();
+                    global.set("_error", error.clone());
+                    Some(format!("Uncaught {}", self.format(ctx, error)))
+                }
+                JsPromiseState::Pending => None,
+            }
+        });
+        if let Some(output) = output {
+            let _ = writeln!(out, "{}", output);
+        }
+    }
+
+    fn record(&mut self, entry: &str) {
+        self.session.push(entry.to_string());
+        let entry = entry.replace('\n', " ");
+        if let Some(file) = &mut self.history_file {
+            let _ = writeln!(file, "{}", entry);
+        }
+        self.history.push(entry);
+    }
+
+    /// Runs a `.command`. Returns false when the REPL should exit.
+    async fn command(&mut self, rt: &mut Runtime, out: &mut impl Write, line: &str) -> bool {
+        let (cmd, arg) = match line.find(char::is_whitespace) {
+            Some(i) => (&line[..i], line[i..].trim()),
+            None => (line, ""),
+        };
+        match cmd {
+            ".exit" => return false,
+            ".help" => {
+                let _ = writeln!(out, "{}", HELP);
+            }
+            ".history" => {
+                for entry in &self.history {
+                    let _ = writeln!(out, "{}", entry);
+                }
+            }
+            ".break" => {}
+            ".load" => match std::fs::read_to_string(arg) {
+                Ok(code) => {
+                    self.record(&code);
+                    self.eval(rt, out, code).await;
+                }
+                Err(e) => eprintln!("Failed to load: {}: {}", arg, e),
+            },
+            ".save" => match std::fs::write(arg, self.session.join("\n") + "\n") {
+                Ok(()) => {
+                    let _ = writeln!(out, "Session saved to: {}", arg);
+                }
+                Err(e) => eprintln!("Failed to save: {}: {}", arg, e),
+            },
+            _ => eprintln!("Invalid REPL keyword"),
+        }
+        true
+    }
+
+    /// Reads entries with `read_line` until it hits the end of input or `.exit`.
+    async fn run<R, W>(mut self, rt: &mut Runtime, mut read_line: R, mut out: W)
+    where
+        R: FnMut(&mut String) -> std::io::Result<usize>,
+        W: Write,
+    {
+        let _ = writeln!(
+            out,
+            "Welcome to WasmEdge QuickJS. Type \".help\" for more information."
+        );
+        let mut buffer = String::new();
+        loop {
+            let _ = write!(out, "{}", if buffer.is_empty() { "> " } else { "... " });
+            let _ = out.flush();
+
+            let mut line = String::new();
+            match read_line(&mut line) {
+                Ok(0) | Err(_) => {
+                    let _ = writeln!(out);
+                    break;
+                }
+                Ok(_) => {}
+            }
+
+            let trimmed = line.trim();
+            if trimmed.starts_with('.') && (buffer.is_empty() || trimmed == ".break") {
+                buffer.clear();
+                if !self.command(rt, &mut out, trimmed).await {
+                    break;
+                }
+                continue;
+            }
+
+            buffer.push_str(&line);
+            if is_incomplete(&buffer) {
+                continue;
+            }
+            let code = std::mem::take(&mut buffer);
+            if code.trim().is_empty() {
+                continue;
+            }
+            self.record(code.trim_end());
+            self.eval(rt, &mut out, code).await;
+        }
+    }
+}
+
+pub async fn run(rt: &mut Runtime) {
+    let repl = Repl::new(rt, history_path().as_deref()).await;
+    // stdin is locked a line at a time, so scripts can read it between entries
+    let read_line = |line: &mut String| std::io::stdin().read_line(line);
+    repl.run(rt, read_line, std::io::stdout()).await;
+    // let whatever the session started finish, as after a script
+    rt.await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::BufRead;
+
+    /// Feeds `input` to a new REPL and returns what it printed.
+    fn run_repl(input: &str, history_path: Option<&Path>) -> String {
+        let tokio_rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        tokio_rt.block_on(async {
+            let mut rt = Runtime::new();
+            let repl = Repl::new(&mut rt, history_path).await;
+            let mut input = std::io::Cursor::new(input.to_string());
+            let mut out = vec![];
+            repl.run(&mut rt, |line: &mut String| input.read_line(line), &mut out)
+                .await;
+            String::from_utf8(out).unwrap()
+        })
+    }
+
+    #[test]
+    fn test_repl_prints_results() {
+        let out = run_repl("1 + 1\n'a' + 'b'\n_\nconst x = 5\nx * 2\n", None);
+        assert!(out.starts_with("Welcome to WasmEdge QuickJS."), "{}", out);
+        assert!(
+            out.contains("> 2\n> 'ab'\n> 'ab'\n> undefined\n> 10\n"),
+            "{}",
+            out
+        );
+    }
+
+    #[test]
+    fn test_repl_multi_line_and_await() {
+        let out = run_repl(
+            "function f(a) {\nreturn `${a}\n`\n}\nf(1).length\nawait new Promise((r) => setTimeout(() => r(7), 5))\n",
+            None,
+        );
+        assert!(
+            out.contains("> ... ... ... undefined\n> 2\n> 7\n"),
+            "{}",
+            out
+        );
+    }
+
+    #[test]
+    fn test_repl_errors_and_commands() {
+        let out = run_repl(
+            "throw new Error('boom')\n_error.message\n{\n.break\n.help\n.exit\n1\n",
+            None,
+        );
+        assert!(out.contains("Uncaught Error: boom"), "{}", out);
+        assert!(out.contains("> 'boom'\n"), "{}", out);
+        // `.break` drops the open block, so `.help` runs at a fresh prompt
+        assert!(out.contains("> ... > .break    Discard"), "{}", out);
+        // and `.exit` stops before `1`
+        assert!(
+            out.ends_with("Press Ctrl+D to exit the REPL\n> "),
+            "{}",
+            out
+        );
+    }
+
+    #[test]
+    fn test_repl_save_and_load() {
+        let path = "target/repl-session-test.js";
+        let out = run_repl(&format!("let n = 20\nn + 1\n.save {}\n", path), None);
+        assert!(
+            out.contains(&format!("Session saved to: {}", path)),
+            "{}",
+            out
+        );
+        assert_eq!(
+            std::fs::read_to_string(path).unwrap(),
+            "let n = 20\nn + 1\n"
+        );
+
+        let out = run_repl(&format!(".load {}\nn * 2\n", path), None);
+        assert!(out.contains("> 40\n"), "{}", out);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_repl_history() {
+        let path = Path::new("target/repl-history-test");
+        let old: Vec<String> = (0..HISTORY_SIZE + 5).map(|i| format!("{}", i)).collect();
+        std::fs::write(path, old.join("\n") + "\n").unwrap();
+
+        // earlier entries are loaded, and the file is cut down to the last HISTORY_SIZE
+        let out = run_repl("'new'\n.history\n", Some(path));
+        let history = std::fs::read_to_string(path).unwrap();
+        let lines: Vec<&str> = history.lines().collect();
+        assert_eq!(lines.len(), HISTORY_SIZE + 1);
+        assert_eq!(lines[0], "5");
+        assert_eq!(lines[HISTORY_SIZE], "'new'");
+        assert!(
+            out.contains(&format!("{}\n'new'\n", HISTORY_SIZE + 4)),
+            "{}",
+            out
+        );
+        assert!(out.contains("> 5\n6\n"), "{}", out);
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,80 @@
+'use strict';
+import assert from 'assert';
+
+assert.throws(() => new AbortSignal(), TypeError);
+
+// abort() fires the event once, onabort first, and keeps the first reason
+{
+  const ac = new AbortController();
+  const { signal } = ac;
+  assert.strictEqual(Object.prototype.toString.call(ac), '[object AbortController]');
+  assert.strictEqual(Object.prototype.toString.call(signal), '[object AbortSignal]');
+  assert.strictEqual(signal.aborted, false);
+  assert.strictEqual(signal.reason, undefined);
+  signal.throwIfAborted();
+
+  const calls = [];
+  signal.onabort = (event) => calls.push(['onabort', event.type]);
+  const listener = function (event) {
+    assert.strictEqual(this, signal);
+    assert.strictEqual(event.target, signal);
+    calls.push(['listener', event.type]);
+  };
+  signal.addEventListener('abort', listener);
+  signal.addEventListener('abort', listener);
+  const removed = () => calls.push(['removed']);
+  signal.addEventListener('abort', removed);
+  signal.removeEventListener('abort', removed);
+  signal.addEventListener('abort', { handleEvent: () => calls.push(['handleEvent']) });
+
+  ac.abort();
+  ac.abort(new Error('second'));
+  assert.deepStrictEqual(calls, [['onabort', 'abort'], ['listener', 'abort'], ['handleEvent']]);
+  assert.strictEqual(signal.aborted, true);
+  assert.strictEqual(signal.reason.name, 'AbortError');
+  assert.strictEqual(signal.reason.code, 20);
+  assert.throws(() => signal.throwIfAborted(), (err) => err === signal.reason);
+}
+
+// any reason is kept as is
+{
+  const ac = new AbortController();
+  ac.abort('why');
+  assert.strictEqual(ac.signal.reason, 'why');
+}
+
+{
+  const signal = AbortSignal.abort(42);
+  assert.strictEqual(signal.aborted, true);
+  assert.strictEqual(signal.reason, 42);
+  assert.strictEqual(AbortSignal.abort().reason.name, 'AbortError');
+}
+
+// any() follows the first of its sources to abort
+{
+  const a = new AbortController();
+  const b = new AbortController();
+  const any = AbortSignal.any([a.signal, b.signal]);
+  let fired = 0;
+  any.addEventListener('abort', () => fired++);
+  assert.strictEqual(any.aborted, false);
+  b.abort('b');
+  a.abort('a');
+  assert.strictEqual(any.aborted, true);
+  assert.strictEqual(any.reason, 'b');
+  assert.strictEqual(fired, 1);
+
+  assert.strictEqual(AbortSignal.any([AbortSignal.abort('done')]).reason, 'done');
+  assert.throws(() => AbortSignal.any([{}]), TypeError);
+}
+
+// timeout() aborts with a TimeoutError, without keeping the loop alive
+{
+  const signal = AbortSignal.timeout(5);
+  assert.throws(() => AbortSignal.timeout(-1), RangeError);
+  AbortSignal.timeout(1000000);
+  setTimeout(() => {
+    assert.strictEqual(signal.aborted, true);
+    assert.strictEqual(signal.reason.name, 'TimeoutError');
+  }, 20);
+}
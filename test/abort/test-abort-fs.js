@@ -0,0 +1,63 @@
+'use strict';
+import assert from 'assert';
+import fs from 'fs';
+import { readFile } from 'fs/promises';
+import { join } from 'path';
+import { Buffer } from 'buffer';
+import process from 'process';
+import tmpdir from '../common/tmpdir';
+
+const file = 'test/abort/test-abort-fs.js';
+
+fs.readFile(file, { signal: AbortSignal.abort() }, (err, data) => {
+  assert.strictEqual(err.name, 'AbortError');
+  assert.strictEqual(data, undefined);
+});
+
+{
+  const ac = new AbortController();
+  fs.readFile(file, { signal: ac.signal }, (err) => {
+    assert.strictEqual(err.name, 'AbortError');
+  });
+  ac.abort();
+}
+
+fs.readFile(file, { encoding: 'utf8', signal: new AbortController().signal }, (err, data) => {
+  assert.ifError(err);
+  assert.ok(data.includes('AbortError'));
+});
+
+// An abort stops reading and writing between chunks, instead of finishing
+// the whole file and only then reporting it.
+tmpdir.refresh();
+const big = join(tmpdir.path, 'big.bin');
+const data = Buffer.alloc(8 * 1024 * 1024, 'x');
+fs.writeFileSync(big, data);
+let stopped = 0;
+
+{
+  const ac = new AbortController();
+  fs.readFile(big, { signal: ac.signal }, (err, result) => {
+    assert.strictEqual(err.name, 'AbortError');
+    assert.strictEqual(result, undefined);
+    stopped++;
+  });
+  ac.abort();
+}
+
+{
+  const target = join(tmpdir.path, 'aborted-write.bin');
+  const ac = new AbortController();
+  fs.writeFile(target, data, { signal: ac.signal }, (err) => {
+    assert.strictEqual(err.name, 'AbortError');
+    const size = fs.statSync(target).size;
+    assert.ok(size < data.length, `wrote ${size} of ${data.length} bytes`);
+    stopped++;
+  });
+  ac.abort();
+}
+
+process.on('exit', () => assert.strictEqual(stopped, 2));
+
+await assert.rejects(readFile(file, { signal: AbortSignal.abort() }), { name: 'AbortError' });
+assert.throws(() => fs.readFile(file, { signal: {} }, () => {}), { code: 'ERR_INVALID_ARG_TYPE' });
@@ -0,0 +1,29 @@
+'use strict';
+import assert from 'assert';
+import * as net from 'wasi_net';
+import { TextDecoder } from 'util';
+
+const port = 8137;
+const server = new net.WasiTcpServer(port);
+const accepted = server.accept();
+
+// a read the peer never answers is cancelled by the signal
+const conn = await net.WasiTcpConn.connect('127.0.0.1', port);
+const peer = await accepted;
+const ac = new AbortController();
+setTimeout(() => ac.abort(new Error('stop reading')), 10);
+await assert.rejects(conn.read(undefined, ac.signal), { message: 'stop reading' });
+
+// the connection is still usable afterwards
+peer.write('hello');
+const data = await conn.read();
+assert.strictEqual(new TextDecoder().decode(data), 'hello');
+
+await assert.rejects(
+  net.WasiTcpConn.connect('127.0.0.1', port, undefined, AbortSignal.abort('no')),
+  (err) => err === 'no',
+);
+
+conn.close();
+peer.close();
+server.close();
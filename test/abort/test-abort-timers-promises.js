@@ -0,0 +1,44 @@
+'use strict';
+import assert from 'assert';
+import { setTimeout, setImmediate, setInterval, scheduler } from 'timers/promises';
+
+assert.strictEqual(await setTimeout(1, 'value'), 'value');
+assert.strictEqual(await setImmediate('now'), 'now');
+await scheduler.wait(1);
+
+// an already aborted signal rejects at once, with the reason as cause
+await assert.rejects(setTimeout(1, 'value', { signal: AbortSignal.abort('early') }), (err) => {
+  assert.strictEqual(err.name, 'AbortError');
+  assert.strictEqual(err.cause, 'early');
+  return true;
+});
+
+// aborting clears the timer
+{
+  const ac = new AbortController();
+  const start = Date.now();
+  const p = setTimeout(100000, 'late', { signal: ac.signal });
+  globalThis.setTimeout(() => ac.abort(), 5);
+  await assert.rejects(p, { name: 'AbortError' });
+  assert.ok(Date.now() - start < 1000);
+}
+
+{
+  const ac = new AbortController();
+  const ticks = [];
+  await assert.rejects(async () => {
+    for await (const value of setInterval(2, 'tick', { signal: ac.signal })) {
+      ticks.push(value);
+      if (ticks.length === 3) {
+        ac.abort();
+      }
+    }
+  }, { name: 'AbortError' });
+  assert.deepStrictEqual(ticks, ['tick', 'tick', 'tick']);
+}
+
+// breaking out of the loop stops the interval
+for await (const value of setInterval(1, 'once')) {
+  assert.strictEqual(value, 'once');
+  break;
+}
@@ -0,0 +1,81 @@
+'use strict';
+import assert from 'assert';
+
+// once
+{
+  const target = new EventTarget();
+  let count = 0;
+  target.addEventListener('foo', () => count++, { once: true });
+  target.dispatchEvent(new Event('foo'));
+  target.dispatchEvent(new Event('foo'));
+  assert.strictEqual(count, 1);
+}
+
+// capture is part of the listener's identity
+{
+  const target = new EventTarget();
+  let count = 0;
+  const listener = () => count++;
+  target.addEventListener('foo', listener, { capture: true });
+  target.addEventListener('foo', listener);
+  target.dispatchEvent(new Event('foo'));
+  assert.strictEqual(count, 2);
+  target.removeEventListener('foo', listener, true);
+  target.dispatchEvent(new Event('foo'));
+  assert.strictEqual(count, 3);
+  target.removeEventListener('foo', listener, { capture: false });
+  target.dispatchEvent(new Event('foo'));
+  assert.strictEqual(count, 3);
+}
+
+// passive listeners can't cancel the event
+{
+  const target = new EventTarget();
+  target.addEventListener('foo', (event) => event.preventDefault(), { passive: true });
+  const event = new Event('foo', { cancelable: true });
+  assert.strictEqual(target.dispatchEvent(event), true);
+  assert.strictEqual(event.defaultPrevented, false);
+}
+
+// signal removes the listener when it aborts
+{
+  const target = new EventTarget();
+  const ac = new AbortController();
+  let count = 0;
+  target.addEventListener('foo', () => count++, { signal: ac.signal });
+  target.dispatchEvent(new Event('foo'));
+  ac.abort();
+  target.dispatchEvent(new Event('foo'));
+  assert.strictEqual(count, 1);
+
+  // an already aborted signal adds nothing
+  target.addEventListener('foo', () => count++, { signal: AbortSignal.abort() });
+  target.dispatchEvent(new Event('foo'));
+  assert.strictEqual(count, 1);
+
+  assert.throws(() => target.addEventListener('foo', () => {}, { signal: {} }), TypeError);
+}
+
+// a listener aborting the signal stops the ones after it sharing that signal
+{
+  const target = new EventTarget();
+  const ac = new AbortController();
+  const calls = [];
+  target.addEventListener('foo', () => { calls.push(1); ac.abort(); }, { signal: ac.signal });
+  target.addEventListener('foo', () => calls.push(2), { signal: ac.signal });
+  target.dispatchEvent(new Event('foo'));
+  assert.deepStrictEqual(calls, [1]);
+}
+
+// a throwing listener is reported as uncaught and the rest still run
+{
+  const target = new EventTarget();
+  const calls = [];
+  const onUncaught = (err) => calls.push(err.message);
+  process.on('uncaughtException', onUncaught);
+  target.addEventListener('foo', () => { throw new Error('boom'); });
+  target.addEventListener('foo', () => calls.push('after'));
+  target.dispatchEvent(new Event('foo'));
+  process.removeListener('uncaughtException', onUncaught);
+  assert.deepStrictEqual(calls, ['boom', 'after']);
+}
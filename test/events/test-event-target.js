@@ -0,0 +1,135 @@
+'use strict';
+import assert from 'assert';
+
+// Event and CustomEvent
+{
+  assert.throws(() => new Event(), TypeError);
+  const event = new Event('foo');
+  assert.strictEqual(event.type, 'foo');
+  assert.strictEqual(event.bubbles, false);
+  assert.strictEqual(event.cancelable, false);
+  assert.strictEqual(event.composed, false);
+  assert.strictEqual(event.isTrusted, false);
+  assert.strictEqual(event.defaultPrevented, false);
+  assert.strictEqual(event.eventPhase, Event.NONE);
+  assert.strictEqual(event.target, null);
+  assert.strictEqual(event.currentTarget, null);
+  assert.strictEqual(typeof event.timeStamp, 'number');
+  assert.deepStrictEqual(event.composedPath(), []);
+  assert.strictEqual(Object.prototype.toString.call(event), '[object Event]');
+  assert.strictEqual(Event.AT_TARGET, 2);
+
+  // not cancelable, so preventDefault() does nothing
+  event.preventDefault();
+  assert.strictEqual(event.defaultPrevented, false);
+
+  const custom = new CustomEvent('bar', { detail: { x: 1 }, cancelable: true });
+  assert.ok(custom instanceof Event);
+  assert.strictEqual(custom.type, 'bar');
+  assert.deepStrictEqual(custom.detail, { x: 1 });
+  assert.strictEqual(new CustomEvent('bar').detail, null);
+  custom.preventDefault();
+  assert.strictEqual(custom.defaultPrevented, true);
+  assert.strictEqual(custom.returnValue, false);
+}
+
+// listeners run in order, with the target as `this`, and only once per callback
+{
+  const target = new EventTarget();
+  const calls = [];
+  const listener = function (event) {
+    assert.strictEqual(this, target);
+    assert.strictEqual(event.target, target);
+    assert.strictEqual(event.currentTarget, target);
+    assert.strictEqual(event.eventPhase, Event.AT_TARGET);
+    assert.deepStrictEqual(event.composedPath(), [target]);
+    calls.push('listener');
+  };
+  target.addEventListener('foo', listener);
+  target.addEventListener('foo', listener);
+  target.addEventListener('foo', { handleEvent: () => calls.push('handleEvent') });
+  target.addEventListener('foo', () => calls.push('capture'), true);
+  target.addEventListener('bar', () => calls.push('bar'));
+  target.addEventListener('foo', null);
+
+  const event = new Event('foo');
+  assert.strictEqual(target.dispatchEvent(event), true);
+  assert.deepStrictEqual(calls, ['capture', 'listener', 'handleEvent']);
+  assert.strictEqual(event.currentTarget, null);
+  assert.strictEqual(event.target, target);
+
+  calls.length = 0;
+  target.removeEventListener('foo', listener);
+  // listeners added during a dispatch only run for later ones
+  target.addEventListener('foo', () => target.addEventListener('foo', () => calls.push('late')));
+  target.dispatchEvent(new Event('foo'));
+  assert.deepStrictEqual(calls, ['capture', 'handleEvent']);
+
+  assert.throws(() => target.addEventListener('foo', 1), TypeError);
+  assert.throws(() => target.dispatchEvent({ type: 'foo' }), TypeError);
+}
+
+// preventDefault() and stopImmediatePropagation()
+{
+  const target = new EventTarget();
+  const calls = [];
+  target.addEventListener('foo', (event) => {
+    event.preventDefault();
+    event.stopImmediatePropagation();
+    calls.push(1);
+  });
+  target.addEventListener('foo', () => calls.push(2));
+  assert.strictEqual(target.dispatchEvent(new Event('foo', { cancelable: true })), false);
+  assert.deepStrictEqual(calls, [1]);
+}
+
+// an event can't be dispatched while it is being dispatched
+{
+  const target = new EventTarget();
+  const event = new Event('foo');
+  let thrown;
+  target.addEventListener('foo', () => {
+    try {
+      target.dispatchEvent(event);
+    } catch (err) {
+      thrown = err;
+    }
+  });
+  target.dispatchEvent(event);
+  assert.strictEqual(thrown.name, 'InvalidStateError');
+  // but it can be dispatched again afterwards
+  target.dispatchEvent(event);
+}
+
+// JS classes can extend EventTarget and Event
+{
+  class MyEvent extends Event {
+    constructor(value) {
+      super('my');
+      this.value = value;
+    }
+  }
+  class MyTarget extends EventTarget {
+    fire(value) {
+      return this.dispatchEvent(new MyEvent(value));
+    }
+  }
+  const target = new MyTarget();
+  assert.ok(target instanceof EventTarget);
+  let received;
+  target.addEventListener('my', (event) => { received = event.value; });
+  target.fire(42);
+  assert.strictEqual(received, 42);
+}
+
+// AbortSignal is an EventTarget
+{
+  const ac = new AbortController();
+  assert.ok(ac.signal instanceof EventTarget);
+  let event;
+  ac.signal.addEventListener('abort', (e) => { event = e; });
+  ac.abort();
+  assert.ok(event instanceof Event);
+  assert.strictEqual(event.type, 'abort');
+  assert.strictEqual(event.isTrusted, true);
+}
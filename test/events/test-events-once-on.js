@@ -0,0 +1,61 @@
+'use strict';
+import assert from 'assert';
+import { EventEmitter, once, on } from 'events';
+
+// once() with an EventTarget resolves with the event and removes its listener
+{
+  const target = new EventTarget();
+  const promise = once(target, 'foo');
+  const event = new Event('foo');
+  target.dispatchEvent(event);
+  const [received] = await promise;
+  assert.strictEqual(received, event);
+  assert.strictEqual(EventEmitter.once, once);
+}
+
+// once() with an EventEmitter
+{
+  const ee = new EventEmitter();
+  const promise = once(ee, 'foo');
+  ee.emit('foo', 1, 2);
+  assert.deepStrictEqual(await promise, [1, 2]);
+  assert.strictEqual(ee.listenerCount('error'), 0);
+
+  const failing = once(ee, 'foo');
+  ee.emit('error', new Error('boom'));
+  await assert.rejects(failing, { message: 'boom' });
+}
+
+// once() rejects with an AbortError when the signal aborts
+{
+  const target = new EventTarget();
+  const ac = new AbortController();
+  const promise = once(target, 'foo', { signal: ac.signal });
+  ac.abort();
+  await assert.rejects(promise, { name: 'AbortError' });
+  await assert.rejects(once(target, 'foo', { signal: AbortSignal.abort() }), { name: 'AbortError' });
+}
+
+// on() iterates the events of an EventTarget until the signal aborts
+{
+  const target = new EventTarget();
+  const ac = new AbortController();
+  const iterator = on(target, 'foo', { signal: ac.signal });
+  assert.strictEqual(EventEmitter.on, on);
+
+  target.dispatchEvent(new CustomEvent('foo', { detail: 1 }));
+  target.dispatchEvent(new CustomEvent('foo', { detail: 2 }));
+  const seen = [];
+  await assert.rejects(async () => {
+    for await (const [event] of iterator) {
+      seen.push(event.detail);
+      if (seen.length === 2) {
+        setTimeout(() => target.dispatchEvent(new CustomEvent('foo', { detail: 3 })), 1);
+      }
+      if (seen.length === 3) {
+        setTimeout(() => ac.abort(), 1);
+      }
+    }
+  }, { name: 'AbortError' });
+  assert.deepStrictEqual(seen, [1, 2, 3]);
+}
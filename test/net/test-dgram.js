@@ -0,0 +1,77 @@
+'use strict';
+import assert from 'assert';
+import dgram from 'dgram';
+import * as net from 'wasi_net';
+import { Buffer } from 'buffer';
+import { TextDecoder } from 'util';
+
+const once = (emitter, name) => new Promise((resolve) => emitter.once(name, (...args) => resolve(args)));
+
+// the raw socket: port 0 picks a free port and datagrams carry their sender
+{
+  const a = new net.WasiUdpSocket({ type: 'udp4', host: '127.0.0.1', port: 0 });
+  const b = new net.WasiUdpSocket({ type: 'udp4', host: '127.0.0.1', port: 0 });
+  const addr = a.localAddress();
+  assert.strictEqual(addr.address, '127.0.0.1');
+  assert.strictEqual(addr.family, 'IPv4');
+  assert.ok(addr.port > 0);
+
+  const received = a.recvFrom();
+  assert.strictEqual(await b.sendTo('ping', '127.0.0.1', addr.port), 4);
+  const msg = await received;
+  assert.strictEqual(new TextDecoder().decode(msg.data), 'ping');
+  assert.strictEqual(msg.size, 4);
+  assert.strictEqual(msg.port, b.localAddress().port);
+
+  a.setBroadcast(true);
+  assert.throws(() => new net.WasiUdpSocket({ type: 'udp5' }), TypeError);
+
+  // closing rejects the pending receive and frees the port right away
+  const pending = a.recvFrom();
+  a.close();
+  const again = new net.WasiUdpSocket({ type: 'udp4', host: '127.0.0.1', port: addr.port });
+  assert.strictEqual(again.localAddress().port, addr.port);
+  await assert.rejects(pending);
+  again.close();
+  b.close();
+}
+
+// an echo between two dgram sockets
+{
+  const server = dgram.createSocket('udp4');
+  server.on('message', (msg, rinfo) => {
+    server.send(msg, rinfo.port, rinfo.address);
+  });
+  server.bind(0, '127.0.0.1');
+  await once(server, 'listening');
+  const { port } = server.address();
+  assert.throws(() => server.bind(0), { code: 'ERR_SOCKET_ALREADY_BOUND' });
+
+  // the client binds on its first send
+  const client = dgram.createSocket({ type: 'udp4' });
+  assert.throws(() => client.address(), { code: 'ERR_SOCKET_DGRAM_NOT_RUNNING' });
+  const sent = new Promise((resolve, reject) => {
+    client.send(Buffer.from('xhellox'), 1, 5, port, '127.0.0.1', (err, bytes) => {
+      err ? reject(err) : resolve(bytes);
+    });
+  });
+  const [msg, rinfo] = await once(client, 'message');
+  assert.strictEqual(await sent, 5);
+  assert.strictEqual(msg.toString(), 'hello');
+  assert.deepStrictEqual(rinfo, { address: '127.0.0.1', family: 'IPv4', port, size: 5 });
+
+  assert.throws(() => client.send('x', 0), { code: 'ERR_SOCKET_BAD_PORT' });
+  assert.throws(() => client.send(42, port), { code: 'ERR_INVALID_ARG_TYPE' });
+
+  await new Promise((resolve) => client.close(resolve));
+  assert.throws(() => client.close(), { code: 'ERR_SOCKET_DGRAM_NOT_RUNNING' });
+  server.close();
+}
+
+assert.throws(() => dgram.createSocket('tcp'), { code: 'ERR_SOCKET_BAD_TYPE' });
+// WasmEdge can't set IPV6_V6ONLY
+assert.throws(() => dgram.createSocket({ type: 'udp6', ipv6Only: true }),
+  { code: 'ERR_FEATURE_UNAVAILABLE_ON_PLATFORM', name: 'TypeError' });
+
+// an unrefed socket doesn't keep the loop alive
+dgram.createSocket('udp4').bind(0).unref();
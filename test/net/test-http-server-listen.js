@@ -0,0 +1,32 @@
+'use strict';
+import assert from 'assert';
+import http from 'http';
+import * as net from 'wasi_net';
+import { TextDecoder } from 'util';
+
+const server = http.createServer((req, res) => {
+  res.end('ok');
+});
+assert.strictEqual(server.address(), null);
+
+// listen(port, host, cb) binds the host, and address() has the port picked for 0
+await new Promise((resolve) => server.listen(0, '127.0.0.1', resolve));
+const { address, family, port } = server.address();
+assert.strictEqual(address, '127.0.0.1');
+assert.strictEqual(family, 'IPv4');
+assert.ok(port > 0);
+
+const conn = await net.WasiTcpConn.connect('127.0.0.1', port);
+conn.write('GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n');
+const response = new TextDecoder().decode(await conn.read());
+assert.match(response, /^HTTP\/1\.1 200/);
+conn.close();
+
+server.close();
+assert.strictEqual(server.address(), null);
+
+// the port can be listened on again as soon as the server is closed
+const again = http.createServer((req, res) => res.end());
+await new Promise((resolve) => again.listen(port, '127.0.0.1', resolve));
+assert.strictEqual(again.address().port, port);
+again.close();
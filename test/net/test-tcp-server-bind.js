@@ -0,0 +1,74 @@
+'use strict';
+import assert from 'assert';
+import * as net from 'wasi_net';
+import { TextDecoder } from 'util';
+
+// port 0 picks a free port, which localAddress() reports
+{
+  const server = new net.WasiTcpServer({ host: '127.0.0.1', port: 0 });
+  const addr = server.localAddress();
+  assert.strictEqual(addr.address, '127.0.0.1');
+  assert.strictEqual(addr.family, 'IPv4');
+  assert.ok(addr.port > 0);
+
+  const accepted = server.accept();
+  const conn = await net.WasiTcpConn.connect('127.0.0.1', addr.port);
+  const peer = await accepted;
+  peer.write('hi');
+  assert.strictEqual(new TextDecoder().decode(await conn.read()), 'hi');
+
+  // the port is taken while the server listens
+  assert.throws(() => new net.WasiTcpServer({ host: '127.0.0.1', port: addr.port }));
+
+  conn.close();
+  peer.close();
+  server.close();
+}
+
+// close() frees the port right away, even with an accept pending
+{
+  const server = new net.WasiTcpServer({ host: '127.0.0.1', port: 0 });
+  const { port } = server.localAddress();
+  const pending = server.accept();
+  server.close();
+  const again = new net.WasiTcpServer({ host: '127.0.0.1', port });
+  assert.strictEqual(again.localAddress().port, port);
+  await assert.rejects(pending);
+  again.close();
+}
+
+// a bare port binds every IPv4 address, as before
+{
+  const server = new net.WasiTcpServer(0);
+  assert.deepStrictEqual(Object.keys(server.localAddress()), ['address', 'family', 'port']);
+  assert.strictEqual(server.localAddress().address, '0.0.0.0');
+  server.close();
+}
+
+// IPv6 literals, with or without brackets
+{
+  const server = new net.WasiTcpServer({ host: '[::1]', port: 0, backlog: 8 });
+  const addr = server.localAddress();
+  assert.strictEqual(addr.address, '::1');
+  assert.strictEqual(addr.family, 'IPv6');
+
+  const accepted = server.accept();
+  const conn = await net.WasiTcpConn.connect('::1', addr.port);
+  (await accepted).close();
+  conn.close();
+  server.close();
+
+  const any = new net.WasiTcpServer({ host: '::', reuseAddr: false });
+  assert.strictEqual(any.localAddress().address, '::');
+  any.close();
+}
+
+assert.throws(() => new net.WasiTcpServer({ port: 65536 }), RangeError);
+assert.throws(() => new net.WasiTcpServer({ port: 1.5 }), RangeError);
+assert.throws(() => new net.WasiTcpServer({ port: '80' }), TypeError);
+assert.throws(() => new net.WasiTcpServer({ host: 1 }), TypeError);
+assert.throws(() => new net.WasiTcpServer({ backlog: -1 }), TypeError);
+assert.throws(() => new net.WasiTcpServer({ reuseAddr: 'yes' }), TypeError);
+// WasmEdge can't set IPV6_V6ONLY
+assert.throws(() => new net.WasiTcpServer({ host: '::', ipv6Only: true }),
+  { code: 'ERR_FEATURE_UNAVAILABLE_ON_PLATFORM', name: 'TypeError' });
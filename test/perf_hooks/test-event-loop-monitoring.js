@@ -0,0 +1,81 @@
+'use strict';
+import assert from 'assert';
+import { monitorEventLoopDelay, createHistogram } from 'perf_hooks';
+
+function busy(ms) {
+  const end = performance.now() + ms;
+  while (performance.now() < end);
+}
+
+// eventLoopUtilization() splits the time since the loop started into idle and active
+{
+  const elu1 = performance.eventLoopUtilization();
+  await new Promise((resolve) => setTimeout(resolve, 50));
+  const elu2 = performance.eventLoopUtilization();
+  assert.ok(elu2.idle >= elu1.idle);
+
+  const idle = performance.eventLoopUtilization(elu2);
+  await new Promise((resolve) => setTimeout(resolve, 50));
+  const waited = performance.eventLoopUtilization(elu2);
+  assert.ok(waited.idle >= 30, `idle ${waited.idle}`);
+  assert.ok(waited.utilization >= 0 && waited.utilization < 0.5, `utilization ${waited.utilization}`);
+
+  const start = performance.eventLoopUtilization();
+  await new Promise((resolve) => setImmediate(resolve));
+  busy(50);
+  await new Promise((resolve) => setImmediate(resolve));
+  const worked = performance.eventLoopUtilization(start);
+  assert.ok(worked.active >= 40, `active ${worked.active}`);
+  assert.ok(worked.utilization > 0.5, `utilization ${worked.utilization}`);
+
+  const delta = performance.eventLoopUtilization(waited, idle);
+  assert.strictEqual(delta.idle, waited.idle - idle.idle);
+  assert.deepStrictEqual(Object.keys(performance.toJSON()), ['timeOrigin', 'eventLoopUtilization']);
+}
+
+// monitorEventLoopDelay() records how late its timer runs, in nanoseconds
+{
+  const h = monitorEventLoopDelay({ resolution: 5 });
+  assert.strictEqual(h.count, 0);
+  assert.ok(Number.isNaN(h.mean));
+  assert.strictEqual(h.enable(), true);
+  assert.strictEqual(h.enable(), false);
+
+  for (let i = 0; i < 5; i++) {
+    await new Promise((resolve) => setTimeout(resolve, 10));
+    busy(20);
+  }
+  assert.strictEqual(h.disable(), true);
+  assert.strictEqual(h.disable(), false);
+
+  assert.ok(h.count > 0);
+  assert.ok(h.min >= 1e6, `min ${h.min}`);
+  assert.ok(h.max >= 20e6, `max ${h.max}`);
+  assert.ok(h.mean >= h.min && h.mean <= h.max);
+  assert.ok(h.percentile(100) <= h.max);
+  assert.ok(h.percentile(50) >= h.min);
+  assert.ok(h.percentiles.get(0) === h.min);
+
+  h.reset();
+  assert.strictEqual(h.count, 0);
+  assert.throws(() => monitorEventLoopDelay({ resolution: 0 }), RangeError);
+}
+
+// createHistogram()
+{
+  const h = createHistogram();
+  [1, 2, 3, 4, 100].forEach((v) => h.record(v));
+  assert.strictEqual(h.count, 5);
+  assert.strictEqual(h.min, 1);
+  assert.strictEqual(h.max, 100);
+  assert.strictEqual(h.mean, 22);
+  assert.strictEqual(h.percentile(50), 3);
+  assert.strictEqual(h.percentile(100), 100);
+  assert.throws(() => h.record(0), RangeError);
+
+  const other = createHistogram();
+  other.record(1000);
+  h.add(other);
+  assert.strictEqual(h.max, 1000);
+  assert.strictEqual(h.count, 6);
+}
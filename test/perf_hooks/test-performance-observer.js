@@ -0,0 +1,57 @@
+'use strict';
+import assert from 'assert';
+import { PerformanceObserver } from 'perf_hooks';
+
+assert.throws(() => new PerformanceObserver(), TypeError);
+assert.deepStrictEqual(PerformanceObserver.supportedEntryTypes, ['mark', 'measure']);
+
+// entries are delivered together, after the current callback
+{
+  const received = [];
+  const observer = new PerformanceObserver(function (list, obs) {
+    assert.strictEqual(this, observer);
+    assert.strictEqual(obs, observer);
+    assert.ok(list instanceof PerformanceObserverEntryList);
+    received.push(list.getEntries().map((e) => `${e.entryType}:${e.name}`));
+    assert.deepStrictEqual(list.getEntriesByType('measure').map((e) => e.name), ['m']);
+    assert.deepStrictEqual(list.getEntriesByName('a').map((e) => e.name), ['a']);
+  });
+  observer.observe({ entryTypes: ['mark', 'measure', 'unknown'] });
+  performance.mark('a');
+  performance.measure('m', 'a');
+  assert.deepStrictEqual(received, []);
+
+  await new Promise((resolve) => setImmediate(resolve));
+  assert.deepStrictEqual(received, [['mark:a', 'measure:m']]);
+
+  observer.disconnect();
+  performance.mark('b');
+  await new Promise((resolve) => setImmediate(resolve));
+  assert.strictEqual(received.length, 1);
+}
+
+// `buffered` delivers the entries recorded before observe()
+{
+  const names = await new Promise((resolve) => {
+    const observer = new PerformanceObserver((list) => {
+      observer.disconnect();
+      resolve(list.getEntries().map((e) => e.name));
+    });
+    observer.observe({ type: 'mark', buffered: true });
+  });
+  assert.deepStrictEqual(names, ['a', 'b']);
+}
+
+// takeRecords() empties the buffer before it is delivered
+{
+  let called = false;
+  const observer = new PerformanceObserver(() => { called = true; });
+  observer.observe({ type: 'measure' });
+  performance.measure('taken');
+  assert.deepStrictEqual(observer.takeRecords().map((e) => e.name), ['taken']);
+  await new Promise((resolve) => setImmediate(resolve));
+  assert.strictEqual(called, false);
+  observer.disconnect();
+}
+
+assert.throws(() => new PerformanceObserver(() => {}).observe({}), TypeError);
@@ -0,0 +1,90 @@
+'use strict';
+import assert from 'assert';
+import process from 'process';
+import perf_hooks from 'perf_hooks';
+
+assert.strictEqual(perf_hooks.performance, performance);
+assert.strictEqual(Object.prototype.toString.call(performance), '[object Performance]');
+assert.throws(() => new Performance(), TypeError);
+assert.throws(() => new PerformanceEntry(), TypeError);
+
+// the clocks are monotonic and finer than a millisecond
+{
+  assert.ok(Math.abs(performance.timeOrigin + performance.now() - Date.now()) < 50);
+  const t0 = performance.now();
+  const [s0, ns0] = process.hrtime();
+  let t1;
+  do {
+    t1 = performance.now();
+  } while (t1 === t0);
+  assert.ok(t1 > t0);
+  assert.ok(t1 - t0 < 1);
+
+  const [s, ns] = process.hrtime([s0, ns0]);
+  assert.ok(s >= 0 && ns >= 0 && ns < 1e9);
+  assert.strictEqual(typeof process.hrtime.bigint(), 'bigint');
+  const b0 = process.hrtime.bigint();
+  assert.ok(process.hrtime.bigint() >= b0);
+  assert.ok(process.uptime() > 0);
+
+  // Event timestamps are on the same clock
+  const before = performance.now();
+  const event = new Event('foo');
+  assert.ok(event.timeStamp >= before && event.timeStamp <= performance.now());
+}
+
+// marks and measures
+{
+  const start = performance.mark('start', { detail: { step: 1 } });
+  assert.ok(start instanceof PerformanceMark);
+  assert.ok(start instanceof PerformanceEntry);
+  assert.strictEqual(start.name, 'start');
+  assert.strictEqual(start.entryType, 'mark');
+  assert.strictEqual(start.duration, 0);
+  assert.deepStrictEqual(start.detail, { step: 1 });
+
+  const end = performance.mark('end', { startTime: start.startTime + 5 });
+  assert.strictEqual(end.startTime, start.startTime + 5);
+
+  const measure = performance.measure('between', 'start', 'end');
+  assert.ok(measure instanceof PerformanceMeasure);
+  assert.strictEqual(measure.entryType, 'measure');
+  assert.strictEqual(measure.startTime, start.startTime);
+  assert.strictEqual(measure.duration, 5);
+
+  const fromOptions = performance.measure('options', { start: 'start', duration: 2, detail: 'x' });
+  assert.strictEqual(fromOptions.duration, 2);
+  assert.strictEqual(fromOptions.detail, 'x');
+
+  const sinceOrigin = performance.measure('origin');
+  assert.strictEqual(sinceOrigin.startTime, 0);
+
+  assert.throws(() => performance.measure('missing', 'nope'), { name: 'SyntaxError' });
+
+  assert.deepStrictEqual(
+    performance.getEntries().map((e) => e.name),
+    ['origin', 'start', 'between', 'options', 'end'],
+  );
+  assert.deepStrictEqual(performance.getEntriesByType('mark').map((e) => e.name), ['start', 'end']);
+  assert.deepStrictEqual(performance.getEntriesByName('between').map((e) => e.duration), [5]);
+  assert.deepStrictEqual(performance.getEntriesByName('start', 'measure'), []);
+
+  assert.deepStrictEqual(measure.toJSON(), {
+    name: 'between',
+    entryType: 'measure',
+    startTime: start.startTime,
+    duration: 5,
+    detail: null,
+  });
+
+  performance.clearMarks('start');
+  assert.deepStrictEqual(performance.getEntriesByType('mark').map((e) => e.name), ['end']);
+  performance.clearMarks();
+  performance.clearMeasures();
+  assert.deepStrictEqual(performance.getEntries(), []);
+
+  // a PerformanceMark made directly is not recorded
+  const detached = new PerformanceMark('detached', { startTime: 1 });
+  assert.strictEqual(detached.startTime, 1);
+  assert.deepStrictEqual(performance.getEntries(), []);
+}
@@ -0,0 +1,55 @@
+'use strict';
+import assert from 'assert';
+import process from 'process';
+import * as net from 'wasi_net';
+
+const count = (name) => process.getActiveResourcesInfo().filter((n) => n === name).length;
+
+assert.deepStrictEqual(process._getActiveHandles(), []);
+assert.deepStrictEqual(process._getActiveRequests(), []);
+
+// timers are listed while they are refed
+{
+  const timeout = setTimeout(() => {}, 1000);
+  assert.strictEqual(count('Timeout'), 1);
+  timeout.unref();
+  assert.strictEqual(count('Timeout'), 0);
+  timeout.ref();
+  assert.strictEqual(count('Timeout'), 1);
+  clearTimeout(timeout);
+  assert.strictEqual(count('Timeout'), 0);
+
+  setImmediate(() => {});
+  assert.strictEqual(count('Immediate'), 1);
+  await new Promise((resolve) => setImmediate(resolve));
+  assert.strictEqual(count('Immediate'), 0);
+}
+
+// sockets and servers are handles from when they open until they close
+{
+  const port = 8138;
+  const server = new net.WasiTcpServer(port);
+  assert.deepStrictEqual(process.getActiveResourcesInfo(), ['TCPServerWrap']);
+  assert.deepStrictEqual(process._getActiveHandles(), [server]);
+  assert.strictEqual(server.hasRef(), true);
+  assert.strictEqual(server.unref(), server);
+  assert.strictEqual(server.hasRef(), false);
+  server.ref();
+
+  const accepted = server.accept();
+  const connecting = net.WasiTcpConn.connect('127.0.0.1', port);
+  // the connect is a request of its own, the accept runs on the server
+  assert.deepStrictEqual(process._getActiveRequests(), [{ type: 'FutureWrap' }]);
+  const conn = await connecting;
+  const peer = await accepted;
+  assert.strictEqual(count('TCPSocketWrap'), 2);
+  assert.ok(process._getActiveHandles().includes(conn));
+  assert.ok(process._getActiveHandles().includes(peer));
+
+  conn.close();
+  peer.close();
+  server.close();
+  assert.strictEqual(conn.hasRef(), false);
+  assert.deepStrictEqual(process._getActiveHandles(), []);
+  assert.deepStrictEqual(process.getActiveResourcesInfo(), []);
+}
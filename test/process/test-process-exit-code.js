@@ -0,0 +1,34 @@
+'use strict';
+import assert from 'assert';
+import process from 'process';
+
+const events = [];
+let rounds = 0;
+
+process.on('beforeExit', (code) => {
+  events.push(`beforeExit ${code}`);
+  // the first round schedules more work, so beforeExit fires again once it is done
+  if (rounds++ === 0) {
+    setTimeout(() => {
+      events.push('timeout');
+      process.exitCode = 3;
+    }, 1);
+  }
+});
+
+process.on('exit', (code) => {
+  events.push(`exit ${code}`);
+  assert.deepStrictEqual(events, [
+    'main',
+    'beforeExit 0',
+    'timeout',
+    'beforeExit 3',
+    'exit 3',
+  ]);
+  assert.strictEqual(process._exiting, true);
+  // scheduled work never runs once exiting
+  setTimeout(() => { process.exitCode = 9; }, 0);
+  process.exitCode = 4;
+});
+
+events.push('main');
@@ -0,0 +1,22 @@
+'use strict';
+import process from 'process';
+
+process.on('beforeExit', () => {
+  process.exitCode = 5;
+});
+
+process.on('exit', (code) => {
+  if (code !== 1) {
+    throw new Error(`expected exit code 1, got ${code}`);
+  }
+  process.exitCode = 6;
+});
+
+setTimeout(() => {
+  throw new Error('uncaught in a timer');
+}, 1);
+
+// never runs: the loop stops at the uncaught exception
+setTimeout(() => {
+  process.exitCode = 7;
+}, 100);
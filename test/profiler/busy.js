@@ -0,0 +1,17 @@
+'use strict';
+
+function fib(n) {
+  return n < 2 ? n : fib(n - 1) + fib(n - 2);
+}
+
+function spin(ms) {
+  const end = Date.now() + ms;
+  let n = 0;
+  while (Date.now() < end) {
+    n += fib(12);
+  }
+  return n;
+}
+
+spin(50);
+setTimeout(() => spin(20), 10);
@@ -0,0 +1,21 @@
+'use strict';
+import assert from 'assert';
+
+// samples taken while errors are thrown and caught must not disturb them
+function thrower(i) {
+  throw new RangeError(`error ${i}`);
+}
+
+const end = Date.now() + 50;
+let caught = 0;
+for (let i = 0; Date.now() < end; i++) {
+  try {
+    thrower(i);
+  } catch (e) {
+    assert.ok(e instanceof RangeError);
+    assert.strictEqual(e.message, `error ${i}`);
+    assert.ok(e.stack.includes('thrower'));
+    caught++;
+  }
+}
+assert.ok(caught > 0);
@@ -0,0 +1,11 @@
+'use strict';
+import process from 'process';
+
+// `--test` goes on to the next file after this one stops,
+// even if the call is wrapped in a try/catch
+try {
+  process.exit(0);
+} catch (e) {
+  throw new Error('caught process.exit()');
+}
+throw new Error('ran past process.exit()');
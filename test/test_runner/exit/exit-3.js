@@ -0,0 +1,8 @@
+'use strict';
+import process from 'process';
+
+// callbacks of the same turn stop with the first one
+setTimeout(() => process.exit(3), 1);
+setTimeout(() => {
+  throw new Error('ran past process.exit()');
+}, 1);
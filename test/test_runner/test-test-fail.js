@@ -0,0 +1,11 @@
+'use strict';
+import assert from 'assert';
+import { test } from 'test';
+
+test('fails', () => {
+  assert.strictEqual(1, 2);
+});
+
+test('times out', { timeout: 5 }, () => new Promise(() => { }));
+
+test('passes', () => { });
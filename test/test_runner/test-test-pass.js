@@ -0,0 +1,123 @@
+'use strict';
+import assert from 'assert';
+import { test, describe, it, before, after, beforeEach, afterEach, mock } from 'test';
+
+const events = [];
+
+before(() => events.push('root before'));
+beforeEach(() => events.push('root beforeEach'));
+
+describe('suite', () => {
+  before(() => events.push('suite before'));
+  beforeEach(() => events.push('suite beforeEach'));
+  afterEach(() => events.push('suite afterEach'));
+  after(() => events.push('suite after'));
+
+  it('sync', () => {
+    events.push('sync');
+  });
+
+  it('async', async () => {
+    await new Promise((resolve) => setTimeout(resolve, 1));
+    events.push('async');
+  });
+
+  it('callback', (t, done) => {
+    setTimeout(() => {
+      events.push('callback');
+      done();
+    }, 1);
+  });
+
+  it.skip('skipped', () => {
+    throw new Error('skipped tests do not run');
+  });
+
+  it.todo('todo that fails', () => {
+    throw new Error('todo failures are not counted');
+  });
+
+  describe('nested', () => {
+    it('inner', () => {
+      events.push('inner');
+    });
+  });
+});
+
+describe('only', () => {
+  it('not run', () => {
+    throw new Error('only the `only` test runs');
+  });
+
+  it.only('run', () => {
+    events.push('only');
+  });
+});
+
+test('subtests', async (t) => {
+  t.beforeEach((t) => events.push(`sub beforeEach ${t.name}`));
+  await t.test('first', () => {
+    events.push('first');
+  });
+  await t.test('second', async () => {
+    events.push('second');
+  });
+  t.diagnostic('two subtests');
+});
+
+test('skip from inside', (t) => {
+  t.skip('not today');
+});
+
+test('mocks', (t) => {
+  const add = mock.fn((a, b) => a + b);
+  assert.strictEqual(add(1, 2), 3);
+  assert.strictEqual(add.mock.callCount(), 1);
+  assert.deepStrictEqual(add.mock.calls[0].arguments, [1, 2]);
+  assert.strictEqual(add.mock.calls[0].result, 3);
+
+  add.mock.mockImplementationOnce(() => 42);
+  assert.strictEqual(add(1, 2), 42);
+  assert.strictEqual(add(1, 2), 3);
+
+  const obj = {
+    value: 1,
+    get() { return this.value; },
+  };
+  const spy = t.mock.method(obj, 'get', function () { return this.value * 10; });
+  assert.strictEqual(obj.get(), 10);
+  assert.strictEqual(spy.mock.calls[0].this, obj);
+  spy.mock.restore();
+  assert.strictEqual(obj.get(), 1);
+
+  t.mock.method(obj, 'get', () => 5);
+  assert.strictEqual(obj.get(), 5);
+  t.after(() => {
+    events.push('after mocks');
+  });
+});
+
+test('timeout', { timeout: 1000 }, async () => {
+  await new Promise((resolve) => setTimeout(resolve, 1));
+});
+
+test('order', () => {
+  assert.deepStrictEqual(events, [
+    'root before',
+    'suite before',
+    'root beforeEach', 'suite beforeEach', 'sync', 'suite afterEach',
+    'root beforeEach', 'suite beforeEach', 'async', 'suite afterEach',
+    'root beforeEach', 'suite beforeEach', 'callback', 'suite afterEach',
+    'root beforeEach', 'suite beforeEach', 'suite afterEach',
+    'root beforeEach', 'suite beforeEach', 'inner', 'suite afterEach',
+    'suite after',
+    'root beforeEach', 'only',
+    'root beforeEach',
+    'root beforeEach', 'sub beforeEach first', 'first',
+    'root beforeEach', 'sub beforeEach second', 'second',
+    'root beforeEach',
+    'root beforeEach', 'after mocks',
+    'root beforeEach',
+    'root beforeEach',
+  ]);
+});
@@ -0,0 +1,94 @@
+'use strict';
+import assert from 'assert';
+import process from 'process';
+import dgram from 'dgram';
+
+// after the main script: ticks drain before promise jobs, and each queue is
+// drained again for whatever the other one queued
+const main = [];
+setImmediate(() => main.push('immediate'));
+process.nextTick(() => main.push('tick 1'));
+Promise.resolve().then(() => {
+  main.push('promise 1');
+  process.nextTick(() => main.push('tick from promise'));
+});
+process.nextTick(() => {
+  main.push('tick 2');
+  Promise.resolve().then(() => main.push('promise from tick'));
+});
+main.push('sync');
+
+// inside a timer: the check phase of the same turn comes before the next timers phase
+const inTimer = [];
+setTimeout(() => {
+  setTimeout(() => inTimer.push('timeout'), 0);
+  setImmediate(() => inTimer.push('immediate'));
+  Promise.resolve().then(() => inTimer.push('promise'));
+  process.nextTick(() => inTimer.push('tick'));
+}, 1);
+
+// microtasks run between the callbacks of one phase
+const timers = [];
+setTimeout(() => {
+  timers.push('timeout 1');
+  Promise.resolve().then(() => timers.push('promise 1'));
+  process.nextTick(() => timers.push('tick 1'));
+}, 5);
+setTimeout(() => timers.push('timeout 2'), 5);
+// earlier deadlines first, equal deadlines in the order they were set
+setTimeout(() => timers.push('timeout 20'), 20);
+setTimeout(() => timers.push('timeout 10'), 10);
+
+// immediates queued by an immediate wait for the next turn
+const immediates = [];
+setImmediate(() => {
+  immediates.push('immediate 1');
+  setImmediate(() => immediates.push('nested immediate'));
+  process.nextTick(() => immediates.push('tick'));
+});
+setImmediate(() => immediates.push('immediate 2'));
+
+// an I/O callback runs in the poll phase: the check phase of its turn comes
+// before the timers phase of the next, even once the timer is due
+const io = [];
+const socket = dgram.createSocket('udp4');
+socket.on('message', (msg) => {
+  io.push(msg.toString());
+  socket.close();
+  setTimeout(() => io.push('timeout'), 0);
+  setImmediate(() => io.push('immediate'));
+  Promise.resolve().then(() => io.push('promise'));
+  const start = Date.now();
+  while (Date.now() - start < 5);
+});
+socket.bind(0, '127.0.0.1', () => {
+  socket.send('io', socket.address().port, '127.0.0.1');
+});
+
+process.on('exit', () => {
+  assert.deepStrictEqual(main, [
+    'sync',
+    'tick 1',
+    'tick 2',
+    'promise 1',
+    'promise from tick',
+    'tick from promise',
+    'immediate',
+  ]);
+  assert.deepStrictEqual(inTimer, ['tick', 'promise', 'immediate', 'timeout']);
+  assert.deepStrictEqual(timers, [
+    'timeout 1',
+    'tick 1',
+    'promise 1',
+    'timeout 2',
+    'timeout 10',
+    'timeout 20',
+  ]);
+  assert.deepStrictEqual(immediates, [
+    'immediate 1',
+    'tick',
+    'immediate 2',
+    'nested immediate',
+  ]);
+  assert.deepStrictEqual(io, ['io', 'promise', 'immediate', 'timeout']);
+});
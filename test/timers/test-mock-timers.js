@@ -0,0 +1,76 @@
+'use strict';
+import assert from 'assert';
+import { test, mock } from 'test';
+
+test('tick runs the timers that come due, in order', (t) => {
+  t.mock.timers.enable();
+  const calls = [];
+  setTimeout(() => calls.push('timeout 50'), 50);
+  setTimeout(() => calls.push('timeout 20'), 20);
+  const interval = setInterval(() => calls.push('interval'), 30);
+
+  t.mock.timers.tick(19);
+  assert.deepStrictEqual(calls, []);
+  t.mock.timers.tick(1);
+  assert.deepStrictEqual(calls, ['timeout 20']);
+  t.mock.timers.tick(70);
+  assert.deepStrictEqual(calls, ['timeout 20', 'interval', 'timeout 50', 'interval', 'interval']);
+  clearInterval(interval);
+});
+
+test('runAll runs every pending timer', (t) => {
+  t.mock.timers.enable();
+  const calls = [];
+  setTimeout(() => {
+    calls.push('first');
+    setTimeout(() => calls.push('nested'), 5);
+  }, 10);
+  setTimeout(() => calls.push('last'), 1000);
+  t.mock.timers.runAll();
+  assert.deepStrictEqual(calls, ['first', 'nested', 'last']);
+});
+
+test('Date.now and performance.now are frozen between ticks', (t) => {
+  t.mock.timers.enable({ now: 1000 });
+  const start = performance.now();
+  assert.strictEqual(Date.now(), 1000);
+  assert.strictEqual(new Date().getTime(), 1000);
+  assert.ok(new Date() instanceof Date);
+  assert.strictEqual(new Date(5).getTime(), 5);
+  assert.strictEqual(typeof Date(), 'string');
+
+  let i = 0;
+  while (i < 1e5) i++;
+  assert.strictEqual(Date.now(), 1000);
+  assert.strictEqual(performance.now(), start);
+
+  t.mock.timers.tick(250);
+  assert.strictEqual(Date.now(), 1250);
+  assert.strictEqual(Math.round(performance.now() - start), 250);
+
+  t.mock.timers.setTime(5000);
+  assert.strictEqual(Date.now(), 5000);
+});
+
+test('timers can be mocked without Date', (t) => {
+  t.mock.timers.enable({ apis: ['setTimeout'] });
+  assert.ok(Date.now() > 1e12);
+  let fired = false;
+  setTimeout(() => { fired = true; }, 10000);
+  t.mock.timers.tick(10000);
+  assert.ok(fired);
+});
+
+test('reset restores real time', async () => {
+  const RealDate = Date;
+  mock.timers.enable();
+  assert.notStrictEqual(Date, RealDate);
+  assert.throws(() => mock.timers.enable(), /already enabled/);
+  mock.timers.reset();
+  assert.strictEqual(Date, RealDate);
+  assert.throws(() => mock.timers.tick(1), /enable MockTimers first/);
+
+  const before = performance.now();
+  await new Promise((resolve) => setTimeout(resolve, 5));
+  assert.ok(performance.now() - before >= 4);
+});
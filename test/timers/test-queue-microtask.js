@@ -0,0 +1,27 @@
+'use strict';
+import assert from 'assert';
+import process from 'process';
+
+assert.throws(() => queueMicrotask(1), TypeError);
+
+// microtasks share the promise job queue, and run after the nextTick queue
+const order = [];
+Promise.resolve().then(() => order.push('promise 1'));
+queueMicrotask(() => {
+  order.push('microtask 1');
+  queueMicrotask(() => order.push('microtask from microtask'));
+});
+Promise.resolve().then(() => order.push('promise 2'));
+process.nextTick(() => order.push('tick'));
+order.push('sync');
+
+setTimeout(() => {
+  assert.deepStrictEqual(order, [
+    'sync',
+    'tick',
+    'promise 1',
+    'microtask 1',
+    'promise 2',
+    'microtask from microtask',
+  ]);
+}, 1);
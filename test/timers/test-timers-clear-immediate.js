@@ -0,0 +1,26 @@
+'use strict';
+import assert from 'assert';
+import process from 'process';
+
+const order = [];
+
+const immediate = setImmediate(() => order.push('cleared'));
+assert.strictEqual(typeof immediate, 'object');
+setImmediate((x) => order.push(x), 'kept');
+clearImmediate(immediate);
+
+const timeout = setTimeout(() => order.push('cleared timeout'), 0);
+clearTimeout(timeout);
+
+// clearing from an earlier callback in the same turn still applies
+let later;
+setImmediate(() => clearImmediate(later));
+later = setImmediate(() => order.push('cleared later'));
+
+// clearing an unknown value is a no-op
+clearImmediate(undefined);
+clearImmediate(42);
+
+process.on('exit', () => {
+  assert.deepStrictEqual(order, ['kept']);
+});
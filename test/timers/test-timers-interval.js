@@ -0,0 +1,50 @@
+'use strict';
+import assert from 'assert';
+import process from 'process';
+
+const start = Date.now();
+let ticks = 0;
+let cleared = false;
+
+const interval = setInterval((a, b) => {
+  assert.strictEqual(a, 'a');
+  assert.strictEqual(b, 'b');
+  ticks++;
+  if (ticks === 5) {
+    // clearing from inside the callback stops the next tick
+    clearInterval(interval);
+    cleared = true;
+    // ticks are scheduled from the start, so five 20ms ticks take about 100ms
+    const elapsed = Date.now() - start;
+    assert.ok(elapsed >= 95, `${elapsed}ms`);
+    assert.ok(elapsed < 400, `${elapsed}ms`);
+  }
+}, 20, 'a', 'b');
+
+// an interval cleared before it fires never runs
+let never = setInterval(() => assert.fail('cleared interval ran'), 5);
+clearInterval(never);
+
+// clearTimeout works on intervals too
+let other = 0;
+const shared = setInterval(() => {
+  if (++other === 2) {
+    clearTimeout(shared);
+  }
+}, 1);
+
+// a zero delay still repeats
+let zero = 0;
+const fast = setInterval(() => {
+  if (++zero === 3) {
+    clearInterval(fast);
+  }
+}, 0);
+
+process.on('exit', () => {
+  // the loop only ended once every interval was cleared
+  assert.ok(cleared);
+  assert.strictEqual(ticks, 5);
+  assert.strictEqual(other, 2);
+  assert.strictEqual(zero, 3);
+});
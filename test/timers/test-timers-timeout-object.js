@@ -0,0 +1,74 @@
+'use strict';
+import assert from 'assert';
+import process from 'process';
+
+const fired = [];
+
+// the handle
+const timeout = setTimeout(() => fired.push('timeout'), 5);
+assert.strictEqual(timeout.constructor.name, 'Timeout');
+assert.strictEqual(timeout.hasRef(), true);
+assert.strictEqual(timeout.unref(), timeout);
+assert.strictEqual(timeout.hasRef(), false);
+assert.strictEqual(timeout.ref(), timeout);
+assert.strictEqual(timeout.hasRef(), true);
+
+// numeric ids
+const byId = setTimeout(() => fired.push('by id'), 5);
+const id = +byId;
+assert.strictEqual(typeof id, 'number');
+assert.notStrictEqual(id, +timeout);
+assert.strictEqual(`${byId}`, String(id));
+clearTimeout(id);
+
+// an unref'd timer does not keep the loop alive
+setTimeout(() => fired.push('unref'), 60 * 1000).unref();
+setInterval(() => fired.push('unref interval'), 60 * 1000).unref();
+
+// refresh restarts the countdown
+const start = Date.now();
+let refreshedAt;
+const refreshed = setTimeout(() => {
+  refreshedAt = Date.now() - start;
+}, 60);
+setTimeout(() => assert.strictEqual(refreshed.refresh(), refreshed), 40);
+
+// refresh reactivates a timer that has fired, but not a cleared one
+let reactivated = 0;
+const again = setTimeout(() => {
+  if (++reactivated === 1) {
+    again.refresh();
+  }
+}, 1);
+const cleared = setTimeout(() => fired.push('cleared'), 1);
+clearTimeout(cleared);
+cleared.refresh();
+
+// delays are coerced like Node.js does
+const warnings = [];
+process.emitWarning = (message, type) => warnings.push(type);
+setTimeout(() => fired.push('string'), '2');
+setTimeout(() => fired.push('missing'));
+setTimeout(() => fired.push('negative'), -10);
+setTimeout(() => fired.push('fraction'), 1.5);
+setTimeout(() => fired.push('overflow'), 2 ** 31);
+assert.deepStrictEqual(warnings, ['TimeoutOverflowWarning']);
+
+assert.throws(() => setTimeout('code', 1), TypeError);
+assert.throws(() => setInterval(undefined, 1), TypeError);
+
+const immediate = setImmediate(() => fired.push('immediate'));
+assert.strictEqual(immediate.hasRef(), true);
+assert.strictEqual(immediate.unref(), immediate);
+assert.strictEqual(immediate.hasRef(), false);
+
+process.on('exit', () => {
+  assert.ok(refreshedAt >= 95, `${refreshedAt}ms`);
+  assert.strictEqual(reactivated, 2);
+  for (const name of ['timeout', 'string', 'missing', 'negative', 'fraction', 'overflow', 'immediate']) {
+    assert.ok(fired.includes(name), name);
+  }
+  for (const name of ['by id', 'unref', 'unref interval', 'cleared']) {
+    assert.ok(!fired.includes(name), name);
+  }
+});
@@ -0,0 +1,25 @@
+'use strict';
+import assert from 'assert';
+import process from 'process';
+
+const count = () => process.getActiveResourcesInfo().filter((n) => n === 'Immediate').length;
+
+// cleared and unref'd immediates are not active resources
+const cleared = setImmediate(() => {});
+assert.strictEqual(count(), 1);
+clearImmediate(cleared);
+assert.strictEqual(count(), 0);
+
+// an unref'd immediate alone does not keep the loop alive
+const immediate = setImmediate(() => {
+  throw new Error('unref\'d immediate ran');
+});
+immediate.unref();
+assert.strictEqual(count(), 0);
+immediate.ref();
+assert.strictEqual(count(), 1);
+immediate.unref();
+
+process.on('exit', () => {
+  assert.strictEqual(immediate.hasRef(), false);
+});
@@ -0,0 +1,5 @@
+'use strict';
+
+// with no uncaughtException listener the error ends the process
+queueMicrotask(() => { throw new Error('fatal'); });
+setTimeout(() => { throw new Error('unreachable'); }, 1);
@@ -0,0 +1,27 @@
+'use strict';
+import assert from 'assert';
+import process from 'process';
+
+// errors from every kind of callback reach the listener instead of being dropped
+const caught = [];
+const monitored = [];
+process.on('uncaughtExceptionMonitor', (err) => monitored.push(err.message));
+process.on('uncaughtException', (err, origin) => {
+  assert.strictEqual(origin, 'uncaughtException');
+  caught.push(err.message);
+});
+
+setTimeout(() => { throw new Error('timeout'); }, 1);
+setImmediate(() => { throw new Error('immediate'); });
+process.nextTick(() => { throw new Error('tick'); });
+queueMicrotask(() => { throw new Error('microtask'); });
+reportError(new Error('reported'));
+
+// the loop goes on after a handled error
+setTimeout(() => {
+  // so a failed assertion is not caught too
+  process.removeAllListeners('uncaughtException');
+  const expected = ['reported', 'tick', 'microtask', 'immediate', 'timeout'];
+  assert.deepStrictEqual(caught.sort(), expected.slice().sort());
+  assert.deepStrictEqual(monitored.sort(), expected.slice().sort());
+}, 10);
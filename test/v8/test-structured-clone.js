@@ -0,0 +1,100 @@
+'use strict';
+import common from '../common';
+import assert from 'assert';
+
+// Primitives round-trip unchanged.
+for (const value of [undefined, null, true, false, 0, -0, 1.5, NaN, 2 ** 40, 'str', 'a\u0000b', 10n ** 20n]) {
+  assert.ok(Object.is(structuredClone(value), value));
+}
+
+// Containers are copied deeply.
+const original = {
+  list: [1, 'two', { three: 3 }],
+  date: new Date(1234567890),
+  re: /ab+c/gi,
+  map: new Map([[1, { a: 1 }], ['k', [2]]]),
+  set: new Set(['x', 'y']),
+  bytes: new Uint8Array([1, 2, 3]),
+  boxed: new Number(7),
+};
+const copy = structuredClone(original);
+assert.notStrictEqual(copy, original);
+assert.notStrictEqual(copy.list[2], original.list[2]);
+assert.deepStrictEqual(copy.list, original.list);
+assert.ok(copy.date instanceof Date);
+assert.strictEqual(copy.date.getTime(), 1234567890);
+assert.ok(copy.re instanceof RegExp);
+assert.strictEqual(copy.re.source, 'ab+c');
+assert.strictEqual(copy.re.flags, 'gi');
+assert.ok(copy.map instanceof Map);
+assert.deepStrictEqual(copy.map.get(1), { a: 1 });
+assert.deepStrictEqual(copy.map.get('k'), [2]);
+assert.deepStrictEqual([...copy.set], ['x', 'y']);
+assert.ok(copy.bytes instanceof Uint8Array);
+assert.deepStrictEqual([...copy.bytes], [1, 2, 3]);
+assert.notStrictEqual(copy.bytes.buffer, original.bytes.buffer);
+assert.strictEqual(typeof copy.boxed, 'object');
+assert.strictEqual(copy.boxed.valueOf(), 7);
+
+// Prototypes are not kept: class instances become plain objects.
+class Point { constructor() { this.x = 1; } }
+const point = structuredClone(new Point());
+assert.strictEqual(Object.getPrototypeOf(point), Object.prototype);
+assert.deepStrictEqual(point, { x: 1 });
+
+// __proto__ is copied as an own property.
+const proto = structuredClone(JSON.parse('{"__proto__": {"polluted": true}}'));
+assert.strictEqual(Object.getPrototypeOf(proto), Object.prototype);
+assert.deepStrictEqual(Object.keys(proto), ['__proto__']);
+
+// Shared and cyclic references are preserved.
+const shared = { s: 1 };
+const cyclic = { a: shared, b: shared };
+cyclic.self = cyclic;
+const cyclicCopy = structuredClone(cyclic);
+assert.strictEqual(cyclicCopy.a, cyclicCopy.b);
+assert.strictEqual(cyclicCopy.self, cyclicCopy);
+const arr = [];
+arr.push(arr);
+const arrCopy = structuredClone(arr);
+assert.strictEqual(arrCopy[0], arrCopy);
+
+// Fresh objects from getters are not mistaken for each other once freed.
+const fresh = structuredClone({ get a() { return { n: 1 }; }, get b() { return { n: 2 }; } });
+assert.notStrictEqual(fresh.a, fresh.b);
+assert.deepStrictEqual(fresh, { a: { n: 1 }, b: { n: 2 } });
+
+// Views over the same buffer keep sharing it.
+const buffer = new ArrayBuffer(8);
+const views = structuredClone([new Uint8Array(buffer, 2, 4), new DataView(buffer)]);
+assert.strictEqual(views[0].buffer, views[1].buffer);
+assert.strictEqual(views[0].byteOffset, 2);
+assert.strictEqual(views[0].length, 4);
+assert.strictEqual(views[1].byteLength, 8);
+
+// Errors keep their type, message and stack.
+const error = structuredClone(new RangeError('out of range'));
+assert.ok(error instanceof RangeError);
+assert.strictEqual(error.message, 'out of range');
+assert.strictEqual(typeof error.stack, 'string');
+
+// Uncloneable values throw a DataCloneError.
+for (const value of [() => {}, Symbol('s'), { f() {} }, new WeakMap(), Promise.resolve()]) {
+  assert.throws(() => structuredClone(value), (e) => {
+    assert.strictEqual(e.name, 'DataCloneError');
+    assert.strictEqual(e.code, 25);
+    return true;
+  });
+}
+
+// Objects backed by Rust classes are host objects.
+assert.throws(() => structuredClone(setTimeout(() => {}, 1)), { name: 'DataCloneError' });
+
+// Transferred buffers are detached.
+const transferred = new ArrayBuffer(4);
+new Uint8Array(transferred)[0] = 9;
+const received = structuredClone(transferred, { transfer: [transferred] });
+assert.strictEqual(transferred.byteLength, 0);
+assert.strictEqual(received.byteLength, 4);
+assert.strictEqual(new Uint8Array(received)[0], 9);
+assert.throws(() => structuredClone(1, { transfer: [{}] }), { name: 'DataCloneError' });
@@ -0,0 +1,43 @@
+'use strict';
+import common from '../common';
+import assert from 'assert';
+import v8 from 'v8';
+import process from 'process';
+
+const stats = v8.getHeapStatistics();
+for (const key of [
+  'total_heap_size',
+  'total_heap_size_executable',
+  'total_physical_size',
+  'total_available_size',
+  'used_heap_size',
+  'heap_size_limit',
+  'malloced_memory',
+  'does_zap_garbage',
+  'number_of_native_contexts',
+  'number_of_detached_contexts',
+  'external_memory',
+]) {
+  assert.strictEqual(typeof stats[key], 'number', key);
+  assert.ok(stats[key] >= 0, key);
+}
+assert.ok(stats.used_heap_size > 0);
+assert.ok(stats.used_heap_size <= stats.total_heap_size);
+assert.ok(stats.total_heap_size <= stats.heap_size_limit);
+// not tracked, rather than reported as the current size
+assert.ok(!('peak_malloced_memory' in stats));
+
+const before = process.memoryUsage();
+assert.ok(before.rss > 0 && before.rss % 65536 === 0);
+assert.ok(before.heapUsed > 0 && before.heapUsed <= before.heapTotal);
+assert.strictEqual(before.external, before.arrayBuffers);
+assert.strictEqual(process.memoryUsage.rss(), process.memoryUsage().rss);
+
+// ArrayBuffer contents count as external memory
+const buffer = new ArrayBuffer(1 << 20);
+const after = process.memoryUsage();
+assert.ok(after.external >= before.external + buffer.byteLength);
+assert.ok(after.rss >= before.rss);
+
+// `gc()` is only defined with --expose-gc
+assert.strictEqual(typeof globalThis.gc, 'undefined');
@@ -0,0 +1,24 @@
+'use strict';
+import common from '../common';
+import assert from 'assert';
+import v8 from 'v8';
+import { Buffer } from 'buffer';
+
+const value = { a: [1, 2n, 'three'], m: new Map([['k', new Set([1])]]) };
+value.self = value;
+
+const data = v8.serialize(value);
+assert.ok(Buffer.isBuffer(data));
+
+const copy = v8.deserialize(data);
+assert.strictEqual(copy.self, copy);
+assert.deepStrictEqual(copy.a, [1, 2n, 'three']);
+assert.deepStrictEqual([...copy.m.get('k')], [1]);
+
+// Any view over the serialized bytes can be read back.
+const padded = Buffer.concat([Buffer.from([0, 0]), data]);
+assert.deepStrictEqual(v8.deserialize(padded.subarray(2)).a, [1, 2n, 'three']);
+
+assert.throws(() => v8.serialize(function f() {}), { name: 'DataCloneError' });
+assert.throws(() => v8.deserialize(Buffer.from([1, 2, 3])), { name: 'DataCloneError' });
+assert.throws(() => v8.deserialize('nope'), { code: 'ERR_INVALID_ARG_TYPE' });
@@ -0,0 +1,52 @@
+'use strict';
+import common from '../common';
+import assert from 'assert';
+import vm from 'vm';
+
+// Globals of a new context don't leak into this one.
+assert.strictEqual(vm.runInNewContext('var leaked = 42; leaked * 2'), 84);
+assert.strictEqual(typeof globalThis.leaked, 'undefined');
+
+// The sandbox is mapped onto the context's global.
+const sandbox = { x: 2, removed: true };
+vm.createContext(sandbox);
+assert.ok(vm.isContext(sandbox));
+assert.strictEqual(vm.runInContext('x += 40; y = "hi"; delete globalThis.removed; x', sandbox), 42);
+assert.strictEqual(sandbox.x, 42);
+assert.strictEqual(sandbox.y, 'hi');
+assert.strictEqual('removed' in sandbox, false);
+
+// Context state survives between runs.
+vm.runInContext('function inc() { return ++x }', sandbox);
+assert.strictEqual(vm.runInContext('inc()', sandbox), 43);
+assert.strictEqual(sandbox.x, 43);
+
+// The sandbox backs the global, so each side sees the other's changes while a script runs.
+const live = { n: 1, report: () => live.n, bump: () => { live.n++; } };
+vm.createContext(live);
+assert.strictEqual(vm.runInContext('n = 7; report()', live), 7);
+assert.strictEqual(vm.runInContext('bump(); n', live), 8);
+vm.runInContext('var count = 1; function tick() { return ++count; }', live);
+assert.strictEqual(live.count, 1);
+live.count = 5;
+assert.strictEqual(vm.runInContext('tick()', live), 6);
+assert.strictEqual(live.count, 6);
+assert.ok(vm.runInContext('Object.keys(globalThis)', live).includes('report'));
+
+// Each context has its own builtins.
+const arr = vm.runInNewContext('[1, 2, 3]');
+assert.strictEqual(arr instanceof Array, false);
+assert.strictEqual(Array.isArray(arr), true);
+
+// Errors from the context are rethrown.
+assert.throws(() => vm.runInNewContext('throw new TypeError("boom")'), /boom/);
+assert.throws(() => new vm.Script('let let = 1'), SyntaxError);
+assert.throws(() => vm.runInContext('1', {}), { code: 'ERR_INVALID_ARG_TYPE' });
+
+// runInThisContext shares this realm.
+globalThis.shared = 1;
+assert.strictEqual(vm.runInThisContext('shared + 1'), 2);
+
+// compileFunction
+const add = vm.compileFunction('return a + b', ['a', 'b']);
+assert.strictEqual(add(1, 2), 3);
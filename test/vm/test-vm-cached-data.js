@@ -0,0 +1,40 @@
+'use strict';
+import common from '../common';
+import assert from 'assert';
+import vm from 'vm';
+import { Buffer } from 'buffer';
+
+const code = 'typeof n === "number" ? n * 2 : "no n"';
+const script = new vm.Script(code, { filename: 'cached.js', produceCachedData: true });
+assert.ok(Buffer.isBuffer(script.cachedData));
+assert.ok(script.cachedData.length > 0);
+
+const cached = new vm.Script(code, { cachedData: script.createCachedData() });
+assert.strictEqual(cached.cachedDataRejected, false);
+assert.strictEqual(cached.runInNewContext({ n: 21 }), 42);
+assert.strictEqual(cached.runInNewContext({}), 'no n');
+
+// The same compiled script runs in several contexts.
+const ctxA = vm.createContext({ n: 1 });
+const ctxB = vm.createContext({ n: 2 });
+assert.strictEqual(script.runInContext(ctxA), 2);
+assert.strictEqual(script.runInContext(ctxB), 4);
+
+// Data for other source, corrupted or truncated data is rejected and the
+// source compiled instead.
+const data = script.createCachedData();
+const other = new vm.Script('"other"', { cachedData: data });
+assert.strictEqual(other.cachedDataRejected, true);
+assert.strictEqual(other.runInThisContext(), 'other');
+
+const corrupted = Buffer.from(data);
+corrupted[corrupted.length - 2] ^= 0xff;
+const fromCorrupted = new vm.Script(code, { cachedData: corrupted });
+assert.strictEqual(fromCorrupted.cachedDataRejected, true);
+assert.strictEqual(fromCorrupted.runInNewContext({ n: 4 }), 8);
+
+for (const bad of [data.subarray(0, 10), Buffer.alloc(0), Buffer.from('not bytecode')]) {
+  const s = new vm.Script(code, { cachedData: bad });
+  assert.strictEqual(s.cachedDataRejected, true);
+  assert.strictEqual(s.runInNewContext({ n: 1 }), 2);
+}
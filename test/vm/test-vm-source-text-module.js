@@ -0,0 +1,58 @@
+'use strict';
+import common from '../common';
+import assert from 'assert';
+import vm from 'vm';
+
+(async () => {
+  const context = vm.createContext({ secret: 42 });
+
+  const dep = new vm.SourceTextModule('export const double = (n) => n * 2;', {
+    identifier: 'dep', context,
+  });
+  await dep.link();
+
+  const m = new vm.SourceTextModule(`
+    import { double } from 'dep';
+    export const value = double(secret);
+    await null;
+    globalThis.done = true;
+  `, { identifier: 'main', context });
+  assert.strictEqual(m.status, 'unlinked');
+  await m.link(() => dep);
+  assert.strictEqual(m.status, 'linked');
+  await m.evaluate();
+  assert.strictEqual(m.status, 'evaluated');
+  assert.strictEqual(m.namespace.value, 84);
+  assert.strictEqual(context.done, true);
+
+  // The linker is called for every import, recursively, and may be async.
+  const leaf = new vm.SourceTextModule('export default 2;', { identifier: 'leaf.js', context });
+  const middle = new vm.SourceTextModule(`
+    import two from './two';
+    export const four = two * 2;
+  `, { identifier: 'middle.js', context });
+  const root = new vm.SourceTextModule(`
+    import { four } from 'numbers';
+    export default four + 1;
+  `, { identifier: 'root.js', context });
+  assert.deepStrictEqual(root.dependencySpecifiers, ['numbers']);
+  const calls = [];
+  await root.link(async (specifier, referencingModule) => {
+    calls.push([specifier, referencingModule.identifier]);
+    return specifier === 'numbers' ? middle : leaf;
+  });
+  assert.deepStrictEqual(calls, [['numbers', 'root.js'], ['./two', 'middle.js']]);
+  assert.strictEqual(middle.status, 'linked');
+  await root.evaluate();
+  assert.strictEqual(root.namespace.default, 5);
+
+  const other = new vm.SourceTextModule('export default 1;', { identifier: 'other' });
+  const wrong = new vm.SourceTextModule('import one from "other";', { identifier: 'wrong', context });
+  await assert.rejects(wrong.link(() => other), { code: 'ERR_VM_MODULE_DIFFERENT_CONTEXT' });
+  await assert.rejects(wrong.link(() => ({})), { code: 'ERR_VM_MODULE_NOT_MODULE' });
+  assert.strictEqual(wrong.status, 'unlinked');
+
+  const bad = new vm.SourceTextModule('import "does-not-exist"', { context });
+  await assert.rejects(bad.link());
+  assert.strictEqual(bad.status, 'errored');
+})().then(common.mustCall());
@@ -0,0 +1,19 @@
+'use strict';
+import common from '../common';
+import assert from 'assert';
+import vm from 'vm';
+
+assert.throws(() => {
+  vm.runInNewContext('while (true) {}', {}, { timeout: 50 });
+}, { code: 'ERR_SCRIPT_EXECUTION_TIMEOUT', message: 'Script execution timed out after 50ms' });
+
+// A try/catch in the script can't swallow the timeout.
+assert.throws(() => {
+  vm.runInNewContext('try { while (true) {} } catch (e) {} "done"', {}, { timeout: 20 });
+}, { code: 'ERR_SCRIPT_EXECUTION_TIMEOUT' });
+
+// Scripts that finish in time are unaffected, and the runtime keeps working.
+assert.strictEqual(vm.runInNewContext('1 + 1', {}, { timeout: 1000 }), 2);
+let i = 0;
+while (i < 1000) i++;
+assert.strictEqual(i, 1000);
@@ -0,0 +1,55 @@
+'use strict';
+import common from '../common';
+import assert from 'assert';
+import { Worker, isMainThread, parentPort, threadId } from 'worker_threads';
+
+assert.strictEqual(isMainThread, true);
+assert.strictEqual(parentPort, null);
+assert.strictEqual(threadId, 0);
+
+const code = `
+import('worker_threads').then(({ parentPort, workerData, isMainThread }) => {
+  parentPort.postMessage({ isMainThread, workerData });
+  parentPort.on('message', (msg) => {
+    if (msg === 'bye') {
+      parentPort.close();
+      return;
+    }
+    const view = new Uint8Array(msg.buffer);
+    parentPort.postMessage({ sum: view.reduce((a, b) => a + b, 0), map: msg.nested });
+  });
+});
+`;
+
+const worker = new Worker(code, {
+  eval: true,
+  workerData: { list: [1, 2, 3], date: new Date(0) },
+});
+assert.ok(worker.threadId > 0);
+worker.on('online', common.mustCall());
+
+let step = 0;
+worker.on('message', common.mustCall((msg) => {
+  if (step++ === 0) {
+    assert.strictEqual(msg.isMainThread, false);
+    assert.deepStrictEqual(msg.workerData.list, [1, 2, 3]);
+    assert.strictEqual(msg.workerData.date.getTime(), 0);
+
+    const buffer = new Uint8Array([1, 2, 3, 4]).buffer;
+    const nested = { a: 1 };
+    nested.self = nested;
+    worker.postMessage({ buffer, nested }, [buffer]);
+    // the transferred buffer is detached here
+    assert.strictEqual(buffer.byteLength, 0);
+  } else {
+    assert.strictEqual(msg.sum, 10);
+    assert.strictEqual(msg.map.self, msg.map);
+    worker.postMessage('bye');
+  }
+}, 2));
+
+worker.on('exit', common.mustCall((code) => {
+  assert.strictEqual(code, 0);
+}));
+
+assert.throws(() => worker.postMessage(() => {}));
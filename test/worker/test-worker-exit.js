@@ -0,0 +1,72 @@
+'use strict';
+import common from '../common';
+import assert from 'assert';
+import { Worker } from 'worker_threads';
+
+{
+  const worker = new Worker('throw new RangeError("worker failed")', { eval: true });
+  worker.on('error', common.mustCall((err) => {
+    assert.strictEqual(err.name, 'RangeError');
+    assert.strictEqual(err.message, 'worker failed');
+  }));
+  worker.on('exit', common.mustCall((code) => assert.strictEqual(code, 1)));
+}
+
+{
+  // thrown from a timer, after the script has run
+  const worker = new Worker('setTimeout(() => { throw new TypeError("late failure"); }, 1)', { eval: true });
+  let failed = false;
+  worker.on('error', common.mustCall((err) => {
+    assert.strictEqual(err.name, 'TypeError');
+    assert.strictEqual(err.message, 'late failure');
+    failed = true;
+  }));
+  worker.on('exit', common.mustCall((code) => {
+    assert.ok(failed);
+    assert.strictEqual(code, 1);
+  }));
+}
+
+{
+  const worker = new Worker('import("process").then(({ default: p }) => p.exit(7))', { eval: true });
+  worker.on('exit', common.mustCall((code) => assert.strictEqual(code, 7)));
+}
+
+{
+  // A worker listening on parentPort stays alive until terminated.
+  const worker = new Worker(`
+    import('worker_threads').then(({ parentPort }) => {
+      parentPort.on('message', () => {});
+      parentPort.postMessage('ready');
+    });
+  `, { eval: true });
+  worker.once('message', common.mustCall(async () => {
+    const code = await worker.terminate();
+    assert.strictEqual(code, 1);
+  }));
+}
+
+{
+  const worker = new Worker(`
+    const chunks = [];
+    while (true) chunks.push(new Array(1e5).fill(1));
+  `, { eval: true, resourceLimits: { maxOldGenerationSizeMb: 16 } });
+  assert.deepStrictEqual(worker.resourceLimits, { maxOldGenerationSizeMb: 16 });
+  worker.on('error', common.mustCall((err) => {
+    assert.match(err.message, /out of memory/);
+  }));
+  worker.on('exit', common.mustCall((code) => assert.strictEqual(code, 1)));
+}
+
+{
+  const limits = { maxOldGenerationSizeMb: 32, stackSizeMb: 4 };
+  const worker = new Worker(`
+    import('worker_threads').then(({ parentPort, resourceLimits }) => {
+      parentPort.postMessage(resourceLimits);
+      parentPort.close();
+    });
+  `, { eval: true, resourceLimits: limits });
+  worker.once('message', common.mustCall((resourceLimits) => {
+    assert.deepStrictEqual(resourceLimits, limits);
+  }));
+}
@@ -0,0 +1,58 @@
+'use strict';
+import common from '../common';
+import assert from 'assert';
+import { Worker, MessageChannel, MessagePort, receiveMessageOnPort } from 'worker_threads';
+
+{
+  const { port1, port2 } = new MessageChannel();
+  port1.postMessage({ hello: 'world' });
+  assert.deepStrictEqual(receiveMessageOnPort(port2), { message: { hello: 'world' } });
+  assert.strictEqual(receiveMessageOnPort(port2), undefined);
+  port1.close();
+  port2.on('close', common.mustCall());
+}
+
+{
+  // Hand one end of a channel to a worker.
+  const { port1, port2 } = new MessageChannel();
+  const worker = new Worker(`
+    import('worker_threads').then(({ parentPort }) => {
+      parentPort.once('message', ({ port }) => {
+        port.postMessage('from worker');
+        port.close();
+        parentPort.close();
+      });
+    });
+  `, { eval: true });
+  worker.postMessage({ port: port2 }, [port2]);
+  port1.on('message', common.mustCall((msg) => {
+    assert.strictEqual(msg, 'from worker');
+  }));
+  port1.on('close', common.mustCall());
+  worker.on('exit', common.mustCall((code) => assert.strictEqual(code, 0)));
+}
+
+{
+  // Ports are found wherever they are nested, and shared references survive.
+  const { port1, port2 } = new MessageChannel();
+  const a = new MessageChannel();
+  const b = new MessageChannel();
+  const shared = { port: a.port1 };
+  const value = { deep: { list: [1, { port: a.port1 }] }, map: new Map([['b', b.port1]]), shared, again: shared };
+  value.self = value;
+  port1.postMessage(value, [a.port1, b.port1]);
+  const { message } = receiveMessageOnPort(port2);
+  assert.ok(message.deep.list[1].port instanceof MessagePort);
+  assert.strictEqual(message.deep.list[1].port, message.shared.port);
+  assert.ok(message.map.get('b') instanceof MessagePort);
+  assert.strictEqual(message.shared, message.again);
+  assert.strictEqual(message.self, message);
+
+  // A port that is not transferred can't be sent.
+  const c = new MessageChannel();
+  assert.throws(() => port1.postMessage({ nested: [c.port1] }), { name: 'DataCloneError' });
+  assert.throws(() => port1.postMessage(new Set([c.port1])), { name: 'DataCloneError' });
+  for (const port of [port1, port2, a.port2, b.port2, c.port1, c.port2, message.shared.port, message.map.get('b')]) {
+    port.close();
+  }
+}
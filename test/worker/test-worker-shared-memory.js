@@ -0,0 +1,77 @@
+'use strict';
+import common from '../common';
+import assert from 'assert';
+import { Worker } from 'worker_threads';
+
+// Atomics on ordinary typed arrays.
+const plain = new Int8Array(4);
+assert.strictEqual(Atomics.store(plain, 0, 300), 300);
+assert.strictEqual(Atomics.load(plain, 0), 44);
+assert.strictEqual(Atomics.add(plain, 1, 5), 0);
+assert.strictEqual(Atomics.sub(plain, 1, 7), 5);
+assert.strictEqual(Atomics.load(plain, 1), -2);
+assert.strictEqual(Atomics.compareExchange(plain, 2, 0, 9), 0);
+assert.strictEqual(Atomics.compareExchange(plain, 2, 0, 1), 9);
+assert.strictEqual(Atomics.exchange(plain, 2, 3), 9);
+assert.strictEqual(Atomics.or(plain, 2, 4), 3);
+assert.strictEqual(Atomics.and(plain, 2, 6), 7);
+assert.strictEqual(Atomics.xor(plain, 2, 1), 6);
+assert.strictEqual(plain[2], 7);
+assert.strictEqual(Atomics.load(new Uint32Array([0xffffffff]), 0), 0xffffffff);
+assert.strictEqual(Atomics.add(new BigInt64Array(1), 0, 5n), 0n);
+assert.strictEqual(Atomics.isLockFree(4), true);
+assert.throws(() => Atomics.load(plain, 4), RangeError);
+assert.throws(() => Atomics.load(new Float64Array(1), 0), TypeError);
+assert.throws(() => Atomics.wait(new Int32Array(1), 0, 0), TypeError);
+assert.strictEqual(Atomics.notify(new Int32Array(1), 0), 0);
+
+// Waiting on shared memory in this thread. Workers share the thread, so
+// nothing could wake a blocking wait.
+const local = new Int32Array(new SharedArrayBuffer(8));
+for (const args of [[0, 1], [0, 0, 10], [0, 0]]) {
+  assert.throws(() => Atomics.wait(local, ...args), {
+    name: 'TypeError',
+    message: 'Atomics.wait cannot be called in this context',
+  });
+}
+assert.deepStrictEqual(Atomics.waitAsync(local, 0, 1), { async: false, value: 'not-equal' });
+assert.deepStrictEqual(Atomics.waitAsync(local, 0, 0, 0), { async: false, value: 'timed-out' });
+const timed = Atomics.waitAsync(local, 0, 0, 10);
+assert.strictEqual(timed.async, true);
+timed.value.then(common.mustCall((v) => assert.strictEqual(v, 'timed-out')));
+const woken = Atomics.waitAsync(local, 1, 0);
+assert.strictEqual(Atomics.notify(local, 1), 1);
+woken.value.then(common.mustCall((v) => assert.strictEqual(v, 'ok')));
+
+// structuredClone keeps sharing the memory.
+const cloned = new Int32Array(structuredClone(local.buffer));
+Atomics.store(cloned, 0, 42);
+assert.strictEqual(Atomics.load(local, 0), 42);
+
+// A worker sees and notifies the same memory.
+const sab = new SharedArrayBuffer(8);
+const shared = new Int32Array(sab);
+const code = `
+import('worker_threads').then(({ workerData }) => {
+  const shared = new Int32Array(workerData.sab);
+  Atomics.add(shared, 0, 5);
+  Atomics.store(shared, 1, 1);
+  Atomics.notify(shared, 1);
+});
+`;
+const waiting = Atomics.waitAsync(shared, 1, 0);
+assert.strictEqual(waiting.async, true);
+const worker = new Worker(code, { eval: true, workerData: { sab } });
+waiting.value.then(common.mustCall((v) => {
+  assert.strictEqual(v, 'ok');
+  assert.strictEqual(Atomics.load(shared, 0), 5);
+}));
+worker.on('exit', common.mustCall((code) => {
+  assert.strictEqual(code, 0);
+  assert.strictEqual(shared[1], 1);
+}));
+
+// v8.serialize has no way to pass shared memory along.
+import('v8').then(common.mustCall(({ serialize }) => {
+  assert.throws(() => serialize(sab), { name: 'DataCloneError' });
+}));
@@ -0,0 +1,97 @@
+//! The harness shared by the integration tests.
+
+use wasmedge_quickjs::*;
+
+/// Runs the module at `file_path` until the loop is done, returning the exit code.
+pub fn run_js_file(file_path: &str) -> i32 {
+    use wasmedge_quickjs as q;
+
+    env_logger::builder()
+        // .filter_level(log::LevelFilter::Trace)
+        .is_test(true)
+        .try_init();
+
+    let tokio_rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+
+    tokio_rt.block_on(async {
+        let mut rt = q::Runtime::new();
+        let file_path = file_path.to_string();
+        rt.run_with_context(|ctx| {
+            let code = std::fs::read_to_string(&file_path);
+            match code {
+                Ok(code) => {
+                    ctx.put_args(vec![file_path.clone()]);
+                    ctx.eval_module_str(code, &file_path);
+                }
+                Err(e) => {
+                    eprintln!("{}", e.to_string());
+                    assert!(false, "run js test file fail");
+                }
+            }
+        });
+        rt.run_until_exit(false).await
+    })
+}
+
+/// Runs the module at `file_path`, then the `_onExit`, `commonExitCheck` and
+/// `assertPass` hooks the Node.js test files define.
+pub fn test_js_file(file_path: &str) {
+    use wasmedge_quickjs as q;
+
+    env_logger::builder()
+        // .filter_level(log::LevelFilter::Trace)
+        .is_test(true)
+        .try_init();
+
+    let tokio_rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+
+    tokio_rt.block_on(async {
+        let mut rt = q::Runtime::new();
+        let file_path = file_path.to_string();
+        rt.async_run_with_context(Box::new(move |ctx| {
+            let code = std::fs::read_to_string(&file_path);
+            match code {
+                Ok(code) => {
+                    ctx.put_args(vec![file_path.clone()]);
+                    ctx.eval_module_str(code, &file_path);
+                }
+                Err(e) => {
+                    eprintln!("{}", e.to_string());
+                    assert!(false, "run js test file fail");
+                }
+            }
+            JsValue::UnDefined
+        }))
+        .await;
+        rt.async_run_with_context(Box::new(|ctx| {
+            log::trace!("try _onExit");
+            if let JsValue::Function(func) = ctx.get_global().get("_onExit") {
+                func.call(&[]);
+            };
+            JsValue::UnDefined
+        }))
+        .await;
+        rt.async_run_with_context(Box::new(|ctx| {
+            log::trace!("try commonExitCheck");
+            if let JsValue::Function(func) = ctx.get_global().get("commonExitCheck") {
+                func.call(&[]);
+            };
+            JsValue::UnDefined
+        }))
+        .await;
+        rt.async_run_with_context(Box::new(|ctx| {
+            log::trace!("try assertPass");
+            if let JsValue::Function(func) = ctx.get_global().get("assertPass") {
+                func.call(&[]);
+            };
+            JsValue::UnDefined
+        }))
+        .await;
+    });
+}
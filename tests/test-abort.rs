@@ -0,0 +1,64 @@
+#![allow(dead_code, unused_imports, unused_must_use)]
+
+use std::borrow::{Borrow, BorrowMut};
+use wasmedge_quickjs::*;
+
+mod common;
+use common::*;
+
+#[test]
+fn test_abort_controller() {
+    assert_eq!(run_js_file("test/abort/test-abort-controller.js"), 0);
+}
+
+#[test]
+fn test_abort_timers_promises() {
+    assert_eq!(run_js_file("test/abort/test-abort-timers-promises.js"), 0);
+}
+
+#[test]
+fn test_abort_fs() {
+    assert_eq!(run_js_file("test/abort/test-abort-fs.js"), 0);
+}
+
+#[test]
+fn test_abort_net() {
+    assert_eq!(run_js_file("test/abort/test-abort-net.js"), 0);
+}
+
+#[test]
+fn test_abort_signal_any_dependents_are_weak() {
+    let mut rt = Runtime::new();
+    rt.expose_gc();
+    rt.run_with_context(|ctx| {
+        ctx.eval_global_str("globalThis.controller = new AbortController(); gc(); 0".to_string());
+    });
+    let before = rt.memory_usage().obj_count;
+
+    rt.run_with_context(|ctx| {
+        ctx.eval_global_str(
+            r#"
+            for (let i = 0; i < 1000; i++) AbortSignal.any([controller.signal]);
+            gc();
+            0
+            "#
+            .to_string(),
+        );
+    });
+    assert!(rt.memory_usage().obj_count < before + 100);
+
+    let fired = rt.run_with_context(|ctx| {
+        ctx.eval_global_str(
+            r#"
+            globalThis.fired = 0;
+            AbortSignal.any([controller.signal]).addEventListener('abort', () => fired++);
+            AbortSignal.any([controller.signal]).onabort = () => fired++;
+            gc();
+            controller.abort();
+            fired
+            "#
+            .to_string(),
+        )
+    });
+    assert!(matches!(fired, JsValue::Int(2)));
+}
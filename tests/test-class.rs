@@ -0,0 +1,210 @@
+#![allow(dead_code, unused_imports, unused_must_use)]
+
+use std::borrow::{Borrow, BorrowMut};
+use wasmedge_quickjs::*;
+
+struct Counter {
+    n: i32,
+}
+
+impl JsClassDef for Counter {
+    type RefType = Counter;
+
+    const CLASS_NAME: &'static str = "Counter";
+    const CONSTRUCTOR_ARGC: u8 = 1;
+
+    const FIELDS: &'static [JsClassField<Self::RefType>] =
+        &[("n", |this, _ctx| JsValue::Int(this.n), None)];
+
+    const METHODS: &'static [JsClassMethod<Self::RefType>] =
+        &[("inc", 0, |this, _this_obj, _ctx, _argv| {
+            this.n += 1;
+            JsValue::Int(this.n)
+        })];
+
+    const STATIC_FIELDS: &'static [JsClassStaticField] = &[("MAX", |_ctx| JsValue::Int(100))];
+
+    const STATIC_METHODS: &'static [JsClassStaticMethod] = &[
+        ("double", 1, |_ctx, _this, argv| match argv.get(0) {
+            Some(JsValue::Int(n)) => JsValue::Int(n * 2),
+            _ => JsValue::UnDefined,
+        }),
+        ("self", 0, |_ctx, this, _argv| this),
+    ];
+
+    const SYMBOL_FIELDS: &'static [JsClassSymbolField<Self::RefType>] = &[(
+        JsClassSymbol::ToStringTag,
+        |_this, ctx| ctx.new_string("Counter").into(),
+        None,
+    )];
+
+    const SYMBOL_METHODS: &'static [JsClassSymbolMethod<Self::RefType>] = &[
+        (
+            JsClassSymbol::ToPrimitive,
+            1,
+            |this, _this_obj, _ctx, _argv| JsValue::Int(this.n),
+        ),
+        (JsClassSymbol::Dispose, 0, |this, _this_obj, _ctx, _argv| {
+            this.n = -1;
+            JsValue::UnDefined
+        }),
+    ];
+
+    unsafe fn mut_class_id_ptr() -> &'static mut u32 {
+        static mut CLASS_ID: u32 = 0;
+        &mut CLASS_ID
+    }
+
+    fn constructor_fn(ctx: &mut Context, argv: &[JsValue]) -> Result<Self::RefType, JsValue> {
+        match argv.get(0) {
+            Some(JsValue::Int(n)) => Ok(Counter { n: *n }),
+            _ => Err(ctx.throw_type_error("n must be an integer").into()),
+        }
+    }
+}
+
+/// Has one async method, to check what stays callable while it is pending.
+struct Waiter {
+    bumps: i32,
+}
+
+impl Waiter {
+    fn js_wait(this: JsClassRef<Self>, _ctx: Context, _argv: Vec<JsValue>) -> JsClassFuture {
+        Box::pin(async move {
+            let _this = this;
+            tokio::time::sleep(std::time::Duration::from_secs(3600)).await;
+            Ok(JsValue::UnDefined)
+        })
+    }
+}
+
+impl JsClassDef for Waiter {
+    type RefType = Waiter;
+
+    const CLASS_NAME: &'static str = "Waiter";
+    const CONSTRUCTOR_ARGC: u8 = 0;
+
+    const FIELDS: &'static [JsClassField<Self::RefType>] = &[];
+
+    const METHODS: &'static [JsClassMethod<Self::RefType>] =
+        &[("bump", 0, |this, _this_obj, _ctx, _argv| {
+            this.bumps += 1;
+            JsValue::Int(this.bumps)
+        })];
+
+    const SHARED_METHODS: &'static [JsClassSharedMethod<Self::RefType>] = &[
+        ("peek", 0, |this, _this_obj, _ctx, _argv| {
+            JsValue::Int(this.bumps)
+        }),
+        ("close", 0, |_this, this_obj, _ctx, _argv| {
+            Self::mark_closed(&this_obj.clone().into());
+            JsValue::UnDefined
+        }),
+    ];
+
+    const ASYNC_METHODS: &'static [JsClassAsyncMethod<Self::RefType>] =
+        &[("wait", 0, Self::js_wait)];
+
+    unsafe fn mut_class_id_ptr() -> &'static mut u32 {
+        static mut CLASS_ID: u32 = 0;
+        &mut CLASS_ID
+    }
+
+    fn constructor_fn(_ctx: &mut Context, _argv: &[JsValue]) -> Result<Self::RefType, JsValue> {
+        Ok(Waiter { bumps: 0 })
+    }
+}
+
+fn eval(rt: &mut Runtime, code: &str) -> String {
+    rt.run_with_context(|ctx| match ctx.eval_global_str(code.to_string()) {
+        JsValue::String(s) => s.as_str().to_string(),
+        JsValue::Exception(e) => panic!("{:?}", e),
+        v => panic!("unexpected {:?}", v),
+    })
+}
+
+#[test]
+fn test_class_static_and_symbol_members() {
+    let tokio_rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+
+    tokio_rt.block_on(async {
+        let mut rt = Runtime::new();
+        rt.run_with_context(|ctx| {
+            let ctor = register_class::<Counter>(ctx);
+            ctx.get_global().set("Counter", ctor);
+        });
+
+        // static members live on the constructor, not on instances
+        assert_eq!(
+            eval(
+                &mut rt,
+                "const c = new Counter(2);
+                JSON.stringify([Counter.MAX, Counter.double(21), Counter.self() === Counter, c.MAX])"
+            ),
+            "[100,42,true,null]"
+        );
+
+        assert_eq!(
+            eval(
+                &mut rt,
+                "JSON.stringify([Object.prototype.toString.call(c), +c, `${c}`, c.inc(), c + 1])"
+            ),
+            r#"["[object Counter]",2,"2",3,4]"#
+        );
+
+        // `Symbol.dispose` is created when the engine lacks it, and reused after
+        assert_eq!(
+            eval(
+                &mut rt,
+                "const d = Symbol.dispose;
+                c[Symbol.dispose]();
+                JSON.stringify([typeof d, d.description, d === Symbol.dispose, c.n,
+                    Object.getOwnPropertyDescriptor(Symbol, 'dispose').writable])"
+            ),
+            r#"["symbol","Symbol.dispose",true,-1,false]"#
+        );
+    });
+}
+
+#[test]
+fn test_class_async_method_busy_and_closed() {
+    let tokio_rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+
+    tokio_rt.block_on(async {
+        let mut rt = Runtime::new();
+        rt.run_with_context(|ctx| {
+            let ctor = register_class::<Waiter>(ctx);
+            ctx.get_global().set("Waiter", ctor);
+        });
+
+        eval(
+            &mut rt,
+            "globalThis.log = [];
+            const w = new Waiter();
+            log.push('bump: ' + w.bump());
+            const first = w.wait();
+            first.catch((e) => log.push('first: ' + e.message));
+            // a second async call and `&mut` methods are refused while it is pending
+            w.wait().catch((e) => log.push(e.message));
+            try { w.bump() } catch (e) { log.push('bump: ' + e.message) }
+            // shared methods stay callable, and close() rejects the pending call
+            log.push('peek: ' + w.peek());
+            w.close();
+            try { w.peek() } catch (e) { log.push('peek: ' + e.message) }
+            w.wait().catch((e) => log.push('after: ' + e.message));
+            ''",
+        );
+        (&mut rt).await;
+
+        assert_eq!(
+            eval(&mut rt, "JSON.stringify(log.sort())"),
+            r#"["Waiter is busy","after: Waiter is closed","bump: 1","bump: Waiter is busy","first: Waiter is closed","peek: 1","peek: Waiter is closed"]"#
+        );
+    });
+}
@@ -0,0 +1,86 @@
+#![allow(dead_code, unused_imports, unused_must_use)]
+
+use std::borrow::{Borrow, BorrowMut};
+use wasmedge_quickjs::*;
+
+fn eval(ctx: &mut Context, code: &str) -> String {
+    match ctx.eval_global_str(code.to_string()) {
+        JsValue::String(s) => s.as_str().to_string(),
+        JsValue::Exception(e) => panic!("{:?}", e),
+        v => panic!("unexpected {:?}", v),
+    }
+}
+
+fn block_on<F: std::future::Future>(f: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap()
+        .block_on(f)
+}
+
+#[test]
+fn test_context_has_own_globals() {
+    block_on(async {
+        let mut rt = Runtime::new();
+        let mut realm = unsafe { rt.new_context(ContextOptions::default()) };
+
+        eval(&mut realm, "globalThis.tenant = 'a'; ''");
+        rt.run_with_context(|ctx| {
+            assert_eq!(eval(ctx, "typeof tenant"), "undefined");
+            eval(ctx, "globalThis.tenant = 'main'; ''");
+        });
+        assert_eq!(eval(&mut realm, "tenant"), "a");
+
+        // each realm has its own intrinsics too
+        eval(&mut realm, "Array.prototype.tenant = 'patched'; ''");
+        rt.run_with_context(|ctx| {
+            assert_eq!(eval(ctx, "typeof [].tenant"), "undefined");
+        });
+
+        drop(realm);
+    });
+}
+
+#[test]
+fn test_context_options() {
+    block_on(async {
+        let mut rt = Runtime::new();
+        let mut full = unsafe { rt.new_context(ContextOptions::default()) };
+        let mut minimal = unsafe { rt.new_context(ContextOptions::minimal()) };
+
+        assert_eq!(
+            eval(&mut full, "[typeof console, typeof setTimeout].join()"),
+            "object,function"
+        );
+        // the core globals are installed regardless of the options
+        assert_eq!(
+            eval(&mut minimal, "[typeof console, typeof setTimeout].join()"),
+            "undefined,function"
+        );
+
+        drop(full);
+        drop(minimal);
+    });
+}
+
+#[test]
+fn test_context_shares_event_loop() {
+    block_on(async {
+        let mut rt = Runtime::new();
+        let mut realm = unsafe { rt.new_context(ContextOptions::minimal()) };
+
+        eval(
+            &mut realm,
+            "globalThis.log = [];
+            setTimeout(() => log.push('timer'), 1);
+            Promise.resolve().then(() => log.push('job'));
+            ''",
+        );
+        // awaiting the runtime drives the realm's timers and jobs
+        (&mut rt).await;
+        assert_eq!(eval(&mut realm, "log.join()"), "job,timer");
+
+        drop(realm);
+    });
+}
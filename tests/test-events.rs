@@ -0,0 +1,22 @@
+#![allow(dead_code, unused_imports, unused_must_use)]
+
+use std::borrow::{Borrow, BorrowMut};
+use wasmedge_quickjs::*;
+
+mod common;
+use common::*;
+
+#[test]
+fn test_event_target() {
+    assert_eq!(run_js_file("test/events/test-event-target.js"), 0);
+}
+
+#[test]
+fn test_event_target_options() {
+    assert_eq!(run_js_file("test/events/test-event-target-options.js"), 0);
+}
+
+#[test]
+fn test_events_once_on() {
+    assert_eq!(run_js_file("test/events/test-events-once-on.js"), 0);
+}
@@ -0,0 +1,22 @@
+#![allow(dead_code, unused_imports, unused_must_use)]
+
+use std::borrow::{Borrow, BorrowMut};
+use wasmedge_quickjs::*;
+
+mod common;
+use common::*;
+
+#[test]
+fn test_tcp_server_bind() {
+    assert_eq!(run_js_file("test/net/test-tcp-server-bind.js"), 0);
+}
+
+#[test]
+fn test_http_server_listen() {
+    assert_eq!(run_js_file("test/net/test-http-server-listen.js"), 0);
+}
+
+#[test]
+fn test_dgram() {
+    assert_eq!(run_js_file("test/net/test-dgram.js"), 0);
+}
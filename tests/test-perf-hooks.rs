@@ -0,0 +1,28 @@
+#![allow(dead_code, unused_imports, unused_must_use)]
+
+use std::borrow::{Borrow, BorrowMut};
+use wasmedge_quickjs::*;
+
+mod common;
+use common::*;
+
+#[test]
+fn test_performance() {
+    assert_eq!(run_js_file("test/perf_hooks/test-performance.js"), 0);
+}
+
+#[test]
+fn test_performance_observer() {
+    assert_eq!(
+        run_js_file("test/perf_hooks/test-performance-observer.js"),
+        0
+    );
+}
+
+#[test]
+fn test_event_loop_monitoring() {
+    assert_eq!(
+        run_js_file("test/perf_hooks/test-event-loop-monitoring.js"),
+        0
+    );
+}
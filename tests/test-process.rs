@@ -0,0 +1,81 @@
+#![allow(dead_code, unused_imports, unused_must_use)]
+
+use std::borrow::{Borrow, BorrowMut};
+use wasmedge_quickjs::*;
+
+mod common;
+use common::*;
+
+#[test]
+fn test_process_exit_code() {
+    assert_eq!(run_js_file("test/process/test-process-exit-code.js"), 4);
+}
+
+#[test]
+fn test_process_uncaught() {
+    assert_eq!(run_js_file("test/process/test-process-uncaught.js"), 6);
+}
+
+#[test]
+fn test_process_active_resources() {
+    assert_eq!(
+        run_js_file("test/process/test-process-active-resources.js"),
+        0
+    );
+}
+
+#[test]
+fn test_unref_and_shutdown() {
+    let tokio_rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+
+    tokio_rt.block_on(async {
+        let mut rt = Runtime::new();
+        let eval = |rt: &mut Runtime, code: &str| {
+            rt.run_with_context(|ctx| match ctx.eval_global_str(code.to_string()) {
+                JsValue::String(s) => s.as_str().to_string(),
+                JsValue::Exception(e) => panic!("{:?}", e),
+                _ => String::new(),
+            })
+        };
+        rt.run_with_context(|ctx| {
+            ctx.eval_module_str(
+                r#"
+                import * as net from 'wasi_net';
+                globalThis.server = new net.WasiTcpServer(8139);
+                server.accept();
+                server.unref();
+                "#
+                .to_string(),
+                "test_unref_and_shutdown.js",
+            );
+        });
+
+        // the pending accept runs on an unrefed server, so it doesn't keep the loop alive
+        tokio::time::timeout(std::time::Duration::from_secs(5), &mut rt)
+            .await
+            .expect("an unrefed server kept the loop alive");
+
+        eval(
+            &mut rt,
+            "globalThis.fired = false; setTimeout(() => { fired = true }, 60000); ''",
+        );
+        let start = std::time::Instant::now();
+        rt.shutdown(std::time::Duration::from_millis(50)).await;
+        assert!(start.elapsed() < std::time::Duration::from_secs(5));
+
+        // the timer is dropped and the server closed
+        assert_eq!(
+            eval(&mut rt, "JSON.stringify([fired, server.hasRef()])"),
+            "[false,false]"
+        );
+        eval(
+            &mut rt,
+            "server.accept().catch((e) => { globalThis.closed = e.message }); ''",
+        );
+        (&mut rt).await;
+        assert_eq!(eval(&mut rt, "closed"), "WasiTcpServer is closed");
+    });
+}
@@ -0,0 +1,63 @@
+#![allow(dead_code, unused_imports, unused_must_use)]
+
+use std::borrow::{Borrow, BorrowMut};
+use std::time::Duration;
+use wasmedge_quickjs::*;
+
+fn profile_js_file(file_path: &str) -> CpuProfile {
+    use wasmedge_quickjs as q;
+
+    let tokio_rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+
+    tokio_rt.block_on(async {
+        let mut rt = q::Runtime::new();
+        rt.start_profiling(Duration::from_micros(100));
+        let file_path = file_path.to_string();
+        rt.run_with_context(|ctx| {
+            let code = std::fs::read_to_string(&file_path).unwrap();
+            ctx.put_args(vec![file_path.clone()]);
+            ctx.eval_module_str(code, &file_path);
+        });
+        assert_eq!(rt.run_until_exit(false).await, 0);
+        rt.stop_profiling().expect("profiler was started")
+    })
+}
+
+#[test]
+fn test_cpu_profile() {
+    let profile = profile_js_file("test/profiler/busy.js");
+    assert!(profile.sample_count() > 0);
+
+    let names = profile.function_names();
+    assert_eq!(names[0], "(root)");
+    assert!(names.contains(&"spin"));
+    assert!(names.contains(&"fib"));
+    assert!(names.contains(&"(idle)"));
+
+    let json = profile.to_json();
+    assert!(json.starts_with("{\"nodes\":[{\"id\":1,"));
+    assert!(json.contains("\"url\":\"test/profiler/busy.js\""));
+    assert!(json.contains("\"timeDeltas\":["));
+}
+
+#[test]
+fn test_cpu_profile_with_exceptions() {
+    let profile = profile_js_file("test/profiler/throws.js");
+    assert!(profile.sample_count() > 0);
+    assert!(profile.function_names().contains(&"thrower"));
+}
+
+#[test]
+fn test_stop_without_start() {
+    let tokio_rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    tokio_rt.block_on(async {
+        let mut rt = Runtime::new();
+        assert!(rt.stop_profiling().is_none());
+    });
+}
@@ -0,0 +1,98 @@
+#![allow(dead_code, unused_imports, unused_must_use)]
+
+use std::borrow::{Borrow, BorrowMut};
+use std::time::Duration;
+use wasmedge_quickjs::*;
+
+fn eval(rt: &mut Runtime, code: &str) -> String {
+    rt.run_with_context(|ctx| match ctx.eval_global_str(code.to_string()) {
+        JsValue::String(s) => s.as_str().to_string(),
+        JsValue::Exception(e) => panic!("{:?}", e),
+        _ => String::new(),
+    })
+}
+
+fn block_on<F: std::future::Future>(f: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap()
+        .block_on(f)
+}
+
+#[test]
+fn test_spawn_js_and_post_message() {
+    block_on(async {
+        let mut rt = Runtime::new();
+        eval(
+            &mut rt,
+            "globalThis.got = []; globalThis.onmessage = (e) => got.push(e.data); ''",
+        );
+        let handle = rt.handle();
+        let thread = std::thread::spawn(move || {
+            handle.spawn_js(|ctx| {
+                ctx.get_global().set("spawned", JsValue::Bool(true));
+            });
+            handle.post_message(r#"{"n":1}"#);
+        });
+
+        tokio::time::timeout(Duration::from_secs(5), &mut rt)
+            .await
+            .expect("the runtime hung after its handle was dropped");
+        thread.join().unwrap();
+        assert_eq!(
+            eval(&mut rt, "JSON.stringify([spawned, got])"),
+            r#"[true,[{"n":1}]]"#
+        );
+    });
+}
+
+#[test]
+fn test_runtime_waits_for_handles() {
+    block_on(async {
+        let mut rt = Runtime::new();
+        let handle = rt.handle();
+
+        // a live handle keeps the runtime pending
+        assert!(tokio::time::timeout(Duration::from_millis(50), &mut rt)
+            .await
+            .is_err());
+
+        // dropping the last one on another thread lets it finish
+        let thread = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(50));
+            drop(handle);
+        });
+        tokio::time::timeout(Duration::from_secs(5), &mut rt)
+            .await
+            .expect("the runtime hung after its last handle was dropped");
+        thread.join().unwrap();
+    });
+}
+
+#[test]
+fn test_concurrent_handle_drops() {
+    block_on(async {
+        let mut rt = Runtime::new();
+        let handles = vec![rt.handle(), rt.handle()];
+        let barrier = std::sync::Arc::new(std::sync::Barrier::new(2));
+        let threads: Vec<_> = handles
+            .into_iter()
+            .map(|handle| {
+                let barrier = barrier.clone();
+                std::thread::spawn(move || {
+                    barrier.wait();
+                    drop(handle);
+                })
+            })
+            .collect();
+
+        // one of the two drops is the last, whichever order they land in
+        tokio::time::timeout(Duration::from_secs(5), &mut rt)
+            .await
+            .expect("the runtime hung after both handles were dropped");
+        for thread in threads {
+            thread.join().unwrap();
+        }
+    });
+}
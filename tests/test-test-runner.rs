@@ -0,0 +1,17 @@
+#![allow(dead_code, unused_imports, unused_must_use)]
+
+use std::borrow::{Borrow, BorrowMut};
+use wasmedge_quickjs::*;
+
+mod common;
+use common::*;
+
+#[test]
+fn test_test_runner_pass() {
+    assert_eq!(run_js_file("test/test_runner/test-test-pass.js"), 0);
+}
+
+#[test]
+fn test_test_runner_fail() {
+    assert_eq!(run_js_file("test/test_runner/test-test-fail.js"), 1);
+}
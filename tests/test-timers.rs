@@ -0,0 +1,132 @@
+#![allow(dead_code, unused_imports, unused_must_use)]
+
+use std::borrow::{Borrow, BorrowMut};
+use wasmedge_quickjs::*;
+
+mod common;
+use common::*;
+
+#[test]
+fn test_timers_interval() {
+    assert_eq!(run_js_file("test/timers/test-timers-interval.js"), 0);
+}
+
+#[test]
+fn test_timers_clear_immediate() {
+    assert_eq!(run_js_file("test/timers/test-timers-clear-immediate.js"), 0);
+}
+
+#[test]
+fn test_timers_unref_immediate() {
+    assert_eq!(run_js_file("test/timers/test-timers-unref-immediate.js"), 0);
+}
+
+#[test]
+fn test_timers_timeout_object() {
+    assert_eq!(run_js_file("test/timers/test-timers-timeout-object.js"), 0);
+}
+
+#[test]
+fn test_event_loop_order() {
+    assert_eq!(run_js_file("test/timers/test-event-loop-order.js"), 0);
+}
+
+#[test]
+fn test_queue_microtask() {
+    assert_eq!(run_js_file("test/timers/test-queue-microtask.js"), 0);
+}
+
+#[test]
+fn test_uncaught_exception() {
+    assert_eq!(run_js_file("test/timers/test-uncaught-exception.js"), 0);
+}
+
+#[test]
+fn test_uncaught_exception_fatal() {
+    assert_eq!(
+        run_js_file("test/timers/test-uncaught-exception-fatal.js"),
+        1
+    );
+}
+
+#[test]
+fn test_mock_timers() {
+    assert_eq!(run_js_file("test/timers/test-mock-timers.js"), 0);
+}
+
+#[test]
+fn test_virtual_clock() {
+    let tokio_rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+
+    tokio_rt.block_on(async {
+        let mut rt = Runtime::new();
+        rt.set_virtual_clock(true);
+        rt.run_with_context(|ctx| {
+            ctx.eval_global_str(
+                r#"
+                globalThis.fired = [];
+                globalThis.start = performance.now();
+                globalThis.dateStart = Date.now();
+                const elapsed = () => Math.round(performance.now() - start);
+                setTimeout(() => fired.push(['timeout', elapsed()]), 60000);
+                let n = 0;
+                const interval = setInterval(() => {
+                    fired.push(['interval', elapsed()]);
+                    Promise.resolve().then(() => fired.push(['microtask']));
+                    if (++n === 3) clearInterval(interval);
+                }, 1000);
+                0
+                "#
+                .to_string(),
+            );
+        });
+
+        // pending timers don't keep a virtual clock's loop running
+        (&mut rt).await;
+        let fired = |rt: &mut Runtime| {
+            rt.run_with_context(|ctx| {
+                match ctx.eval_global_str("JSON.stringify(fired)".to_string()) {
+                    JsValue::String(s) => s.as_str().to_string(),
+                    _ => panic!("fired is not serializable"),
+                }
+            })
+        };
+        assert_eq!(fired(&mut rt), "[]");
+        // `Date.now()` stands still with the clock too
+        let date_elapsed = |rt: &mut Runtime| {
+            rt.run_with_context(|ctx| {
+                match ctx.eval_global_str("Date.now() - dateStart".to_string()) {
+                    JsValue::Int(ms) => ms as f64,
+                    JsValue::Float(ms) => ms,
+                    _ => panic!("Date.now() is not a number"),
+                }
+            })
+        };
+        assert_eq!(date_elapsed(&mut rt), 0.0);
+
+        rt.advance_time(std::time::Duration::from_millis(2500));
+        assert_eq!(
+            fired(&mut rt),
+            r#"[["interval",1000],["microtask"],["interval",2000],["microtask"]]"#
+        );
+
+        rt.advance_time(std::time::Duration::from_secs(60));
+        assert_eq!(
+            fired(&mut rt),
+            r#"[["interval",1000],["microtask"],["interval",2000],["microtask"],["interval",3000],["microtask"],["timeout",60000]]"#
+        );
+        assert_eq!(date_elapsed(&mut rt), 62500.0);
+
+        rt.set_virtual_clock(false);
+        rt.run_with_context(|ctx| {
+            match ctx.eval_global_str("performance.now() - start".to_string()) {
+                JsValue::Float(elapsed) => assert!(elapsed >= 60000.0),
+                JsValue::Int(elapsed) => assert!(elapsed >= 60000),
+                _ => panic!("performance.now() is not a number"),
+            }
+        });
+    });
+}
@@ -0,0 +1,45 @@
+#![allow(dead_code, unused_imports, unused_must_use)]
+
+use std::borrow::{Borrow, BorrowMut};
+use wasmedge_quickjs::*;
+
+mod common;
+use common::*;
+
+#[test]
+fn test_structured_clone() {
+    test_js_file("test/v8/test-structured-clone.js");
+}
+
+#[test]
+fn test_v8_serialize() {
+    test_js_file("test/v8/test-v8-serialize.js");
+}
+
+#[test]
+fn test_v8_heap_statistics() {
+    test_js_file("test/v8/test-v8-heap-statistics.js");
+}
+
+#[test]
+fn test_memory_usage() {
+    let mut rt = Runtime::new();
+    let before = rt.memory_usage();
+    assert!(before.memory_used_size > 0);
+    assert_eq!(before.malloc_limit, -1);
+
+    rt.run_with_context(|ctx| {
+        ctx.eval_global_str("globalThis.garbage = new Array(10000).fill({}); 0".to_string());
+    });
+    let grown = rt.memory_usage();
+    assert!(grown.memory_used_size > before.memory_used_size);
+
+    rt.expose_gc();
+    rt.run_with_context(|ctx| {
+        ctx.eval_global_str("globalThis.garbage = undefined; gc(); 0".to_string());
+    });
+    assert!(rt.memory_usage().memory_used_size < grown.memory_used_size);
+
+    rt.set_memory_limit(64 << 20);
+    assert_eq!(rt.memory_usage().malloc_limit, 64 << 20);
+}
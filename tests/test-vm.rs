@@ -0,0 +1,27 @@
+#![allow(dead_code, unused_imports, unused_must_use)]
+
+use std::borrow::{Borrow, BorrowMut};
+use wasmedge_quickjs::*;
+
+mod common;
+use common::*;
+
+#[test]
+fn test_vm_basic() {
+    test_js_file("test/vm/test-vm-basic.js");
+}
+
+#[test]
+fn test_vm_cached_data() {
+    test_js_file("test/vm/test-vm-cached-data.js");
+}
+
+#[test]
+fn test_vm_timeout() {
+    test_js_file("test/vm/test-vm-timeout.js");
+}
+
+#[test]
+fn test_vm_source_text_module() {
+    test_js_file("test/vm/test-vm-source-text-module.js");
+}
@@ -0,0 +1,100 @@
+#![allow(dead_code, unused_imports, unused_must_use)]
+
+use std::borrow::{Borrow, BorrowMut};
+use wasmedge_quickjs::*;
+
+mod common;
+use common::*;
+
+#[test]
+fn test_worker_basic() {
+    test_js_file("test/worker/test-worker-basic.js");
+}
+
+#[test]
+fn test_worker_message_channel() {
+    test_js_file("test/worker/test-worker-message-channel.js");
+}
+
+#[test]
+fn test_worker_exit() {
+    test_js_file("test/worker/test-worker-exit.js");
+}
+
+#[test]
+fn test_worker_shared_memory() {
+    test_js_file("test/worker/test-worker-shared-memory.js");
+}
+
+fn eval(ctx: &mut Context, code: &str) -> String {
+    match ctx.eval_global_str(code.to_string()) {
+        JsValue::String(s) => s.as_str().to_string(),
+        JsValue::Exception(e) => panic!("{:?}", e),
+        v => panic!("unexpected {:?}", v),
+    }
+}
+
+fn block_on<F: std::future::Future>(f: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap()
+        .block_on(f)
+}
+
+#[test]
+fn test_atomics_wait_across_threads() {
+    let buffer = SharedBuffer::new(8);
+    let (ready_tx, ready_rx) = std::sync::mpsc::channel();
+
+    let shared = buffer.clone();
+    let thread = std::thread::spawn(move || {
+        block_on(async {
+            let mut rt = Runtime::new();
+            rt.run_with_context(|ctx| {
+                let sab = shared.to_js(ctx);
+                ctx.get_global().set("sab", sab);
+            });
+            ready_tx.send(()).unwrap();
+            std::thread::sleep(std::time::Duration::from_millis(100));
+            rt.run_with_context(|ctx| {
+                eval(
+                    ctx,
+                    "const a = new Int32Array(sab); Atomics.store(a, 0, 1); `${Atomics.notify(a, 0)}`",
+                )
+            })
+        })
+    });
+
+    block_on(async {
+        let mut rt = Runtime::new();
+        rt.run_with_context(|ctx| {
+            let sab = buffer.to_js(ctx);
+            ctx.get_global().set("sab", sab);
+            // until another thread has the memory, nothing could wake a waiter
+            assert_eq!(
+                eval(
+                    ctx,
+                    "try { Atomics.wait(new Int32Array(sab), 0, 0, 10) } catch (e) { e.message }"
+                ),
+                "Atomics.wait cannot be called in this context"
+            );
+            ready_rx.recv().unwrap();
+            assert_eq!(
+                eval(ctx, "Atomics.wait(new Int32Array(sab), 0, 0, 5000)"),
+                "ok"
+            );
+        });
+        assert_eq!(thread.join().unwrap(), "1");
+        // the other thread let go of the memory along with its runtime
+        rt.run_with_context(|ctx| {
+            assert_eq!(
+                eval(
+                    ctx,
+                    "try { Atomics.wait(new Int32Array(sab), 0, 0, 10) } catch (e) { e.message }"
+                ),
+                "Atomics.wait cannot be called in this context"
+            );
+        });
+    });
+}